@@ -1,5 +1,5 @@
 use gossip_lib::GLOBALS;
-use gossip_lib::{Person, PersonList, PersonTable, Private, Table};
+use gossip_lib::{CustomEmoji, Person, PersonList, PersonTable, Private, Table};
 use std::collections::HashMap;
 
 use nostr_types::{
@@ -7,6 +7,10 @@ use nostr_types::{
     NostrBech32, PublicKey, RelayUrl, ShatteredContent, Unixtime,
 };
 
+/// The number of downvotes at which a post counts as heavily downvoted (if it also has
+/// fewer likes)
+const HEAVILY_DOWNVOTED: usize = 3;
+
 #[derive(PartialEq)]
 pub(crate) enum RepostType {
     /// Damus style, kind 6 repost where the reposted note's JSON
@@ -60,14 +64,23 @@ pub(crate) struct NoteData {
     pub mentions: Vec<(usize, Id)>,
 
     /// Known reactions to this post
-    pub reactions: Vec<(char, usize)>,
+    pub reactions: Vec<(String, usize)>,
 
     /// Has the current user reacted to this post?
-    pub our_reaction: Option<char>,
+    pub our_reaction: Option<String>,
+
+    /// Who reacted to this post with what
+    pub reactors: Vec<(PublicKey, String)>,
+
+    /// The custom emoji used in reactions to this post
+    pub reaction_emojis: Vec<CustomEmoji>,
 
     /// The total amount of MilliSatoshi zapped to this note
     pub zaptotal: MilliSatoshi,
 
+    /// The bitcoin block (height, time) this note is verifiably timestamped at
+    pub timestamp: Option<(u64, i64)>,
+
     /// Relays this event was seen on and when, if any
     pub seen_on: Vec<(RelayUrl, Unixtime)>,
 
@@ -128,11 +141,15 @@ impl NoteData {
             .get_reactions(event.id)
             .unwrap_or((vec![], None));
 
+        let (reactors, reaction_emojis) = load_reactors(event.id);
+
         let zaptotal = GLOBALS
             .db()
             .get_zap_total(event.id)
             .unwrap_or(MilliSatoshi(0));
 
+        let timestamp = gossip_lib::ots::verified_timestamp(event.id);
+
         // build a list of all cached mentions and their index
         // only notes that are in the cache will be rendered as reposts
         let mentions = {
@@ -196,6 +213,10 @@ impl NoteData {
             EventKind::ChannelMessage => (event.content.clone(), None),
             EventKind::LiveChatMessage => (event.content.clone(), None),
             EventKind::DraftLongFormContent => (event.content.clone(), None),
+            k if gossip_lib::nip17::is_file_message(k) => (event.content.trim().to_string(), None),
+            k if gossip_lib::nip71::is_video(k) => {
+                (gossip_lib::nip71::display_content(&event), None)
+            }
             k => {
                 if k.is_feed_displayable() {
                     (event.content.clone(), Some(format!("kind={:?}", k)))
@@ -307,7 +328,10 @@ impl NoteData {
             mentions,
             reactions,
             our_reaction,
+            reactors,
+            reaction_emojis,
             zaptotal,
+            timestamp,
             seen_on,
             shattered_content,
             error_content,
@@ -328,6 +352,10 @@ impl NoteData {
         self.reactions.clear();
         self.reactions.append(&mut reactions);
         self.our_reaction = our_reaction;
+        (self.reactors, self.reaction_emojis) = load_reactors(self.event.id);
+
+        // Update timestamp
+        self.timestamp = gossip_lib::ots::verified_timestamp(self.event.id);
 
         // Update seen_on
         let mut seen_on = GLOBALS
@@ -360,6 +388,20 @@ impl NoteData {
         self.lists.contains_key(&PersonList::Muted)
     }
 
+    /// How many people reacted with this
+    pub(super) fn reaction_count(&self, reaction: &str) -> usize {
+        self.reactions
+            .iter()
+            .find_map(|(r, count)| if r == reaction { Some(*count) } else { None })
+            .unwrap_or_default()
+    }
+
+    /// Whether enough people downvoted this post (and more than liked it) to hide it
+    pub(super) fn heavily_downvoted(&self) -> bool {
+        let downvotes = self.reaction_count("-");
+        downvotes >= HEAVILY_DOWNVOTED && downvotes > self.reaction_count("+")
+    }
+
     pub(super) fn event_reference(&self) -> EventReference {
         if self.event.kind.is_replaceable() {
             EventReference::Addr(NAddr {
@@ -378,3 +420,21 @@ impl NoteData {
         }
     }
 }
+
+// Who reacted with what, and the custom emoji of those reactions
+fn load_reactors(id: Id) -> (Vec<(PublicKey, String)>, Vec<CustomEmoji>) {
+    let mut reactors: Vec<(PublicKey, String)> = Vec::new();
+    let mut emojis: Vec<CustomEmoji> = Vec::new();
+    for (by, reaction, reaction_id) in GLOBALS.db().get_reactors(id).unwrap_or_default() {
+        if reaction.starts_with(':') && !emojis.iter().any(|e| e.as_content() == reaction) {
+            if let Ok(Some(event)) = GLOBALS.db().read_event(reaction_id) {
+                if let Some(emoji) = gossip_lib::emoji::reaction_emoji(&event) {
+                    emojis.push(emoji);
+                }
+            }
+        }
+        reactors.push((by, reaction));
+    }
+    reactors.sort_by(|a, b| a.1.cmp(&b.1));
+    (reactors, emojis)
+}
@@ -0,0 +1,117 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui::{self, RichText};
+use egui::{Context, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{ChatChannel, FeedKind, GLOBALS};
+use nostr_types::{EventKind, Tag};
+use std::time::{Duration, Instant};
+
+/// The channels we know of, which are refreshed every few seconds as loading them
+/// requires looking up their metadata
+pub struct ChatChannelsUi {
+    channels: Vec<ChatChannel>,
+    next_refresh: Instant,
+}
+
+impl Default for ChatChannelsUi {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            next_refresh: Instant::now(),
+        }
+    }
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    if app.chat_channels.next_refresh < Instant::now() {
+        app.chat_channels.channels = ChatChannel::load_all().unwrap_or_default();
+        app.chat_channels.next_refresh = Instant::now() + Duration::new(5, 0);
+    }
+
+    widgets::page_header(ui, Page::ChatChannels.name(), |ui| {
+        if widgets::Button::primary(&app.theme, "Discover")
+            .small(true)
+            .show(ui)
+            .on_hover_text("Look for public chat channels on your read relays")
+            .clicked()
+        {
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::DiscoverChatChannels);
+            // pick up the results soon
+            app.chat_channels.next_refresh = Instant::now() + Duration::new(2, 0);
+        }
+    });
+
+    // Joined channels first
+    let joined = app.lists.my_public_chats().to_vec();
+    let mut channels = app.chat_channels.channels.clone();
+    channels.sort_by_key(|c| !joined.contains(&c.id));
+
+    if channels.is_empty() {
+        ui.label("You don't know of any public chat channels yet.");
+        ui.label("Press Discover to look for them on your read relays.");
+        return;
+    }
+
+    let can_join = GLOBALS.identity.is_unlocked();
+
+    app.vert_scroll_area()
+        .id_source("chat_channels_scroll")
+        .show(ui, |ui| {
+            for channel in channels.drain(..) {
+                let is_joined = joined.contains(&channel.id);
+                ui.horizontal(|ui| {
+                    if ui
+                        .link(RichText::new(channel.display_name()).heading())
+                        .clicked()
+                    {
+                        app.set_page(ctx, Page::Feed(FeedKind::Channel(channel.id)));
+                    }
+                    if can_join {
+                        if is_joined {
+                            if widgets::Button::bordered(&app.theme, "Leave")
+                                .small(true)
+                                .show(ui)
+                                .clicked()
+                            {
+                                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListRm(
+                                    EventKind::PublicChatsList,
+                                    "".to_owned(),
+                                    "e".to_owned(),
+                                    channel.id.as_hex_string(),
+                                ));
+                            }
+                        } else if widgets::Button::primary(&app.theme, "Join")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                                EventKind::PublicChatsList,
+                                "".to_owned(),
+                                Tag::new_event(
+                                    channel.id,
+                                    channel.relays.first().map(|r| r.to_unchecked_url()),
+                                    None,
+                                    None,
+                                ),
+                                false,
+                            ));
+                        }
+                    }
+                });
+                if !channel.about.is_empty() {
+                    ui.label(&channel.about);
+                }
+                ui.label(
+                    RichText::new(format!(
+                        "created by {}",
+                        gossip_lib::names::best_name_from_pubkey_lookup(&channel.creator)
+                    ))
+                    .weak(),
+                );
+                ui.add_space(10.0);
+            }
+        });
+}
@@ -0,0 +1,131 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui::{self, RichText};
+use egui::{Context, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Community, FeedKind, GLOBALS};
+use nostr_types::{EventKind, Tag};
+use std::time::{Duration, Instant};
+
+/// The communities we know of, which are refreshed every few seconds as loading them
+/// requires parsing their definitions
+pub struct CommunitiesUi {
+    communities: Vec<Community>,
+    next_refresh: Instant,
+}
+
+impl Default for CommunitiesUi {
+    fn default() -> Self {
+        Self {
+            communities: Vec::new(),
+            next_refresh: Instant::now(),
+        }
+    }
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    if app.communities.next_refresh < Instant::now() {
+        app.communities.communities = Community::load_all().unwrap_or_default();
+        app.communities.next_refresh = Instant::now() + Duration::new(5, 0);
+    }
+
+    widgets::page_header(ui, Page::Communities.name(), |ui| {
+        if widgets::Button::primary(&app.theme, "Discover")
+            .small(true)
+            .show(ui)
+            .on_hover_text("Look for moderated communities on your read relays")
+            .clicked()
+        {
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::DiscoverCommunities);
+            // pick up the results soon
+            app.communities.next_refresh = Instant::now() + Duration::new(2, 0);
+        }
+    });
+
+    // Followed communities first
+    let followed: Vec<String> = app
+        .lists
+        .my_communities()
+        .iter()
+        .map(|(naddr, _)| gossip_lib::community::coordinate(&naddr.author, &naddr.d))
+        .collect();
+    let mut communities = app.communities.communities.clone();
+    communities.sort_by_key(|c| !followed.contains(&c.coordinate()));
+
+    if communities.is_empty() {
+        ui.label("You don't know of any communities yet.");
+        ui.label("Press Discover to look for them on your read relays.");
+        return;
+    }
+
+    let can_follow = GLOBALS.identity.is_unlocked();
+    let my_pubkey = GLOBALS.identity.public_key();
+
+    app.vert_scroll_area()
+        .id_source("communities_scroll")
+        .show(ui, |ui| {
+            for community in communities.drain(..) {
+                let coordinate = community.coordinate();
+                let is_followed = followed.contains(&coordinate);
+                ui.horizontal(|ui| {
+                    if ui
+                        .link(RichText::new(community.display_name()).heading())
+                        .clicked()
+                    {
+                        app.set_page(
+                            ctx,
+                            Page::Feed(FeedKind::Community(
+                                community.author,
+                                community.d.clone(),
+                                false,
+                            )),
+                        );
+                    }
+                    if let Some(pk) = my_pubkey {
+                        if community.is_moderator(&pk) {
+                            ui.label(RichText::new("moderator").weak());
+                        }
+                    }
+                    if can_follow {
+                        if is_followed {
+                            if widgets::Button::bordered(&app.theme, "Unfollow")
+                                .small(true)
+                                .show(ui)
+                                .clicked()
+                            {
+                                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListRm(
+                                    EventKind::CommunityList,
+                                    "".to_owned(),
+                                    "a".to_owned(),
+                                    coordinate.clone(),
+                                ));
+                            }
+                        } else if widgets::Button::primary(&app.theme, "Follow")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                                EventKind::CommunityList,
+                                "".to_owned(),
+                                Tag::new_address(&community.naddr(), None),
+                                false,
+                            ));
+                        }
+                    }
+                });
+                if !community.description.is_empty() {
+                    ui.label(&community.description);
+                }
+                ui.label(
+                    RichText::new(format!(
+                        "owned by {}",
+                        gossip_lib::names::best_name_from_pubkey_lookup(&community.author)
+                    ))
+                    .weak(),
+                );
+                ui.add_space(10.0);
+            }
+        });
+}
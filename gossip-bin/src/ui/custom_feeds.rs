@@ -0,0 +1,329 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui::{self, RichText};
+use egui::{Context, Ui};
+use gossip_lib::{CustomFeed, CustomFeedsTable, FeedKind, PersonList, Table, GLOBALS};
+use nostr_types::{EventKind, PublicKey, RelayUrl};
+
+/// The state of the custom feed editor
+#[derive(Default)]
+pub struct CustomFeedsUi {
+    /// Whether we are editing a feed
+    editing: bool,
+    /// The name of the feed being edited, if it already existed
+    original_name: Option<String>,
+    name: String,
+    /// npubs or hex keys, separated by spaces or commas
+    authors: String,
+    lists: Vec<PersonList>,
+    /// kind numbers, separated by spaces or commas
+    kinds: String,
+    hashtags: String,
+    relays: String,
+    include: String,
+    exclude: String,
+    include_replies: bool,
+    apply_spam_filter: bool,
+    /// Any errors while entering
+    err: Option<String>,
+    /// Our feeds sorted by name, loaded when first needed and again after a change
+    feeds: Option<Vec<CustomFeed>>,
+}
+
+impl CustomFeedsUi {
+    /// Our feeds, sorted by name
+    pub(super) fn feeds(&mut self) -> &[CustomFeed] {
+        self.feeds.get_or_insert_with(|| {
+            let mut feeds = CustomFeedsTable::filter_records(|_| true).unwrap_or_default();
+            feeds.sort_by_key(|feed| feed.name.to_lowercase());
+            feeds
+        })
+    }
+
+    /// Start editing a feed, or a new one if None
+    pub(super) fn edit(&mut self, feed: Option<CustomFeed>) {
+        let feed = feed.unwrap_or_default();
+        *self = CustomFeedsUi {
+            editing: true,
+            original_name: if feed.name.is_empty() {
+                None
+            } else {
+                Some(feed.name.clone())
+            },
+            name: feed.name.clone(),
+            authors: feed
+                .authors
+                .iter()
+                .map(|pk| pk.as_bech32_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+            lists: feed.lists.clone(),
+            kinds: feed
+                .kinds
+                .iter()
+                .map(|k| u32::from(*k).to_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+            hashtags: feed.hashtags.join(" "),
+            relays: feed
+                .relays
+                .iter()
+                .map(|r| r.as_str())
+                .collect::<Vec<&str>>()
+                .join(" "),
+            include: feed.include.join(" "),
+            exclude: feed.exclude.join(" "),
+            include_replies: feed.include_replies,
+            apply_spam_filter: feed.apply_spam_filter,
+            err: None,
+            feeds: self.feeds.take(),
+        };
+    }
+
+    /// Build the feed from what was entered
+    fn to_feed(&self) -> Result<CustomFeed, String> {
+        let name = self.name.trim().to_owned();
+        if name.is_empty() {
+            return Err("The feed needs a name".to_owned());
+        }
+
+        let mut authors: Vec<PublicKey> = Vec::new();
+        for s in split(&self.authors) {
+            match PublicKey::try_from_bech32_string(&s, true)
+                .or_else(|_| PublicKey::try_from_hex_string(&s, true))
+            {
+                Ok(pk) => authors.push(pk),
+                Err(_) => return Err(format!("Not a public key: {}", s)),
+            }
+        }
+
+        let mut kinds: Vec<EventKind> = Vec::new();
+        for s in split(&self.kinds) {
+            match s.parse::<u32>() {
+                Ok(k) => kinds.push(k.into()),
+                Err(_) => return Err(format!("Not an event kind number: {}", s)),
+            }
+        }
+
+        let mut relays: Vec<RelayUrl> = Vec::new();
+        for s in split(&self.relays) {
+            match RelayUrl::try_from_str(&s) {
+                Ok(url) => relays.push(url),
+                Err(_) => return Err(format!("Not a relay URL: {}", s)),
+            }
+        }
+
+        let feed = CustomFeed {
+            name,
+            authors,
+            lists: self.lists.clone(),
+            kinds,
+            hashtags: split(&self.hashtags),
+            relays,
+            include: split(&self.include),
+            exclude: split(&self.exclude),
+            include_replies: self.include_replies,
+            apply_spam_filter: self.apply_spam_filter,
+        };
+
+        if feed.is_relay_feed() && feed.relays.is_empty() {
+            return Err("The feed needs authors, lists, hashtags or relays".to_owned());
+        }
+
+        Ok(feed)
+    }
+}
+
+fn split(s: &str) -> Vec<String> {
+    s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect()
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    widgets::page_header(ui, Page::CustomFeeds.name(), |ui| {
+        if !app.custom_feeds.editing
+            && widgets::Button::primary(&app.theme, "New Feed")
+                .small(true)
+                .show(ui)
+                .clicked()
+        {
+            app.custom_feeds.edit(None);
+        }
+    });
+
+    if app.custom_feeds.editing {
+        edit_feed(app, ui);
+        return;
+    }
+
+    let mut feeds = app.custom_feeds.feeds().to_vec();
+
+    if feeds.is_empty() {
+        ui.label("You have no custom feeds.");
+        ui.label(
+            "A custom feed is a saved filter of authors, lists, kinds, hashtags and keywords.",
+        );
+        return;
+    }
+
+    app.vert_scroll_area()
+        .id_source("custom_feeds_scroll")
+        .show(ui, |ui| {
+            for feed in feeds.drain(..) {
+                ui.horizontal(|ui| {
+                    if ui.link(RichText::new(&feed.name).heading()).clicked() {
+                        app.set_page(ctx, Page::Feed(FeedKind::Custom(feed.name.clone())));
+                    }
+                    if widgets::Button::bordered(&app.theme, "Edit")
+                        .small(true)
+                        .show(ui)
+                        .clicked()
+                    {
+                        app.custom_feeds.edit(Some(feed.clone()));
+                    }
+                    if widgets::Button::bordered(&app.theme, "Delete")
+                        .small(true)
+                        .show(ui)
+                        .clicked()
+                    {
+                        let _ = CustomFeedsTable::delete_record(feed.name.clone(), None);
+                        app.custom_feeds.feeds = None;
+                    }
+                });
+                ui.add_space(10.0);
+            }
+        });
+}
+
+fn edit_feed(app: &mut GossipUi, ui: &mut Ui) {
+    let all_lists = GLOBALS
+        .db()
+        .get_all_person_list_metadata()
+        .unwrap_or_default();
+
+    app.vert_scroll_area()
+        .id_source("custom_feed_edit_scroll")
+        .show(ui, |ui| {
+            egui::Grid::new("custom_feed_grid")
+                .num_columns(2)
+                .spacing([20.0, 10.0])
+                .show(ui, |ui| {
+                    ui.label("Name");
+                    ui.add(text_edit_line!(app, app.custom_feeds.name).desired_width(400.0));
+                    ui.end_row();
+
+                    ui.label("Authors");
+                    ui.add(
+                        text_edit_line!(app, app.custom_feeds.authors)
+                            .hint_text("npub1... npub1...")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Lists");
+                    ui.horizontal_wrapped(|ui| {
+                        for (list, metadata) in all_lists.iter() {
+                            if *list == PersonList::Muted {
+                                continue;
+                            }
+                            let mut checked = app.custom_feeds.lists.contains(list);
+                            if ui.checkbox(&mut checked, &metadata.title).changed() {
+                                if checked {
+                                    app.custom_feeds.lists.push(*list);
+                                } else {
+                                    app.custom_feeds.lists.retain(|l| l != list);
+                                }
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Kinds");
+                    ui.add(
+                        text_edit_line!(app, app.custom_feeds.kinds)
+                            .hint_text("e.g. 1 30023 (empty for all feed kinds)")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Hashtags");
+                    ui.add(
+                        text_edit_line!(app, app.custom_feeds.hashtags)
+                            .hint_text("#nostr #bitcoin")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Relays");
+                    ui.add(
+                        text_edit_line!(app, app.custom_feeds.relays)
+                            .hint_text("wss://... (empty for your read relays)")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Include keywords");
+                    ui.add(
+                        text_edit_line!(app, app.custom_feeds.include)
+                            .hint_text("any of these must be in the content")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Exclude keywords");
+                    ui.add(
+                        text_edit_line!(app, app.custom_feeds.exclude)
+                            .hint_text("none of these may be in the content")
+                            .desired_width(400.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Options");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut app.custom_feeds.include_replies, "Include replies");
+                        ui.checkbox(&mut app.custom_feeds.apply_spam_filter, "Apply spam filter")
+                            .on_hover_text(
+                                "The spam filter script is called with caller \"Custom\"",
+                            );
+                    });
+                    ui.end_row();
+                });
+
+            ui.add_space(10.0);
+            if let Some(err) = &app.custom_feeds.err {
+                ui.label(RichText::new(err).color(app.theme.warning_marker_text_color()));
+                ui.add_space(10.0);
+            }
+
+            ui.horizontal(|ui| {
+                if widgets::Button::primary(&app.theme, "Save")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    match app.custom_feeds.to_feed() {
+                        Ok(mut feed) => {
+                            if let Some(original) = &app.custom_feeds.original_name {
+                                if *original != feed.name {
+                                    let _ = CustomFeedsTable::delete_record(original.clone(), None);
+                                }
+                            }
+                            match CustomFeedsTable::write_record(&mut feed, None) {
+                                Ok(_) => app.custom_feeds = Default::default(),
+                                Err(e) => app.custom_feeds.err = Some(format!("{}", e)),
+                            }
+                        }
+                        Err(e) => app.custom_feeds.err = Some(e),
+                    }
+                }
+                if widgets::Button::bordered(&app.theme, "Cancel")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    app.custom_feeds = Default::default();
+                }
+            });
+        });
+}
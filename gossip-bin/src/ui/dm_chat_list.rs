@@ -1,15 +1,19 @@
 use crate::AVATAR_SIZE_F32;
 
 use super::{widgets, GossipUi, Page};
+use chrono::{Local, NaiveDate};
 use eframe::egui;
 use eframe::egui::vec2;
 use eframe::egui::Rect;
 use egui::{Context, Label, RichText, Ui};
+use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::FeedKind;
 use gossip_lib::Person;
 use gossip_lib::GLOBALS;
+use gossip_lib::{DmChannel, DmSearch};
 use gossip_lib::{Error, ErrorKind};
 use gossip_lib::{PersonTable, Table};
+use nostr_types::{PublicKey, Unixtime};
 use std::time::{Duration, Instant};
 
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
@@ -52,8 +56,22 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         {
             let _ = GLOBALS.db().mark_all_dms_read();
         }
+        ui.add_space(16.0);
+        ui.checkbox(&mut app.dm_show_archived, "Show archived");
     });
 
+    search_bar(app, ui);
+    if app.dm_searching {
+        search_results(app, ctx, ui);
+        return;
+    }
+
+    // Archived conversations, and those continued elsewhere, stay out of the way
+    // unless they have something new
+    if !app.dm_show_archived {
+        channels.retain(|c| !(c.archived || c.continued) || c.unread_message_count > 0);
+    }
+
     let is_signer_ready = GLOBALS.identity.is_unlocked();
 
     app.vert_scroll_area()
@@ -100,10 +118,29 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
 
                                 ui.vertical(|ui| {
                                     ui.horizontal_wrapped(|ui| {
-                                        let channel_name = channeldata.dm_channel.name();
+                                        if channeldata.pinned {
+                                            ui.label(RichText::new("📌").weak())
+                                                .on_hover_text("Pinned");
+                                        }
+                                        let channel_name = match &channeldata.subject {
+                                            Some(subject) => subject.clone(),
+                                            None => channeldata.dm_channel.name(),
+                                        };
                                         ui.add(Label::new(
                                             RichText::new(channel_name).heading().color(color),
                                         ));
+                                        if channeldata.subject.is_some() {
+                                            ui.label(
+                                                RichText::new(channeldata.dm_channel.name()).weak(),
+                                            );
+                                        }
+                                        if channeldata.muted {
+                                            ui.label(RichText::new("🔇").weak())
+                                                .on_hover_text("Muted");
+                                        }
+                                        if channeldata.archived {
+                                            ui.label(RichText::new("archived").weak());
+                                        }
 
                                         ui.with_layout(
                                             egui::Layout::right_to_left(egui::Align::TOP),
@@ -173,28 +210,225 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
                         row_response.response.rect.height(),
                     ),
                 );
-                if ui
+                let response = ui
                     .interact(rect, ui.next_auto_id(), egui::Sense::click())
-                    .on_hover_cursor(egui::CursorIcon::PointingHand)
-                    .clicked()
-                {
-                    app.set_page(
-                        ctx,
-                        Page::Feed(FeedKind::DmChat(channeldata.dm_channel.clone())),
-                    );
-                    app.draft_needs_focus = true;
-
-                    // Maybe clear the draft, if we are going into a different channel than last
-                    // time
-                    if let Some(oldtarget) = &app.dm_draft_data_target {
-                        if *oldtarget != channeldata.dm_channel {
-                            app.dm_draft_data.clear();
-                        }
-                    } else {
-                        app.dm_draft_data.clear();
+                    .on_hover_cursor(egui::CursorIcon::PointingHand);
+                response.context_menu(|ui| {
+                    let channel = &channeldata.dm_channel;
+                    let mut changed = false;
+                    if ui
+                        .button(if channeldata.pinned { "Unpin" } else { "Pin" })
+                        .clicked()
+                    {
+                        let _ = channel.modify_conversation(|c| c.pinned = !c.pinned);
+                        changed = true;
+                    }
+                    if ui
+                        .button(if channeldata.muted { "Unmute" } else { "Mute" })
+                        .clicked()
+                    {
+                        let _ = channel.modify_conversation(|c| c.muted = !c.muted);
+                        changed = true;
+                    }
+                    if ui
+                        .button(if channeldata.archived {
+                            "Unarchive"
+                        } else {
+                            "Archive"
+                        })
+                        .clicked()
+                    {
+                        let _ = channel.modify_conversation(|c| c.archived = !c.archived);
+                        changed = true;
+                    }
+                    if ui.button("Mark read").clicked() {
+                        let _ = channel.mark_read();
+                        changed = true;
                     }
-                    app.dm_draft_data_target = Some(channeldata.dm_channel.clone());
+                    if changed {
+                        app.dm_channel_next_refresh = Instant::now();
+                        ui.close_menu();
+                    }
+                });
+                if response.clicked() {
+                    open_channel(app, ctx, &channeldata.dm_channel);
                 }
             }
         });
 }
+
+fn open_channel(app: &mut GossipUi, ctx: &Context, channel: &DmChannel) {
+    app.set_page(ctx, Page::Feed(FeedKind::DmChat(channel.clone())));
+    app.draft_needs_focus = true;
+
+    // Maybe clear the draft, if we are going into a different channel than last
+    // time
+    if let Some(oldtarget) = &app.dm_draft_data_target {
+        if oldtarget != channel {
+            app.dm_draft_data.clear();
+        }
+    } else {
+        app.dm_draft_data.clear();
+    }
+    app.dm_draft_data_target = Some(channel.clone());
+}
+
+fn search_bar(app: &mut GossipUi, ui: &mut Ui) {
+    let mut trigger_search = false;
+
+    ui.horizontal_wrapped(|ui| {
+        let response = ui.add(
+            text_edit_line!(app, app.dm_search_text)
+                .hint_text("Search messages")
+                .desired_width(200.0),
+        );
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            trigger_search = true;
+        }
+        ui.label("with");
+        ui.add(
+            text_edit_line!(app, app.dm_search_participant)
+                .hint_text("npub or hex")
+                .desired_width(150.0),
+        );
+        ui.label("from");
+        ui.add(
+            text_edit_line!(app, app.dm_search_since)
+                .hint_text("YYYY-MM-DD")
+                .desired_width(90.0),
+        );
+        ui.label("to");
+        ui.add(
+            text_edit_line!(app, app.dm_search_until)
+                .hint_text("YYYY-MM-DD")
+                .desired_width(90.0),
+        );
+        if ui.button("Search").clicked() {
+            trigger_search = true;
+        }
+        if app.dm_searching && ui.button("Clear").clicked() {
+            app.dm_searching = false;
+            app.dm_search_text.clear();
+            app.dm_search_participant.clear();
+            app.dm_search_since.clear();
+            app.dm_search_until.clear();
+            GLOBALS.dm_search_results.write().clear();
+        }
+        if ui
+            .button("Reindex")
+            .on_hover_text("Rebuild the DM search index from all your DMs")
+            .clicked()
+        {
+            let _ = GLOBALS.db().set_flag_rebuild_dm_search_needed(true, None);
+        }
+    });
+
+    if !trigger_search {
+        return;
+    }
+
+    let participant = app.dm_search_participant.trim();
+    let participant = if participant.is_empty() {
+        None
+    } else {
+        match PublicKey::try_from_hex_string(participant, true)
+            .or_else(|_| PublicKey::try_from_bech32_string(participant, true))
+        {
+            Ok(pk) => Some(pk),
+            Err(_) => {
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write("That is not a public key".to_owned());
+                return;
+            }
+        }
+    };
+
+    let (since, until) = match (
+        parse_date(&app.dm_search_since, false),
+        parse_date(&app.dm_search_until, true),
+    ) {
+        (Ok(since), Ok(until)) => (since, until),
+        _ => {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("Dates must look like 2024-12-31".to_owned());
+            return;
+        }
+    };
+
+    let query = DmSearch {
+        text: app.dm_search_text.trim().to_owned(),
+        participant,
+        since,
+        until,
+    };
+    if query.is_empty() {
+        return;
+    }
+
+    let _ = GLOBALS
+        .to_overlord
+        .send(ToOverlordMessage::SearchDms(query));
+    app.dm_searching = true;
+}
+
+// A local date as the start (or the end) of that day
+fn parse_date(s: &str, end_of_day: bool) -> Result<Option<Unixtime>, ()> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| ())?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    }
+    .ok_or(())?;
+    let local = time.and_local_timezone(Local).earliest().ok_or(())?;
+    Ok(Some(Unixtime(local.timestamp())))
+}
+
+fn search_results(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
+    let results = GLOBALS.dm_search_results.read().clone();
+    if results.is_empty() {
+        ui.add_space(10.0);
+        ui.label("No messages found");
+        return;
+    }
+
+    app.vert_scroll_area()
+        .id_source("dm_search_results")
+        .show(ui, |ui| {
+            for result in results.iter() {
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .link(RichText::new(&result.title).color(app.theme.accent_color()))
+                        .clicked()
+                    {
+                        open_channel(app, ctx, &result.channel);
+                    }
+                    ui.label(
+                        RichText::new(gossip_lib::names::best_name_from_pubkey_lookup(
+                            &result.author,
+                        ))
+                        .weak(),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                        ui.label(crate::date_ago::date_ago(result.created_at));
+                    });
+                });
+                widgets::truncated_label(
+                    ui,
+                    result.text.replace('\n', " "),
+                    ui.available_width() - 20.0,
+                );
+                ui.add_space(4.0);
+                ui.separator();
+            }
+        });
+}
@@ -0,0 +1,89 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui::{self, RichText};
+use egui::{Context, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{Dvm, FeedKind, GLOBALS};
+use std::time::{Duration, Instant};
+
+/// The content discovery DVMs we know of, which are refreshed every few seconds as
+/// loading them requires scanning their handler events
+pub struct DvmsUi {
+    dvms: Vec<Dvm>,
+    next_refresh: Instant,
+}
+
+impl Default for DvmsUi {
+    fn default() -> Self {
+        Self {
+            dvms: Vec::new(),
+            next_refresh: Instant::now(),
+        }
+    }
+}
+
+impl DvmsUi {
+    /// Reload the DVMs the next time the page is drawn
+    pub(super) fn enter_page(&mut self) {
+        self.next_refresh = Instant::now();
+    }
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    if app.dvms.next_refresh < Instant::now() {
+        app.dvms.dvms = Dvm::load_all().unwrap_or_default();
+        app.dvms.next_refresh = Instant::now() + Duration::new(5, 0);
+    }
+
+    widgets::page_header(ui, Page::Dvms.name(), |ui| {
+        if widgets::Button::primary(&app.theme, "Discover")
+            .small(true)
+            .show(ui)
+            .on_hover_text("Look for content discovery DVMs on your read relays")
+            .clicked()
+        {
+            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::DiscoverDvms);
+            // pick up the results soon
+            app.dvms.next_refresh = Instant::now() + Duration::new(2, 0);
+        }
+    });
+
+    let mut dvms = app.dvms.dvms.clone();
+
+    if dvms.is_empty() {
+        ui.label("You don't know of any content discovery DVMs yet.");
+        ui.label("Press Discover to look for them on your read relays.");
+        return;
+    }
+
+    let signer_ready = GLOBALS.identity.is_unlocked();
+    if !signer_ready {
+        ui.label(
+            RichText::new("You need to unlock your key to request a feed from a DVM.")
+                .color(app.theme.warning_marker_text_color()),
+        );
+        ui.add_space(10.0);
+    }
+
+    app.vert_scroll_area()
+        .id_source("dvms_scroll")
+        .show(ui, |ui| {
+            for dvm in dvms.drain(..) {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(dvm.name()).heading());
+                    ui.add_enabled_ui(signer_ready, |ui| {
+                        if widgets::Button::primary(&app.theme, "View Feed")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            app.set_page(ctx, Page::Feed(FeedKind::Dvm(dvm.pubkey)));
+                        }
+                    });
+                });
+                if let Some(about) = dvm.about() {
+                    ui.label(about);
+                }
+                ui.add_space(10.0);
+            }
+        });
+}
@@ -1,7 +1,12 @@
+use super::GossipUi;
 use eframe::egui::{self, vec2, Button};
-use egui::Ui;
+use egui::{Image, Response, RichText, Sense, Ui};
+use gossip_lib::emoji::EmojiSpan;
+use gossip_lib::{CustomEmoji, MediaLoadingResult};
 
-pub fn emoji_picker(ui: &mut Ui) -> Option<char> {
+/// Pick an emoji, which is either a unicode emoji or one of our custom emoji as
+/// `:shortcode:`
+pub fn emoji_picker(app: &mut GossipUi, ui: &mut Ui) -> Option<String> {
     let mut emojis = "🤙👍👌🙏🤝💪🤘👏🙌🤟🤌🫶👊👆✊\
                       🫂💜❤💟💖✨💫🌈\
                       ✔✅🔥👀💯🚀⚡🎉\
@@ -12,14 +17,29 @@ pub fn emoji_picker(ui: &mut Ui) -> Option<char> {
                       📖🐈🫧🕊🚩💩"
         .chars();
 
-    let mut output: Option<char> = None;
+    let mut output: Option<String> = None;
 
     ui.vertical(|ui| {
         if ui.add(Button::new("LIKE").small()).clicked() {
-            output = Some('+');
+            output = Some("+".to_owned());
         }
     });
 
+    // Our custom emoji from our NIP-51 emoji list and sets
+    let custom = gossip_lib::emoji::my_emojis();
+    for row in custom.chunks(10) {
+        ui.horizontal(|ui| {
+            for emoji in row.iter() {
+                if custom_emoji(app, ui, emoji, 20.0)
+                    .on_hover_text(emoji.as_content())
+                    .clicked()
+                {
+                    output = Some(emoji.as_content());
+                }
+            }
+        });
+    }
+
     let mut quit: bool = false;
 
     loop {
@@ -35,7 +55,7 @@ pub fn emoji_picker(ui: &mut Ui) -> Option<char> {
                             )
                             .clicked()
                         {
-                            output = Some(emoji);
+                            output = Some(emoji.to_string());
                         }
                     } else {
                         quit = true;
@@ -51,3 +71,39 @@ pub fn emoji_picker(ui: &mut Ui) -> Option<char> {
 
     output
 }
+
+/// Show a custom emoji as its image, or as its `:shortcode:` until the image loads
+pub fn custom_emoji(app: &mut GossipUi, ui: &mut Ui, emoji: &CustomEmoji, size: f32) -> Response {
+    if let Some(url) = app.try_check_url(&emoji.url) {
+        if let MediaLoadingResult::Ready(texture) = app.try_get_media(ui.ctx(), url, false, None) {
+            return ui.add(
+                Image::new(&texture)
+                    .max_size(vec2(size, size))
+                    .maintain_aspect_ratio(true)
+                    .sense(Sense::click()),
+            );
+        }
+    }
+    ui.add(egui::Label::new(emoji.as_content()).sense(Sense::click()))
+}
+
+/// Show text with the given custom emoji in it as images
+pub fn text_with_emojis(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    text: &str,
+    emojis: &[CustomEmoji],
+    style: impl Fn(RichText) -> RichText,
+) {
+    let size = ui.text_style_height(&egui::TextStyle::Body);
+    for span in gossip_lib::emoji::split_emojis(text, emojis) {
+        match span {
+            EmojiSpan::Text(text) => {
+                ui.label(style(RichText::new(text)));
+            }
+            EmojiSpan::Emoji(emoji) => {
+                custom_emoji(app, ui, emoji, size).on_hover_text(emoji.as_content());
+            }
+        }
+    }
+}
@@ -1,13 +1,18 @@
 use super::{widgets, GossipUi, Page};
 use eframe::egui::{self, Align, Rect};
 use egui::{Context, RichText, Ui, Vec2};
-use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::comms::{PostDestination, ToOverlordMessage};
 use gossip_lib::relay::Relay;
-use gossip_lib::DmChannel;
+use gossip_lib::ChatChannel;
+use gossip_lib::Community;
 use gossip_lib::FeedKind;
+use gossip_lib::Nip51List;
 use gossip_lib::GLOBALS;
-use nostr_types::Id;
+use gossip_lib::{CustomFeedsTable, GroupMetadata, Table};
+use gossip_lib::{DmChannel, DmConversation};
+use nostr_types::{EventKind, Id, PublicKey, RelayUrl, Tag};
 use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
 mod note;
 pub use note::NoteRenderData;
@@ -26,6 +31,72 @@ struct FeedNoteParams {
 pub(super) struct Feeds {
     thread_needs_scroll: bool,
     last_enter_feed_time: f64,
+
+    // What the feed header shows. Naming some feeds needs database reads (and
+    // decryption), so this is loaded when the feed changes and refreshed every few
+    // seconds, not every frame.
+    header_kind: Option<FeedKind>,
+    header_next_refresh: Option<Instant>,
+    title: String,
+    relay_set: Vec<RelayUrl>,
+    channel: Option<ChatChannel>,
+    group: Option<GroupMetadata>,
+    community: Option<Community>,
+    dm_conversation: Option<DmConversation>,
+    dm_predecessor: Option<(DmChannel, String)>, // (channel, title)
+    dm_successor: Option<(DmChannel, String)>,   // (channel, title)
+}
+
+// Load what the feed header shows, if it is for another feed or is getting old
+fn refresh_header(app: &mut GossipUi, feed_kind: &FeedKind) {
+    let due = match app.feeds.header_next_refresh {
+        Some(when) => when < Instant::now(),
+        None => true,
+    };
+    if !due && app.feeds.header_kind.as_ref() == Some(feed_kind) {
+        return;
+    }
+
+    app.feeds.title = format!("{}", feed_kind);
+    app.feeds.relay_set = match feed_kind {
+        FeedKind::RelaySet(d) => Nip51List::load_mine(EventKind::RelaySets, d)
+            .map(|list| list.relay_urls())
+            .unwrap_or_default(),
+        _ => vec![],
+    };
+    app.feeds.channel = match feed_kind {
+        FeedKind::Channel(id) => ChatChannel::load(*id).ok().flatten(),
+        _ => None,
+    };
+    app.feeds.group = match feed_kind {
+        FeedKind::Group(relay, id) => GroupMetadata::load(relay, id).ok().flatten(),
+        _ => None,
+    };
+    app.feeds.community = match feed_kind {
+        FeedKind::Community(author, d, _) => Community::load(*author, d).ok().flatten(),
+        _ => None,
+    };
+    app.feeds.dm_conversation = None;
+    app.feeds.dm_predecessor = None;
+    app.feeds.dm_successor = None;
+    if let FeedKind::DmChat(channel) = feed_kind {
+        let conversation = channel.conversation();
+        if let Some(ref subject) = conversation.subject {
+            app.feeds.title = subject.clone();
+        }
+        let with_title = |id: &String| {
+            DmChannel::from_unique_id(id).map(|channel| {
+                let title = channel.title();
+                (channel, title)
+            })
+        };
+        app.feeds.dm_predecessor = conversation.predecessor.as_ref().and_then(with_title);
+        app.feeds.dm_successor = conversation.successor.as_ref().and_then(with_title);
+        app.feeds.dm_conversation = Some(conversation);
+    }
+
+    app.feeds.header_kind = Some(feed_kind.clone());
+    app.feeds.header_next_refresh = Some(Instant::now() + Duration::new(5, 0));
 }
 
 pub(super) fn enter_feed(app: &mut GossipUi, ctx: &Context, kind: FeedKind) {
@@ -55,6 +126,7 @@ pub(super) fn enter_feed(app: &mut GossipUi, ctx: &Context, kind: FeedKind) {
     }
 
     app.feeds.last_enter_feed_time = ctx.input(|i| i.time);
+    app.feeds.header_kind = None;
 
     // clear the displayed feed
     app.displayed_feed = vec![];
@@ -94,6 +166,8 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
     // was last set to.
     let scroll_widget_id = feed_kind.anchor_key();
 
+    refresh_header(app, &feed_kind);
+
     match feed_kind {
         FeedKind::List(list, mut with_replies) => {
             let metadata = GLOBALS
@@ -252,6 +326,343 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
 
             render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
         }
+        FeedKind::RelaySet(_) => {
+            let relays = app.feeds.relay_set.clone();
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+            });
+            ui.label(
+                relays
+                    .iter()
+                    .map(|r| r.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            );
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
+        FeedKind::BookmarkSet(_) => {
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+            });
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
+        FeedKind::Custom(ref name) => {
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+                if widgets::Button::bordered(&app.theme, "Edit")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    let feed = CustomFeedsTable::read_record(name.clone(), None)
+                        .ok()
+                        .flatten();
+                    app.custom_feeds.edit(feed);
+                    app.set_page(ctx, Page::CustomFeeds);
+                }
+            });
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
+        FeedKind::Channel(id) => {
+            let channel = app.feeds.channel.clone();
+            let joined = app.lists.my_public_chats().contains(&id);
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+                if GLOBALS.identity.is_unlocked() {
+                    if joined {
+                        if widgets::Button::bordered(&app.theme, "Leave")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListRm(
+                                EventKind::PublicChatsList,
+                                "".to_owned(),
+                                "e".to_owned(),
+                                id.as_hex_string(),
+                            ));
+                        }
+                    } else if widgets::Button::primary(&app.theme, "Join")
+                        .small(true)
+                        .show(ui)
+                        .clicked()
+                    {
+                        let relay = channel
+                            .as_ref()
+                            .and_then(|c| c.relays.first())
+                            .map(|r| r.to_unchecked_url());
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                            EventKind::PublicChatsList,
+                            "".to_owned(),
+                            Tag::new_event(id, relay, None, None),
+                            false,
+                        ));
+                    }
+                }
+                if widgets::Button::bordered(&app.theme, "Load older")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::LoadMoreCurrentFeed);
+                }
+            });
+            if let Some(channel) = &channel {
+                if !channel.about.is_empty() {
+                    ui.horizontal(|ui| {
+                        add_left_space(ui);
+                        ui.label(RichText::new(&channel.about).weak());
+                    });
+                }
+            }
+            ui.add_space(6.0);
+
+            render_dm_feed(app, ui, &scroll_widget_id);
+        }
+        FeedKind::Group(ref relay, ref id) => {
+            let metadata = app.feeds.group.clone();
+            let joined = app.groups.is_joined(relay, id);
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+                if GLOBALS.identity.is_unlocked() {
+                    if joined {
+                        if widgets::Button::bordered(&app.theme, "Leave")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS
+                                .to_overlord
+                                .send(ToOverlordMessage::LeaveGroup(relay.clone(), id.clone()));
+                            app.groups.refresh_soon();
+                        }
+                    } else if widgets::Button::primary(&app.theme, "Join")
+                        .small(true)
+                        .show(ui)
+                        .clicked()
+                    {
+                        let _ = GLOBALS
+                            .to_overlord
+                            .send(ToOverlordMessage::JoinGroup(relay.clone(), id.clone()));
+                        app.groups.refresh_soon();
+                    }
+                }
+                if widgets::Button::bordered(&app.theme, "Groups")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    app.set_page(ctx, Page::Groups);
+                }
+            });
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.label(RichText::new(relay.as_str()).weak());
+                if let Some(metadata) = &metadata {
+                    if !metadata.about.is_empty() {
+                        ui.label(RichText::new(&metadata.about).weak());
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.collapsing("Members", |ui| {
+                    let admins = gossip_lib::group::group_admins(relay, id).unwrap_or_default();
+                    for (pubkey, roles) in admins.iter() {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .link(gossip_lib::names::best_name_from_pubkey_lookup(pubkey))
+                                .clicked()
+                            {
+                                app.set_page(ctx, Page::Person(*pubkey));
+                            }
+                            ui.label(RichText::new(roles.join(", ")).weak());
+                        });
+                    }
+                    let members = gossip_lib::group::group_members(relay, id).unwrap_or_default();
+                    for pubkey in members.iter() {
+                        if admins.iter().any(|(pk, _)| pk == pubkey) {
+                            continue;
+                        }
+                        if ui
+                            .link(gossip_lib::names::best_name_from_pubkey_lookup(pubkey))
+                            .clicked()
+                        {
+                            app.set_page(ctx, Page::Person(*pubkey));
+                        }
+                    }
+                    if admins.is_empty() && members.is_empty() {
+                        ui.label("The relay has not published the members of this group.");
+                    }
+                });
+            });
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
+        FeedKind::Community(ref author, ref d, pending) => {
+            let community = app.feeds.community.clone();
+            let coordinate = gossip_lib::community::coordinate(author, d);
+            let following = app
+                .lists
+                .my_communities()
+                .iter()
+                .any(|(naddr, _)| naddr.author == *author && naddr.d == *d);
+            let is_moderator = match (&community, GLOBALS.identity.public_key()) {
+                (Some(community), Some(pk)) => community.is_moderator(&pk),
+                _ => false,
+            };
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+                if GLOBALS.identity.is_unlocked() {
+                    if following {
+                        if widgets::Button::bordered(&app.theme, "Unfollow")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListRm(
+                                EventKind::CommunityList,
+                                "".to_owned(),
+                                "a".to_owned(),
+                                coordinate.clone(),
+                            ));
+                        }
+                    } else if let Some(community) = &community {
+                        if widgets::Button::primary(&app.theme, "Follow")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                                EventKind::CommunityList,
+                                "".to_owned(),
+                                Tag::new_address(&community.naddr(), None),
+                                false,
+                            ));
+                        }
+                    }
+                }
+                if is_moderator {
+                    let label = if pending { "Approved" } else { "Pending" };
+                    if widgets::Button::bordered(&app.theme, label)
+                        .small(true)
+                        .show(ui)
+                        .on_hover_text("Switch between approved posts and posts awaiting approval")
+                        .clicked()
+                    {
+                        app.set_page(
+                            ctx,
+                            Page::Feed(FeedKind::Community(*author, d.clone(), !pending)),
+                        );
+                    }
+                }
+                if widgets::Button::bordered(&app.theme, "Communities")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    app.set_page(ctx, Page::Communities);
+                }
+            });
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                if pending {
+                    ui.label(RichText::new("Posts awaiting approval").weak());
+                }
+                if let Some(community) = &community {
+                    if !community.description.is_empty() {
+                        ui.label(RichText::new(&community.description).weak());
+                    }
+                }
+            });
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
+        FeedKind::Dvm(_) => {
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+                if widgets::Button::bordered(&app.theme, "DVMs")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    app.set_page(ctx, Page::Dvms);
+                }
+            });
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
+        FeedKind::Hashtag(ref hashtag) => {
+            let following = app.lists.my_interests().contains(hashtag);
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                add_left_space(ui);
+                ui.heading(&app.feeds.title);
+                recompute_btn(app, ui);
+                if GLOBALS.identity.public_key().is_some() {
+                    if following {
+                        if widgets::Button::bordered(&app.theme, "Unfollow")
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListRm(
+                                EventKind::InterestsList,
+                                "".to_owned(),
+                                "t".to_owned(),
+                                hashtag.clone(),
+                            ));
+                        }
+                    } else if widgets::Button::primary(&app.theme, "Follow")
+                        .small(true)
+                        .show(ui)
+                        .clicked()
+                    {
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                            EventKind::InterestsList,
+                            "".to_owned(),
+                            Tag::new_hashtag(hashtag.clone()),
+                            false,
+                        ));
+                    }
+                }
+            });
+            ui.add_space(6.0);
+
+            render_a_feed(app, ctx, ui, None, &scroll_widget_id, load_more);
+        }
         FeedKind::DmChat(channel) => {
             if !GLOBALS.identity.is_unlocked() {
                 ui.add_space(10.0);
@@ -283,11 +694,11 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                             widgets::AvatarSize::Mini.get_size(),
                         );
 
-                        if ui.link(RichText::new(channel.name()).heading()).clicked() {
+                        if ui.link(RichText::new(&app.feeds.title).heading()).clicked() {
                             app.set_page(ctx, Page::Person(key.to_owned()));
                         }
                     } else {
-                        ui.heading(channel.name());
+                        ui.heading(&app.feeds.title);
                     }
                     recompute_btn(app, ui);
 
@@ -317,8 +728,10 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                 },
             );
 
+            dm_conversation_bar(app, ctx, ui, &channel);
+
             ui.add_space(6.0);
-            render_dm_feed(app, ui, channel);
+            render_dm_feed(app, ui, &channel.name());
         }
     }
 
@@ -326,6 +739,123 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
     app.handle_visible_note_changes();
 }
 
+// Where a DM conversation came from and went, and what can be done with it
+fn dm_conversation_bar(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, channel: &DmChannel) {
+    // Loaded with the feed header
+    let conversation = match app.feeds.dm_conversation.clone() {
+        Some(conversation) => conversation,
+        None => return,
+    };
+
+    ui.horizontal_wrapped(|ui| {
+        add_left_space(ui);
+
+        if let Some((predecessor, title)) = app.feeds.dm_predecessor.clone() {
+            if ui.link(format!("Continued from {}", title)).clicked() {
+                app.set_page(ctx, Page::Feed(FeedKind::DmChat(predecessor)));
+            }
+            ui.add_space(10.0);
+        }
+        if let Some((successor, title)) = app.feeds.dm_successor.clone() {
+            if ui.link(format!("Continued in {}", title)).clicked() {
+                app.set_page(ctx, Page::Feed(FeedKind::DmChat(successor)));
+            }
+            ui.add_space(10.0);
+        }
+
+        let mut pinned = conversation.pinned;
+        if ui.checkbox(&mut pinned, "Pinned").changed() {
+            let _ = channel.modify_conversation(|c| c.pinned = pinned);
+            app.feeds.header_next_refresh = None;
+        }
+        let mut muted = conversation.muted;
+        if ui.checkbox(&mut muted, "Muted").changed() {
+            let _ = channel.modify_conversation(|c| c.muted = muted);
+            app.feeds.header_next_refresh = None;
+        }
+        let mut archived = conversation.archived;
+        if ui.checkbox(&mut archived, "Archived").changed() {
+            let _ = channel.modify_conversation(|c| c.archived = archived);
+            app.feeds.header_next_refresh = None;
+        }
+
+        // Subjects and membership are NIP-17 things
+        if !channel.can_use_nip17() {
+            return;
+        }
+
+        ui.add_space(10.0);
+        ui.menu_button("Rename ▼", |ui| {
+            ui.label("Subject of the conversation");
+            ui.add(
+                text_edit_line!(app, app.dm_rename)
+                    .hint_text(conversation.subject.as_deref().unwrap_or(""))
+                    .desired_width(200.0),
+            );
+            if ui.button("Rename").clicked() {
+                let subject = app.dm_rename.trim().to_owned();
+                if !subject.is_empty() {
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
+                        content: format!("Renamed the conversation to \"{}\"", subject),
+                        tags: vec![Tag::new_subject(subject)],
+                        in_reply_to: None,
+                        annotation: false,
+                        destination: PostDestination::Dm(channel.to_owned()),
+                    });
+                    app.dm_rename.clear();
+                    ui.close_menu();
+                }
+            }
+        });
+
+        ui.menu_button("Members ▼", |ui| {
+            for key in channel.keys() {
+                ui.horizontal(|ui| {
+                    ui.label(gossip_lib::names::best_name_from_pubkey_lookup(key));
+                    if channel.keys().len() > 1 && ui.button("Remove").clicked() {
+                        match channel.change_members(&[], &[*key]) {
+                            Ok(successor) => {
+                                app.set_page(ctx, Page::Feed(FeedKind::DmChat(successor)))
+                            }
+                            Err(e) => GLOBALS.status_queue.write().write(format!("{}", e)),
+                        }
+                        ui.close_menu();
+                    }
+                });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(
+                    text_edit_line!(app, app.dm_add_member)
+                        .hint_text("npub or hex")
+                        .desired_width(200.0),
+                );
+                if ui.button("Add").clicked() {
+                    let s = app.dm_add_member.trim().to_owned();
+                    let pubkey = match PublicKey::try_from_hex_string(&s, true) {
+                        Ok(pk) => Some(pk),
+                        Err(_) => PublicKey::try_from_bech32_string(&s, true).ok(),
+                    };
+                    match pubkey {
+                        Some(pubkey) => match channel.change_members(&[pubkey], &[]) {
+                            Ok(successor) => {
+                                app.dm_add_member.clear();
+                                app.set_page(ctx, Page::Feed(FeedKind::DmChat(successor)));
+                                ui.close_menu();
+                            }
+                            Err(e) => GLOBALS.status_queue.write().write(format!("{}", e)),
+                        },
+                        None => GLOBALS
+                            .status_queue
+                            .write()
+                            .write("That is not a public key".to_owned()),
+                    }
+                }
+            });
+        });
+    });
+}
+
 #[allow(clippy::too_many_arguments)]
 fn render_a_feed(
     app: &mut GossipUi,
@@ -444,9 +974,9 @@ fn render_a_feed(
         });
 }
 
-fn render_dm_feed(app: &mut GossipUi, ui: &mut Ui, channel: DmChannel) {
+/// Render a feed chat style, as used for DMs and public chat channels
+fn render_dm_feed(app: &mut GossipUi, ui: &mut Ui, scroll_area_id: &str) {
     let feed = app.displayed_feed.clone();
-    let scroll_area_id = channel.name();
     let feed_newest_at_bottom = GLOBALS.db().read_setting_feed_newest_at_bottom();
     let iterator: Box<dyn Iterator<Item = &Id>> = if feed_newest_at_bottom {
         Box::new(feed.iter().rev())
@@ -2,7 +2,7 @@ use crate::ui::GossipUi;
 use eframe::{egui, epaint};
 use egui::{Image, Response, RichText, Ui};
 use epaint::Vec2;
-use gossip_lib::{MediaLoadingResult, GLOBALS};
+use gossip_lib::{FileMessage, MediaLoadingResult, GLOBALS};
 use nostr_types::{FileMetadata, Url};
 
 pub fn show_image(
@@ -78,6 +78,66 @@ pub fn show_image(
     ui.set_row_height(row_height);
 }
 
+/// An encrypted file that we cannot show: fetch and decrypt it when asked, and save it
+pub fn show_file(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    url: Url,
+    volatile: bool,
+    file_message: &FileMessage,
+) {
+    // insert a newline if the current line has text
+    if ui.cursor().min.x > ui.max_rect().min.x {
+        ui.end_row();
+    }
+
+    let name = file_message.filename();
+    if let Some(path) = app.saved_files.get(&url) {
+        ui.label(format!("[ {} saved ]", name))
+            .on_hover_text(format!("{}", path.display()));
+    } else if !app.media_show_list.contains(&url) {
+        if ui
+            .link(format!("[ Encrypted file: {} ]", name))
+            .on_hover_text("Download, decrypt and save to your downloads directory")
+            .clicked()
+        {
+            app.media_show_list.insert(url.clone());
+        }
+    } else {
+        let file_metadata = file_message.file_metadata();
+        match GLOBALS.media.get_data(&url, volatile, Some(&file_metadata)) {
+            MediaLoadingResult::Disabled => {
+                ui.label("[ Fetch Media setting is disabled ]");
+            }
+            MediaLoadingResult::Loading => {
+                ui.label(format!("[ Decrypting {}... ]", name));
+            }
+            MediaLoadingResult::Ready(data) => {
+                app.media_show_list.remove(&url);
+                match gossip_lib::nip17::save(file_message, &data) {
+                    Ok(path) => {
+                        GLOBALS
+                            .status_queue
+                            .write()
+                            .write(format!("Saved {}", path.display()));
+                        app.saved_files.insert(url.clone(), path);
+                    }
+                    Err(e) => {
+                        GLOBALS.status_queue.write().write(format!("{}", e));
+                    }
+                }
+            }
+            MediaLoadingResult::Failed(s) => {
+                ui.label(
+                    RichText::new(format!("[ {}: {} ]", name, s)).color(ui.visuals().warn_fg_color),
+                );
+            }
+        }
+    }
+
+    ui.end_row();
+}
+
 pub fn show_video(
     app: &mut GossipUi,
     ui: &mut Ui,
@@ -85,6 +145,50 @@ pub fn show_video(
     privacy_issue: bool,
     volatile: bool,
     file_metadata: Option<FileMetadata>,
+    poster: Option<Url>,
+) {
+    show_playable(
+        app,
+        ui,
+        url,
+        privacy_issue,
+        volatile,
+        file_metadata,
+        poster,
+        false,
+    );
+}
+
+pub fn show_audio(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    url: Url,
+    privacy_issue: bool,
+    volatile: bool,
+    file_metadata: Option<FileMetadata>,
+) {
+    show_playable(
+        app,
+        ui,
+        url,
+        privacy_issue,
+        volatile,
+        file_metadata,
+        None,
+        true,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn show_playable(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    url: Url,
+    privacy_issue: bool,
+    volatile: bool,
+    file_metadata: Option<FileMetadata>,
+    poster: Option<Url>,
+    audio: bool,
 ) {
     // insert a newline if the current line has text
     if ui.cursor().min.x > ui.max_rect().min.x {
@@ -92,11 +196,24 @@ pub fn show_video(
     }
     let row_height = ui.cursor().height();
     let mut show_link = true;
+    let what = if audio { "Audio" } else { "Video" };
 
-    // Show video player or loading placeholder
+    // Show player or loading placeholder, or else the poster frame
     if show(app, &url, privacy_issue) {
-        if try_render_video(app, ui, url.clone(), volatile, file_metadata) {
+        if try_render_video(
+            app,
+            ui,
+            url.clone(),
+            volatile,
+            file_metadata,
+            poster.as_ref(),
+            audio,
+        ) {
             show_link = false;
+        } else if let Some(poster) = poster {
+            if try_render_poster(app, ui, poster, &url, volatile, true) {
+                show_link = false;
+            }
         }
     }
 
@@ -106,13 +223,15 @@ pub fn show_video(
 
         // show media toggle
         let response = if privacy_issue {
-            ui.link("[ PRIVACY RISK Video ]").on_hover_text(format!(
+            ui.link(format!("[ PRIVACY RISK {} ]", what))
+                .on_hover_text(format!(
                 "The sender might be trying to associate your pubkey with your IP address. URL={}",
                 url_string
             ))
         } else {
             // show url on hover
-            ui.link("[ Video ]").on_hover_text(url_string.clone())
+            ui.link(format!("[ {} ]", what))
+                .on_hover_text(url_string.clone())
         };
 
         if response.clicked() {
@@ -154,6 +273,54 @@ pub fn show_video(
     ui.set_row_height(row_height);
 }
 
+/// Render the poster frame of a video (from its imeta tag), with a play symbol over it.
+/// If `open` is set, clicking it opens the video in the browser (for when we cannot play
+/// it ourselves).
+///  - return: true if rendered, false otherwise
+fn try_render_poster(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    poster: Url,
+    video: &Url,
+    volatile: bool,
+    open: bool,
+) -> bool {
+    let texture = match app.try_get_media(ui.ctx(), poster, volatile, None) {
+        MediaLoadingResult::Ready(texture) => texture,
+        _ => return false,
+    };
+    let size = media_scale(false, ui, texture.size_vec2());
+    let response = ui.add(
+        Image::new(&texture)
+            .max_size(size)
+            .maintain_aspect_ratio(true)
+            .sense(egui::Sense::click()),
+    );
+    ui.painter().text(
+        response.rect.center(),
+        egui::Align2::CENTER_CENTER,
+        "\u{25B6}",
+        egui::FontId::proportional(size.y.min(size.x) / 4.0),
+        egui::Color32::from_white_alpha(200),
+    );
+    if open {
+        if response.hovered() {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        }
+        if response.clicked() {
+            let modifiers = ui.ctx().input(|i| i.modifiers);
+            ui.ctx().output_mut(|o| {
+                o.open_url = Some(egui::output::OpenUrl {
+                    url: video.to_string(),
+                    new_tab: modifiers.any(),
+                });
+            });
+        }
+        response.on_hover_text("Open in browser");
+    }
+    true
+}
+
 /// Try to fetch and render a piece of media
 ///  - return: true if successfully rendered, false otherwise
 fn try_render_image(
@@ -254,6 +421,8 @@ fn try_render_video(
     url: Url,
     volatile: bool,
     file_metadata: Option<FileMetadata>,
+    poster: Option<&Url>,
+    audio: bool,
 ) -> bool {
     let show_full_width = app.media_full_width_list.contains(&url);
     match app.try_get_player(ui.ctx(), url.clone(), volatile, file_metadata.as_ref()) {
@@ -262,6 +431,7 @@ fn try_render_video(
             false
         }
         MediaLoadingResult::Loading => {
+            let what = if audio { "audio" } else { "video" };
             egui::Frame::none()
                 .inner_margin(egui::Margin::same(0.0))
                 .outer_margin(egui::Margin {
@@ -273,33 +443,44 @@ fn try_render_video(
                 .fill(egui::Color32::TRANSPARENT)
                 .rounding(ui.style().noninteractive().rounding)
                 .show(ui, |ui| {
+                    // Show the poster frame while the video loads, if it has one
+                    if let Some(poster) = poster {
+                        if try_render_poster(app, ui, poster.clone(), &url, volatile, false) {
+                            return;
+                        }
+                    }
                     let text = if let Some(fm) = &file_metadata {
                         // FIXME do blurhash
                         if let Some(alt) = &fm.alt {
-                            &format!("Loading video: {alt}")
+                            &format!("Loading {what}: {alt}")
                         } else if let Some(summary) = &fm.summary {
-                            &format!("Loading video: {summary}")
+                            &format!("Loading {what}: {summary}")
                         } else {
-                            "Loading video..."
+                            &format!("Loading {what}...")
                         }
                     } else {
-                        "Loading video..."
+                        &format!("Loading {what}...")
                     };
                     let color = app.theme.notice_marker_text_color();
-                    ui.label(RichText::new(text).color(color))
+                    ui.label(RichText::new(text).color(color));
                 });
             true
         }
         MediaLoadingResult::Ready(player_ref) => {
             if let Ok(mut player) = player_ref.try_borrow_mut() {
-                let size = media_scale(
-                    show_full_width,
-                    ui,
-                    Vec2 {
-                        x: player.width as f32,
-                        y: player.height as f32,
-                    },
-                );
+                let size = if audio {
+                    // There is no picture, just the controls
+                    Vec2::new(ui.available_width() / 2.0, 48.0)
+                } else {
+                    media_scale(
+                        show_full_width,
+                        ui,
+                        Vec2 {
+                            x: player.width as f32,
+                            y: player.height as f32,
+                        },
+                    )
+                };
 
                 // show the player
                 if !show_full_width {
@@ -345,6 +526,8 @@ fn try_render_video(
     _url: Url,
     _volatile: bool,
     _file_metadata: Option<FileMetadata>,
+    _poster: Option<&Url>,
+    _audio: bool,
 ) -> bool {
     false
 }
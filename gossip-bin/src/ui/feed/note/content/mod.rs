@@ -2,7 +2,7 @@ mod media;
 
 use super::{GossipUi, NoteData, Page, RepostType};
 use eframe::egui;
-use egui::{Button, Color32, Margin, Pos2, RichText, Stroke, Ui};
+use egui::{Button, Color32, Label, Margin, Pos2, RichText, Sense, Stroke, Ui};
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::FeedKind;
 use gossip_lib::GLOBALS;
@@ -173,7 +173,7 @@ pub(super) fn render_content(
                                 render_event_link(app, ui, note.event.id, id);
                             }
                         } else if let Ok(hashtag) = tag.parse_hashtag() {
-                            render_hashtag(ui, &hashtag);
+                            render_hashtag(app, ui, &hashtag);
                         } else {
                             render_unknown_reference(ui, *num);
                         }
@@ -221,6 +221,12 @@ pub(super) fn render_hyperlink(
         }
     }
 
+    // A file sent to us encrypted (NIP-17), decrypted as it loads
+    let file_message = gossip_lib::nip17::file_message(&note.event).filter(|fm| fm.url == link);
+    if let Some(ref fm) = file_message {
+        file_metadata = Some(fm.file_metadata());
+    }
+
     if let Ok(relay_url) = RelayUrl::try_from_str(link) {
         render_relay_link(app, ui, relay_url);
         return;
@@ -231,20 +237,96 @@ pub(super) fn render_hyperlink(
     let privacy_issue = note.direct_message;
 
     if let (Ok(url), Some(nurl)) = (url::Url::try_from(link), app.try_check_url(link)) {
-        if let Some(mimetype) = gossip_lib::media_url_mimetype(url.path()) {
+        // The imeta tag knows the type of media at URLs without a file extension
+        let mimetype = match file_metadata.as_ref().and_then(|fm| fm.m.clone()) {
+            Some(m) => Some(m),
+            None => gossip_lib::media_url_mimetype(url.path())
+                .map(|m| m.to_owned())
+                .or_else(|| {
+                    file_message
+                        .as_ref()
+                        .map(|_| "application/octet-stream".to_owned())
+                }),
+        };
+        if let Some(ref fm) = file_message {
+            GLOBALS.media.set_encrypted(&nurl, fm);
+        }
+        if let Some(mimetype) = mimetype {
+            // If the media breaks, we may find it on the author's blossom servers
+            GLOBALS.media.set_author(&nurl, note.event.pubkey);
             if mimetype.starts_with("image/") {
                 media::show_image(app, ui, nurl, privacy_issue, note.volatile, file_metadata);
             } else if mimetype.starts_with("video/") {
-                media::show_video(app, ui, nurl, privacy_issue, note.volatile, file_metadata);
+                let poster = gossip_lib::nip71::imeta_entry(&note.event, link, "image")
+                    .and_then(|poster| app.try_check_url(&poster));
+                media::show_video(
+                    app,
+                    ui,
+                    nurl,
+                    privacy_issue,
+                    note.volatile,
+                    file_metadata,
+                    poster,
+                );
+            } else if mimetype.starts_with("audio/") {
+                media::show_audio(app, ui, nurl, privacy_issue, note.volatile, file_metadata);
+            } else if let Some(ref fm) = file_message {
+                media::show_file(app, ui, nurl, note.volatile, fm);
+            } else {
+                crate::ui::widgets::break_anywhere_hyperlink_to(ui, link, link);
             }
         } else {
             crate::ui::widgets::break_anywhere_hyperlink_to(ui, link, link);
+            if !privacy_issue {
+                render_website_reactions(app, ui, link);
+            }
         }
     } else {
         crate::ui::widgets::break_anywhere_hyperlink_to(ui, link, link);
     }
 }
 
+// A button to react to a website, showing the reactions we know of on hover
+fn render_website_reactions(app: &mut GossipUi, ui: &mut Ui, link: &str) {
+    if !read_setting!(reactions) {
+        return;
+    }
+
+    let bar_id = ui.id().with(("website_reactions", link));
+    let mut bar_state = egui::menu::BarState::load(ui.ctx(), bar_id);
+
+    let response = ui
+        .add(
+            Label::new(RichText::new(" ♡").weak())
+                .selectable(false)
+                .sense(Sense::click()),
+        )
+        .on_hover_ui(|ui| {
+            let (reactions, our_reaction) =
+                gossip_lib::website_reaction::reactions_to(link).unwrap_or((vec![], None));
+            if reactions.is_empty() {
+                ui.label("No known reactions to this website");
+            }
+            for (reaction, count) in reactions.iter() {
+                ui.label(format!("{} {}", reaction, count));
+            }
+            if let Some(reaction) = our_reaction {
+                ui.label(RichText::new(format!("You reacted with {}", reaction)).weak());
+            }
+        });
+
+    if GLOBALS.identity.is_unlocked() {
+        bar_state.bar_menu(&response, |ui| {
+            if let Some(emoji) = crate::ui::emojis::emoji_picker(app, ui) {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::ReactToWebsite(link.to_owned(), emoji));
+            }
+        });
+    }
+    bar_state.store(ui.ctx(), bar_id);
+}
+
 pub(super) fn render_plain(
     app: &mut GossipUi,
     ui: &mut Ui,
@@ -254,6 +336,7 @@ pub(super) fn render_plain(
     content_start: Pos2,
 ) -> bool {
     let text = note.shattered_content.slice(textspan).unwrap();
+    let emojis = gossip_lib::emoji::emojis_of(&note.event);
 
     let mut first = true;
     for line in text.split('\n') {
@@ -279,7 +362,15 @@ pub(super) fn render_plain(
             ui.end_row();
         }
 
-        if as_deleted {
+        if !emojis.is_empty() {
+            crate::ui::emojis::text_with_emojis(app, ui, line, &emojis, |text| {
+                if as_deleted {
+                    text.strikethrough()
+                } else {
+                    text
+                }
+            });
+        } else if as_deleted {
             ui.label(RichText::new(line).strikethrough());
         } else {
             ui.label(line);
@@ -361,12 +452,9 @@ pub(super) fn render_parameterized_event_link(
     };
 }
 
-pub(super) fn render_hashtag(ui: &mut Ui, s: &String) {
+pub(super) fn render_hashtag(app: &mut GossipUi, ui: &mut Ui, s: &String) {
     if ui.link(format!("#{}", s)).clicked() {
-        GLOBALS
-            .status_queue
-            .write()
-            .write("Gossip doesn't have a hashtag feed yet.".to_owned());
+        app.set_page(ui.ctx(), Page::Feed(FeedKind::Hashtag(s.to_lowercase())));
     }
 }
 
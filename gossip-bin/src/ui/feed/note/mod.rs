@@ -18,9 +18,10 @@ use egui::{
     Align, Context, Frame, Label, Layout, RichText, Sense, Separator, Stroke, TextStyle, Ui,
 };
 use gossip_lib::comms::ToOverlordMessage;
-use gossip_lib::{relay, DmChannel, FeedKind, ZapState, GLOBALS};
+use gossip_lib::{relay, Count, CountKind, CustomEmoji, DmChannel, FeedKind, ZapState, GLOBALS};
 use nostr_types::{
-    Event, EventDelegation, EventKind, EventReference, IdHex, NAddr, NEvent, NostrUrl, UncheckedUrl,
+    Event, EventDelegation, EventKind, EventReference, IdHex, NAddr, NEvent, NostrUrl, Tag,
+    UncheckedUrl,
 };
 use serde::Serialize;
 
@@ -81,7 +82,8 @@ pub(super) fn render_note(
             let skip = ((note_data.muted() && read_setting!(hide_mutes_entirely))
                 && !matches!(app.page, Page::Feed(FeedKind::DmChat(_)))
                 && !matches!(app.page, Page::Feed(FeedKind::Person(_))))
-                || (!note_data.deletions.is_empty() && !read_setting!(show_deleted_events));
+                || (!note_data.deletions.is_empty() && !read_setting!(show_deleted_events))
+                || (note_data.heavily_downvoted() && read_setting!(hide_downvoted));
 
             if skip {
                 return;
@@ -280,7 +282,11 @@ pub fn render_note_inner(
     if let Ok(note) = note_ref.try_borrow() {
         let collapsed = app.collapsed.contains(&note.event.id);
 
-        let is_dm_feed = matches!(app.page, Page::Feed(FeedKind::DmChat(_)));
+        // Public chat channels are shown like DMs
+        let is_dm_feed = matches!(
+            app.page,
+            Page::Feed(FeedKind::DmChat(_)) | Page::Feed(FeedKind::Channel(_))
+        );
 
         // Load avatar texture
         let avatar = if note.muted() {
@@ -540,6 +546,35 @@ pub fn render_note_inner(
                             );
                         }
 
+                        if let Some((height, block_time)) = note.timestamp {
+                            let color = app.theme.notice_marker_text_color();
+                            let mut hover = format!("Timestamped at bitcoin block {}", height);
+                            if let Ok(stamp) = time::OffsetDateTime::from_unix_timestamp(block_time)
+                            {
+                                if let Ok(formatted) =
+                                    stamp.format(&time::format_description::well_known::Rfc2822)
+                                {
+                                    hover = format!("{} on {}", hover, formatted);
+                                }
+                            }
+                            ui.label(
+                                RichText::new(format!("⏱ BLOCK {}", height))
+                                    .color(color)
+                                    .text_style(TextStyle::Small),
+                            )
+                            .on_hover_text(hover);
+                        }
+
+                        if gossip_lib::relay::is_protected(&note.event) {
+                            let color = app.theme.notice_marker_text_color();
+                            ui.label(
+                                RichText::new("PROTECTED")
+                                    .color(color)
+                                    .text_style(TextStyle::Small),
+                            )
+                            .on_hover_text("Only the author may publish this to relays (NIP-70)");
+                        }
+
                         if note.repost.is_some() {
                             let color = app.theme.notice_marker_text_color();
                             ui.label(
@@ -790,6 +825,13 @@ pub fn render_note_inner(
                                     }
                                 };
 
+                                // NIP-45 reply count
+                                if let Some(count) =
+                                    GLOBALS.counts.get(CountKind::Replies(note.event.id))
+                                {
+                                    render_count(ui, count, "replies");
+                                }
+
                                 ui.add_space(24.0);
 
                                 if note.event.kind != EventKind::EncryptedDirectMessage
@@ -809,6 +851,13 @@ pub fn render_note_inner(
                                         app.draft_data.replying_to = None;
                                     }
 
+                                    // NIP-45 repost count
+                                    if let Some(count) =
+                                        GLOBALS.counts.get(CountKind::Reposts(note.event.id))
+                                    {
+                                        render_count(ui, count, "reposts");
+                                    }
+
                                     ui.add_space(24.0);
 
                                     // Button to quote note
@@ -919,8 +968,14 @@ pub fn render_note_inner(
 
                                 // Buttons to react and reaction counts
                                 if read_setting!(reactions) && !note.muted() {
-                                    if let Some(reaction) = note.our_reaction {
-                                        ui.label(RichText::new(reaction).size(16.0));
+                                    if let Some(reaction) = &note.our_reaction {
+                                        render_reaction(
+                                            app,
+                                            ui,
+                                            reaction,
+                                            &note.reaction_emojis,
+                                            16.0,
+                                        );
                                     } else if can_sign {
                                         let bar_id = ui.id().with("emoji_picker");
                                         let mut bar_state =
@@ -935,7 +990,8 @@ pub fn render_note_inner(
                                             .on_hover_cursor(egui::CursorIcon::PointingHand);
 
                                         bar_state.bar_menu(&button_response, |ui| {
-                                            if let Some(emoji) = crate::ui::emojis::emoji_picker(ui)
+                                            if let Some(emoji) =
+                                                crate::ui::emojis::emoji_picker(app, ui)
                                             {
                                                 let _ = GLOBALS.to_overlord.send(
                                                     ToOverlordMessage::React(
@@ -947,6 +1003,25 @@ pub fn render_note_inner(
                                             }
                                         });
                                         bar_state.store(ui.ctx(), bar_id);
+
+                                        ui.add_space(4.0);
+                                        if ui
+                                            .add(
+                                                Label::new(RichText::new("👎").size(14.0))
+                                                    .selectable(false)
+                                                    .sense(Sense::click()),
+                                            )
+                                            .on_hover_text("Downvote")
+                                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                            .clicked()
+                                        {
+                                            let _ =
+                                                GLOBALS.to_overlord.send(ToOverlordMessage::React(
+                                                    note.event.id,
+                                                    note.event.pubkey,
+                                                    "-".to_owned(),
+                                                ));
+                                        }
                                     } else {
                                         ui.label(RichText::new('♡').size(20.0));
                                     }
@@ -954,8 +1029,8 @@ pub fn render_note_inner(
                                     let hover_ui = |ui: &mut Ui| {
                                         ui.horizontal_wrapped(|ui| {
                                             let mut col = 0;
-                                            for (ch, count) in note.reactions.iter() {
-                                                if *ch != '+' {
+                                            for (reaction, count) in note.reactions.iter() {
+                                                if reaction != "+" && reaction != "-" {
                                                     egui::Frame::none()
                                                         .inner_margin(egui::Margin::from(
                                                             ui.spacing().item_spacing,
@@ -966,7 +1041,7 @@ pub fn render_note_inner(
                                                                 egui::Label::new(
                                                                     RichText::new(format!(
                                                                         "{} {}",
-                                                                        ch, count
+                                                                        reaction, count
                                                                     ))
                                                                     .weak(),
                                                                 ),
@@ -984,32 +1059,53 @@ pub fn render_note_inner(
                                                 }
                                             }
                                         });
+                                        ui.label(RichText::new("Click to see who reacted").weak());
                                     };
-                                    let like_count = note
-                                        .reactions
-                                        .iter()
-                                        .find_map(
-                                            |(ch, count)| {
-                                                if *ch == '+' {
-                                                    Some(*count)
-                                                } else {
-                                                    None
-                                                }
-                                            },
-                                        )
-                                        .unwrap_or_default();
+                                    let like_count = note.reaction_count("+");
+                                    let downvote_count = note.reaction_count("-");
                                     let reaction_count: usize = note
                                         .reactions
                                         .iter()
-                                        .filter_map(|(c, s)| if *c == '+' { None } else { Some(s) })
+                                        .filter_map(|(r, s)| {
+                                            if r == "+" || r == "-" {
+                                                None
+                                            } else {
+                                                Some(s)
+                                            }
+                                        })
                                         .sum();
 
-                                    ui.add(
-                                        Label::new(format!("{}+{}", like_count, reaction_count))
-                                            .sense(Sense::hover()),
-                                    )
-                                    .on_hover_ui(hover_ui)
-                                    .on_disabled_hover_ui(hover_ui);
+                                    let counts = if downvote_count > 0 {
+                                        format!(
+                                            "{}+{} -{}",
+                                            like_count, reaction_count, downvote_count
+                                        )
+                                    } else {
+                                        format!("{}+{}", like_count, reaction_count)
+                                    };
+
+                                    // Click the counts for who reacted with what
+                                    let bar_id = ui.id().with("reactors");
+                                    let mut bar_state =
+                                        egui::menu::BarState::load(ui.ctx(), bar_id);
+                                    let counts_response = ui
+                                        .add(
+                                            Label::new(counts)
+                                                .selectable(false)
+                                                .sense(Sense::click()),
+                                        )
+                                        .on_hover_ui(hover_ui);
+                                    bar_state.bar_menu(&counts_response, |ui| {
+                                        render_reactors(app, ui, &note);
+                                    });
+                                    bar_state.store(ui.ctx(), bar_id);
+
+                                    // NIP-45 reaction count (may include reactions we don't have)
+                                    if let Some(count) =
+                                        GLOBALS.counts.get(CountKind::Reactions(note.event.id))
+                                    {
+                                        render_count(ui, count, "reactions");
+                                    }
                                 }
                             });
 
@@ -1231,6 +1327,60 @@ fn render_content(
     }
 }
 
+// Show a reaction, with custom emoji as images and likes and downvotes as symbols
+fn render_reaction(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    reaction: &str,
+    emojis: &[CustomEmoji],
+    size: f32,
+) {
+    match emojis.iter().find(|e| e.as_content() == reaction) {
+        Some(emoji) => {
+            crate::ui::emojis::custom_emoji(app, ui, emoji, size).on_hover_text(reaction);
+        }
+        None => {
+            let text = match reaction {
+                "+" => "♥",
+                "-" => "👎",
+                other => other,
+            };
+            ui.label(RichText::new(text).size(size));
+        }
+    }
+}
+
+// Who reacted to a note with what
+fn render_reactors(app: &mut GossipUi, ui: &mut Ui, note: &std::cell::Ref<NoteData>) {
+    if note.reactors.is_empty() {
+        ui.label(RichText::new("no reactions").italics().weak());
+        return;
+    }
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .id_source(("reactors", note.event.id))
+        .show(ui, |ui| {
+            for (pubkey, reaction) in note.reactors.iter() {
+                ui.horizontal(|ui| {
+                    render_reaction(app, ui, reaction, &note.reaction_emojis, 14.0);
+                    ui.add_space(6.0);
+                    let name = gossip_lib::names::best_name_from_pubkey_lookup(pubkey);
+                    if ui.link(name).clicked() {
+                        app.set_page(ui.ctx(), Page::Person(*pubkey));
+                    }
+                });
+            }
+        });
+}
+
+fn render_count(ui: &mut Ui, count: Count, what: &str) {
+    ui.add(Label::new(RichText::new(count.to_string()).weak()).sense(Sense::hover()))
+        .on_hover_text(format!(
+            "{} {} counted by {} relays. A ~ means the count is approximate.",
+            count, what, count.relays
+        ));
+}
+
 fn render_repost(
     app: &mut GossipUi,
     ui: &mut Ui,
@@ -1434,6 +1584,33 @@ fn note_actions(
                 }
             }
 
+            // Pin or Unpin to our profile
+            if app.lists.my_pins().contains(&note.event.id) {
+                my_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                    "Unpin",
+                    Box::new(|_, _| {
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListRm(
+                            EventKind::PinList,
+                            "".to_owned(),
+                            "e".to_owned(),
+                            note.event.id.as_hex_string(),
+                        ));
+                    }),
+                )));
+            } else {
+                my_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                    "Pin to profile",
+                    Box::new(|_, _| {
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                            EventKind::PinList,
+                            "".to_owned(),
+                            Tag::new_event(note.event.id, None, None, Some(note.event.pubkey)),
+                            false,
+                        ));
+                    }),
+                )));
+            }
+
             items.push(MoreMenuItem::SubMenu(MoreMenuSubMenu::new(
                 "Manage", my_items, &menu,
             )))
@@ -1474,6 +1651,90 @@ fn note_actions(
         )));
     } // end Bookmark
 
+    // ---- Bookmark Sets ----
+    let bookmark_sets = app.lists.my_bookmark_sets().to_vec();
+    if !bookmark_sets.is_empty() {
+        let mut bs_items: Vec<MoreMenuItem> = Vec::new();
+        for (d, name) in bookmark_sets {
+            bs_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                name,
+                Box::new(move |_, _| {
+                    let tag = match note.event_reference() {
+                        EventReference::Id {
+                            id, author, relays, ..
+                        } => Tag::new_event(
+                            id,
+                            relays.first().map(|r| r.to_unchecked_url()),
+                            None,
+                            author,
+                        ),
+                        EventReference::Addr(ea) => Tag::new_address(&ea, None),
+                    };
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                        EventKind::BookmarkSets,
+                        d,
+                        tag,
+                        false,
+                    ));
+                }),
+            )));
+        }
+        items.push(MoreMenuItem::SubMenu(MoreMenuSubMenu::new(
+            "Add to set",
+            bs_items,
+            &menu,
+        )));
+    } // end Bookmark Sets
+
+    // ---- Channel moderation ----
+    if note.event.kind == EventKind::ChannelMessage
+        && GLOBALS.identity.is_unlocked()
+        && Some(note.event.pubkey) != GLOBALS.identity.public_key()
+    {
+        let mut ch_items: Vec<MoreMenuItem> = Vec::new();
+        ch_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+            "Hide message",
+            Box::new(|_, _| {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::HideChannelMessage(note.event.id));
+            }),
+        )));
+        ch_items.push(MoreMenuItem::Button(MoreMenuButton::new(
+            "Mute author",
+            Box::new(|_, _| {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::MuteChannelUser(note.event.pubkey));
+            }),
+        )));
+        items.push(MoreMenuItem::SubMenu(MoreMenuSubMenu::new(
+            "Channel", ch_items, &menu,
+        )));
+    } // Channel moderation SubMenu
+
+    // ---- Community moderation ----
+    if let Page::Feed(FeedKind::Community(author, d, true)) = &app.page {
+        if GLOBALS.identity.is_unlocked() {
+            let author = *author;
+            let d = d.clone();
+            let id = note.event.id;
+            let cm_items: Vec<MoreMenuItem> = vec![MoreMenuItem::Button(MoreMenuButton::new(
+                "Approve post",
+                Box::new(move |_, _| {
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::ApproveCommunityPost(author, d, id));
+                }),
+            ))];
+            items.push(MoreMenuItem::SubMenu(MoreMenuSubMenu::new(
+                "Community",
+                cm_items,
+                &menu,
+            )));
+        }
+    } // Community moderation SubMenu
+
     // ---- Open with ----
     if !note.event.kind.is_direct_message_related() {
         let mut my_items: Vec<MoreMenuItem> = Vec::new();
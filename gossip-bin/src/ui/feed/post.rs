@@ -8,7 +8,7 @@ use egui::{Align, Context, Key, Layout, Modifiers, RichText, Ui};
 use egui_winit::egui::text::{CCursor, CCursorRange};
 use egui_winit::egui::text_edit::TextEditOutput;
 use egui_winit::egui::{vec2, AboveOrBelow, Id};
-use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::comms::{PostDestination, ToOverlordMessage};
 use gossip_lib::{DmChannel, PersonTable, Relay, Table, GLOBALS};
 use memoize::memoize;
 use nostr_types::{ContentSegment, NostrBech32, NostrUrl, ShatteredContent, Tag};
@@ -391,12 +391,12 @@ fn dm_posting_area(
 
             // Emoji picker
             ui.menu_button(RichText::new("😀▼").size(14.0), |ui| {
-                if let Some(emoji) = crate::ui::emojis::emoji_picker(ui) {
-                    app.dm_draft_data.draft.push(emoji);
+                if let Some(emoji) = crate::ui::emojis::emoji_picker(app, ui) {
+                    app.dm_draft_data.draft.push_str(&emoji);
                 }
             });
 
-            offer_attachment(app, ctx, ui, true);
+            offer_attachment(app, ctx, ui, Some(dm_channel));
         });
     });
 
@@ -417,7 +417,7 @@ fn dm_posting_area(
             tags,
             in_reply_to: None,
             annotation: app.dm_draft_data.is_annotate,
-            dm_channel: Some(dm_channel.to_owned()),
+            destination: PostDestination::Dm(dm_channel.to_owned()),
         });
 
         app.reset_draft();
@@ -524,6 +524,17 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     ui.add_space(10.0);
                 }
 
+                if app.draft_data.protected {
+                    ui.label(
+                        RichText::new(
+                            "Protected: only relays that authenticate you will accept this, \
+                             and others cannot republish it",
+                        )
+                        .weak(),
+                    );
+                    ui.add_space(10.0);
+                }
+
                 // if we are tagging, we will consume arrow presses and enter key
                 let enter_key;
                 (app.draft_data.tagging_search_selected, enter_key) =
@@ -678,6 +689,22 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     )));
                 }
 
+                if app.draft_data.protected {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Unprotect",
+                        Box::new(|_, app| {
+                            app.draft_data.protected = false;
+                        }),
+                    )));
+                } else if app.draft_data.repost.is_none() {
+                    items.push(MoreMenuItem::Button(MoreMenuButton::new(
+                        "Protect",
+                        Box::new(|_, app| {
+                            app.draft_data.protected = true;
+                        }),
+                    )));
+                }
+
                 items.push(MoreMenuItem::Button(
                     MoreMenuButton::new(
                         "Show raw preview",
@@ -761,13 +788,13 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                 if app.draft_data.repost.is_none() {
                     // Emoji picker
                     ui.menu_button(RichText::new("😀▼").size(14.0), |ui| {
-                        if let Some(emoji) = crate::ui::emojis::emoji_picker(ui) {
-                            app.draft_data.draft.push(emoji);
+                        if let Some(emoji) = crate::ui::emojis::emoji_picker(app, ui) {
+                            app.draft_data.draft.push_str(&emoji);
                         }
                     });
                 }
 
-                offer_attachment(app, ctx, ui, false);
+                offer_attachment(app, ctx, ui, None);
             });
         } else {
             // raw preview
@@ -798,12 +825,27 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
         if app.draft_data.include_content_warning {
             tags.push(Tag::new_content_warning(&app.draft_data.content_warning));
         }
+        if app.draft_data.protected {
+            tags.push(Tag::new(&["-"]));
+        }
         if let Some(delegatee_tag) = GLOBALS.delegation.get_delegatee_tag() {
             tags.push(delegatee_tag);
         }
         if app.draft_data.include_subject {
             tags.push(Tag::new_subject(app.draft_data.subject.clone()));
         }
+        // In a public chat channel we post chat messages, in a relay-based group we post
+        // to the group, and in a moderated community we submit posts to the community
+        let destination = match &app.page {
+            Page::Feed(FeedKind::Channel(id)) => PostDestination::Channel(*id),
+            Page::Feed(FeedKind::Group(relay, id)) => {
+                PostDestination::Group(relay.clone(), id.clone())
+            }
+            Page::Feed(FeedKind::Community(author, d, _)) => {
+                PostDestination::Community(*author, d.clone())
+            }
+            _ => PostDestination::Note,
+        };
         match app.draft_data.replying_to {
             Some(replying_to_id) => {
                 let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Post {
@@ -811,7 +853,7 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                     tags,
                     in_reply_to: Some(replying_to_id),
                     annotation: app.draft_data.is_annotate,
-                    dm_channel: None,
+                    destination,
                 });
             }
             None => {
@@ -825,7 +867,7 @@ fn real_posting_area(app: &mut GossipUi, ctx: &Context, ui: &mut Ui) {
                         tags,
                         in_reply_to: None,
                         annotation: app.draft_data.is_annotate,
-                        dm_channel: None,
+                        destination,
                     });
                 }
             }
@@ -1067,13 +1109,21 @@ fn do_replacements(draft: &str, replacements: &HashMap<String, ContentSegment>)
     output
 }
 
-fn offer_attachment(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, dm: bool) {
-    // Skip if no blossom servers configured:
-    let blossom_servers = GLOBALS.db().read_setting_blossom_servers();
-    if blossom_servers.split_whitespace().next().is_none() {
+fn offer_attachment(
+    app: &mut GossipUi,
+    ctx: &Context,
+    ui: &mut Ui,
+    dm_channel: Option<&DmChannel>,
+) {
+    // Skip if no blossom or NIP-96 servers configured:
+    if gossip_lib::upload::upload_server().is_none() {
         return;
     }
 
+    // Files in NIP-17 DMs are encrypted and sent in file messages of their own
+    let dm = dm_channel.is_some();
+    let file_message_channel = dm_channel.filter(|c| c.can_use_nip17());
+
     let mut clear_uploading: bool = false;
     let mut clear_upload: bool = false;
 
@@ -1082,7 +1132,9 @@ fn offer_attachment(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, dm: bool) {
         if let Some(result) = GLOBALS.blossom_uploads.get(pathbuf) {
             match result.value() {
                 Ok(bd) => {
-                    if dm {
+                    if file_message_channel.is_some() {
+                        // Already sent
+                    } else if dm {
                         app.dm_draft_data.draft.push(' ');
                         app.dm_draft_data.draft.push_str(&bd.url);
                         if bd.url.len() > 5 && !bd.url[bd.url.len() - 5..].contains('.') {
@@ -1129,8 +1181,10 @@ fn offer_attachment(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, dm: bool) {
     app.file_dialog.update(ctx);
     if let Some(pathbuf) = app.file_dialog.take_selected() {
         app.uploading = Some(pathbuf.clone());
-        let _ = GLOBALS
-            .to_overlord
-            .send(ToOverlordMessage::BlossomUpload(pathbuf));
+        let message = match file_message_channel {
+            Some(channel) => ToOverlordMessage::SendFileMessage(pathbuf, channel.to_owned()),
+            None => ToOverlordMessage::BlossomUpload(pathbuf, dm),
+        };
+        let _ = GLOBALS.to_overlord.send(message);
     }
 }
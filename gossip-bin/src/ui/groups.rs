@@ -0,0 +1,187 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui::{self, RichText};
+use egui::{Context, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{FeedKind, Group1, GroupMetadata, GroupsTable, Table, GLOBALS};
+use nostr_types::RelayUrl;
+use std::time::{Duration, Instant};
+
+/// The groups we have joined and the relay we are browsing for groups. These are
+/// refreshed every few seconds as the sidebar lists the joined groups on every frame.
+pub struct GroupsUi {
+    relay: String,
+    joined: Vec<Group1>,
+    found: Vec<GroupMetadata>,
+    found_on: Option<RelayUrl>,
+    next_refresh: Instant,
+}
+
+impl Default for GroupsUi {
+    fn default() -> Self {
+        Self {
+            relay: String::new(),
+            joined: Vec::new(),
+            found: Vec::new(),
+            found_on: None,
+            next_refresh: Instant::now(),
+        }
+    }
+}
+
+impl GroupsUi {
+    /// The groups we have joined, sorted by name
+    pub(super) fn joined(&mut self) -> &[Group1] {
+        self.refresh();
+        &self.joined
+    }
+
+    pub(super) fn is_joined(&mut self, relay: &RelayUrl, id: &str) -> bool {
+        self.joined()
+            .iter()
+            .any(|g| &g.relay == relay && g.id == id)
+    }
+
+    /// Pick up the results of a join, leave or discover soon
+    pub(super) fn refresh_soon(&mut self) {
+        self.next_refresh = Instant::now() + Duration::new(2, 0);
+    }
+
+    fn refresh(&mut self) {
+        if self.next_refresh < Instant::now() {
+            self.joined = GroupsTable::filter_records(|_| true).unwrap_or_default();
+            self.joined.sort_by_key(|g| g.name.to_lowercase());
+            self.found = match &self.found_on {
+                Some(url) => GroupMetadata::load_on_relay(url).unwrap_or_default(),
+                None => Vec::new(),
+            };
+            self.next_refresh = Instant::now() + Duration::new(5, 0);
+        }
+    }
+
+    fn browse(&mut self, relay_url: Option<RelayUrl>) {
+        if self.found_on != relay_url {
+            self.found_on = relay_url;
+            self.next_refresh = Instant::now();
+        }
+    }
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    widgets::page_header(ui, Page::Groups.name(), |_ui| {});
+
+    let can_join = GLOBALS.identity.is_unlocked();
+
+    let relay_url = RelayUrl::try_from_str(app.groups.relay.trim()).ok();
+    app.groups.browse(relay_url.clone());
+
+    let joined = app.groups.joined().to_vec();
+
+    ui.heading("My Groups");
+    if joined.is_empty() {
+        ui.label("You have not joined any groups yet.");
+    }
+    for group in joined.iter() {
+        ui.horizontal(|ui| {
+            if ui.link(RichText::new(&group.name).strong()).clicked() {
+                app.set_page(
+                    ctx,
+                    Page::Feed(FeedKind::Group(group.relay.clone(), group.id.clone())),
+                );
+            }
+            ui.label(RichText::new(group.relay.as_str()).weak());
+            if can_join
+                && widgets::Button::bordered(&app.theme, "Leave")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+            {
+                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::LeaveGroup(
+                    group.relay.clone(),
+                    group.id.clone(),
+                ));
+                app.groups.refresh_soon();
+            }
+        });
+    }
+
+    ui.add_space(20.0);
+    ui.heading("Find Groups");
+    ui.horizontal(|ui| {
+        ui.label("Relay:");
+        ui.add(text_edit_line!(app, app.groups.relay).hint_text("wss://groups.example.com"));
+        ui.add_enabled_ui(relay_url.is_some(), |ui| {
+            if widgets::Button::primary(&app.theme, "Discover")
+                .small(true)
+                .show(ui)
+                .on_hover_text("Ask this relay for the groups it hosts")
+                .clicked()
+            {
+                if let Some(url) = &relay_url {
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::DiscoverGroups(url.to_owned()));
+                }
+                app.groups.refresh_soon();
+            }
+        });
+    });
+
+    if relay_url.is_none() {
+        return;
+    }
+
+    let mut groups = app.groups.found.clone();
+    if groups.is_empty() {
+        ui.label("No groups known on this relay. Press Discover to ask it.");
+        return;
+    }
+
+    app.vert_scroll_area()
+        .id_source("groups_scroll")
+        .show(ui, |ui| {
+            for group in groups.drain(..) {
+                let is_joined = joined
+                    .iter()
+                    .any(|g| g.relay == group.relay && g.id == group.id);
+                ui.horizontal(|ui| {
+                    if ui
+                        .link(RichText::new(group.display_name()).heading())
+                        .clicked()
+                    {
+                        app.set_page(
+                            ctx,
+                            Page::Feed(FeedKind::Group(group.relay.clone(), group.id.clone())),
+                        );
+                    }
+                    if !group.public {
+                        ui.label(RichText::new("private").weak());
+                    }
+                    if !group.open {
+                        ui.label(RichText::new("closed").weak());
+                    }
+                    if can_join && !is_joined {
+                        let label = if group.open {
+                            "Join"
+                        } else {
+                            "Request to Join"
+                        };
+                        if widgets::Button::primary(&app.theme, label)
+                            .small(true)
+                            .show(ui)
+                            .clicked()
+                        {
+                            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::JoinGroup(
+                                group.relay.clone(),
+                                group.id.clone(),
+                            ));
+                            app.groups.refresh_soon();
+                        }
+                    }
+                });
+                if !group.about.is_empty() {
+                    ui.label(&group.about);
+                }
+                ui.add_space(10.0);
+            }
+        });
+}
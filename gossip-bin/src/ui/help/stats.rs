@@ -1,7 +1,9 @@
 use super::GossipUi;
 use eframe::egui;
 use egui::{Context, Ui};
-use gossip_lib::{FollowingsTable, HandlersTable, PersonTable, Table, GLOBALS};
+use gossip_lib::{
+    CustomFeedsTable, FollowingsTable, GroupsTable, HandlersTable, PersonTable, Table, GLOBALS,
+};
 use humansize::{format_size, DECIMAL};
 use std::sync::atomic::Ordering;
 
@@ -165,5 +167,17 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
             GLOBALS.db().get_configured_handlers_len().unwrap_or(0)
         ));
         ui.add_space(6.0);
+
+        ui.label(format!(
+            "Custom Feeds: {} records",
+            CustomFeedsTable::num_records().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
+
+        ui.label(format!(
+            "Joined Groups: {} records",
+            GroupsTable::num_records().unwrap_or(0)
+        ));
+        ui.add_space(6.0);
     });
 }
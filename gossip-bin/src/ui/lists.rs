@@ -0,0 +1,420 @@
+use super::{widgets, GossipUi, Page};
+use eframe::egui::{self, RichText};
+use egui::{Context, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{nip51_kind_name, FeedKind, Nip51List, GLOBALS, NIP51_LIST_KINDS};
+use nostr_types::{EventKind, Id, NAddr, NostrBech32, NostrUrl, RelayUrl, Tag};
+use std::time::{Duration, Instant};
+
+const CACHE_DURATION: Duration = Duration::from_secs(3);
+
+pub struct Lists {
+    /// The kind of list or set being viewed
+    kind: EventKind,
+    /// The 'd' tag of the set being viewed (empty for lists that are not sets)
+    selected: Option<String>,
+    /// Entry text for a new entry
+    new_entry: String,
+    /// Whether the new entry will be private
+    new_entry_private: bool,
+    /// Entry text for the name of a new set
+    new_set: String,
+    /// Any errors while entering
+    err: Option<String>,
+    /// Cache of our pins, bookmark sets, interests and public chats, for the note menus,
+    /// sidebar and channels page
+    cache: ListsCache,
+}
+
+impl Default for Lists {
+    fn default() -> Self {
+        Self {
+            kind: EventKind::PinList,
+            selected: None,
+            new_entry: "".to_owned(),
+            new_entry_private: false,
+            new_set: "".to_owned(),
+            err: None,
+            cache: Default::default(),
+        }
+    }
+}
+
+/// The note menus are rendered for every note every frame, so we don't load (and decrypt)
+/// our lists for each of them, we cache them for a short while.
+#[derive(Default)]
+struct ListsCache {
+    loaded: Option<Instant>,
+    pins: Vec<Id>,
+    bookmark_sets: Vec<(String, String)>, // (d, name)
+    interests: Vec<String>,
+    public_chats: Vec<Id>,
+    communities: Vec<(NAddr, String)>, // (naddr, name)
+}
+
+impl Lists {
+    fn refresh_cache(&mut self) {
+        if let Some(loaded) = self.cache.loaded {
+            if loaded.elapsed() < CACHE_DURATION {
+                return;
+            }
+        }
+
+        self.cache.pins = Nip51List::load_mine(EventKind::PinList, "")
+            .map(|list| list.referenced_ids())
+            .unwrap_or_default();
+        self.cache.bookmark_sets = match GLOBALS.identity.public_key() {
+            Some(pk) => Nip51List::load_all(EventKind::BookmarkSets, pk)
+                .unwrap_or_default()
+                .iter()
+                .map(|list| (list.d.clone(), list.name()))
+                .collect(),
+            None => vec![],
+        };
+        self.cache.interests = Nip51List::load_mine(EventKind::InterestsList, "")
+            .map(|list| list.values("t"))
+            .unwrap_or_default();
+        self.cache.public_chats = Nip51List::load_mine(EventKind::PublicChatsList, "")
+            .map(|list| list.referenced_ids())
+            .unwrap_or_default();
+        self.cache.communities = gossip_lib::community::followed_communities()
+            .into_iter()
+            .map(|naddr| {
+                let name = match gossip_lib::Community::load(naddr.author, &naddr.d) {
+                    Ok(Some(community)) => community.display_name(),
+                    _ => naddr.d.clone(),
+                };
+                (naddr, name)
+            })
+            .collect();
+        self.cache.loaded = Some(Instant::now());
+    }
+
+    /// Our pinned notes
+    pub(super) fn my_pins(&mut self) -> &[Id] {
+        self.refresh_cache();
+        &self.cache.pins
+    }
+
+    /// Our bookmark sets, as ('d' tag, name)
+    pub(super) fn my_bookmark_sets(&mut self) -> &[(String, String)] {
+        self.refresh_cache();
+        &self.cache.bookmark_sets
+    }
+
+    /// The hashtags we follow
+    pub(super) fn my_interests(&mut self) -> &[String] {
+        self.refresh_cache();
+        &self.cache.interests
+    }
+
+    /// The public chat channels we joined
+    pub(super) fn my_public_chats(&mut self) -> &[Id] {
+        self.refresh_cache();
+        &self.cache.public_chats
+    }
+
+    /// The communities we follow, as (naddr, name)
+    pub(super) fn my_communities(&mut self) -> &[(NAddr, String)] {
+        self.refresh_cache();
+        &self.cache.communities
+    }
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    widgets::page_header(ui, Page::Nip51Lists.name(), |_ui| {});
+
+    let public_key = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => {
+            ui.label("You need to setup an identity to have lists.");
+            return;
+        }
+    };
+
+    ui.horizontal_wrapped(|ui| {
+        for kind in NIP51_LIST_KINDS {
+            if ui
+                .selectable_label(app.lists.kind == kind, nip51_kind_name(kind))
+                .clicked()
+            {
+                app.lists.kind = kind;
+                app.lists.selected = None;
+                app.lists.err = None;
+            }
+        }
+    });
+    ui.add_space(10.0);
+
+    let kind = app.lists.kind;
+
+    if kind.is_parameterized_replaceable() {
+        let sets = Nip51List::load_all(kind, public_key).unwrap_or_default();
+        ui.horizontal_wrapped(|ui| {
+            for set in sets.iter() {
+                if ui
+                    .selectable_label(app.lists.selected.as_ref() == Some(&set.d), set.name())
+                    .clicked()
+                {
+                    app.lists.selected = Some(set.d.clone());
+                    app.lists.err = None;
+                }
+            }
+        });
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.add(
+                text_edit_line!(app, app.lists.new_set)
+                    .hint_text("Name of a new set")
+                    .desired_width(200.0),
+            );
+            if widgets::Button::primary(&app.theme, "Create")
+                .small(true)
+                .show(ui)
+                .clicked()
+                && !app.lists.new_set.trim().is_empty()
+            {
+                let name = app.lists.new_set.trim().to_owned();
+                let mut list = Nip51List::new(kind, name.clone());
+                list.title = name.clone();
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::Nip51ListSave(list));
+                app.lists.selected = Some(name);
+                app.lists.new_set = "".to_owned();
+            }
+        });
+        ui.add_space(10.0);
+        ui.separator();
+    } else {
+        app.lists.selected = Some("".to_owned());
+    }
+
+    let d = match app.lists.selected.clone() {
+        Some(d) => d,
+        None => return,
+    };
+
+    let list = match Nip51List::load_mine(kind, &d) {
+        Ok(list) => list,
+        Err(e) => {
+            ui.label(format!("{}", e));
+            return;
+        }
+    };
+
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.heading(list.name());
+        match kind {
+            EventKind::RelaySets => {
+                if widgets::Button::bordered(&app.theme, "View Feed")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    app.set_page(ctx, Page::Feed(FeedKind::RelaySet(d.clone())));
+                }
+            }
+            EventKind::BookmarkSets => {
+                if widgets::Button::bordered(&app.theme, "View Feed")
+                    .small(true)
+                    .show(ui)
+                    .clicked()
+                {
+                    app.set_page(ctx, Page::Feed(FeedKind::BookmarkSet(d.clone())));
+                }
+            }
+            _ => {}
+        }
+        if list.is_set()
+            && widgets::Button::bordered(&app.theme, "Delete Set")
+                .small(true)
+                .show(ui)
+                .clicked()
+        {
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::Nip51ListDelete(kind, d.clone()));
+            app.lists.selected = None;
+        }
+    });
+    if !list.description.is_empty() {
+        ui.label(&list.description);
+    }
+    ui.add_space(10.0);
+
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            text_edit_line!(app, app.lists.new_entry)
+                .hint_text(entry_hint(kind))
+                .desired_width(400.0),
+        );
+        ui.checkbox(&mut app.lists.new_entry_private, "Private");
+        if widgets::Button::primary(&app.theme, "Add")
+            .small(true)
+            .show(ui)
+            .clicked()
+            || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+        {
+            match parse_entry(kind, app.lists.new_entry.trim()) {
+                Some(tag) => {
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListAdd(
+                        kind,
+                        d.clone(),
+                        tag,
+                        app.lists.new_entry_private,
+                    ));
+                    app.lists.new_entry = "".to_owned();
+                    app.lists.err = None;
+                }
+                None => app.lists.err = Some(format!("Expected {}", entry_hint(kind))),
+            }
+        }
+    });
+    if let Some(err) = &app.lists.err {
+        ui.label(RichText::new(err).color(app.theme.warning_marker_text_color()));
+    }
+    ui.add_space(10.0);
+
+    app.vert_scroll_area()
+        .id_source("nip51_list_scroll")
+        .show(ui, |ui| {
+            for (tag, private) in list.entries.iter() {
+                ui.horizontal(|ui| {
+                    if widgets::Button::bordered(&app.theme, "Remove")
+                        .small(true)
+                        .show(ui)
+                        .clicked()
+                    {
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::Nip51ListRm(
+                            kind,
+                            d.clone(),
+                            tag.tagname().to_owned(),
+                            tag.value().to_owned(),
+                        ));
+                    }
+                    ui.label(RichText::new(tag.tagname()).weak());
+                    if tag.tagname() == "t" {
+                        if ui.link(describe_entry(tag)).clicked() {
+                            app.set_page(
+                                ctx,
+                                Page::Feed(FeedKind::Hashtag(tag.value().to_lowercase())),
+                            );
+                        }
+                    } else if kind == EventKind::PublicChatsList && tag.tagname() == "e" {
+                        if ui.link(describe_entry(tag)).clicked() {
+                            if let Ok((id, _, _, _)) = tag.parse_event() {
+                                app.set_page(ctx, Page::Feed(FeedKind::Channel(id)));
+                            }
+                        }
+                    } else if kind == EventKind::CommunityList && tag.tagname() == "a" {
+                        if ui.link(describe_entry(tag)).clicked() {
+                            if let Ok((ea, _)) = tag.parse_address() {
+                                app.set_page(
+                                    ctx,
+                                    Page::Feed(FeedKind::Community(ea.author, ea.d, false)),
+                                );
+                            }
+                        }
+                    } else {
+                        ui.label(describe_entry(tag));
+                    }
+                    if *private {
+                        ui.label(RichText::new("private").italics().weak());
+                    }
+                });
+            }
+        });
+}
+
+fn entry_hint(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::PinList | EventKind::BookmarkSets => "note1..., nevent1... or naddr1...",
+        EventKind::PublicChatsList => "note1... or nevent1... of a channel",
+        EventKind::CommunityList => "naddr1... of a community",
+        EventKind::InterestsList | EventKind::InterestSets => "#hashtag",
+        EventKind::RelaySets => "wss://relay.example.com",
+        EventKind::UserEmojiList => "naddr1... of an emoji set, or: shortcode https://image",
+        EventKind::EmojiSets => "shortcode https://image",
+        _ => "",
+    }
+}
+
+/// Turn what the user typed into a list entry tag, as appropriate for the kind of list
+fn parse_entry(kind: EventKind, input: &str) -> Option<Tag> {
+    let mut input = input.to_owned();
+    if let Some(nurl) = NostrUrl::try_from_string(&input) {
+        input = format!("{}", nurl.0);
+    }
+
+    match kind {
+        EventKind::PinList | EventKind::BookmarkSets => {
+            match NostrBech32::try_from_string(&input)? {
+                NostrBech32::Id(id) => Some(Tag::new_event(id, None, None, None)),
+                NostrBech32::NEvent(ne) => Some(Tag::new_event(
+                    ne.id,
+                    ne.relays.first().map(|r| r.to_unchecked_url()),
+                    None,
+                    ne.author,
+                )),
+                NostrBech32::NAddr(ea) => Some(Tag::new_address(&ea, None)),
+                _ => None,
+            }
+        }
+        EventKind::PublicChatsList => match NostrBech32::try_from_string(&input)? {
+            NostrBech32::Id(id) => Some(Tag::new_event(id, None, None, None)),
+            NostrBech32::NEvent(ne) => Some(Tag::new_event(
+                ne.id,
+                ne.relays.first().map(|r| r.to_unchecked_url()),
+                None,
+                ne.author,
+            )),
+            _ => None,
+        },
+        EventKind::CommunityList => match NostrBech32::try_from_string(&input)? {
+            NostrBech32::NAddr(ea) if ea.kind == EventKind::CommunityDefinition => {
+                Some(Tag::new_address(&ea, None))
+            }
+            _ => None,
+        },
+        EventKind::InterestsList | EventKind::InterestSets => {
+            let hashtag = input.trim_start_matches('#').to_lowercase();
+            if hashtag.is_empty() || hashtag.contains(char::is_whitespace) {
+                None
+            } else {
+                Some(Tag::new_hashtag(hashtag))
+            }
+        }
+        EventKind::RelaySets => {
+            let url = RelayUrl::try_from_str(&input).ok()?;
+            Some(Tag::new(&["relay", url.as_str()]))
+        }
+        EventKind::UserEmojiList | EventKind::EmojiSets => {
+            if kind == EventKind::UserEmojiList {
+                if let Some(NostrBech32::NAddr(ea)) = NostrBech32::try_from_string(&input) {
+                    return Some(Tag::new_address(&ea, None));
+                }
+            }
+            let (shortcode, url) = input.split_once(char::is_whitespace)?;
+            let shortcode = shortcode.trim_matches(':');
+            let url = url.trim();
+            if shortcode.is_empty() || !url.starts_with("http") {
+                return None;
+            }
+            Some(Tag::new(&["emoji", shortcode, url]))
+        }
+        _ => None,
+    }
+}
+
+fn describe_entry(tag: &Tag) -> String {
+    match tag.tagname() {
+        "e" => match tag.parse_event() {
+            Ok((id, _, _, _)) => gossip_lib::names::hex_id_short(&id.into()),
+            Err(_) => tag.value().to_owned(),
+        },
+        "emoji" => format!(":{}: {}", tag.value(), tag.get_index(2)),
+        _ => tag.value().to_owned(),
+    }
+}
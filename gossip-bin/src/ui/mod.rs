@@ -34,11 +34,17 @@ macro_rules! write_setting {
 }
 
 mod assets;
+mod chat_channels;
+mod communities;
+mod custom_feeds;
 mod dm_chat_list;
+mod dvms;
 mod emojis;
 mod feed;
+mod groups;
 mod handler;
 mod help;
+mod lists;
 mod notifications;
 mod people;
 mod relays;
@@ -52,8 +58,12 @@ mod you;
 use crate::about::About;
 pub use crate::ui::theme::{Theme, ThemeVariant};
 use crate::unsaved_settings::UnsavedSettings;
+use chat_channels::ChatChannelsUi;
+use communities::CommunitiesUi;
 #[cfg(feature = "video-ffmpeg")]
 use core::cell::RefCell;
+use custom_feeds::CustomFeedsUi;
+use dvms::DvmsUi;
 use eframe::egui;
 use eframe::egui::vec2;
 use eframe::egui::FontId;
@@ -68,11 +78,14 @@ use egui_winit::egui::Rect;
 use egui_winit::egui::Response;
 use egui_winit::egui::ViewportBuilder;
 use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::emoji::EmojiSpan;
 use gossip_lib::{
-    DmChannel, DmChannelData, Error, FeedKind, MediaLoadingResult, Person, PersonList, Private,
-    RunState, ZapState, GLOBALS,
+    BadgeDefinition, CacheUsage, DmChannel, DmChannelData, Error, FeedKind, IdentityClaim,
+    MediaLoadingResult, Nip51List, Person, PersonList, Private, RunState, Table, ZapState, GLOBALS,
 };
+use groups::GroupsUi;
 use handler::Handlers;
+use lists::Lists;
 use nostr_types::ContentSegment;
 use nostr_types::RelayUrl;
 use nostr_types::{
@@ -160,20 +173,29 @@ pub fn run() -> Result<(), Error> {
 
 #[derive(Debug, Clone, PartialEq)]
 enum Page {
+    ChatChannels,
+    Communities,
+    CustomFeeds,
     DmChatList,
+    Dvms,
     Feed(FeedKind),
+    Groups,
     HandlerKinds,
     Handlers(EventKind),
+    Nip51Lists,
     Notifications,
     PeopleLists,
     PeopleList(PersonList),
     Person(PublicKey),
     PersonFollows(PublicKey),
     PersonFollowers(PublicKey),
+    PersonMedia(PublicKey),
     YourKeys,
     YourMetadata,
     YourDelegation,
     YourNostrConnect,
+    YourBadges,
+    YourMedia,
     RelaysActivityMonitor,
     RelaysCoverage,
     RelaysMine,
@@ -203,10 +225,16 @@ impl Page {
 impl Page {
     pub fn to_readable(&self) -> (&'static str /* Category */, String /* Name */) {
         match self {
+            Page::ChatChannels => (SubMenu::Feeds.as_str(), "Public Chats".into()),
+            Page::Communities => (SubMenu::Feeds.as_str(), "Communities".into()),
+            Page::CustomFeeds => (SubMenu::Feeds.as_str(), "Custom Feeds".into()),
             Page::DmChatList => (SubMenu::Feeds.as_str(), "Private chats".into()),
+            Page::Dvms => (SubMenu::Feeds.as_str(), "DVM Feeds".into()),
             Page::Feed(feedkind) => ("Feed", feedkind.to_string()),
+            Page::Groups => (SubMenu::Feeds.as_str(), "Groups".into()),
             Page::HandlerKinds => ("Event Handlers", "Event Handlers".into()),
             Page::Handlers(kind) => ("Event Handler", format!("{:?}", kind)),
+            Page::Nip51Lists => ("Lists", "Pins & Sets".into()),
             Page::Notifications => ("Notifications", "Notifications".into()),
             Page::PeopleLists => ("Lists", "Lists".into()),
             Page::PeopleList(list) => {
@@ -229,10 +257,16 @@ impl Page {
                 let name = gossip_lib::names::best_name_from_pubkey_lookup(pk);
                 ("Followers", name)
             }
+            Page::PersonMedia(pk) => {
+                let name = gossip_lib::names::best_name_from_pubkey_lookup(pk);
+                ("Media", name)
+            }
             Page::YourKeys => (SubMenu::Account.as_str(), "Keys".into()),
             Page::YourMetadata => (SubMenu::Account.as_str(), "Profile".into()),
             Page::YourDelegation => (SubMenu::Account.as_str(), "Delegation".into()),
             Page::YourNostrConnect => (SubMenu::Account.as_str(), "Nostr Connect".into()),
+            Page::YourBadges => (SubMenu::Account.as_str(), "Badges".into()),
+            Page::YourMedia => (SubMenu::Account.as_str(), "Media".into()),
             Page::RelaysActivityMonitor => (SubMenu::Relays.as_str(), "Active Relays".into()),
             Page::RelaysCoverage => (SubMenu::Relays.as_str(), "Coverage Report".into()),
             Page::RelaysMine => (SubMenu::Relays.as_str(), "My Relays".into()),
@@ -275,9 +309,13 @@ impl Page {
             Page::Person(_) => name_cat(self),
             Page::PersonFollows(_) => name_cat(self),
             Page::PersonFollowers(_) => name_cat(self),
-            Page::YourKeys | Page::YourMetadata | Page::YourDelegation | Page::YourNostrConnect => {
-                cat_name(self)
-            }
+            Page::PersonMedia(_) => name_cat(self),
+            Page::YourKeys
+            | Page::YourMetadata
+            | Page::YourDelegation
+            | Page::YourNostrConnect
+            | Page::YourBadges
+            | Page::YourMedia => cat_name(self),
             Page::Wizard(_) => name_cat(self),
             _ => name(self),
         }
@@ -361,6 +399,9 @@ pub struct DraftData {
     pub include_content_warning: bool,
     pub content_warning: String,
 
+    // If relays should only accept this from us (NIP-70)
+    pub protected: bool,
+
     // Data for normal draft
     pub repost: Option<Id>,
     pub replying_to: Option<Id>,
@@ -392,6 +433,7 @@ impl Default for DraftData {
             subject: "".to_owned(),
             include_content_warning: false,
             content_warning: "".to_owned(),
+            protected: false,
 
             // The following are ignored for DMs
             repost: None,
@@ -422,6 +464,7 @@ impl DraftData {
         self.subject = "".to_owned();
         self.include_content_warning = false;
         self.content_warning = "".to_owned();
+        self.protected = false;
         self.repost = None;
         self.replying_to = None;
         self.are_you_sure_cancel = false;
@@ -475,9 +518,42 @@ struct GossipUi {
     // people::ListUi
     people_list: people::ListUi,
 
+    // people::GalleryUi
+    gallery: people::GalleryUi,
+
+    // External identities and profile badges of the person being viewed, loaded on
+    // entering their page
+    person_claims: Vec<IdentityClaim>,
+    person_badges: Vec<(BadgeDefinition, Id)>,
+
+    // Media cache usage, for the cache inspector
+    cache_usage: Option<CacheUsage>,
+
     // Handlers Ui
     handlers: Handlers,
 
+    // NIP-51 Lists Ui
+    lists: Lists,
+
+    // Custom feeds Ui
+    custom_feeds: CustomFeedsUi,
+
+    // Public chat channels Ui
+    chat_channels: ChatChannelsUi,
+
+    // Relay-based groups Ui
+    groups: GroupsUi,
+
+    // Moderated communities Ui
+    communities: CommunitiesUi,
+
+    // Content discovery DVMs Ui
+    dvms: DvmsUi,
+
+    // Badges Ui
+    badges: you::BadgesUi,
+    media: you::MediaUi,
+
     // Post rendering
     render_raw: Option<(Id, String)>,
     render_qr: Option<Id>,
@@ -516,6 +592,8 @@ struct GossipUi {
     media_hide_list: HashSet<Url>,
     /// media that the user has selected to show full-width
     media_full_width_list: HashSet<Url>,
+    /// encrypted files the user has saved, and where
+    saved_files: HashMap<Url, PathBuf>,
 
     // User entry: posts
     show_post_area: bool,
@@ -529,6 +607,10 @@ struct GossipUi {
     editing_metadata: bool,
     metadata: Metadata,
 
+    // User entry: status, and how many seconds until it expires (0 for never)
+    status: String,
+    status_expiry: i64,
+
     // User entry: delegatee tag (as JSON string)
     delegatee_tag_str: String,
 
@@ -583,6 +665,18 @@ struct GossipUi {
     dm_channel_cache: Vec<DmChannelData>,
     dm_channel_next_refresh: Instant,
     dm_channel_error: Option<String>,
+    dm_show_archived: bool,
+
+    // DM search
+    dm_searching: bool,
+    dm_search_text: String,
+    dm_search_participant: String,
+    dm_search_since: String,
+    dm_search_until: String,
+
+    // DM conversation editing
+    dm_rename: String,
+    dm_add_member: String,
 
     file_dialog: FileDialog,
     uploading: Option<PathBuf>,
@@ -738,7 +832,19 @@ impl GossipUi {
             notification_data: NotificationData::new(),
             relays: relays::RelayUi::new(),
             people_list: people::ListUi::new(),
+            gallery: Default::default(),
+            person_claims: Vec::new(),
+            person_badges: Vec::new(),
+            cache_usage: None,
             handlers: Default::default(),
+            lists: Default::default(),
+            custom_feeds: Default::default(),
+            chat_channels: Default::default(),
+            groups: Default::default(),
+            communities: Default::default(),
+            dvms: Default::default(),
+            badges: Default::default(),
+            media: Default::default(),
             render_raw: None,
             render_qr: None,
             approved: HashSet::new(),
@@ -766,6 +872,7 @@ impl GossipUi {
             media_show_list: HashSet::new(),
             media_hide_list: HashSet::new(),
             media_full_width_list: HashSet::new(),
+            saved_files: HashMap::new(),
             show_post_area: false,
             draft_needs_focus: false,
             unlock_needs_focus: true,
@@ -774,6 +881,8 @@ impl GossipUi {
             dm_draft_data_target: None,
             editing_metadata: false,
             metadata: Metadata::new(),
+            status: "".to_owned(),
+            status_expiry: 0,
             delegatee_tag_str: "".to_owned(),
             add_contact: "".to_owned(),
             password: "".to_owned(),
@@ -809,6 +918,14 @@ impl GossipUi {
             dm_channel_cache: vec![],
             dm_channel_next_refresh: Instant::now(),
             dm_channel_error: None,
+            dm_show_archived: false,
+            dm_searching: false,
+            dm_search_text: String::new(),
+            dm_search_participant: String::new(),
+            dm_search_since: String::new(),
+            dm_search_until: String::new(),
+            dm_rename: String::new(),
+            dm_add_member: String::new(),
             file_dialog: FileDialog::new(),
             uploading: None,
         }
@@ -906,6 +1023,10 @@ impl GossipUi {
                     self.close_all_menus_except_feeds(ctx);
                 }
             }
+            Page::Dvms => {
+                self.dvms.enter_page();
+                self.close_all_menus_except_feeds(ctx);
+            }
             Page::PeopleLists => {
                 people::enter_page(self);
                 self.close_all_menus_except_feeds(ctx);
@@ -917,6 +1038,19 @@ impl GossipUi {
                 let _ = GLOBALS
                     .to_overlord
                     .send(ToOverlordMessage::UpdateMetadata(*pubkey));
+
+                // Fetch any of their pinned notes that we don't have yet
+                if let Ok(Some(list)) = Nip51List::load(EventKind::PinList, *pubkey, "") {
+                    for id in list.referenced_ids() {
+                        let _ = GLOBALS
+                            .to_overlord
+                            .send(ToOverlordMessage::FetchEvent(id, vec![]));
+                    }
+                }
+
+                // Load their external identities and badges
+                self.person_claims = gossip_lib::nip39::claims_of(*pubkey).unwrap_or_default();
+                self.person_badges = gossip_lib::badge::profile_badges(*pubkey).unwrap_or_default();
             }
             Page::PersonFollows(pubkey) => {
                 self.close_all_menus_except_feeds(ctx);
@@ -944,9 +1078,21 @@ impl GossipUi {
                         .send(ToOverlordMessage::TrackFollowers(*pubkey));
                 }
             }
+            Page::PersonMedia(pubkey) => {
+                self.close_all_menus_except_feeds(ctx);
+                self.gallery.load(*pubkey);
+            }
             Page::YourKeys | Page::YourMetadata | Page::YourDelegation | Page::YourNostrConnect => {
                 self.open_menu(ctx, SubMenu::Account);
             }
+            Page::YourBadges => {
+                self.badges.enter_page();
+                self.open_menu(ctx, SubMenu::Account);
+            }
+            Page::YourMedia => {
+                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::BlossomList);
+                self.open_menu(ctx, SubMenu::Account);
+            }
             Page::RelaysActivityMonitor | Page::RelaysCoverage | Page::RelaysMine => {
                 self.relays.enter_page(None);
                 self.open_menu(ctx, SubMenu::Relays);
@@ -1100,6 +1246,38 @@ impl GossipUi {
                     more += 1;
                 }
             }
+            for feed in self.custom_feeds.feeds().to_vec() {
+                self.add_menu_item_page(
+                    ui,
+                    Page::Feed(FeedKind::Custom(feed.name.clone())),
+                    Some(&feed.name),
+                    true,
+                );
+            }
+            for group in self.groups.joined().to_vec() {
+                self.add_menu_item_page(
+                    ui,
+                    Page::Feed(FeedKind::Group(group.relay.clone(), group.id.clone())),
+                    Some(&group.name),
+                    true,
+                );
+            }
+            for (naddr, name) in self.lists.my_communities().to_vec() {
+                self.add_menu_item_page(
+                    ui,
+                    Page::Feed(FeedKind::Community(naddr.author, naddr.d.clone(), false)),
+                    Some(&name),
+                    true,
+                );
+            }
+            for hashtag in self.lists.my_interests().to_vec() {
+                self.add_menu_item_page(
+                    ui,
+                    Page::Feed(FeedKind::Hashtag(hashtag.clone())),
+                    Some(&format!("#{}", hashtag)),
+                    true,
+                );
+            }
             if more != 0 {
                 self.add_menu_item_page(
                     ui,
@@ -1108,6 +1286,11 @@ impl GossipUi {
                     false, // do not highlight this entry
                 );
             }
+            self.add_menu_item_page(ui, Page::CustomFeeds, None, true);
+            self.add_menu_item_page(ui, Page::Dvms, None, true);
+            self.add_menu_item_page(ui, Page::ChatChannels, None, true);
+            self.add_menu_item_page(ui, Page::Groups, None, true);
+            self.add_menu_item_page(ui, Page::Communities, None, true);
         });
         self.after_openable_menu(ui, &cstate);
     }
@@ -1244,6 +1427,12 @@ impl GossipUi {
         {
             self.set_page(ctx, Page::PeopleLists);
         }
+        if self
+            .add_selected_label(ui, self.page == Page::Nip51Lists, "Pins & Sets")
+            .clicked()
+        {
+            self.set_page(ctx, Page::Nip51Lists);
+        }
     }
 
     fn add_relays_submenu(&mut self, ui: &mut Ui, ctx: &Context) {
@@ -1263,6 +1452,8 @@ impl GossipUi {
             self.add_menu_item_page(ui, Page::YourKeys, None, true);
             self.add_menu_item_page(ui, Page::YourDelegation, None, true);
             self.add_menu_item_page(ui, Page::YourNostrConnect, None, true);
+            self.add_menu_item_page(ui, Page::YourBadges, None, true);
+            self.add_menu_item_page(ui, Page::YourMedia, None, true);
         });
         self.after_openable_menu(ui, &cstate);
     }
@@ -1541,9 +1732,20 @@ impl GossipUi {
                 false
             };
 
+            // Custom emoji in the name are shown as images after the menu
+            let name = person.best_name();
+            let name_emojis = GLOBALS.people.get_name_emojis(person.pubkey);
+            let name_spans = gossip_lib::emoji::split_emojis(&name, &name_emojis);
+
             let tag_name_menu = {
                 let text = if !profile_page {
-                    person.best_name()
+                    name_spans
+                        .iter()
+                        .filter_map(|span| match span {
+                            EmojiSpan::Text(text) => Some(*text),
+                            EmojiSpan::Emoji(_) => None,
+                        })
+                        .collect::<String>()
                 } else {
                     "ACTIONS".to_string()
                 };
@@ -1613,6 +1815,15 @@ impl GossipUi {
                 }
             });
 
+            if !profile_page {
+                for span in name_spans.iter() {
+                    if let EmojiSpan::Emoji(emoji) = span {
+                        emojis::custom_emoji(app, ui, emoji, 16.0)
+                            .on_hover_text(emoji.as_content());
+                    }
+                }
+            }
+
             if person.petname.is_some() {
                 ui.label(RichText::new("†").color(app.theme.accent_complementary_color()))
                     .on_hover_text("trusted petname");
@@ -1642,6 +1853,18 @@ impl GossipUi {
                         },
                     );
                 }
+
+                for status in GLOBALS.people.get_statuses(person.pubkey) {
+                    let text = if status.d == gossip_lib::user_status::MUSIC {
+                        format!("♫ {}", status.content)
+                    } else {
+                        status.content.clone()
+                    };
+                    let response = ui.label(RichText::new(text).italics().small().weak());
+                    if let Some(url) = &status.url {
+                        response.on_hover_text(url);
+                    }
+                }
             }
         });
     }
@@ -2110,7 +2333,11 @@ impl GossipUi {
             return false;
         }
 
-        self.show_post_area || matches!(self.page, Page::Feed(FeedKind::DmChat(_)))
+        self.show_post_area
+            || matches!(
+                self.page,
+                Page::Feed(FeedKind::DmChat(_)) | Page::Feed(FeedKind::Channel(_))
+            )
     }
 
     #[inline]
@@ -2401,20 +2628,29 @@ impl eframe::App for GossipUi {
             .show(ctx, |ui| {
                 self.begin_ui(ui);
                 match self.page {
+                    Page::ChatChannels => chat_channels::update(self, ctx, frame, ui),
+                    Page::Communities => communities::update(self, ctx, frame, ui),
+                    Page::CustomFeeds => custom_feeds::update(self, ctx, frame, ui),
                     Page::DmChatList => dm_chat_list::update(self, ctx, frame, ui),
+                    Page::Dvms => dvms::update(self, ctx, frame, ui),
                     Page::Feed(_) => feed::update(self, ctx, ui),
+                    Page::Groups => groups::update(self, ctx, frame, ui),
                     Page::HandlerKinds => handler::update_all_kinds(self, ctx, ui),
                     Page::Handlers(kind) => handler::update_kind(self, ctx, ui, kind),
+                    Page::Nip51Lists => lists::update(self, ctx, frame, ui),
                     Page::Notifications => notifications::update(self, ui),
                     Page::PeopleLists
                     | Page::PeopleList(_)
                     | Page::Person(_)
                     | Page::PersonFollows(_)
-                    | Page::PersonFollowers(_) => people::update(self, ctx, frame, ui),
+                    | Page::PersonFollowers(_)
+                    | Page::PersonMedia(_) => people::update(self, ctx, frame, ui),
                     Page::YourKeys
                     | Page::YourMetadata
                     | Page::YourDelegation
-                    | Page::YourNostrConnect => you::update(self, ctx, frame, ui),
+                    | Page::YourNostrConnect
+                    | Page::YourBadges
+                    | Page::YourMedia => you::update(self, ctx, frame, ui),
                     Page::RelaysActivityMonitor
                     | Page::RelaysCoverage
                     | Page::RelaysMine
@@ -0,0 +1,130 @@
+use super::{GossipUi, Page};
+use eframe::egui;
+use egui::{vec2, Context, Image, RichText, Sense, Ui};
+use gossip_lib::{FeedKind, MediaLoadingResult, Person, PersonTable, PostedMedia, Table};
+use nostr_types::PublicKey;
+
+const CELL: f32 = 160.0;
+
+/// The media a person has posted
+#[derive(Default)]
+pub struct GalleryUi {
+    who: Option<PublicKey>,
+    media: Vec<PostedMedia>,
+}
+
+impl GalleryUi {
+    pub(in crate::ui) fn load(&mut self, pubkey: PublicKey) {
+        self.who = Some(pubkey);
+        self.media = match gossip_lib::nip71::posted_media(pubkey) {
+            Ok(media) => media,
+            Err(e) => {
+                tracing::error!("{}", e);
+                vec![]
+            }
+        };
+    }
+}
+
+pub(super) fn update(
+    app: &mut GossipUi,
+    ctx: &Context,
+    _frame: &mut eframe::Frame,
+    ui: &mut Ui,
+    pubkey: PublicKey,
+) {
+    let person = match PersonTable::read_record(pubkey, None) {
+        Ok(Some(p)) => p,
+        _ => Person::new(pubkey.to_owned()),
+    };
+
+    if app.gallery.who != Some(pubkey) {
+        app.gallery.load(pubkey);
+    }
+
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.add_space(10.0);
+        ui.label(
+            RichText::new(person.best_name())
+                .size(22.0)
+                .color(app.theme.accent_color()),
+        );
+        ui.add_space(10.0);
+        if ui.button("Refresh").clicked() {
+            app.gallery.load(pubkey);
+        }
+    });
+
+    ui.add_space(5.0);
+    ui.heading(format!("{} Media", app.gallery.media.len()));
+    ui.label(RichText::new("From the posts of theirs that we have").weak());
+    ui.add_space(10.0);
+
+    let show_media = read_setting!(show_media);
+    let media = app.gallery.media.clone();
+
+    app.vert_scroll_area()
+        .id_source("person_media_scroll")
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for item in media.iter() {
+                    let response = ui
+                        .allocate_ui(vec2(CELL, CELL), |ui| {
+                            ui.set_min_size(vec2(CELL, CELL));
+                            render_cell(app, ui, item, show_media);
+                        })
+                        .response
+                        .interact(Sense::click())
+                        .on_hover_text(&item.url);
+
+                    if response.clicked() {
+                        app.set_page(
+                            ctx,
+                            Page::Feed(FeedKind::Thread {
+                                id: item.id,
+                                referenced_by: item.id,
+                                author: Some(pubkey),
+                            }),
+                        );
+                    }
+                }
+            });
+        });
+}
+
+// A thumbnail of an image, the poster frame of a video, or else a symbol for the kind
+// of media
+fn render_cell(app: &mut GossipUi, ui: &mut Ui, item: &PostedMedia, show_media: bool) {
+    let picture = if item.is_image() {
+        Some(item.url.as_str())
+    } else {
+        item.poster.as_deref()
+    };
+
+    if show_media {
+        if let Some(url) = picture.and_then(|p| app.try_check_url(p)) {
+            if let MediaLoadingResult::Ready(texture) =
+                app.try_get_media(ui.ctx(), url, false, None)
+            {
+                ui.add(
+                    Image::new(&texture)
+                        .max_size(vec2(CELL, CELL))
+                        .maintain_aspect_ratio(true),
+                );
+                return;
+            }
+        }
+    }
+
+    let symbol = if item.is_video() {
+        "\u{25B6} Video"
+    } else if item.is_audio() {
+        "\u{266B} Audio"
+    } else {
+        "\u{1F5BC} Image"
+    };
+    ui.centered_and_justified(|ui| {
+        ui.label(RichText::new(symbol).size(18.0).weak());
+    });
+}
@@ -7,11 +7,13 @@ mod followers;
 mod follows;
 mod list;
 mod lists;
+mod media;
 mod person;
 
 pub(in crate::ui) use list::layout_list_title;
 pub(in crate::ui) use list::ListUi;
 pub(in crate::ui) use lists::sort_lists;
+pub(in crate::ui) use media::GalleryUi;
 
 pub(super) fn enter_page(app: &mut GossipUi) {
     if app.page == Page::PeopleLists {
@@ -30,6 +32,7 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         Page::Person(_) => person::update(app, ctx, _frame, ui),
         Page::PersonFollows(who) => follows::update(app, ctx, _frame, ui, who),
         Page::PersonFollowers(who) => followers::update(app, ctx, _frame, ui, who),
+        Page::PersonMedia(who) => media::update(app, ctx, _frame, ui, who),
         _ => (),
     }
 }
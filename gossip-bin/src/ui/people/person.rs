@@ -12,10 +12,10 @@ use egui_winit::egui::Response;
 use egui_winit::egui::Widget;
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{
-    DmChannel, FeedKind, Freshness, People, Person, PersonList, PersonTable, Private, Table,
-    GLOBALS,
+    CountKind, DmChannel, FeedKind, Freshness, Nip51List, People, Person, PersonList, PersonTable,
+    Private, Table, GLOBALS,
 };
-use nostr_types::PublicKey;
+use nostr_types::{EventKind, PublicKey};
 use serde_json::Value;
 
 const ITEM_V_SPACE: f32 = 2.0;
@@ -110,6 +110,75 @@ fn content(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, pubkey: PublicKey, pe
                 );
             });
 
+            let statuses = GLOBALS.people.get_statuses(pubkey);
+            if !statuses.is_empty() {
+                ui.with_layout(layout, |ui| {
+                    for status in statuses.iter() {
+                        let label = format!("{} status", status.d);
+                        let content = match &status.url {
+                            Some(url) => format!("{} ({})", status.content, url),
+                            None => status.content.clone(),
+                        };
+                        profile_item(ui, app, lwidth, label, content);
+                    }
+                });
+            }
+
+            let claims = app.person_claims.clone();
+            if !claims.is_empty() {
+                make_frame().show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        item_label(ui, "External Identities");
+                        ui.add_space(ITEM_V_SPACE);
+                        for claim in claims.iter() {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label(format!("{}:", claim.platform));
+                                match gossip_lib::nip39::proof_url(claim) {
+                                    Some(url) => widgets::break_anywhere_hyperlink_to(
+                                        ui,
+                                        &claim.identity,
+                                        url,
+                                    ),
+                                    None => {
+                                        ui.label(&claim.identity);
+                                    }
+                                }
+                                if claim.valid {
+                                    ui.label(
+                                        RichText::new("✔ verified")
+                                            .color(app.theme.accent_color()),
+                                    );
+                                } else if !gossip_lib::nip39::can_verify(claim) {
+                                    ui.label(RichText::new("cannot be verified").weak())
+                                        .on_hover_text("Check the proof yourself with the link");
+                                } else if claim.last_checked.is_some() {
+                                    ui.label(RichText::new("✖ not verified").weak());
+                                } else {
+                                    ui.label(RichText::new("not checked yet").weak());
+                                }
+                            });
+                        }
+                    });
+                });
+            }
+
+            let badges = app.person_badges.clone();
+            if !badges.is_empty() {
+                make_frame().show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        item_label(ui, "Badges");
+                        ui.add_space(ITEM_V_SPACE);
+                        ui.horizontal_wrapped(|ui| {
+                            for (definition, _award) in badges.iter() {
+                                if widgets::badge(app, ui, definition, 48.0).clicked() {
+                                    app.set_page(ctx, Page::Person(definition.author));
+                                }
+                            }
+                        });
+                    });
+                });
+            }
+
             if !is_self {
                 // Petname and petname editing
                 make_frame().show(ui, |ui| {
@@ -187,6 +256,52 @@ fn content(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, pubkey: PublicKey, pe
                 }
             }
 
+            let pins = Nip51List::load(EventKind::PinList, pubkey, "")
+                .unwrap_or_default()
+                .map(|list| list.referenced_ids())
+                .unwrap_or_default();
+            if !pins.is_empty() {
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.add_space(10.0);
+                    ui.heading("Pinned notes");
+                });
+                ui.separator();
+                ui.add_space(10.0);
+
+                make_frame().show(ui, |ui| {
+                    for id in pins {
+                        match GLOBALS.db().read_event(id) {
+                            Ok(Some(event)) => {
+                                let mut text: String = event.content.chars().take(120).collect();
+                                if text.len() < event.content.len() {
+                                    text.push('…');
+                                }
+                                if ui.link(text).clicked() {
+                                    app.set_page(
+                                        ctx,
+                                        Page::Feed(FeedKind::Thread {
+                                            id,
+                                            referenced_by: id,
+                                            author: Some(event.pubkey),
+                                        }),
+                                    );
+                                }
+                            }
+                            _ => {
+                                ui.label(
+                                    RichText::new(format!(
+                                        "{} (not loaded)",
+                                        gossip_lib::names::hex_id_short(&id.into())
+                                    ))
+                                    .weak(),
+                                );
+                            }
+                        }
+                    }
+                });
+            }
+
             ui.add_space(10.0);
             ui.horizontal(|ui| {
                 ui.add_space(10.0);
@@ -556,6 +671,32 @@ fn content(app: &mut GossipUi, ctx: &Context, ui: &mut Ui, pubkey: PublicKey, pe
                             {
                                 app.set_page(ctx, Page::PersonFollowers(pubkey));
                             }
+
+                            ui.add_space(BTN_SPACING);
+
+                            if widgets::Button::primary(&app.theme, "Their Media")
+                                .show(ui)
+                                .clicked()
+                            {
+                                app.set_page(ctx, Page::PersonMedia(pubkey));
+                            }
+
+                            // NIP-45 follower count
+                            let count_kind = CountKind::Followers(pubkey);
+                            if GLOBALS.counts.should_request(count_kind) {
+                                let _ = GLOBALS
+                                    .to_overlord
+                                    .send(ToOverlordMessage::Count(vec![count_kind]));
+                            }
+                            if let Some(count) = GLOBALS.counts.get(count_kind) {
+                                ui.add_space(BTN_SPACING);
+                                ui.label(format!("{} followers", count)).on_hover_text(
+                                    format!(
+                                        "Counted by {} relays. A ~ means the count is approximate.",
+                                        count.relays
+                                    ),
+                                );
+                            }
                         });
                     });
                 });
@@ -45,6 +45,12 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         "Enable zap receipts",
     );
 
+    ui.checkbox(
+        &mut app.unsaved_settings.count_note_interactions,
+        "Ask relays to count replies, reactions and reposts (NIP-45)",
+    )
+    .on_hover_text("Counts are shown next to notes. Relays that support it count events we have not downloaded. Counts marked with ~ are approximate.");
+
     ui.checkbox(&mut app.unsaved_settings.reposts, "Enable reposts (show)");
 
     ui.checkbox(
@@ -123,6 +129,12 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
     )
         .on_hover_text("If on, muted events wont be in the feed at all. If off, they will be in the feed, but the content will be replaced with the word MUTED. You will see replies to them, and you can peek at the content by viewing the note in raw form.");
 
+    ui.checkbox(
+        &mut app.unsaved_settings.hide_downvoted,
+        "Hide heavily downvoted events",
+    )
+    .on_hover_text("If on, events with at least 3 downvotes and more downvotes than likes won't be in the feed.");
+
     ui.checkbox(
         &mut app.unsaved_settings.show_deleted_events,
         "Render delete events, but labeled as deleted",
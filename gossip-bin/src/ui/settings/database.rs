@@ -2,7 +2,7 @@ use crate::ui::GossipUi;
 use crate::unsaved_settings::UnsavedSettings;
 use eframe::egui;
 use egui::widgets::Slider;
-use egui::{Context, Ui};
+use egui::{Context, RichText, Ui};
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::GLOBALS;
 
@@ -25,6 +25,27 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
         );
     });
 
+    ui.horizontal(|ui| {
+        ui.label("Space for avatars").on_hover_text(
+            "When cached avatars take more than this, the least recently used are deleted",
+        );
+        ui.add(
+            Slider::new(&mut app.unsaved_settings.cache_avatar_budget_mb, 10..=5000)
+                .logarithmic(true)
+                .text("MB"),
+        );
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Space for other media")
+            .on_hover_text("When other cached files (images, videos, ...) take more than this, the least recently used are deleted");
+        ui.add(
+            Slider::new(&mut app.unsaved_settings.cache_media_budget_mb, 100..=100000)
+                .logarithmic(true)
+                .text("MB"),
+        );
+    });
+
     // Only let them prune after they have saved
     let stored_settings = UnsavedSettings::load();
     if stored_settings == app.unsaved_settings {
@@ -45,4 +66,64 @@ pub(super) fn update(app: &mut GossipUi, _ctx: &Context, _frame: &mut eframe::Fr
     }
 
     ui.add_space(20.0);
+    ui.separator();
+    ui.add_space(10.0);
+
+    ui.horizontal(|ui| {
+        ui.heading("Media Cache");
+        if ui.button("Refresh").clicked() || app.cache_usage.is_none() {
+            app.cache_usage = match gossip_lib::media_cache::usage() {
+                Ok(usage) => Some(usage),
+                Err(e) => {
+                    tracing::error!("{}", e);
+                    Some(Default::default())
+                }
+            };
+        }
+    });
+    ui.label(
+        RichText::new("Files with the same content are stored once. Limits are applied every 10 minutes, and when deleting old downloaded files.")
+            .weak(),
+    );
+
+    let usage = match app.cache_usage {
+        Some(ref usage) => usage.clone(),
+        None => return,
+    };
+
+    ui.add_space(10.0);
+    ui.label(format!("{} files", usage.files));
+    ui.label(format!(
+        "Avatars: {} of {} MB",
+        megabytes(usage.avatar_bytes),
+        app.unsaved_settings.cache_avatar_budget_mb
+    ));
+    ui.label(format!(
+        "Other media: {} of {} MB",
+        megabytes(usage.media_bytes),
+        app.unsaved_settings.cache_media_budget_mb
+    ));
+
+    ui.add_space(10.0);
+    egui::Grid::new("cache_hosts")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label(RichText::new("Host").strong());
+            ui.label(RichText::new("Files").strong());
+            ui.label(RichText::new("MB").strong());
+            ui.end_row();
+            for (host, files, bytes) in usage.hosts.iter() {
+                ui.label(host);
+                ui.label(format!("{}", files));
+                ui.label(megabytes(*bytes));
+                ui.end_row();
+            }
+        });
+
+    ui.add_space(20.0);
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1}", bytes as f64 / (1024.0 * 1024.0))
 }
@@ -1,7 +1,7 @@
 use crate::ui::{GossipUi, Page};
 use eframe::egui;
 use egui::widgets::Slider;
-use egui::{Context, Ui};
+use egui::{Context, TextEdit, Ui};
 
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     ui.heading("Network Settings");
@@ -17,6 +17,8 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
 
     ui.checkbox(&mut app.unsaved_settings.check_nip05, "Check NIP-05").on_hover_text("If disabled, NIP-05 fetches will not be performed, but existing knowledge will be preserved, and following someone by NIP-05 will override this and do the fetch. Takes effect on save.");
 
+    ui.checkbox(&mut app.unsaved_settings.check_nip39, "Check External Identities").on_hover_text("If disabled, the proofs of identities people claim on other platforms (NIP-39) will not be fetched, but existing knowledge will be preserved. Takes effect on save.");
+
     ui.checkbox(&mut app.unsaved_settings.automatically_fetch_metadata, "Automatically Fetch Metadata").on_hover_text("If enabled, metadata that is entirely missing will be fetched as you scroll past people. Existing metadata won't be updated. Takes effect on save.");
 
     ui.checkbox(
@@ -127,6 +129,17 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
             .text("seconds"),
         );
     });
+    ui.horizontal(|ui| {
+        ui.label("Hosts to authenticate to (NIP-98)").on_hover_text(
+            "HTTP requests to these hosts are signed with your key, so you can see \
+                 members-only media, private NIP-05 endpoints and paid file hosts. \
+                 This tells these hosts who you are. Separate them by spaces or newlines.",
+        );
+        ui.add(
+            TextEdit::multiline(&mut app.unsaved_settings.fetcher_auth_hosts)
+                .desired_width(f32::INFINITY),
+        );
+    });
 
     ui.add_space(10.0);
     ui.heading("Websocket Settings");
@@ -0,0 +1,35 @@
+use crate::ui::GossipUi;
+use egui_winit::egui::{self, vec2, Image, Response, RichText, Sense, Ui};
+use gossip_lib::{BadgeDefinition, MediaLoadingResult};
+
+/// Show a badge with its image (or its name until the image loads), and its
+/// name and description on hover
+pub(crate) fn badge(
+    app: &mut GossipUi,
+    ui: &mut Ui,
+    definition: &BadgeDefinition,
+    size: f32,
+) -> Response {
+    let url = definition
+        .small_image()
+        .and_then(|image| app.try_check_url(image));
+    let response = match url.map(|url| app.try_get_media(ui.ctx(), url, false, None)) {
+        Some(MediaLoadingResult::Ready(texture)) => ui.add(
+            Image::new(&texture)
+                .max_size(vec2(size, size))
+                .maintain_aspect_ratio(true)
+                .sense(Sense::click()),
+        ),
+        _ => ui.add(
+            egui::Label::new(RichText::new(format!("🏅 {}", definition.display_name())))
+                .sense(Sense::click()),
+        ),
+    };
+
+    let hover = if definition.description.is_empty() {
+        definition.display_name()
+    } else {
+        format!("{}\n{}", definition.display_name(), definition.description)
+    };
+    response.on_hover_text(hover)
+}
@@ -3,6 +3,9 @@ use std::sync::Arc;
 
 pub(crate) use avatar::{paint_avatar, paint_avatar_only, AvatarSize};
 
+mod badge;
+pub(crate) use badge::badge;
+
 mod button;
 pub use button::Button;
 
@@ -0,0 +1,225 @@
+use super::{GossipUi, Page};
+use crate::ui::widgets;
+use eframe::egui;
+use egui::{Context, RichText, Ui};
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{BadgeDefinition, GLOBALS};
+use nostr_types::{Id, PublicKey};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const CACHE_DURATION: Duration = Duration::from_secs(5);
+
+/// Badges being created or awarded
+#[derive(Default)]
+pub struct BadgesUi {
+    d: String,
+    name: String,
+    description: String,
+    image: String,
+
+    // Who to award each of our badges to, by 'd' tag
+    award_to: HashMap<String, String>,
+
+    // Our badges, loaded on entering the page and refreshed every few seconds
+    // to pick up what we accept, award and create
+    loaded: Option<Instant>,
+    accepted: Vec<(BadgeDefinition, Id)>,
+    awarded: Vec<(BadgeDefinition, Id)>,
+    mine: Vec<BadgeDefinition>,
+}
+
+impl BadgesUi {
+    /// Reload our badges the next time the page is drawn
+    pub(in crate::ui) fn enter_page(&mut self) {
+        self.loaded = None;
+    }
+
+    fn refresh(&mut self, public_key: PublicKey) {
+        if let Some(loaded) = self.loaded {
+            if loaded.elapsed() < CACHE_DURATION {
+                return;
+            }
+        }
+
+        self.accepted = gossip_lib::badge::profile_badges(public_key).unwrap_or_default();
+        self.awarded = gossip_lib::badge::unaccepted_awards(public_key).unwrap_or_default();
+        self.mine = BadgeDefinition::load_by(public_key).unwrap_or_default();
+        self.loaded = Some(Instant::now());
+    }
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    ui.add_space(10.0);
+    ui.horizontal_wrapped(|ui| {
+        ui.heading("Badges");
+    });
+    ui.add_space(10.0);
+
+    let public_key = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => {
+            ui.horizontal(|ui| {
+                ui.label("You need to");
+                if ui.link("setup an identity").clicked() {
+                    app.set_page(ctx, Page::YourKeys);
+                }
+                ui.label("to have badges.");
+            });
+            return;
+        }
+    };
+    let can_sign = GLOBALS.identity.is_unlocked();
+
+    app.badges.refresh(public_key);
+
+    app.vert_scroll_area()
+        .id_source("your_badges_scroll")
+        .show(ui, |ui| {
+            ui.heading("On my profile");
+            ui.add_space(6.0);
+            let accepted = app.badges.accepted.clone();
+            if accepted.is_empty() {
+                ui.label(RichText::new("none").italics().weak());
+            }
+            for (definition, award) in accepted.iter() {
+                ui.horizontal(|ui| {
+                    widgets::badge(app, ui, definition, 32.0);
+                    ui.label(definition.display_name());
+                    if can_sign && ui.button("Remove").clicked() {
+                        let _ = GLOBALS
+                            .to_overlord
+                            .send(ToOverlordMessage::AcceptBadge(*award, false));
+                    }
+                });
+            }
+
+            ui.add_space(18.0);
+            ui.heading("Awarded to me");
+            ui.add_space(6.0);
+            let awarded = app.badges.awarded.clone();
+            if awarded.is_empty() {
+                ui.label(RichText::new("none").italics().weak());
+            }
+            for (definition, award) in awarded.iter() {
+                ui.horizontal(|ui| {
+                    widgets::badge(app, ui, definition, 32.0);
+                    ui.label(definition.display_name());
+                    ui.label(
+                        RichText::new(format!(
+                            "from {}",
+                            gossip_lib::names::best_name_from_pubkey_lookup(&definition.author)
+                        ))
+                        .weak(),
+                    );
+                    if can_sign && ui.button("Accept").clicked() {
+                        let _ = GLOBALS
+                            .to_overlord
+                            .send(ToOverlordMessage::AcceptBadge(*award, true));
+                    }
+                });
+            }
+
+            ui.add_space(18.0);
+            ui.separator();
+            ui.add_space(10.0);
+            ui.heading("Badges I issue");
+            ui.add_space(6.0);
+            let mine = app.badges.mine.clone();
+            if mine.is_empty() {
+                ui.label(RichText::new("none").italics().weak());
+            }
+            for definition in mine.iter() {
+                ui.horizontal(|ui| {
+                    widgets::badge(app, ui, definition, 32.0);
+                    ui.label(definition.display_name());
+                    if !can_sign {
+                        return;
+                    }
+                    let award_to = app
+                        .badges
+                        .award_to
+                        .entry(definition.d.clone())
+                        .or_default();
+                    ui.add(
+                        egui::TextEdit::singleline(award_to)
+                            .hint_text("npub1... npub1...")
+                            .desired_width(300.0),
+                    );
+                    let awardees: Vec<PublicKey> = award_to
+                        .split_whitespace()
+                        .filter_map(|s| {
+                            PublicKey::try_from_bech32_string(s, true)
+                                .or_else(|_| PublicKey::try_from_hex_string(s, true))
+                                .ok()
+                        })
+                        .collect();
+                    if ui
+                        .add_enabled(!awardees.is_empty(), egui::Button::new("Award"))
+                        .clicked()
+                    {
+                        let _ = GLOBALS.to_overlord.send(ToOverlordMessage::AwardBadge(
+                            definition.d.clone(),
+                            awardees,
+                        ));
+                        award_to.clear();
+                    }
+                });
+            }
+
+            if !can_sign {
+                ui.add_space(18.0);
+                ui.horizontal(|ui| {
+                    ui.label("You need to");
+                    if ui.link("unlock your private key").clicked() {
+                        app.set_page(ctx, Page::YourKeys);
+                    }
+                    ui.label("to accept, award or create badges.");
+                });
+                return;
+            }
+
+            ui.add_space(18.0);
+            ui.heading("Create a badge");
+            ui.add_space(6.0);
+            egui::Grid::new("create_badge_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Identifier:");
+                    ui.add(text_edit_line!(app, app.badges.d).hint_text("e.g. contributor-2024"));
+                    ui.end_row();
+                    ui.label("Name:");
+                    ui.add(text_edit_line!(app, app.badges.name));
+                    ui.end_row();
+                    ui.label("Description:");
+                    ui.add(text_edit_line!(app, app.badges.description));
+                    ui.end_row();
+                    ui.label("Image URL:");
+                    ui.add(text_edit_line!(app, app.badges.image).hint_text("https://..."));
+                    ui.end_row();
+                });
+            let d = app.badges.d.trim().to_owned();
+            if ui
+                .add_enabled(!d.is_empty(), egui::Button::new("Create"))
+                .on_hover_text("Publishes the badge definition. Using an existing identifier replaces that badge.")
+                .clicked()
+            {
+                let image = app.badges.image.trim().to_owned();
+                let definition = BadgeDefinition {
+                    author: public_key,
+                    d,
+                    name: app.badges.name.trim().to_owned(),
+                    description: app.badges.description.trim().to_owned(),
+                    image: if image.is_empty() { None } else { Some(image) },
+                    thumb: None,
+                };
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::CreateBadge(definition));
+                app.badges.d.clear();
+                app.badges.name.clear();
+                app.badges.description.clear();
+                app.badges.image.clear();
+            }
+        });
+}
@@ -0,0 +1,155 @@
+use super::{GossipUi, Page};
+use eframe::egui;
+use egui::{vec2, Context, Image, RichText, Ui};
+use gossip_lib::blossom::BlobDescriptor;
+use gossip_lib::comms::ToOverlordMessage;
+use gossip_lib::{MediaLoadingResult, GLOBALS};
+use std::collections::BTreeMap;
+
+/// Our media on our blossom servers
+#[derive(Default)]
+pub struct MediaUi {
+    // The blob (sha256) we are asking about deleting
+    confirm_delete: Option<String>,
+}
+
+pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
+    ui.add_space(10.0);
+    ui.horizontal_wrapped(|ui| {
+        ui.heading("My Media");
+        if ui.button("Refresh").clicked() {
+            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::BlossomList);
+        }
+    });
+    ui.add_space(10.0);
+
+    if GLOBALS.identity.public_key().is_none() {
+        ui.horizontal(|ui| {
+            ui.label("You need to");
+            if ui.link("setup an identity").clicked() {
+                app.set_page(ctx, Page::YourKeys);
+            }
+            ui.label("to have media.");
+        });
+        return;
+    }
+
+    let servers = gossip_lib::blossom::server_base_urls();
+    if servers.is_empty() {
+        ui.label("You have no blossom servers. Add them in Settings > Posting.");
+        return;
+    }
+
+    // Every blob (by sha256) and the servers that have it
+    let mut blobs: BTreeMap<String, (BlobDescriptor, Vec<String>)> = BTreeMap::new();
+    for server in servers.iter() {
+        match GLOBALS.blossom_blobs.get(server) {
+            None => {
+                ui.label(RichText::new(format!("{}: loading...", server)).weak());
+            }
+            Some(entry) => match entry.value() {
+                Err(e) => {
+                    ui.label(
+                        RichText::new(format!("{}: {}", server, e))
+                            .color(app.theme.warning_marker_text_color()),
+                    );
+                }
+                Ok(listed) => {
+                    for bd in listed.iter() {
+                        blobs
+                            .entry(bd.sha256.clone())
+                            .or_insert_with(|| (bd.clone(), vec![]))
+                            .1
+                            .push(server.clone());
+                    }
+                }
+            },
+        }
+    }
+
+    // Newest first
+    let mut blobs: Vec<(BlobDescriptor, Vec<String>)> = blobs.into_values().collect();
+    blobs.sort_by_key(|(bd, _)| std::cmp::Reverse(bd.uploaded.or(bd.created).unwrap_or(0)));
+
+    ui.add_space(10.0);
+
+    app.vert_scroll_area()
+        .id_source("your_media_scroll")
+        .show(ui, |ui| {
+            if blobs.is_empty() {
+                ui.label(RichText::new("none").italics().weak());
+            }
+            for (bd, on_servers) in blobs.iter() {
+                ui.horizontal(|ui| {
+                    let is_image = bd
+                        .mime_type
+                        .as_ref()
+                        .map(|m| m.starts_with("image/"))
+                        .unwrap_or(false);
+                    if is_image {
+                        if let Some(url) = app.try_check_url(&bd.url) {
+                            if let MediaLoadingResult::Ready(texture) =
+                                app.try_get_media(ui.ctx(), url, false, None)
+                            {
+                                ui.add(
+                                    Image::new(&texture)
+                                        .max_size(vec2(64.0, 64.0))
+                                        .maintain_aspect_ratio(true),
+                                );
+                            }
+                        }
+                    }
+
+                    ui.vertical(|ui| {
+                        ui.hyperlink_to(&bd.url, &bd.url);
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{}  {}",
+                                    bd.mime_type.as_deref().unwrap_or("unknown type"),
+                                    size_text(bd.size)
+                                ))
+                                .weak(),
+                            );
+                            let text =
+                                format!("on {} of {} servers", on_servers.len(), servers.len());
+                            let text = if on_servers.len() < servers.len() {
+                                RichText::new(text).color(app.theme.warning_marker_text_color())
+                            } else {
+                                RichText::new(text).weak()
+                            };
+                            ui.label(text).on_hover_text(on_servers.join("\n"));
+                        });
+                    });
+
+                    if !GLOBALS.identity.is_unlocked() {
+                        return;
+                    }
+                    if app.media.confirm_delete.as_ref() == Some(&bd.sha256) {
+                        if ui.button("Really delete").clicked() {
+                            let _ = GLOBALS
+                                .to_overlord
+                                .send(ToOverlordMessage::BlossomDelete(bd.sha256.clone()));
+                            app.media.confirm_delete = None;
+                        }
+                        if ui.button("Keep").clicked() {
+                            app.media.confirm_delete = None;
+                        }
+                    } else if ui.button("Delete").clicked() {
+                        app.media.confirm_delete = Some(bd.sha256.clone());
+                    }
+                });
+                ui.add_space(6.0);
+            }
+        });
+}
+
+fn size_text(size: u64) -> String {
+    if size >= 1024 * 1024 {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    } else if size >= 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        format!("{} bytes", size)
+    }
+}
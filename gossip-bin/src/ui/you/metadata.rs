@@ -4,9 +4,10 @@ use egui::{Align, Color32, Context, Layout, RichText, TextEdit, Ui};
 use gossip_lib::comms::ToOverlordMessage;
 use gossip_lib::{PersonTable, Relay, Table, GLOBALS};
 use lazy_static::lazy_static;
-use nostr_types::Metadata;
+use nostr_types::{Metadata, Unixtime};
 use serde_json::map::Map;
 use serde_json::value::Value;
+use std::time::Duration;
 
 lazy_static! {
     pub static ref EMPTY_METADATA: Metadata = Metadata::new();
@@ -146,6 +147,94 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
             }
         });
     });
+
+    ui.add_space(18.0);
+    ui.separator();
+    ui.add_space(10.0);
+    ui.heading("My status");
+    ui.add_space(10.0);
+
+    let statuses = GLOBALS.people.get_statuses(public_key);
+    if statuses.is_empty() {
+        view_line(ui, "Status", None);
+    }
+    for status in statuses.iter() {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} status: ", status.d));
+            ui.label(&status.content);
+            if let Some(expiration) = status.expiration {
+                if let Ok(stamp) = time::OffsetDateTime::from_unix_timestamp(expiration.0) {
+                    if let Ok(formatted) =
+                        stamp.format(&time::format_description::well_known::Rfc2822)
+                    {
+                        ui.label(RichText::new(format!("(expires {})", formatted)).weak());
+                    }
+                }
+            }
+        });
+    }
+    ui.add_space(10.0);
+
+    if !GLOBALS.identity.is_unlocked() {
+        return;
+    }
+
+    let general_is_set = statuses
+        .iter()
+        .any(|s| s.d == gossip_lib::user_status::GENERAL);
+    ui.horizontal(|ui| {
+        ui.label("Set status: ");
+        ui.add(text_edit_line!(app, app.status).hint_text("What are you up to?"));
+        egui::ComboBox::from_id_source("StatusExpiry")
+            .selected_text(expiry_name(app.status_expiry))
+            .show_ui(ui, |ui| {
+                for (secs, _) in STATUS_EXPIRIES.iter() {
+                    ui.selectable_value(&mut app.status_expiry, *secs, expiry_name(*secs));
+                }
+            });
+        if ui
+            .button("SET")
+            .on_hover_text("Publishes your status to your outbox relays.")
+            .clicked()
+            && !app.status.trim().is_empty()
+        {
+            let expiration = if app.status_expiry == 0 {
+                None
+            } else {
+                Some(Unixtime::now() + Duration::from_secs(app.status_expiry as u64))
+            };
+            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::PushStatus(
+                gossip_lib::user_status::GENERAL.to_owned(),
+                app.status.trim().to_owned(),
+                expiration,
+            ));
+            app.status = "".to_owned();
+        }
+        if general_is_set && ui.button("CLEAR").clicked() {
+            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::PushStatus(
+                gossip_lib::user_status::GENERAL.to_owned(),
+                "".to_owned(),
+                None,
+            ));
+        }
+    });
+}
+
+/// How long a status we set lasts, in seconds (0 for never)
+const STATUS_EXPIRIES: [(i64, &str); 5] = [
+    (0, "never expires"),
+    (3600, "for an hour"),
+    (4 * 3600, "for 4 hours"),
+    (86400, "for a day"),
+    (7 * 86400, "for a week"),
+];
+
+fn expiry_name(secs: i64) -> &'static str {
+    STATUS_EXPIRIES
+        .iter()
+        .find(|(s, _)| *s == secs)
+        .map(|(_, name)| *name)
+        .unwrap_or("")
 }
 
 fn view_line(ui: &mut Ui, field: &str, data: Option<&String>) {
@@ -7,10 +7,15 @@ use gossip_lib::{Globals, GLOBALS};
 use nostr_types::{KeySecurity, PublicKeyHex};
 use zeroize::Zeroize;
 
+mod badges;
 mod delegation;
+mod media;
 mod metadata;
 mod nostr_connect;
 
+pub(super) use badges::BadgesUi;
+pub(super) use media::MediaUi;
+
 pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Frame, ui: &mut Ui) {
     if app.page == Page::YourKeys {
         ui.add_space(10.0);
@@ -119,6 +124,10 @@ pub(super) fn update(app: &mut GossipUi, ctx: &Context, _frame: &mut eframe::Fra
         delegation::update(app, ctx, _frame, ui);
     } else if app.page == Page::YourNostrConnect {
         nostr_connect::update(app, ctx, _frame, ui);
+    } else if app.page == Page::YourBadges {
+        badges::update(app, ctx, _frame, ui);
+    } else if app.page == Page::YourMedia {
+        media::update(app, ctx, _frame, ui);
     }
 }
 
@@ -43,6 +43,7 @@ pub struct UnsavedSettings {
     pub load_avatars: bool,
     pub load_media: bool,
     pub check_nip05: bool,
+    pub check_nip39: bool,
     pub automatically_fetch_metadata: bool,
     pub relay_connection_requires_approval: bool,
     pub relay_auth_requires_approval: bool,
@@ -64,8 +65,10 @@ pub struct UnsavedSettings {
 
     // Event Content Settings
     pub hide_mutes_entirely: bool,
+    pub hide_downvoted: bool,
     pub reactions: bool,
     pub enable_zap_receipts: bool,
+    pub count_note_interactions: bool,
     pub show_media: bool,
     pub approve_content_warning: bool,
     pub show_deleted_events: bool,
@@ -121,12 +124,26 @@ pub struct UnsavedSettings {
     pub fetcher_host_exclusion_on_low_error_secs: u64,
     pub fetcher_host_exclusion_on_med_error_secs: u64,
     pub fetcher_host_exclusion_on_high_error_secs: u64,
+    pub fetcher_auth_hosts: String,
 
     // Database settings
     pub prune_period_days: u64,
     pub cache_prune_period_days: u64,
+    pub cache_avatar_budget_mb: u64,
+    pub cache_media_budget_mb: u64,
 
     pub blossom_servers: String,
+    pub nip96_servers: String,
+    pub upload_server: String,
+    pub upload_strip_metadata: bool,
+    pub upload_max_dimension: u32,
+    pub upload_publish_file_metadata: bool,
+
+    // Timestamp settings
+    pub ots_headers_file: String,
+    pub ots_bitcoind_url: String,
+    pub ots_calendar_url: String,
+    pub timestamp_own_posts: bool,
 }
 
 impl Default for UnsavedSettings {
@@ -139,6 +156,7 @@ impl Default for UnsavedSettings {
             load_avatars: default_setting!(load_avatars),
             load_media: default_setting!(load_media),
             check_nip05: default_setting!(check_nip05),
+            check_nip39: default_setting!(check_nip39),
             automatically_fetch_metadata: default_setting!(automatically_fetch_metadata),
             relay_connection_requires_approval: default_setting!(
                 relay_connection_requires_approval
@@ -154,8 +172,10 @@ impl Default for UnsavedSettings {
             direct_messages: default_setting!(direct_messages),
             future_allowance_secs: default_setting!(future_allowance_secs),
             hide_mutes_entirely: default_setting!(hide_mutes_entirely),
+            hide_downvoted: default_setting!(hide_downvoted),
             reactions: default_setting!(reactions),
             enable_zap_receipts: default_setting!(enable_zap_receipts),
+            count_note_interactions: default_setting!(count_note_interactions),
             show_media: default_setting!(show_media),
             approve_content_warning: default_setting!(approve_content_warning),
             show_deleted_events: default_setting!(show_deleted_events),
@@ -213,9 +233,21 @@ impl Default for UnsavedSettings {
             fetcher_host_exclusion_on_high_error_secs: default_setting!(
                 fetcher_host_exclusion_on_high_error_secs
             ),
+            fetcher_auth_hosts: default_setting!(fetcher_auth_hosts),
             prune_period_days: default_setting!(prune_period_days),
             cache_prune_period_days: default_setting!(prune_period_days),
+            cache_avatar_budget_mb: default_setting!(cache_avatar_budget_mb),
+            cache_media_budget_mb: default_setting!(cache_media_budget_mb),
             blossom_servers: default_setting!(blossom_servers),
+            nip96_servers: default_setting!(nip96_servers),
+            upload_server: default_setting!(upload_server),
+            upload_strip_metadata: default_setting!(upload_strip_metadata),
+            upload_max_dimension: default_setting!(upload_max_dimension),
+            upload_publish_file_metadata: default_setting!(upload_publish_file_metadata),
+            ots_headers_file: default_setting!(ots_headers_file),
+            ots_bitcoind_url: default_setting!(ots_bitcoind_url),
+            ots_calendar_url: default_setting!(ots_calendar_url),
+            timestamp_own_posts: default_setting!(timestamp_own_posts),
         }
     }
 }
@@ -230,6 +262,7 @@ impl UnsavedSettings {
             load_avatars: load_setting!(load_avatars),
             load_media: load_setting!(load_media),
             check_nip05: load_setting!(check_nip05),
+            check_nip39: load_setting!(check_nip39),
             automatically_fetch_metadata: load_setting!(automatically_fetch_metadata),
             relay_connection_requires_approval: load_setting!(relay_connection_requires_approval),
             relay_auth_requires_approval: load_setting!(relay_auth_requires_approval),
@@ -243,8 +276,10 @@ impl UnsavedSettings {
             direct_messages: load_setting!(direct_messages),
             future_allowance_secs: load_setting!(future_allowance_secs),
             hide_mutes_entirely: load_setting!(hide_mutes_entirely),
+            hide_downvoted: load_setting!(hide_downvoted),
             reactions: load_setting!(reactions),
             enable_zap_receipts: load_setting!(enable_zap_receipts),
+            count_note_interactions: load_setting!(count_note_interactions),
             show_media: load_setting!(show_media),
             approve_content_warning: load_setting!(approve_content_warning),
             show_deleted_events: load_setting!(show_deleted_events),
@@ -300,9 +335,21 @@ impl UnsavedSettings {
             fetcher_host_exclusion_on_high_error_secs: load_setting!(
                 fetcher_host_exclusion_on_high_error_secs
             ),
+            fetcher_auth_hosts: load_setting!(fetcher_auth_hosts),
             prune_period_days: load_setting!(prune_period_days),
             cache_prune_period_days: load_setting!(cache_prune_period_days),
+            cache_avatar_budget_mb: load_setting!(cache_avatar_budget_mb),
+            cache_media_budget_mb: load_setting!(cache_media_budget_mb),
             blossom_servers: load_setting!(blossom_servers),
+            nip96_servers: load_setting!(nip96_servers),
+            upload_server: load_setting!(upload_server),
+            upload_strip_metadata: load_setting!(upload_strip_metadata),
+            upload_max_dimension: load_setting!(upload_max_dimension),
+            upload_publish_file_metadata: load_setting!(upload_publish_file_metadata),
+            ots_headers_file: load_setting!(ots_headers_file),
+            ots_bitcoind_url: load_setting!(ots_bitcoind_url),
+            ots_calendar_url: load_setting!(ots_calendar_url),
+            timestamp_own_posts: load_setting!(timestamp_own_posts),
         }
     }
 
@@ -315,6 +362,7 @@ impl UnsavedSettings {
         save_setting!(load_avatars, self, txn);
         save_setting!(load_media, self, txn);
         save_setting!(check_nip05, self, txn);
+        save_setting!(check_nip39, self, txn);
         save_setting!(automatically_fetch_metadata, self, txn);
         save_setting!(relay_connection_requires_approval, self, txn);
         save_setting!(relay_auth_requires_approval, self, txn);
@@ -328,8 +376,10 @@ impl UnsavedSettings {
         save_setting!(direct_messages, self, txn);
         save_setting!(future_allowance_secs, self, txn);
         save_setting!(hide_mutes_entirely, self, txn);
+        save_setting!(hide_downvoted, self, txn);
         save_setting!(reactions, self, txn);
         save_setting!(enable_zap_receipts, self, txn);
+        save_setting!(count_note_interactions, self, txn);
         save_setting!(show_media, self, txn);
         save_setting!(approve_content_warning, self, txn);
         save_setting!(show_deleted_events, self, txn);
@@ -375,9 +425,21 @@ impl UnsavedSettings {
         save_setting!(fetcher_host_exclusion_on_low_error_secs, self, txn);
         save_setting!(fetcher_host_exclusion_on_med_error_secs, self, txn);
         save_setting!(fetcher_host_exclusion_on_high_error_secs, self, txn);
+        save_setting!(fetcher_auth_hosts, self, txn);
         save_setting!(prune_period_days, self, txn);
         save_setting!(cache_prune_period_days, self, txn);
+        save_setting!(cache_avatar_budget_mb, self, txn);
+        save_setting!(cache_media_budget_mb, self, txn);
         save_setting!(blossom_servers, self, txn);
+        save_setting!(nip96_servers, self, txn);
+        save_setting!(upload_server, self, txn);
+        save_setting!(upload_strip_metadata, self, txn);
+        save_setting!(upload_max_dimension, self, txn);
+        save_setting!(upload_publish_file_metadata, self, txn);
+        save_setting!(ots_headers_file, self, txn);
+        save_setting!(ots_bitcoind_url, self, txn);
+        save_setting!(ots_calendar_url, self, txn);
+        save_setting!(timestamp_own_posts, self, txn);
         txn.commit()?;
 
         let runstate = *GLOBALS.read_runstate.borrow();
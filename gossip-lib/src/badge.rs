@@ -0,0 +1,320 @@
+//! NIP-58 badges
+//!
+//! An issuer defines a badge with a kind 30009 event and awards it to people with kind 8
+//! events that reference the definition with an 'a' tag and the awardees with 'p' tags.
+//! People choose which of their awards to show in their kind 30008 profile badges event,
+//! which lists pairs of 'a' (definition) and 'e' (award) tags.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use nostr_types::{Event, EventKind, Filter, Id, NAddr, PreEvent, PublicKey, Tag, Unixtime};
+
+/// The 'd' tag of the profile badges event
+pub const PROFILE_BADGES_D: &str = "profile_badges";
+
+/// A NIP-58 badge definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct BadgeDefinition {
+    /// The issuer of the badge
+    pub author: PublicKey,
+
+    /// The 'd' tag of the definition
+    pub d: String,
+
+    pub name: String,
+    pub description: String,
+
+    /// The full size image of the badge
+    pub image: Option<String>,
+
+    /// The smallest thumbnail of the badge, if any
+    pub thumb: Option<String>,
+}
+
+impl BadgeDefinition {
+    /// Interpret a badge definition event
+    pub fn from_event(event: &Event) -> Result<BadgeDefinition, Error> {
+        if event.kind != EventKind::BadgeDefinition {
+            return Err(ErrorKind::WrongEventKind.into());
+        }
+
+        let mut definition = BadgeDefinition {
+            author: event.pubkey,
+            d: event.parameter().unwrap_or("".to_owned()),
+            name: "".to_owned(),
+            description: "".to_owned(),
+            image: None,
+            thumb: None,
+        };
+
+        for tag in event.tags.iter() {
+            match tag.tagname() {
+                "name" => definition.name = tag.value().to_owned(),
+                "description" => definition.description = tag.value().to_owned(),
+                "image" => definition.image = Some(tag.value().to_owned()),
+                // Later thumbs are usually smaller, but any will do
+                "thumb" if definition.thumb.is_none() => {
+                    definition.thumb = Some(tag.value().to_owned())
+                }
+                _ => {}
+            }
+        }
+
+        Ok(definition)
+    }
+
+    /// The name of the badge, falling back to its 'd' tag
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() {
+            self.d.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// The image to show the badge with in a list, preferring the thumbnail
+    pub fn small_image(&self) -> Option<&str> {
+        self.thumb.as_deref().or(self.image.as_deref())
+    }
+
+    /// The address of the badge definition
+    pub fn naddr(&self) -> NAddr {
+        NAddr {
+            d: self.d.clone(),
+            relays: vec![],
+            kind: EventKind::BadgeDefinition,
+            author: self.author,
+        }
+    }
+
+    /// Load a badge definition from local storage
+    pub fn load(author: PublicKey, d: &str) -> Result<Option<BadgeDefinition>, Error> {
+        match GLOBALS
+            .db()
+            .get_replaceable_event(EventKind::BadgeDefinition, author, d)?
+        {
+            Some(event) => Ok(Some(Self::from_event(&event)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// All the badges issued by someone (locally), sorted by name
+    pub fn load_by(author: PublicKey) -> Result<Vec<BadgeDefinition>, Error> {
+        let mut filter = Filter::new();
+        filter.add_author(author);
+        filter.add_event_kind(EventKind::BadgeDefinition);
+
+        let mut definitions: Vec<BadgeDefinition> = Vec::new();
+        for event in GLOBALS.db().find_events_by_filter(&filter, |_| true)? {
+            let definition = Self::from_event(&event)?;
+            // Events are newest first, so keep the latest definition
+            if !definitions.iter().any(|b| b.d == definition.d) {
+                definitions.push(definition);
+            }
+        }
+        definitions.sort_by_key(|b| b.display_name().to_lowercase());
+        Ok(definitions)
+    }
+}
+
+/// The badge definition an award refers to
+pub fn awarded_badge(award: &Event) -> Option<NAddr> {
+    award.tags.iter().find_map(|tag| match tag.parse_address() {
+        Ok((ea, _)) if ea.kind == EventKind::BadgeDefinition && ea.author == award.pubkey => {
+            Some(ea)
+        }
+        _ => None,
+    })
+}
+
+/// Whether an award was given to a person
+fn awards(award: &Event, pubkey: PublicKey) -> bool {
+    award.kind == EventKind::BadgeAward
+        && award
+            .tags
+            .iter()
+            .any(|tag| matches!(tag.parse_pubkey(), Ok((pk, _, _)) if pk == pubkey))
+}
+
+/// The badges a person shows on their profile, with the award of each, in their order.
+/// Badges whose award or definition we don't have (or which don't check out) are
+/// left out.
+pub fn profile_badges(pubkey: PublicKey) -> Result<Vec<(BadgeDefinition, Id)>, Error> {
+    let mut badges: Vec<(BadgeDefinition, Id)> = Vec::new();
+    for (naddr, award_id) in profile_badge_refs(pubkey)? {
+        let award = match GLOBALS.db().read_event(award_id)? {
+            Some(award) => award,
+            None => continue,
+        };
+        let matches_definition = match awarded_badge(&award) {
+            Some(ea) => ea.author == naddr.author && ea.d == naddr.d,
+            None => false,
+        };
+        if !awards(&award, pubkey) || !matches_definition {
+            continue;
+        }
+        if let Some(definition) = BadgeDefinition::load(naddr.author, &naddr.d)? {
+            badges.push((definition, award_id));
+        }
+    }
+    Ok(badges)
+}
+
+/// The (definition, award) pairs in a person's profile badges event
+fn profile_badge_refs(pubkey: PublicKey) -> Result<Vec<(NAddr, Id)>, Error> {
+    let event = match GLOBALS.db().get_replaceable_event(
+        EventKind::ProfileBadges,
+        pubkey,
+        PROFILE_BADGES_D,
+    )? {
+        Some(event) => event,
+        None => return Ok(vec![]),
+    };
+    Ok(pairs_of(&event))
+}
+
+/// The consecutive 'a' and 'e' tag pairs of a profile badges event
+pub(crate) fn pairs_of(event: &Event) -> Vec<(NAddr, Id)> {
+    let mut pairs: Vec<(NAddr, Id)> = Vec::new();
+    let mut pending: Option<NAddr> = None;
+    for tag in event.tags.iter() {
+        match tag.tagname() {
+            "a" => pending = tag.parse_address().ok().map(|(ea, _)| ea),
+            "e" => {
+                if let (Some(naddr), Ok((id, _, _, _))) = (pending.take(), tag.parse_event()) {
+                    pairs.push((naddr, id));
+                }
+            }
+            _ => {}
+        }
+    }
+    pairs
+}
+
+/// The badges awarded to a person that they have not put on their profile yet, newest first
+pub fn unaccepted_awards(pubkey: PublicKey) -> Result<Vec<(BadgeDefinition, Id)>, Error> {
+    let accepted: Vec<Id> = profile_badge_refs(pubkey)?
+        .iter()
+        .map(|(_, id)| *id)
+        .collect();
+
+    let mut filter = Filter::new();
+    filter.add_event_kind(EventKind::BadgeAward);
+    filter.set_tag_values('p', vec![pubkey.as_hex_string()]);
+
+    let mut found: Vec<(BadgeDefinition, Id)> = Vec::new();
+    for award in GLOBALS
+        .db()
+        .find_events_by_filter(&filter, |e| !accepted.contains(&e.id))?
+    {
+        if let Some(naddr) = awarded_badge(&award) {
+            if let Some(definition) = BadgeDefinition::load(naddr.author, &naddr.d)? {
+                found.push((definition, award.id));
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Create a badge definition (kind 30009)
+pub(crate) fn definition_event(definition: &BadgeDefinition) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let mut tags: Vec<Tag> = vec![
+        Tag::new_identifier(definition.d.clone()),
+        Tag::new(&["name", &definition.name]),
+    ];
+    if !definition.description.is_empty() {
+        tags.push(Tag::new(&["description", &definition.description]));
+    }
+    if let Some(image) = &definition.image {
+        tags.push(Tag::new(&["image", image]));
+    }
+    if let Some(thumb) = &definition.thumb {
+        tags.push(Tag::new(&["thumb", thumb]));
+    }
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: EventKind::BadgeDefinition,
+        tags,
+        content: "".to_owned(),
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
+
+/// Create an award (kind 8) of one of our badges
+pub(crate) fn award_event(d: &str, awardees: &[PublicKey]) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let naddr = NAddr {
+        d: d.to_owned(),
+        relays: vec![],
+        kind: EventKind::BadgeDefinition,
+        author: public_key,
+    };
+    let mut tags: Vec<Tag> = vec![Tag::new_address(&naddr, None)];
+    for pubkey in awardees.iter() {
+        tags.push(Tag::new_pubkey(*pubkey, None, None));
+    }
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: EventKind::BadgeAward,
+        tags,
+        content: "".to_owned(),
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
+
+/// Create our profile badges event (kind 30008) with an award added or removed
+pub(crate) fn profile_badges_event(award_id: Id, accept: bool) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let mut pairs = profile_badge_refs(public_key)?;
+    pairs.retain(|(_, id)| *id != award_id);
+    if accept {
+        let award = match GLOBALS.db().read_event(award_id)? {
+            Some(award) => award,
+            None => return Err(ErrorKind::EventNotFound.into()),
+        };
+        let naddr = match awarded_badge(&award) {
+            Some(naddr) if awards(&award, public_key) => naddr,
+            _ => {
+                return Err(
+                    ErrorKind::General("This is not a badge award to you".to_owned()).into(),
+                )
+            }
+        };
+        pairs.push((naddr, award_id));
+    }
+
+    let mut tags: Vec<Tag> = vec![Tag::new_identifier(PROFILE_BADGES_D.to_owned())];
+    for (naddr, id) in pairs.iter() {
+        tags.push(Tag::new_address(naddr, None));
+        tags.push(Tag::new_event(*id, None, None, None));
+    }
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: EventKind::ProfileBadges,
+        tags,
+        content: "".to_owned(),
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
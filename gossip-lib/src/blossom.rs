@@ -1,22 +1,29 @@
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
-use base64::Engine;
 use memmap2::Mmap;
 use mime::Mime;
-use nostr_types::{EventKind, PreEvent, Tag, Unixtime};
+use nostr_types::{Event, EventKind, PreEvent, PublicKey, Tag, Unixtime};
 use reqwest::header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE};
 use reqwest::{Body, Client, Response};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::path::Path;
 use std::time::Duration;
 
 /// A simple type for a SHA-256 hash output of 32 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct HashOutput([u8; 32]);
 
 impl HashOutput {
+    pub fn from_hex(hex: &str) -> Result<HashOutput, Error> {
+        let bytes = hex::decode(hex)
+            .map_err(|_| ErrorKind::BlossomError(format!("Bad sha256 hash: {}", hex)))?;
+        Ok(HashOutput(bytes.as_slice().try_into()?))
+    }
+
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<HashOutput, Error> {
         let sha256hash = {
             let file = File::open(path)?;
@@ -28,6 +35,12 @@ impl HashOutput {
 
         Ok(HashOutput(sha256hash.into()))
     }
+
+    pub fn from_bytes(bytes: &[u8]) -> HashOutput {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        HashOutput(hasher.finalize().into())
+    }
 }
 
 impl fmt::Display for HashOutput {
@@ -219,20 +232,287 @@ impl Blossom {
         }
     }
 
-    // BUD-04  PUT /mirror
-    //pub async fn mirror() {
-    //
-    //}
+    /// BUD-04  PUT /mirror
+    /// Ask the blossom server to copy a blob from a URL (usually on another blossom server)
+    pub async fn mirror(
+        &self,
+        base_url: String,
+        blob_url: String,
+        hash: HashOutput,
+    ) -> Result<BlobDescriptor, Error> {
+        let authorization = authorization(
+            BlossomVerb::Upload,
+            "Mirror".to_owned(),
+            Unixtime::now() + Duration::new(60, 0),
+            vec![hash],
+        )?;
 
-    // BUD-02  GET /list/<pubkey>
-    //pub async fn list() {
-    //    unimplemented!()
-    //}
+        let url = format!("{}mirror", base_url);
+        let response = self
+            .client
+            .put(url)
+            .header(AUTHORIZATION, format!("Nostr {}", authorization))
+            .json(&serde_json::json!({ "url": blob_url }))
+            .send()
+            .await?;
 
-    // BUD-02  DELETE /<sha256>
-    //pub async fn delete() {
-    //    unimplemented!()
-    //}
+        if response.status().as_u16() < 300 {
+            Ok(response.json::<BlobDescriptor>().await?)
+        } else {
+            Err(get_error(&response))
+        }
+    }
+
+    /// BUD-02  GET /list/<pubkey>
+    pub async fn list(
+        &self,
+        base_url: String,
+        pubkey: PublicKey,
+    ) -> Result<Vec<BlobDescriptor>, Error> {
+        let authorization = authorization(
+            BlossomVerb::List,
+            "List".to_owned(),
+            Unixtime::now() + Duration::new(60, 0),
+            vec![],
+        )?;
+
+        let url = format!("{}list/{}", base_url, pubkey.as_hex_string());
+        let response = self
+            .client
+            .get(url)
+            .header(AUTHORIZATION, format!("Nostr {}", authorization))
+            .send()
+            .await?;
+
+        if response.status().as_u16() < 300 {
+            Ok(response.json::<Vec<BlobDescriptor>>().await?)
+        } else {
+            Err(get_error(&response))
+        }
+    }
+
+    /// BUD-02  DELETE /<sha256>
+    pub async fn delete(&self, base_url: String, hash: HashOutput) -> Result<(), Error> {
+        let authorization = authorization(
+            BlossomVerb::Delete,
+            "Delete".to_owned(),
+            Unixtime::now() + Duration::new(60, 0),
+            vec![hash],
+        )?;
+
+        let url = format!("{}{}", base_url, hash);
+        let response = self
+            .client
+            .delete(url)
+            .header(AUTHORIZATION, format!("Nostr {}", authorization))
+            .send()
+            .await?;
+
+        if response.status().as_u16() < 300 {
+            Ok(())
+        } else {
+            Err(get_error(&response))
+        }
+    }
+}
+
+/// The shared Blossom client
+pub(crate) fn blossom() -> Result<&'static Blossom, Error> {
+    match GLOBALS.blossom.get() {
+        Some(b) => Ok(b),
+        None => {
+            let blossom = Blossom::new()?;
+            let _ = GLOBALS.blossom.set(blossom);
+            Ok(GLOBALS.blossom.get().unwrap())
+        }
+    }
+}
+
+/// The base URLs of our blossom servers, in the order configured
+pub fn server_base_urls() -> Vec<String> {
+    GLOBALS
+        .db()
+        .read_setting_blossom_servers()
+        .split_whitespace()
+        .filter_map(|bs| match base_url(bs) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                tracing::warn!("Bad blossom server {}: {}", bs, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The blossom servers in a user server list (BUD-03) event
+pub fn servers_of(event: &Event) -> Vec<String> {
+    event
+        .tags
+        .iter()
+        .filter(|tag| tag.tagname() == "server")
+        .map(|tag| tag.value().to_owned())
+        .collect()
+}
+
+/// The base URLs of someone's blossom servers, from their user server list (BUD-03)
+pub fn user_server_base_urls(pubkey: PublicKey) -> Vec<String> {
+    match GLOBALS
+        .db()
+        .get_replaceable_event(EventKind::UserServerList, pubkey, "")
+    {
+        Ok(Some(event)) => servers_of(&event)
+            .iter()
+            .filter_map(|bs| base_url(bs).ok())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// If a URL looks like a blossom blob URL (the last path segment is a sha256 hash, with
+/// an optional extension), the hash and the extension
+pub fn blob_hash_of(url: &str) -> Option<(String, String)> {
+    let parsed = url::Url::parse(url).ok()?;
+    let last = parsed.path_segments()?.last()?;
+    let (hash, ext) = match last.find('.') {
+        Some(i) => (&last[..i], &last[i..]),
+        None => (last, ""),
+    };
+    if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some((hash.to_lowercase(), ext.to_owned()))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn base_url(server: &str) -> Result<String, Error> {
+    use http::uri::{Parts, PathAndQuery, Scheme};
+    use http::Uri;
+
+    let uri = server.parse::<Uri>()?;
+    let mut parts: Parts = uri.into_parts();
+    parts.path_and_query = Some(PathAndQuery::from_static("/")); // Force no path
+    if parts.scheme.is_none() {
+        // Default to https
+        parts.scheme = Some(Scheme::HTTPS);
+    }
+    let uri = Uri::from_parts(parts)?;
+    Ok(format!("{}", uri))
+}
+
+/// List our blobs on all of our blossom servers, into GLOBALS.blossom_blobs
+pub(crate) async fn list_all() -> Result<(), Error> {
+    let pubkey = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+    let blossom = blossom()?;
+
+    let servers = server_base_urls();
+    GLOBALS
+        .blossom_blobs
+        .retain(|server, _| servers.contains(server));
+    for server in servers {
+        let result = blossom.list(server.clone(), pubkey).await;
+        GLOBALS.blossom_blobs.insert(server, result);
+    }
+
+    Ok(())
+}
+
+/// Delete one of our blobs from all of our blossom servers that have it
+pub(crate) async fn delete_everywhere(sha256: String) -> Result<(), Error> {
+    let blossom = blossom()?;
+    let hash = HashOutput::from_hex(&sha256)?;
+
+    for server in server_base_urls() {
+        let has_it = match GLOBALS.blossom_blobs.get(&server) {
+            Some(entry) => match entry.value() {
+                Ok(blobs) => blobs.iter().any(|bd| bd.sha256 == sha256),
+                Err(_) => true, // try anyway
+            },
+            None => true,
+        };
+        if !has_it {
+            continue;
+        }
+        if let Err(e) = blossom.delete(server.clone(), hash).await {
+            tracing::warn!("Could not delete {} from {}: {}", sha256, server, e);
+        }
+    }
+
+    list_all().await
+}
+
+/// Mirror a blob we uploaded to all of our other blossom servers (BUD-04)
+pub(crate) async fn mirror_everywhere(bd: &BlobDescriptor, uploaded_to: &str) -> Result<(), Error> {
+    let blossom = blossom()?;
+    let hash = HashOutput::from_hex(&bd.sha256)?;
+
+    for server in server_base_urls() {
+        if server == uploaded_to {
+            continue;
+        }
+        match blossom.mirror(server.clone(), bd.url.clone(), hash).await {
+            Ok(_) => tracing::info!("Mirrored {} to {}", bd.sha256, server),
+            Err(e) => tracing::warn!("Could not mirror {} to {}: {}", bd.sha256, server, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-mirror our blobs that are missing from any of our blossom servers
+pub(crate) async fn remirror_missing() -> Result<(), Error> {
+    let servers = server_base_urls();
+    if servers.len() < 2 || !GLOBALS.identity.is_unlocked() {
+        return Ok(());
+    }
+
+    list_all().await?;
+
+    // Every blob, where we can get it from, and which servers have it
+    let mut blobs: HashMap<String, (BlobDescriptor, Vec<String>)> = HashMap::new();
+    for server in servers.iter() {
+        let listed = match GLOBALS.blossom_blobs.get(server) {
+            Some(entry) => match entry.value() {
+                Ok(listed) => listed.clone(),
+                // We don't know what it is missing
+                Err(_) => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        for bd in listed {
+            blobs
+                .entry(bd.sha256.clone())
+                .or_insert_with(|| (bd.clone(), vec![]))
+                .1
+                .push(server.clone());
+        }
+    }
+
+    let blossom = blossom()?;
+    let mut mirrored = false;
+    for (sha256, (bd, has_it)) in blobs.iter() {
+        let hash = HashOutput::from_hex(sha256)?;
+        for server in servers.iter() {
+            if has_it.contains(server) {
+                continue;
+            }
+            match blossom.mirror(server.clone(), bd.url.clone(), hash).await {
+                Ok(_) => {
+                    tracing::info!("Re-mirrored {} to {}", sha256, server);
+                    mirrored = true;
+                }
+                Err(e) => tracing::warn!("Could not mirror {} to {}: {}", sha256, server, e),
+            }
+        }
+    }
+
+    if mirrored {
+        list_all().await?;
+    }
+
+    Ok(())
 }
 
 // This returns the base64 encoded authorization event
@@ -263,11 +543,7 @@ fn authorization(
         content: purpose,
     };
 
-    let event = GLOBALS.identity.sign_event(pre_event)?;
-    let event_json = serde_json::to_string(&event)?;
-    let base64 = base64::engine::general_purpose::STANDARD.encode(&event_json);
-
-    Ok(base64)
+    GLOBALS.identity.sign_authorization(pre_event)
 }
 
 fn get_error(response: &Response) -> Error {
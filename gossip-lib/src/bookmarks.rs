@@ -1,9 +1,7 @@
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
-use nostr_types::{
-    ContentEncryptionAlgorithm, Event, EventKind, EventReference, Id, PreEvent, RelayUrl, Tag,
-    Unixtime,
-};
+use crate::lists::{decrypt_private_tags, encrypt_private_tags};
+use nostr_types::{Event, EventKind, EventReference, Id, PreEvent, RelayUrl, Tag, Unixtime};
 use std::collections::BTreeMap;
 
 pub struct BookmarkList(Vec<(EventReference, bool)>);
@@ -86,11 +84,7 @@ impl BookmarkList {
 
         let mut bml = Self::empty();
         bml.add_tags(event.tags.as_ref(), false)?;
-        if let Ok(json_string) = GLOBALS.identity.decrypt(&public_key, &event.content) {
-            if let Ok(vectags) = serde_json::from_str::<Vec<Tag>>(&json_string) {
-                bml.add_tags(vectags.as_ref(), true)?;
-            }
-        }
+        bml.add_tags(&decrypt_private_tags(&public_key, event), true)?;
 
         Ok(bml)
     }
@@ -141,12 +135,7 @@ impl BookmarkList {
                     },
                 )
                 .collect();
-            let private_json = serde_json::to_string(&private)?;
-            GLOBALS.identity.encrypt(
-                &public_key,
-                &private_json,
-                ContentEncryptionAlgorithm::Nip44v2,
-            )?
+            encrypt_private_tags(&public_key, &private)?
         };
 
         let pre_event = PreEvent {
@@ -0,0 +1,202 @@
+//! NIP-28 public chat channels
+//!
+//! A channel is created with a kind 40 event, and its creator may update its metadata
+//! with kind 41 events. Messages are kind 42 events with a root 'e' tag pointing at the
+//! channel. Kind 43 (hide message) and kind 44 (mute user) are moderation events that
+//! we publish and honor for ourselves only.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use nostr_types::{Event, EventKind, Filter, Id, PreEvent, PublicKey, RelayUrl, Tag, Unixtime};
+use serde::Deserialize;
+
+/// The JSON content of kind 40 and kind 41 events
+#[derive(Debug, Default, Deserialize)]
+struct ChannelMetadataContent {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    about: String,
+    #[serde(default)]
+    picture: String,
+    #[serde(default)]
+    relays: Vec<String>,
+}
+
+/// A NIP-28 public chat channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatChannel {
+    /// The id of the channel creation event (kind 40)
+    pub id: Id,
+
+    pub creator: PublicKey,
+    pub created_at: Unixtime,
+
+    pub name: String,
+    pub about: String,
+    pub picture: String,
+
+    /// The relays the channel recommends, and the relays we saw it on
+    pub relays: Vec<RelayUrl>,
+}
+
+impl ChatChannel {
+    /// Interpret a channel creation event, applying the latest metadata from its creator
+    pub fn from_event(event: &Event) -> Result<ChatChannel, Error> {
+        if event.kind != EventKind::ChannelCreation {
+            return Err(ErrorKind::WrongEventKind.into());
+        }
+
+        let mut channel = ChatChannel {
+            id: event.id,
+            creator: event.pubkey,
+            created_at: event.created_at,
+            name: "".to_owned(),
+            about: "".to_owned(),
+            picture: "".to_owned(),
+            relays: vec![],
+        };
+        channel.apply_metadata(&event.content);
+
+        // Only the creator may change the metadata
+        let mut filter = Filter::new();
+        filter.add_event_kind(EventKind::ChannelMetadata);
+        filter.add_author(event.pubkey);
+        filter.set_tag_values('e', vec![event.id.as_hex_string()]);
+        let updates = GLOBALS.db().find_events_by_filter(&filter, |_| true)?;
+        if let Some(latest) = updates.iter().max_by_key(|e| e.created_at) {
+            channel.apply_metadata(&latest.content);
+        }
+
+        for (url, _) in GLOBALS.db().get_event_seen_on_relay(event.id)? {
+            if !channel.relays.contains(&url) {
+                channel.relays.push(url);
+            }
+        }
+
+        Ok(channel)
+    }
+
+    fn apply_metadata(&mut self, content: &str) {
+        let metadata = match serde_json::from_str::<ChannelMetadataContent>(content) {
+            Ok(m) => m,
+            Err(_) => return,
+        };
+        self.name = metadata.name;
+        self.about = metadata.about;
+        self.picture = metadata.picture;
+        for r in metadata.relays.iter() {
+            if let Ok(url) = RelayUrl::try_from_str(r) {
+                if !self.relays.contains(&url) {
+                    self.relays.push(url);
+                }
+            }
+        }
+    }
+
+    /// The name of the channel, falling back to its id
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() {
+            crate::names::hex_id_short(&self.id.into())
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Load a channel from local storage
+    pub fn load(id: Id) -> Result<Option<ChatChannel>, Error> {
+        match GLOBALS.db().read_event(id)? {
+            Some(event) => Ok(Some(Self::from_event(&event)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// All the channels we know of (locally), newest first
+    pub fn load_all() -> Result<Vec<ChatChannel>, Error> {
+        let mut filter = Filter::new();
+        filter.add_event_kind(EventKind::ChannelCreation);
+
+        let mut channels: Vec<ChatChannel> = GLOBALS
+            .db()
+            .find_events_by_filter(&filter, |_| true)?
+            .iter()
+            .filter_map(|event| Self::from_event(event).ok())
+            .collect();
+        channels.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(channels)
+    }
+
+    /// The locally available messages of this channel, newest first, without those we
+    /// have hidden or whose authors we have muted
+    pub fn message_ids(&self) -> Result<Vec<Id>, Error> {
+        let hidden = hidden_message_ids()?;
+        let muted = muted_users()?;
+
+        let mut filter = Filter::new();
+        filter.add_event_kind(EventKind::ChannelMessage);
+        filter.set_tag_values('e', vec![self.id.as_hex_string()]);
+
+        let mut events = GLOBALS.db().find_events_by_filter(&filter, |event| {
+            !hidden.contains(&event.id) && !muted.contains(&event.pubkey)
+        })?;
+        events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(events.iter().map(|e| e.id).collect())
+    }
+}
+
+/// Our own moderation events of a kind (43 or 44)
+fn my_moderation_events(kind: EventKind) -> Result<Vec<Event>, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Ok(vec![]),
+        Some(pk) => pk,
+    };
+
+    let mut filter = Filter::new();
+    filter.add_event_kind(kind);
+    filter.add_author(public_key);
+    GLOBALS.db().find_events_by_filter(&filter, |_| true)
+}
+
+/// The channel messages we have hidden
+pub fn hidden_message_ids() -> Result<Vec<Id>, Error> {
+    let mut ids: Vec<Id> = Vec::new();
+    for event in my_moderation_events(EventKind::ChannelHideMessage)? {
+        for tag in event.tags.iter() {
+            if let Ok((id, _, _, _)) = tag.parse_event() {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// The people we have muted in channels
+pub fn muted_users() -> Result<Vec<PublicKey>, Error> {
+    let mut pubkeys: Vec<PublicKey> = Vec::new();
+    for event in my_moderation_events(EventKind::ChannelMuteUser)? {
+        for tag in event.tags.iter() {
+            if let Ok((pubkey, _, _)) = tag.parse_pubkey() {
+                pubkeys.push(pubkey);
+            }
+        }
+    }
+    Ok(pubkeys)
+}
+
+/// Create a moderation event (kind 43 hiding a message, or kind 44 muting a user)
+pub(crate) fn moderation_event(kind: EventKind, tag: Tag) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind,
+        tags: vec![tag],
+        content: "".to_owned(),
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
@@ -1,5 +1,9 @@
+use crate::badge::BadgeDefinition;
+use crate::count::CountKind;
 use crate::dm_channel::DmChannel;
+use crate::dm_search::DmSearch;
 use crate::filter_set::FilterSet;
+use crate::lists::Nip51List;
 use crate::misc::Private;
 use crate::nostr_connect_server::{Approval, ParsedCommand};
 use crate::people::PersonList;
@@ -19,6 +23,10 @@ use std::path::PathBuf;
 /// renderer.
 #[derive(Debug, Clone)]
 pub enum ToOverlordMessage {
+    /// Calls [accept_badge](crate::Overlord::accept_badge)
+    /// pass 'false' as the second parameter to remove the badge from our profile
+    AcceptBadge(Id, bool),
+
     /// Calls [add_relay](crate::Overlord::add_relay)
     AddRelay(RelayUrl),
 
@@ -28,6 +36,9 @@ pub enum ToOverlordMessage {
     /// Calls [advertise_relay_list_one](crate::Overlord::advertise_relay_list)
     AdvertiseRelayListOne(RelayUrl, Box<Event>, Box<Event>),
 
+    /// Calls [approve_community_post](crate::Overlord::approve_community_post)
+    ApproveCommunityPost(PublicKey, String, Id),
+
     /// Calls [auth_approved](crate::Overlord::auth_approved)
     /// pass 'true' as the second parameter for a permanent approval
     AuthApproved(RelayUrl, bool),
@@ -36,9 +47,20 @@ pub enum ToOverlordMessage {
     /// pass 'true' as the second parameter for a permanent approval
     AuthDeclined(RelayUrl, bool),
 
+    /// Calls [award_badge](crate::Overlord::award_badge)
+    AwardBadge(String, Vec<PublicKey>),
+
+    /// Calls [blossom_delete](crate::Overlord::blossom_delete)
+    /// Deletes our blob (by sha256) from all of our blossom servers
+    BlossomDelete(String),
+
+    /// Calls [blossom_list](crate::Overlord::blossom_list)
+    /// Lists our blobs on all of our blossom servers
+    BlossomList,
+
     /// Calls [blossom_upload](crate::Overlord::blossom_upload)
-    /// Uploads the local file to a blossom server
-    BlossomUpload(PathBuf),
+    /// Uploads the local file to our blossom servers. Pass 'true' if it is for a DM.
+    BlossomUpload(PathBuf, bool),
 
     /// Calls [bookmark_add](crate::Overlord::bookmark_add)
     /// Adds a bookmark, possibly privately, and publishes new bookmarks list
@@ -62,6 +84,13 @@ pub enum ToOverlordMessage {
     /// pass 'true' as the second parameter for a permanent approval
     ConnectDeclined(RelayUrl, bool),
 
+    /// Calls [count](crate::Overlord::count)
+    /// Asks relays to COUNT these things (NIP-45), results go into GLOBALS.counts
+    Count(Vec<CountKind>),
+
+    /// Calls [create_badge](crate::Overlord::create_badge)
+    CreateBadge(BadgeDefinition),
+
     /// Calls [delegation_reset](crate::Overlord::delegation_reset)
     DelegationReset,
 
@@ -77,6 +106,18 @@ pub enum ToOverlordMessage {
     /// Calls [delete_pub](crate::Overlord::delete_pub)
     DeletePub,
 
+    /// Calls [discover_chat_channels](crate::Overlord::discover_chat_channels)
+    DiscoverChatChannels,
+
+    /// Calls [discover_communities](crate::Overlord::discover_communities)
+    DiscoverCommunities,
+
+    /// Calls [discover_dvms](crate::Overlord::discover_dvms)
+    DiscoverDvms,
+
+    /// Calls [discover_groups](crate::Overlord::discover_groups)
+    DiscoverGroups(RelayUrl),
+
     /// Calls [drop_relay](crate::Overlord::drop_relay)
     DropRelay(RelayUrl),
 
@@ -98,6 +139,9 @@ pub enum ToOverlordMessage {
     /// Calls [generate_private_key](crate::Overlord::generate_private_key)
     GeneratePrivateKey(String),
 
+    /// Calls [hide_channel_message](crate::Overlord::hide_channel_message)
+    HideChannelMessage(Id),
+
     /// Calls [hide_or_show_relay](crate::Overlord::hide_or_show_relay)
     HideOrShowRelay(RelayUrl, bool),
 
@@ -111,6 +155,12 @@ pub enum ToOverlordMessage {
     /// Calls [import_pub](crate::Overlord::import_pub)
     ImportPub(String),
 
+    /// Calls [join_group](crate::Overlord::join_group)
+    JoinGroup(RelayUrl, String),
+
+    /// Calls [leave_group](crate::Overlord::leave_group)
+    LeaveGroup(RelayUrl, String),
+
     /// Calls [load_more_current_feed](crate::Overlord::load_more_current_feed)
     LoadMoreCurrentFeed,
 
@@ -120,16 +170,35 @@ pub enum ToOverlordMessage {
     /// internal (minions use this channel too)
     MinionJobUpdated(RelayUrl, u64, u64),
 
+    /// Calls [mute_channel_user](crate::Overlord::mute_channel_user)
+    MuteChannelUser(PublicKey),
+
     /// Calls [nip46_server_op_approval_response](crate::Overlord::nip46_server_op_approval_response)
     Nip46ServerOpApprovalResponse(PublicKey, ParsedCommand, Approval),
 
+    /// Calls [nip51_list_add](crate::Overlord::nip51_list_add)
+    /// Adds an entry (possibly privately) to one of our NIP-51 lists or sets, and publishes it
+    Nip51ListAdd(EventKind, String, Tag, bool),
+
+    /// Calls [nip51_list_delete](crate::Overlord::nip51_list_delete)
+    /// Deletes one of our NIP-51 sets
+    Nip51ListDelete(EventKind, String),
+
+    /// Calls [nip51_list_rm](crate::Overlord::nip51_list_rm)
+    /// Removes the entry with this tagname and value from one of our NIP-51 lists or sets
+    Nip51ListRm(EventKind, String, String, String),
+
+    /// Calls [nip51_list_save](crate::Overlord::nip51_list_save)
+    /// Publishes one of our NIP-51 lists or sets (e.g. after creating it or renaming it)
+    Nip51ListSave(Nip51List),
+
     /// Calls [post](crate::Overlord::post)
     Post {
         content: String,
         tags: Vec<Tag>,
         in_reply_to: Option<Id>,
         annotation: bool,
-        dm_channel: Option<DmChannel>,
+        destination: PostDestination,
     },
 
     /// Calls [post_again](crate::Overlord::post_again)
@@ -156,11 +225,17 @@ pub enum ToOverlordMessage {
     /// Calls [push_metadata](crate::Overlord::push_metadata)
     PushMetadata(Metadata),
 
+    /// Calls [push_status](crate::Overlord::push_status)
+    PushStatus(String, String, Option<Unixtime>),
+
     /// Calls [rank_relay](crate::Overlord::rank_relay)
     RankRelay(RelayUrl, u8),
 
     /// Calls [react](crate::Overlord::react)
-    React(Id, PublicKey, char),
+    React(Id, PublicKey, String),
+
+    /// Calls [react_to_website](crate::Overlord::react_to_website)
+    ReactToWebsite(String, String),
 
     /// internal (the overlord sends messages to itself sometimes!)
     ReengageMinion(RelayUrl, Vec<RelayJob>),
@@ -174,27 +249,56 @@ pub enum ToOverlordMessage {
     /// Calls [repost](crate::Overlord::repost)
     Repost(Id),
 
+    /// Calls [search_dms](crate::Overlord::search_dms)
+    SearchDms(DmSearch),
+
     /// Calls [search](crate::Overlord::search_locally)
     SearchLocally(String),
 
     /// Calls [search](crate::Overlord::search_relays)
     SearchRelays(String),
 
+    /// Calls [send_file_message](crate::Overlord::send_file_message)
+    /// Encrypts the local file, uploads it to our blossom server, and sends it in a
+    /// NIP-17 file message
+    SendFileMessage(PathBuf, DmChannel),
+
     /// Calls [set_active_person](crate::Overlord::set_active_person)
     SetActivePerson(PublicKey),
 
+    /// internal
+    SetChatChannel(Id),
+
+    /// internal
+    SetCommunityFeed(PublicKey, String),
+
+    /// internal
+    SetCustomFeed(String, Unixtime),
+
     /// internal
     SetDmChannel(DmChannel),
 
+    /// internal
+    SetDvmFeed(PublicKey),
+
     /// internal
     SetGlobalFeed(Unixtime),
 
+    /// internal
+    SetGroupFeed(RelayUrl, String),
+
+    /// internal
+    SetHashtagFeed(String, Unixtime),
+
     /// internal
     SetPersonFeed(PublicKey, Unixtime),
 
     /// internal
     SetRelayFeed(RelayUrl, Unixtime),
 
+    /// internal
+    SetRelaySetFeed(String, Unixtime),
+
     /// internal
     SetThreadFeed {
         id: Id,
@@ -257,6 +361,26 @@ pub enum ToOverlordMessage {
     Zap(Id, PublicKey, MilliSatoshi, String),
 }
 
+/// Where a post is going
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PostDestination {
+    /// A regular note (or reply)
+    #[default]
+    Note,
+
+    /// A DM channel
+    Dm(DmChannel),
+
+    /// A public chat channel, by its creation event
+    Channel(Id),
+
+    /// A relay-based group, by its relay and id
+    Group(RelayUrl, String),
+
+    /// A moderated community, by owner and 'd' tag
+    Community(PublicKey, String),
+}
+
 /// Internal to gossip-lib.
 /// This is a message sent to the minions
 #[derive(Debug, Clone)]
@@ -288,6 +412,7 @@ pub(crate) enum ToMinionPayloadDetail {
     AdvertiseRelayList(Box<Event>, Box<Event>),
     AuthApproved,
     AuthDeclined,
+    Count(Vec<CountKind>),
     FetchEvent(Id),
     FetchNAddr(NAddr),
     PostEvents(Vec<Event>),
@@ -323,6 +448,17 @@ pub enum RelayConnectionReason {
     Search,
     SubscribePerson,
     SubscribeGlobal,
+    SubscribeHashtag,
+    SubscribeCustomFeed,
+    DiscoverDvms,
+    DvmJob,
+    SubscribeChatChannel,
+    DiscoverChatChannels,
+    SubscribeGroup,
+    DiscoverGroups,
+    GroupMembership,
+    SubscribeCommunity,
+    DiscoverCommunities,
 }
 
 impl fmt::Display for RelayConnectionReason {
@@ -360,6 +496,17 @@ impl RelayConnectionReason {
             Search => "Search",
             SubscribePerson => "Subscribe to the events of a person",
             SubscribeGlobal => "Subscribe to the global feed on a relay",
+            SubscribeHashtag => "Subscribe to a hashtag feed",
+            SubscribeCustomFeed => "Subscribe to a custom feed",
+            DiscoverDvms => "Searching for data vending machines",
+            DvmJob => "Requesting a feed from a data vending machine",
+            SubscribeChatChannel => "Reading a public chat channel",
+            DiscoverChatChannels => "Searching for public chat channels",
+            SubscribeGroup => "Reading a relay-based group",
+            DiscoverGroups => "Searching for relay-based groups",
+            GroupMembership => "Joining or leaving a relay-based group",
+            SubscribeCommunity => "Reading a moderated community",
+            DiscoverCommunities => "Searching for moderated communities",
         }
     }
 
@@ -390,6 +537,17 @@ impl RelayConnectionReason {
             Search => false,
             SubscribePerson => false,
             SubscribeGlobal => false,
+            SubscribeHashtag => false,
+            SubscribeCustomFeed => false,
+            DiscoverDvms => false,
+            DvmJob => false,
+            SubscribeChatChannel => false,
+            DiscoverChatChannels => false,
+            SubscribeGroup => false,
+            DiscoverGroups => false,
+            GroupMembership => false,
+            SubscribeCommunity => false,
+            DiscoverCommunities => false,
         }
     }
 }
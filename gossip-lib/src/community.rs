@@ -0,0 +1,258 @@
+//! NIP-72 moderated communities
+//!
+//! A community is defined by its owner with a kind 34550 event listing its moderators
+//! and preferred relays. Posts are submitted to a community with an 'a' tag referencing
+//! that definition (kind 1111 comments, or kind 1 notes from older clients). A post only
+//! shows in the community once a moderator approves it with a kind 4550 event.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use crate::lists::Nip51List;
+use nostr_types::{
+    Event, EventKind, Filter, Id, NAddr, PreEvent, PublicKey, RelayUrl, Tag, Unixtime,
+};
+
+/// A comment, which is how posts are submitted to a community
+pub const COMMENT: u32 = 1111;
+
+/// A NIP-72 moderated community
+#[derive(Debug, Clone, PartialEq)]
+pub struct Community {
+    /// The owner of the community (the author of its definition)
+    pub author: PublicKey,
+
+    /// The 'd' tag of its definition
+    pub d: String,
+
+    pub name: String,
+    pub description: String,
+    pub image: String,
+
+    /// The moderators, not including the owner
+    pub moderators: Vec<PublicKey>,
+
+    /// The relays the community prefers
+    pub relays: Vec<RelayUrl>,
+}
+
+impl Community {
+    /// Interpret a community definition event
+    pub fn from_event(event: &Event) -> Result<Community, Error> {
+        if event.kind != EventKind::CommunityDefinition {
+            return Err(ErrorKind::WrongEventKind.into());
+        }
+
+        let mut community = Community {
+            author: event.pubkey,
+            d: event.parameter().unwrap_or("".to_owned()),
+            name: "".to_owned(),
+            description: "".to_owned(),
+            image: "".to_owned(),
+            moderators: vec![],
+            relays: vec![],
+        };
+
+        for tag in event.tags.iter() {
+            match tag.tagname() {
+                "name" => community.name = tag.value().to_owned(),
+                "description" => community.description = tag.value().to_owned(),
+                "image" => community.image = tag.value().to_owned(),
+                "p" => {
+                    if tag.get_index(3) == "moderator" {
+                        if let Ok((pubkey, _, _)) = tag.parse_pubkey() {
+                            if !community.moderators.contains(&pubkey) {
+                                community.moderators.push(pubkey);
+                            }
+                        }
+                    }
+                }
+                "relay" => {
+                    if let Ok(url) = RelayUrl::try_from_str(tag.value()) {
+                        if !community.relays.contains(&url) {
+                            community.relays.push(url);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(community)
+    }
+
+    /// The name of the community, falling back to its 'd' tag
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() {
+            self.d.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// The address of the community definition
+    pub fn naddr(&self) -> NAddr {
+        NAddr {
+            d: self.d.clone(),
+            relays: self.relays.iter().map(|r| r.to_unchecked_url()).collect(),
+            kind: EventKind::CommunityDefinition,
+            author: self.author,
+        }
+    }
+
+    /// The value of 'a' tags referencing this community
+    pub fn coordinate(&self) -> String {
+        coordinate(&self.author, &self.d)
+    }
+
+    /// Whether this person may approve posts (the owner or a moderator)
+    pub fn is_moderator(&self, pubkey: &PublicKey) -> bool {
+        self.author == *pubkey || self.moderators.contains(pubkey)
+    }
+
+    /// Load a community from local storage
+    pub fn load(author: PublicKey, d: &str) -> Result<Option<Community>, Error> {
+        match GLOBALS
+            .db()
+            .get_replaceable_event(EventKind::CommunityDefinition, author, d)?
+        {
+            Some(event) => Ok(Some(Self::from_event(&event)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// All the communities we know of (locally), sorted by name
+    pub fn load_all() -> Result<Vec<Community>, Error> {
+        let mut filter = Filter::new();
+        filter.add_event_kind(EventKind::CommunityDefinition);
+
+        let mut communities: Vec<Community> = Vec::new();
+        for event in GLOBALS.db().find_events_by_filter(&filter, |_| true)? {
+            let community = Self::from_event(&event)?;
+            // Events are newest first, so keep the latest definition
+            if !communities
+                .iter()
+                .any(|c| c.author == community.author && c.d == community.d)
+            {
+                communities.push(community);
+            }
+        }
+        communities.sort_by_key(|c| c.display_name().to_lowercase());
+        Ok(communities)
+    }
+
+    /// The approval events for this community by its moderators
+    fn approvals(&self) -> Result<Vec<Event>, Error> {
+        let mut filter = Filter::new();
+        filter.add_event_kind(EventKind::CommunityPostApproval);
+        filter.set_tag_values('a', vec![self.coordinate()]);
+        GLOBALS
+            .db()
+            .find_events_by_filter(&filter, |event| self.is_moderator(&event.pubkey))
+    }
+
+    /// The ids of the posts that moderators approved
+    pub fn approved_ids(&self) -> Result<Vec<Id>, Error> {
+        let mut ids: Vec<Id> = Vec::new();
+        for approval in self.approvals()? {
+            for tag in approval.tags.iter() {
+                if let Ok((id, _, _, _)) = tag.parse_event() {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+            }
+        }
+        Ok(ids)
+    }
+
+    /// The locally available posts submitted to this community
+    fn posts(&self) -> Result<Vec<Event>, Error> {
+        let mut filter = Filter::new();
+        filter.kinds = vec![EventKind::TextNote, COMMENT.into()];
+        filter.set_tag_values('a', vec![self.coordinate()]);
+        GLOBALS.db().find_events_by_filter(&filter, |_| true)
+    }
+
+    /// The locally available approved posts, newest first
+    pub fn approved_post_ids(&self) -> Result<Vec<Id>, Error> {
+        let approved = self.approved_ids()?;
+        let mut events: Vec<Event> = Vec::new();
+        for id in approved.iter() {
+            if let Some(event) = GLOBALS.db().read_event(*id)? {
+                events.push(event);
+            }
+        }
+        events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(events.iter().map(|e| e.id).collect())
+    }
+
+    /// The locally available posts that have not been approved yet, newest first
+    pub fn pending_post_ids(&self) -> Result<Vec<Id>, Error> {
+        let approved = self.approved_ids()?;
+        let mut events = self.posts()?;
+        events.retain(|e| !approved.contains(&e.id));
+        events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(events.iter().map(|e| e.id).collect())
+    }
+
+    /// Create an approval (kind 4550) of a post to this community
+    pub(crate) fn approval_event(&self, post: &Event) -> Result<Event, Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            None => return Err(ErrorKind::NoPublicKey.into()),
+            Some(pk) => pk,
+        };
+
+        let pre_event = PreEvent {
+            pubkey: public_key,
+            created_at: Unixtime::now(),
+            kind: EventKind::CommunityPostApproval,
+            tags: vec![
+                Tag::new_address(&self.naddr(), None),
+                Tag::new_event(post.id, None, None, None),
+                Tag::new_pubkey(post.pubkey, None, None),
+                Tag::new(&["k", &u32::from(post.kind).to_string()]),
+            ],
+            content: serde_json::to_string(post)?,
+        };
+
+        GLOBALS.identity.sign_event(pre_event)
+    }
+}
+
+/// The value of 'a' tags referencing a community
+pub fn coordinate(author: &PublicKey, d: &str) -> String {
+    format!(
+        "{}:{}:{}",
+        u32::from(EventKind::CommunityDefinition),
+        author.as_hex_string(),
+        d
+    )
+}
+
+/// The communities we follow (from our NIP-51 communities list)
+pub fn followed_communities() -> Vec<NAddr> {
+    match Nip51List::load_mine(EventKind::CommunityList, "") {
+        Ok(list) => list
+            .referenced_addrs()
+            .drain(..)
+            .filter(|naddr| naddr.kind == EventKind::CommunityDefinition)
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// The post embedded in an approval, if it is valid
+pub fn approved_post(approval: &Event) -> Option<Event> {
+    let post: Event = serde_json::from_str(&approval.content).ok()?;
+    if post.verify(None).is_err() {
+        return None;
+    }
+    if !approval
+        .tags
+        .iter()
+        .any(|tag| matches!(tag.parse_event(), Ok((id, _, _, _)) if id == post.id))
+    {
+        return None;
+    }
+    Some(post)
+}
@@ -0,0 +1,289 @@
+//! NIP-45 event counts
+//!
+//! We ask relays to COUNT events rather than pulling every event when all we want
+//! is a number (followers of a person, replies/reactions/reposts of a note). Relays
+//! that support HyperLogLog give us registers which we merge across relays to avoid
+//! double counting. Relays that don't only give us a count, and the best we can do
+//! with those is to take the maximum, so results are generally approximate.
+
+use dashmap::DashMap;
+use nostr_types::{EventKind, Filter, Id, PublicKey, RelayUrl, Unixtime};
+use std::collections::HashMap;
+
+/// How long (in seconds) we consider a count fresh before asking relays again
+const COUNT_FRESHNESS_SECS: i64 = 60 * 10;
+
+/// Number of HyperLogLog registers defined by NIP-45
+const HLL_REGISTERS: usize = 256;
+
+/// Something we can count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CountKind {
+    /// People who follow this pubkey (contact lists that `p` tag it)
+    Followers(PublicKey),
+
+    /// Replies to this note
+    Replies(Id),
+
+    /// Reactions to this note
+    Reactions(Id),
+
+    /// Reposts of this note
+    Reposts(Id),
+}
+
+impl CountKind {
+    /// The filter used to count this on a relay
+    pub fn filter(&self) -> Filter {
+        let mut filter = Filter::new();
+        match self {
+            CountKind::Followers(pubkey) => {
+                filter.kinds = vec![EventKind::ContactList];
+                filter.set_tag_values('p', vec![pubkey.as_hex_string()]);
+            }
+            CountKind::Replies(id) => {
+                filter.kinds = vec![EventKind::TextNote];
+                filter.set_tag_values('e', vec![id.as_hex_string()]);
+            }
+            CountKind::Reactions(id) => {
+                filter.kinds = vec![EventKind::Reaction];
+                filter.set_tag_values('e', vec![id.as_hex_string()]);
+            }
+            CountKind::Reposts(id) => {
+                filter.kinds = vec![EventKind::Repost, EventKind::GenericRepost];
+                filter.set_tag_values('e', vec![id.as_hex_string()]);
+            }
+        }
+        filter
+    }
+
+    /// The note this count is about, if it is about a note
+    pub fn note_id(&self) -> Option<Id> {
+        match self {
+            CountKind::Followers(_) => None,
+            CountKind::Replies(id) | CountKind::Reactions(id) | CountKind::Reposts(id) => Some(*id),
+        }
+    }
+
+    /// All the counts we show on a note
+    pub fn for_note(id: Id) -> Vec<CountKind> {
+        vec![
+            CountKind::Replies(id),
+            CountKind::Reactions(id),
+            CountKind::Reposts(id),
+        ]
+    }
+}
+
+/// HyperLogLog registers as returned by a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hll([u8; HLL_REGISTERS]);
+
+impl Hll {
+    /// Parse from the hex string a relay returns
+    pub fn from_hex(s: &str) -> Option<Hll> {
+        let bytes = hex::decode(s).ok()?;
+        let registers: [u8; HLL_REGISTERS] = bytes.try_into().ok()?;
+        Some(Hll(registers))
+    }
+
+    /// Merge another set of registers into this one
+    pub fn merge(&mut self, other: &Hll) {
+        for (mine, theirs) in self.0.iter_mut().zip(other.0.iter()) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+
+    /// Estimate the cardinality
+    pub fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.0.iter().map(|r| 2.0_f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        // Small range correction
+        let zeroes = self.0.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeroes > 0 {
+            (m * (m / zeroes as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// A count result from a single relay
+#[derive(Debug, Clone)]
+pub struct CountResult {
+    pub count: u64,
+    pub approximate: bool,
+    pub hll: Option<Hll>,
+}
+
+/// A count aggregated across relays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Count {
+    pub count: u64,
+
+    /// If the count may be off. This is true if any relay said so, if we estimated from
+    /// HyperLogLog registers, or if we combined plain counts from multiple relays.
+    pub approximate: bool,
+
+    /// How many relays contributed to this count
+    pub relays: usize,
+}
+
+impl std::fmt::Display for Count {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.approximate {
+            write!(f, "~{}", self.count)
+        } else {
+            write!(f, "{}", self.count)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CountEntry {
+    requested_at: Option<Unixtime>,
+    results: HashMap<RelayUrl, CountResult>,
+
+    // Results from the previous request, shown until the relay answers again
+    previous: HashMap<RelayUrl, CountResult>,
+}
+
+/// Cache of counts, keyed by what is being counted
+#[derive(Debug, Default)]
+pub struct Counts {
+    entries: DashMap<CountKind, CountEntry>,
+}
+
+impl Counts {
+    pub fn new() -> Counts {
+        Counts::default()
+    }
+
+    /// Returns true if this count is missing or stale and should be requested. This also
+    /// marks it as requested, so that callers that run every frame only ask once.
+    pub fn should_request(&self, kind: CountKind) -> bool {
+        let now = Unixtime::now();
+        let mut entry = self.entries.entry(kind).or_default();
+        if let Some(when) = entry.requested_at {
+            if now.0 - when.0 < COUNT_FRESHNESS_SECS {
+                return false;
+            }
+        }
+        entry.requested_at = Some(now);
+        if !entry.results.is_empty() {
+            entry.previous = std::mem::take(&mut entry.results);
+        }
+        true
+    }
+
+    /// Record a count result from a relay
+    pub fn record(&self, kind: CountKind, relay: RelayUrl, result: CountResult) {
+        let mut entry = self.entries.entry(kind).or_default();
+        entry.results.insert(relay, result);
+    }
+
+    /// Get the aggregated count, if any relay has answered
+    pub fn get(&self, kind: CountKind) -> Option<Count> {
+        let entry = self.entries.get(&kind)?;
+
+        // Fresh results, and previous results from relays that haven't answered again yet
+        let results: Vec<&CountResult> = entry
+            .results
+            .values()
+            .chain(
+                entry
+                    .previous
+                    .iter()
+                    .filter(|(relay, _)| !entry.results.contains_key(*relay))
+                    .map(|(_, result)| result),
+            )
+            .collect();
+        if results.is_empty() {
+            return None;
+        }
+
+        let relays = results.len();
+        let mut approximate = relays > 1;
+        let mut max: u64 = 0;
+        let mut hll: Option<Hll> = None;
+        for result in results {
+            approximate |= result.approximate;
+            max = max.max(result.count);
+            if let Some(ref h) = result.hll {
+                match hll {
+                    Some(ref mut merged) => merged.merge(h),
+                    None => hll = Some(h.clone()),
+                }
+            }
+        }
+
+        // A merged HLL deduplicates across relays, but relays that gave us no
+        // registers may know of more than it does, so we never go below the max.
+        let count = match hll {
+            Some(merged) => {
+                approximate = true;
+                merged.estimate().max(max)
+            }
+            None => max,
+        };
+
+        Some(Count {
+            count,
+            approximate,
+            relays,
+        })
+    }
+
+    /// Forget a count (so it will be requested again)
+    pub fn invalidate(&self, kind: CountKind) {
+        self.entries.remove(&kind);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hll_parse_and_merge() {
+        assert!(Hll::from_hex("00").is_none());
+
+        let empty = Hll::from_hex(&"00".repeat(HLL_REGISTERS)).unwrap();
+        assert_eq!(empty.estimate(), 0);
+
+        let mut a = Hll::from_hex(&format!("01{}", "00".repeat(HLL_REGISTERS - 1))).unwrap();
+        let b = Hll::from_hex(&format!("0001{}", "00".repeat(HLL_REGISTERS - 2))).unwrap();
+        let one = a.estimate();
+        a.merge(&b);
+        assert_eq!(one, 1);
+        assert_eq!(a.estimate(), 2);
+    }
+
+    #[test]
+    fn test_count_kept_while_refreshing() {
+        let counts = Counts::new();
+        let kind = CountKind::Reactions(Id([7; 32]));
+        let relay = RelayUrl::try_from_str("wss://relay.example.com").unwrap();
+        let result = |count| CountResult {
+            count,
+            approximate: false,
+            hll: None,
+        };
+
+        assert!(counts.should_request(kind));
+        assert!(!counts.should_request(kind));
+        counts.record(kind, relay.clone(), result(5));
+        assert_eq!(counts.get(kind).map(|c| c.count), Some(5));
+
+        // Once stale it is asked for again, but still shows until the answer comes
+        counts.entries.get_mut(&kind).unwrap().requested_at = Some(Unixtime(0));
+        assert!(counts.should_request(kind));
+        assert_eq!(counts.get(kind).map(|c| c.count), Some(5));
+
+        counts.record(kind, relay, result(6));
+        assert_eq!(counts.get(kind).map(|c| c.count), Some(6));
+    }
+}
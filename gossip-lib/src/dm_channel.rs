@@ -1,7 +1,12 @@
+use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
+use crate::storage::types::DmConversation1;
+use crate::storage::{DmConversationsTable, Table};
 use nostr_types::{Event, EventKind, PublicKey, Unixtime};
 use sha2::Digest;
 
+pub type DmConversation = DmConversation1;
+
 /// This represents a DM (direct message) channel which includes a set
 /// of participants (usually just one, but can be a small group).
 // internally the pubkeys are kept sorted so they can be compared
@@ -81,6 +86,96 @@ impl DmChannel {
         self.1
     }
 
+    /// The channel with a unique id, if we have kept its conversation
+    pub fn from_unique_id(id: &str) -> Option<DmChannel> {
+        match DmConversationsTable::read_record(id.to_owned(), None) {
+            Ok(Some(conversation)) => Some(DmChannel::new(&conversation.members)),
+            _ => None,
+        }
+    }
+
+    /// What we keep about the conversation in this channel
+    pub fn conversation(&self) -> DmConversation {
+        match DmConversationsTable::read_record(self.unique_id(), None) {
+            Ok(Some(conversation)) => conversation,
+            _ => DmConversation {
+                id: self.unique_id(),
+                members: self.0.clone(),
+                subject: None,
+                subject_at: 0,
+                predecessor: None,
+                successor: None,
+                muted: false,
+                archived: false,
+                pinned: false,
+                read_until: 0,
+            },
+        }
+    }
+
+    /// Change what we keep about the conversation in this channel
+    pub fn modify_conversation<M>(&self, modify: M) -> Result<(), Error>
+    where
+        M: FnOnce(&mut DmConversation),
+    {
+        let mut conversation = self.conversation();
+        modify(&mut conversation);
+        DmConversationsTable::write_record(&mut conversation, None)
+    }
+
+    /// The subject of the conversation if it has one, else the names of its members
+    pub fn title(&self) -> String {
+        match self.conversation().subject {
+            Some(subject) => subject,
+            None => self.name(),
+        }
+    }
+
+    /// Note a subject set in the conversation, if it is newer than the one we have
+    pub(crate) fn saw_subject(&self, subject: &str, at: Unixtime) -> Result<(), Error> {
+        let conversation = self.conversation();
+        if at.0 > conversation.subject_at && conversation.subject.as_deref() != Some(subject) {
+            self.modify_conversation(|c| {
+                c.subject = Some(subject.to_owned());
+                c.subject_at = at.0;
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Mark every message up to now as read, including any we fetch later
+    pub fn mark_read(&self) -> Result<(), Error> {
+        GLOBALS.db().mark_dm_channel_read(self)?;
+        self.modify_conversation(|c| c.read_until = Unixtime::now().0)
+    }
+
+    /// Start a new conversation with different members, which continues this one (and
+    /// keeps its subject). NIP-17 conversations are defined by their members, so this is
+    /// the only way to add or remove someone.
+    pub fn change_members(
+        &self,
+        add: &[PublicKey],
+        remove: &[PublicKey],
+    ) -> Result<DmChannel, Error> {
+        let members = changed_members(&self.0, add, remove, GLOBALS.identity.public_key());
+        if members.is_empty() || members == self.0 {
+            return Err(ErrorKind::General("The members have not changed".to_owned()).into());
+        }
+        let successor = DmChannel::new(&members);
+
+        let conversation = self.conversation();
+        successor.modify_conversation(|c| {
+            c.predecessor = Some(conversation.id.clone());
+            if c.subject.is_none() {
+                c.subject = conversation.subject.clone();
+                c.subject_at = conversation.subject_at;
+            }
+        })?;
+        self.modify_conversation(|c| c.successor = Some(successor.unique_id()))?;
+
+        Ok(successor)
+    }
+
     pub fn from_event(event: &Event, my_pubkey: Option<PublicKey>) -> Option<DmChannel> {
         let my_pubkey = match my_pubkey {
             Some(pk) => pk,
@@ -110,7 +205,7 @@ impl DmChannel {
             } else {
                 None
             }
-        } else if event.kind == EventKind::DmChat {
+        } else if event.kind == EventKind::DmChat || crate::nip17::is_file_message(event.kind) {
             // unwrapped rumor
             let mut people: Vec<PublicKey> = event.people().iter().map(|(pk, _, _)| *pk).collect();
             people.push(event.pubkey); // include author too
@@ -122,8 +217,28 @@ impl DmChannel {
     }
 }
 
+// The members of a conversation after adding and removing some, sorted as a DmChannel
+// keeps them, and never including us
+fn changed_members(
+    members: &[PublicKey],
+    add: &[PublicKey],
+    remove: &[PublicKey],
+    me: Option<PublicKey>,
+) -> Vec<PublicKey> {
+    let mut members: Vec<PublicKey> = members
+        .iter()
+        .filter(|pk| !remove.contains(pk))
+        .chain(add.iter())
+        .filter(|pk| Some(**pk) != me)
+        .copied()
+        .collect();
+    members.sort();
+    members.dedup();
+    members
+}
+
 /// Data about a DM channel such as when the latest message occurred, how many massages
-/// it has, and how many are unread.
+/// it has, how many are unread, and what we keep about its conversation.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DmChannelData {
     pub dm_channel: DmChannel,
@@ -131,4 +246,44 @@ pub struct DmChannelData {
     pub latest_message_content: Option<String>,
     pub message_count: usize,
     pub unread_message_count: usize,
+    pub subject: Option<String>,
+    pub muted: bool,
+    pub archived: bool,
+    pub pinned: bool,
+    /// Whether a conversation with different members continues this one
+    pub continued: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pubkey(hex: &str) -> PublicKey {
+        PublicKey::try_from_hex_string(hex, false).unwrap()
+    }
+
+    #[test]
+    fn test_changed_members() {
+        let me = pubkey("ce4e68468c717280aa2fdd9db282897c969c172ba06fd7096b785c3c3ce79903");
+        let a = pubkey("32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245");
+        let b = pubkey("3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d");
+        let c = pubkey("82341f882b6eabcd2ba7f1ef90aad961cf074af15b9ef44a09f9d2a8fbfbe6a2");
+
+        let mut sorted = vec![a, b, c];
+        sorted.sort();
+
+        // Adding
+        let members = changed_members(&[b], &[c, a], &[], Some(me));
+        assert_eq!(members, sorted);
+
+        // Removing, and adding someone already there
+        let members = changed_members(&sorted, &[a], &[b], Some(me));
+        let mut expected = vec![a, c];
+        expected.sort();
+        assert_eq!(members, expected);
+
+        // We are never a member
+        assert_eq!(changed_members(&[a], &[me], &[], Some(me)), vec![a]);
+        assert!(changed_members(&[a], &[], &[a], Some(me)).is_empty());
+    }
 }
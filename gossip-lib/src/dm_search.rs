@@ -0,0 +1,570 @@
+//! Searching direct messages
+//!
+//! DMs are encrypted, and giftwrapped DMs are slow to open, so we can't search them the
+//! way we search notes. Instead, each DM is indexed as we open it: what we search on is
+//! encrypted (AES-GCM, under a key derived from our private key) and stored under HMACs
+//! of its channel and id. Searching needs one cheap decryption per message rather than
+//! unwrapping every giftwrap, and the index leaks nothing the giftwraps do not.
+
+use crate::dm_channel::DmChannel;
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use crate::storage::types::{ByteRep, DmSearchEntry1, Record};
+use crate::storage::{DmSearchTable, Table};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use heed::RoTxn;
+use hmac::{Hmac, Mac};
+use nostr_types::{Event, EventKind, Filter, Id, PublicKey, Tag, Unixtime};
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use speedy::{Readable, Writable};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Whether the index is being rebuilt, so rebuilds don't overlap
+static REBUILDING: AtomicBool = AtomicBool::new(false);
+
+// Messages indexed while a rebuild is running, which it must not clear away. Indexing
+// and the end of a rebuild happen under this lock.
+static INDEXED_DURING_REBUILD: Mutex<Vec<DmSearchEntry1>> = Mutex::new(Vec::new());
+
+/// What to search DMs for. Everything given must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DmSearch {
+    /// Words that must all appear in the message (in any case)
+    pub text: String,
+
+    /// Someone who must be in the conversation
+    pub participant: Option<PublicKey>,
+
+    /// Only messages sent at or after this time
+    pub since: Option<Unixtime>,
+
+    /// Only messages sent at or before this time
+    pub until: Option<Unixtime>,
+}
+
+impl DmSearch {
+    /// Whether there is anything to search for
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+            && self.participant.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+}
+
+/// A DM found by a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DmSearchResult {
+    /// The channel it is in
+    pub channel: DmChannel,
+
+    /// The title of that channel
+    pub title: String,
+
+    /// Its id (the id of the giftwrap, if it was giftwrapped)
+    pub id: Id,
+
+    /// When it was sent
+    pub created_at: Unixtime,
+
+    /// Who sent it
+    pub author: PublicKey,
+
+    /// Its text
+    pub text: String,
+}
+
+// What we encrypt of a DM
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Writable)]
+struct IndexedMessage {
+    id: Id,
+    created_at: i64,
+    author: PublicKey,
+    members: Vec<PublicKey>,
+    text: String,
+}
+
+// The key the index is encrypted under. Only available while the private key is.
+fn index_key() -> Result<[u8; 32], Error> {
+    let pubkey = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+    let conversation_key = GLOBALS.identity.nip44_conversation_key(&pubkey)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"gossip dm search index");
+    hasher.update(conversation_key);
+    Ok(hasher.finalize().into())
+}
+
+// An HMAC under the index key (hex), so table keys can't be matched to channels or
+// messages without it
+fn mac(key: &[u8; 32], data: &[u8]) -> Result<String, Error> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+        .map_err(|_| ErrorKind::General("DM search index key is unusable".to_owned()))?;
+    mac.update(data);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+fn encrypt(
+    key: &[u8; 32],
+    channel_id: &str,
+    message: &IndexedMessage,
+) -> Result<DmSearchEntry1, Error> {
+    let nonce: [u8; 12] = rand::random();
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            message.write_to_vec()?.as_slice(),
+        )
+        .map_err(|_| ErrorKind::General("DM search index encryption failed".to_owned()))?;
+
+    Ok(DmSearchEntry1 {
+        channel: mac(key, channel_id.as_bytes())?,
+        message: mac(key, message.id.as_slice())?,
+        nonce: nonce.to_vec(),
+        ciphertext,
+    })
+}
+
+fn decrypt(entry: &DmSearchEntry1, key: &[u8; 32]) -> Result<IndexedMessage, Error> {
+    if entry.nonce.len() != 12 {
+        return Err(
+            ErrorKind::General("DM search index nonce is the wrong size".to_owned()).into(),
+        );
+    }
+    let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+        .decrypt(Nonce::from_slice(&entry.nonce), entry.ciphertext.as_slice())
+        .map_err(|_| ErrorKind::General("DM search index decryption failed".to_owned()))?;
+    Ok(IndexedMessage::read_from_buffer(&plaintext)?)
+}
+
+// The text we index of a NIP-17 rumor
+fn rumor_text(rumor: &Event) -> Option<String> {
+    if rumor.kind == EventKind::DmChat {
+        Some(chat_text(&rumor.content, &rumor.tags))
+    } else if crate::nip17::is_file_message(rumor.kind) {
+        Some(
+            match crate::nip17::file_message(rumor).and_then(|fm| fm.mime) {
+                Some(mime) => format!("[File] {}", mime),
+                None => "[File]".to_owned(),
+            },
+        )
+    } else {
+        None
+    }
+}
+
+// The text of a chat message, with its subject
+fn chat_text(content: &str, tags: &[Tag]) -> String {
+    let mut text = content.to_owned();
+    if let Some(tag) = tags.iter().find(|t| t.tagname() == "subject") {
+        text.push('\n');
+        text.push_str(tag.value());
+    }
+    text
+}
+
+// The channel of a DM, and what we index of it. This takes NIP-04 DMs as stored and
+// NIP-17 rumors already unwrapped (with the id of their giftwrap).
+fn prepare(event: &Event) -> Option<(DmChannel, IndexedMessage)> {
+    let text = if event.kind == EventKind::EncryptedDirectMessage {
+        GLOBALS.identity.decrypt_event_contents(event).ok()?
+    } else {
+        rumor_text(event)?
+    };
+
+    let channel = DmChannel::from_event(event, None)?;
+    let message = IndexedMessage {
+        id: event.id,
+        created_at: event.created_at.0,
+        author: event.pubkey,
+        members: channel.keys().to_vec(),
+        text,
+    };
+    Some((channel, message))
+}
+
+/// Add a DM to the search index: a NIP-04 DM, or a NIP-17 rumor already unwrapped and
+/// given the id of its giftwrap. Other events are ignored, as are DMs we can't open
+/// (they are indexed when the index is next rebuilt).
+pub(crate) fn index(event: &Event) -> Result<(), Error> {
+    if !GLOBALS.identity.is_unlocked() {
+        return Ok(());
+    }
+
+    let (channel, message) = match prepare(event) {
+        Some(prepared) => prepared,
+        None => return Ok(()),
+    };
+
+    let key = index_key()?;
+    let mut entry = encrypt(&key, &channel.unique_id(), &message)?;
+
+    let mut during_rebuild = INDEXED_DURING_REBUILD.lock();
+    if DmSearchTable::read_record(entry.key(), None)?.is_some() {
+        return Ok(());
+    }
+    DmSearchTable::write_record(&mut entry, None)?;
+    if REBUILDING.load(Ordering::SeqCst) {
+        during_rebuild.push(entry);
+    }
+    Ok(())
+}
+
+/// Add giftwrapped DMs to the search index, unwrapping them
+pub(crate) fn index_giftwraps(events: &[Event]) -> Result<(), Error> {
+    if !GLOBALS.identity.is_unlocked() {
+        return Ok(());
+    }
+    let key = index_key()?;
+
+    let mut entries: Vec<DmSearchEntry1> = Vec::new();
+    for event in events.iter().filter(|e| e.kind == EventKind::GiftWrap) {
+        if let Some(entry) = index_entry(event, &key)? {
+            entries.push(entry);
+        }
+    }
+
+    let mut during_rebuild = INDEXED_DURING_REBUILD.lock();
+    let mut txn = GLOBALS.db().get_write_txn()?;
+    for entry in entries.iter_mut() {
+        DmSearchTable::write_record(entry, Some(&mut txn))?;
+    }
+    txn.commit()?;
+    if REBUILDING.load(Ordering::SeqCst) {
+        during_rebuild.extend(entries);
+    }
+
+    Ok(())
+}
+
+// The index entry of a DM as stored (unwrapping it if it is giftwrapped)
+fn index_entry(event: &Event, key: &[u8; 32]) -> Result<Option<DmSearchEntry1>, Error> {
+    let prepared = if event.kind == EventKind::GiftWrap {
+        let mut rumor = match GLOBALS.identity.unwrap_giftwrap(event) {
+            Ok(rumor) => rumor.into_event_with_bad_signature(),
+            Err(_) => return Ok(None),
+        };
+        rumor.id = event.id;
+        prepare(&rumor)
+    } else {
+        prepare(event)
+    };
+    match prepared {
+        Some((channel, message)) => Ok(Some(encrypt(key, &channel.unique_id(), &message)?)),
+        None => Ok(None),
+    }
+}
+
+/// Rebuild the search index from all the DMs we have. Returns how many were indexed.
+/// This is slow, so call it with spawn_blocking. Does nothing if a rebuild is already
+/// running.
+pub fn rebuild() -> Result<usize, Error> {
+    if REBUILDING.swap(true, Ordering::SeqCst) {
+        return Ok(0);
+    }
+    let result = inner_rebuild();
+    REBUILDING.store(false, Ordering::SeqCst);
+    INDEXED_DURING_REBUILD.lock().clear();
+    result
+}
+
+fn inner_rebuild() -> Result<usize, Error> {
+    if !GLOBALS.identity.is_unlocked() {
+        return Err(ErrorKind::NoPrivateKey.into());
+    }
+    let key = index_key()?;
+
+    let mut filter = Filter::new();
+    filter.kinds = vec![EventKind::EncryptedDirectMessage, EventKind::GiftWrap];
+    let events = GLOBALS.db().find_events_by_filter(&filter, |_| true)?;
+
+    // Unwrap and encrypt before taking the lock, as that is the slow part
+    let mut entries: Vec<DmSearchEntry1> = Vec::new();
+    for event in &events {
+        if let Some(entry) = index_entry(event, &key)? {
+            entries.push(entry);
+        }
+    }
+
+    let mut during_rebuild = INDEXED_DURING_REBUILD.lock();
+    let mut txn = GLOBALS.db().get_write_txn()?;
+    DmSearchTable::clear(Some(&mut txn))?;
+    for entry in entries.iter_mut().chain(during_rebuild.iter_mut()) {
+        DmSearchTable::write_record(entry, Some(&mut txn))?;
+    }
+    GLOBALS
+        .db()
+        .set_flag_rebuild_dm_search_needed(false, Some(&mut txn))?;
+    txn.commit()?;
+    during_rebuild.clear();
+
+    tracing::info!("Indexed {} DMs for search", entries.len());
+
+    Ok(entries.len())
+}
+
+// The words of a search, in lower case
+fn search_words(query: &DmSearch) -> Vec<String> {
+    query
+        .text
+        .split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+// Whether a message matches a search
+fn matches(query: &DmSearch, words: &[String], message: &IndexedMessage) -> bool {
+    if let Some(participant) = query.participant {
+        if !message.members.contains(&participant) {
+            return false;
+        }
+    }
+    if query
+        .since
+        .is_some_and(|since| message.created_at < since.0)
+        || query
+            .until
+            .is_some_and(|until| message.created_at > until.0)
+    {
+        return false;
+    }
+    let text = message.text.to_lowercase();
+    words.iter().all(|w| text.contains(w.as_str()))
+}
+
+// The HMACs of the channels someone is in. A channel's messages are stored together
+// under the HMAC of the channel, so this opens just one message of each channel. Also
+// returns whether any of those could not be opened.
+fn participant_channels(
+    txn: &RoTxn<'_>,
+    key: &[u8; 32],
+    participant: PublicKey,
+) -> Result<(Vec<String>, bool), Error> {
+    let db = DmSearchTable::db()?;
+    let mut channels: Vec<String> = Vec::new();
+    let mut undecryptable = false;
+
+    let mut found = db.first(txn)?;
+    while let Some((_, valbytes)) = found {
+        let entry = DmSearchEntry1::from_bytes(valbytes)?;
+        match decrypt(&entry, key) {
+            Ok(message) if message.members.contains(&participant) => {
+                channels.push(entry.channel.clone())
+            }
+            Ok(_) => {}
+            Err(_) => undecryptable = true,
+        }
+
+        // Skip the rest of this channel (the HMACs are lowercase hex)
+        let past = format!("{}g", entry.channel);
+        found = db.get_greater_than_or_equal_to(txn, past.as_bytes())?;
+    }
+
+    Ok((channels, undecryptable))
+}
+
+/// Search DMs, newest first
+pub fn search(query: &DmSearch) -> Result<Vec<DmSearchResult>, Error> {
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+    let key = index_key()?;
+    let words = search_words(query);
+
+    // Only the channels of the participant need be scanned
+    let mut entries: Vec<DmSearchEntry1> = Vec::new();
+    let mut undecryptable = false;
+    {
+        let txn = GLOBALS.db().get_read_txn()?;
+        match query.participant {
+            Some(participant) => {
+                let (channels, failed) = participant_channels(&txn, &key, participant)?;
+                undecryptable |= failed;
+                let db = DmSearchTable::db()?;
+                for channel in channels.iter() {
+                    for result in db.prefix_iter(&txn, channel.as_bytes())? {
+                        let (_, valbytes) = result?;
+                        entries.push(DmSearchEntry1::from_bytes(valbytes)?);
+                    }
+                }
+            }
+            None => entries.extend(DmSearchTable::iter(&txn)?.map(|(_, entry)| entry)),
+        }
+    }
+
+    // The channels of the results and their titles, by the HMAC they are stored under
+    let mut channels: HashMap<String, (DmChannel, String)> = HashMap::new();
+
+    let mut results: Vec<DmSearchResult> = Vec::new();
+    for entry in entries.drain(..) {
+        let message = match decrypt(&entry, &key) {
+            Ok(message) => message,
+            Err(_) => {
+                undecryptable = true;
+                continue;
+            }
+        };
+        if !matches(query, &words, &message) {
+            continue;
+        }
+        let (channel, title) = channels
+            .entry(entry.channel)
+            .or_insert_with(|| {
+                let channel = DmChannel::new(&message.members);
+                let title = channel.title();
+                (channel, title)
+            })
+            .clone();
+        results.push(DmSearchResult {
+            channel,
+            title,
+            id: message.id,
+            created_at: Unixtime(message.created_at),
+            author: message.author,
+            text: message.text,
+        });
+    }
+
+    // Entries we can't open were made under another key (or are damaged), so
+    // index again rather than miss them in every search
+    if undecryptable && !GLOBALS.db().get_flag_rebuild_dm_search_needed() {
+        tracing::warn!("The DM search index has entries we can't read, rebuilding it");
+        GLOBALS.db().set_flag_rebuild_dm_search_needed(true, None)?;
+    }
+
+    results.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pubkey(hex: &str) -> PublicKey {
+        PublicKey::try_from_hex_string(hex, false).unwrap()
+    }
+
+    fn message() -> IndexedMessage {
+        let alice = pubkey("32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245");
+        let bob = pubkey("3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d");
+        IndexedMessage {
+            id: Id([9; 32]),
+            created_at: 1700000000,
+            author: alice,
+            members: vec![alice, bob],
+            text: chat_text(
+                "Shall we meet at the Harbour Cafe?",
+                &[Tag::new(&["subject", "Saturday lunch"])],
+            ),
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = [7; 32];
+        let message = message();
+
+        let entry = encrypt(&key, "channel", &message).unwrap();
+        assert_eq!(decrypt(&entry, &key).unwrap(), message);
+
+        // Nothing readable is stored
+        assert_eq!(entry.nonce.len(), 12);
+        assert!(!String::from_utf8_lossy(&entry.ciphertext).contains("Harbour"));
+        assert_ne!(entry.channel, "channel");
+
+        // The same message is stored under the same key, encrypted afresh
+        let again = encrypt(&key, "channel", &message).unwrap();
+        assert_eq!(again.key(), entry.key());
+        assert_ne!(again.ciphertext, entry.ciphertext);
+
+        // Under another key it is stored elsewhere, and can't be read
+        let other = encrypt(&[8; 32], "channel", &message).unwrap();
+        assert_ne!(other.key(), entry.key());
+        assert!(decrypt(&entry, &[8; 32]).is_err());
+
+        let mut tampered = entry.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(decrypt(&tampered, &key).is_err());
+        tampered.nonce.truncate(8);
+        assert!(decrypt(&tampered, &key).is_err());
+    }
+
+    #[test]
+    fn test_mac() {
+        let a = mac(&[1; 32], b"channel").unwrap();
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, mac(&[1; 32], b"channel").unwrap());
+        assert_ne!(a, mac(&[2; 32], b"channel").unwrap());
+        assert_ne!(a, mac(&[1; 32], b"channe1").unwrap());
+    }
+
+    #[test]
+    fn test_matches() {
+        let message = message();
+        let search = |text: &str| DmSearch {
+            text: text.to_owned(),
+            ..Default::default()
+        };
+        let is_match = |query: &DmSearch| matches(query, &search_words(query), &message);
+
+        assert!(is_match(&search("harbour")));
+        assert!(is_match(&search("  CAFE   meet ")));
+        assert!(is_match(&search("saturday")));
+        assert!(!is_match(&search("harbour dinner")));
+
+        let query = DmSearch {
+            participant: Some(message.members[1]),
+            ..search("lunch")
+        };
+        assert!(is_match(&query));
+        let query = DmSearch {
+            participant: Some(pubkey(
+                "82341f882b6eabcd2ba7f1ef90aad961cf074af15b9ef44a09f9d2a8fbfbe6a2",
+            )),
+            ..search("lunch")
+        };
+        assert!(!is_match(&query));
+
+        let query = DmSearch {
+            since: Some(Unixtime(1700000000)),
+            until: Some(Unixtime(1700000000)),
+            ..Default::default()
+        };
+        assert!(is_match(&query));
+        let query = DmSearch {
+            since: Some(Unixtime(1700000001)),
+            ..Default::default()
+        };
+        assert!(!is_match(&query));
+        let query = DmSearch {
+            until: Some(Unixtime(1699999999)),
+            ..Default::default()
+        };
+        assert!(!is_match(&query));
+    }
+
+    #[test]
+    fn test_chat_text() {
+        assert_eq!(chat_text("hello", &[]), "hello");
+        assert_eq!(
+            chat_text(
+                "hello",
+                &[Tag::new(&["p", "x"]), Tag::new(&["subject", "Hi"])]
+            ),
+            "hello\nHi"
+        );
+        assert!(DmSearch::default().is_empty());
+        assert!(DmSearch {
+            text: "  ".to_owned(),
+            ..Default::default()
+        }
+        .is_empty());
+    }
+}
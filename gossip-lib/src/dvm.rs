@@ -0,0 +1,262 @@
+//! NIP-90 data vending machine (DVM) content feeds
+//!
+//! Content discovery DVMs announce themselves with NIP-89 handler information events
+//! (kind 31990) that list kind 5300 in a 'k' tag. We send them a job request (kind 5300)
+//! and they answer with a job result (kind 6300) whose content is a JSON list of tags
+//! referencing the events of the feed, in the order the DVM wants them shown.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use crate::relationship::RelationshipById;
+use crate::relay::Relay;
+use nostr_types::{
+    Event, EventKind, EventReference, Filter, Id, Metadata, PreEvent, PublicKey, RelayUrl, Tag,
+    Unixtime,
+};
+
+/// The job request kind for content discovery
+pub const CONTENT_DISCOVERY_REQUEST: u32 = 5300;
+
+/// The job result kind for content discovery
+pub const CONTENT_DISCOVERY_RESULT: u32 = 6300;
+
+/// A content discovery DVM
+#[derive(Debug, Clone)]
+pub struct Dvm {
+    pub pubkey: PublicKey,
+
+    /// The 'd' tag of its handler information event
+    pub d: String,
+
+    pub metadata: Option<Metadata>,
+
+    /// The relays we saw its handler information event on
+    pub relays: Vec<RelayUrl>,
+}
+
+impl Dvm {
+    /// Interpret a handler information event, if it is for a content discovery DVM
+    pub fn from_event(event: &Event) -> Option<Dvm> {
+        if event.kind != EventKind::HandlerInformation {
+            return None;
+        }
+
+        let k = CONTENT_DISCOVERY_REQUEST.to_string();
+        if !event
+            .tags
+            .iter()
+            .any(|tag| tag.tagname() == "k" && tag.value() == k)
+        {
+            return None;
+        }
+
+        let relays = GLOBALS
+            .db()
+            .get_event_seen_on_relay(event.id)
+            .unwrap_or_default()
+            .drain(..)
+            .map(|(url, _)| url)
+            .collect();
+
+        Some(Dvm {
+            pubkey: event.pubkey,
+            d: event.parameter().unwrap_or("".to_owned()),
+            metadata: serde_json::from_str::<Metadata>(&event.content).ok(),
+            relays,
+        })
+    }
+
+    /// The name it gives itself, else the name of its author
+    pub fn name(&self) -> String {
+        if let Some(name) = self.metadata.as_ref().and_then(|m| m.name.as_ref()) {
+            if !name.is_empty() {
+                return name.to_owned();
+            }
+        }
+        crate::names::best_name_from_pubkey_lookup(&self.pubkey)
+    }
+
+    pub fn about(&self) -> Option<&str> {
+        self.metadata.as_ref().and_then(|m| m.about.as_deref())
+    }
+
+    // Handler information events of content discovery DVMs
+    fn handler_filter() -> Filter {
+        let mut filter = Filter::new();
+        filter.add_event_kind(EventKind::HandlerInformation);
+        filter.set_tag_values('k', vec![CONTENT_DISCOVERY_REQUEST.to_string()]);
+        filter
+    }
+
+    /// All the content discovery DVMs we know of (locally), sorted by name
+    pub fn load_all() -> Result<Vec<Dvm>, Error> {
+        let filter = Self::handler_filter();
+
+        let mut dvms: Vec<Dvm> = Vec::new();
+        for event in GLOBALS.db().find_events_by_filter(&filter, |_| true)? {
+            if let Some(dvm) = Dvm::from_event(&event) {
+                // One feed per DVM, even if it announces itself more than once
+                if !dvms.iter().any(|d| d.pubkey == dvm.pubkey) {
+                    dvms.push(dvm);
+                }
+            }
+        }
+        dvms.sort_by_key(|dvm| dvm.name().to_lowercase());
+        Ok(dvms)
+    }
+
+    /// Load a content discovery DVM
+    pub fn load(pubkey: PublicKey) -> Result<Option<Dvm>, Error> {
+        let mut filter = Self::handler_filter();
+        filter.add_author(pubkey);
+
+        Ok(GLOBALS
+            .db()
+            .find_events_by_filter(&filter, |_| true)?
+            .iter()
+            .find_map(Dvm::from_event))
+    }
+
+    /// Create a job request asking for up to max_results events, with the results to be
+    /// published to result_relays
+    pub(crate) fn job_request(
+        &self,
+        max_results: usize,
+        result_relays: &[RelayUrl],
+    ) -> Result<Event, Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            None => return Err(ErrorKind::NoPublicKey.into()),
+            Some(pk) => pk,
+        };
+
+        let mut relays_tag: Vec<&str> = vec!["relays"];
+        relays_tag.extend(result_relays.iter().map(|r| r.as_str()));
+
+        let pre_event = PreEvent {
+            pubkey: public_key,
+            created_at: Unixtime::now(),
+            kind: CONTENT_DISCOVERY_REQUEST.into(),
+            tags: vec![
+                Tag::new_pubkey(self.pubkey, None, None),
+                Tag::new(&relays_tag),
+                Tag::new(&["param", "max_results", &max_results.to_string()]),
+            ],
+            content: "".to_owned(),
+        };
+
+        GLOBALS.identity.sign_event(pre_event)
+    }
+}
+
+/// The latest job request we sent to this DVM, and how many results it asked for. We
+/// keep our job requests, so this survives a restart.
+pub fn latest_job(dvm: PublicKey) -> Result<Option<(Id, usize)>, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Ok(None),
+        Some(pk) => pk,
+    };
+
+    let mut filter = Filter::new();
+    filter.add_author(public_key);
+    filter.add_event_kind(CONTENT_DISCOVERY_REQUEST.into());
+    filter.set_tag_values('p', vec![dvm.as_hex_string()]);
+
+    let latest = GLOBALS
+        .db()
+        .find_events_by_filter(&filter, |_| true)?
+        .into_iter()
+        .max_by_key(|event| event.created_at);
+
+    Ok(latest.map(|event| {
+        let max_results = event
+            .tags
+            .iter()
+            .find(|tag| tag.tagname() == "param" && tag.value() == "max_results")
+            .and_then(|tag| tag.get_index(2).parse::<usize>().ok())
+            .unwrap_or_else(|| GLOBALS.db().read_setting_load_more_count() as usize);
+        (event.id, max_results)
+    }))
+}
+
+/// The references (ids and addresses) in a content discovery job result, in order
+pub fn result_references(result: &Event) -> Vec<EventReference> {
+    let tags = serde_json::from_str::<Vec<Tag>>(&result.content).unwrap_or_default();
+
+    let mut output: Vec<EventReference> = Vec::new();
+    for tag in tags.iter() {
+        match tag.tagname() {
+            "e" => {
+                if let Ok((id, hint, _, author)) = tag.parse_event() {
+                    output.push(EventReference::Id {
+                        id,
+                        author,
+                        relays: hint
+                            .and_then(|uurl| RelayUrl::try_from_unchecked_url(&uurl).ok())
+                            .into_iter()
+                            .collect(),
+                        marker: None,
+                    });
+                }
+            }
+            "a" => {
+                if let Ok((naddr, _)) = tag.parse_address() {
+                    output.push(EventReference::Addr(naddr));
+                }
+            }
+            _ => continue,
+        }
+    }
+    output
+}
+
+/// The latest result from this DVM to our job request, if any
+pub fn latest_result(job: Id, dvm: PublicKey) -> Result<Option<Event>, Error> {
+    let mut latest: Option<Event> = None;
+    for (result_id, rel) in GLOBALS.db().find_relationships_by_id(job)? {
+        if rel != RelationshipById::SuppliesJobResult {
+            continue;
+        }
+        if let Some(result) = GLOBALS.db().read_event(result_id)? {
+            if result.pubkey != dvm || u32::from(result.kind) != CONTENT_DISCOVERY_RESULT {
+                continue;
+            }
+            let newer = match &latest {
+                Some(l) => result.created_at > l.created_at,
+                None => true,
+            };
+            if newer {
+                latest = Some(result);
+            }
+        }
+    }
+    Ok(latest)
+}
+
+/// The locally available events of a job result, in the order given by the DVM
+pub fn result_event_ids(result: &Event) -> Result<Vec<Id>, Error> {
+    let mut output: Vec<Id> = Vec::new();
+    for eref in result_references(result) {
+        match eref {
+            EventReference::Id { id, .. } => {
+                if GLOBALS.db().has_event(id)? {
+                    output.push(id);
+                }
+            }
+            EventReference::Addr(naddr) => {
+                if let Some(event) =
+                    GLOBALS
+                        .db()
+                        .get_replaceable_event(naddr.kind, naddr.author, &naddr.d)?
+                {
+                    output.push(event.id);
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Relays that DVM results are sent to: our read relays
+pub(crate) fn result_relays() -> Result<Vec<RelayUrl>, Error> {
+    Relay::choose_relay_urls(Relay::READ, |_| true)
+}
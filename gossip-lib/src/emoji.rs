@@ -0,0 +1,168 @@
+//! NIP-30 custom emoji
+//!
+//! Events can use `:shortcode:` in their content (and in names, for metadata) when they
+//! carry a matching `["emoji", "shortcode", "https://image"]` tag. Reactions can be a
+//! single custom emoji the same way. Our own custom emoji come from our NIP-51 emoji
+//! list (kind 10030), both directly and from the emoji sets (kind 30030) it references.
+
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::lists::Nip51List;
+use nostr_types::{Event, EventKind, PublicKey, Tag};
+
+/// A custom emoji
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CustomEmoji {
+    /// The shortcode, without the surrounding colons
+    pub shortcode: String,
+
+    /// The url of the image
+    pub url: String,
+}
+
+impl CustomEmoji {
+    /// The emoji as it appears in content: `:shortcode:`
+    pub fn as_content(&self) -> String {
+        format!(":{}:", self.shortcode)
+    }
+
+    /// The tag that defines this emoji
+    pub fn to_tag(&self) -> Tag {
+        Tag::new(&["emoji", &self.shortcode, &self.url])
+    }
+}
+
+/// A piece of text with custom emoji in it
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmojiSpan<'a> {
+    Text(&'a str),
+    Emoji(&'a CustomEmoji),
+}
+
+// Shortcodes may only be alphanumeric characters and underscores
+fn valid_shortcode(shortcode: &str) -> bool {
+    !shortcode.is_empty()
+        && shortcode
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The custom emoji defined by the tags of an event
+pub fn emojis_of(event: &Event) -> Vec<CustomEmoji> {
+    emojis_in(&event.tags)
+}
+
+fn emojis_in(tags: &[Tag]) -> Vec<CustomEmoji> {
+    let mut emojis: Vec<CustomEmoji> = Vec::new();
+    for tag in tags.iter() {
+        if tag.tagname() != "emoji" {
+            continue;
+        }
+        let shortcode = tag.value();
+        let url = tag.get_index(2);
+        if !valid_shortcode(shortcode) || url.is_empty() {
+            continue;
+        }
+        if !emojis.iter().any(|e| e.shortcode == shortcode) {
+            emojis.push(CustomEmoji {
+                shortcode: shortcode.to_owned(),
+                url: url.to_owned(),
+            });
+        }
+    }
+    emojis
+}
+
+/// The custom emoji a reaction is, if it is one
+pub fn reaction_emoji(reaction: &Event) -> Option<CustomEmoji> {
+    let shortcode = reaction
+        .content
+        .strip_prefix(':')
+        .and_then(|s| s.strip_suffix(':'))?;
+    emojis_of(reaction)
+        .into_iter()
+        .find(|e| e.shortcode == shortcode)
+}
+
+/// Split text into plain text and the custom emoji it uses (of those given)
+pub fn split_emojis<'a>(text: &'a str, emojis: &'a [CustomEmoji]) -> Vec<EmojiSpan<'a>> {
+    let mut spans: Vec<EmojiSpan<'a>> = Vec::new();
+    let mut rest = text;
+    let mut pos = 0;
+    while let Some(open) = rest[pos..].find(':').map(|i| pos + i) {
+        let close = match rest[open + 1..].find(':') {
+            Some(i) => open + 1 + i,
+            None => break,
+        };
+        match emojis.iter().find(|e| e.shortcode == rest[open + 1..close]) {
+            Some(emoji) => {
+                if open > 0 {
+                    spans.push(EmojiSpan::Text(&rest[..open]));
+                }
+                spans.push(EmojiSpan::Emoji(emoji));
+                rest = &rest[close + 1..];
+                pos = 0;
+            }
+            // The closing colon may open the next shortcode
+            None => pos = close,
+        }
+    }
+    if !rest.is_empty() {
+        spans.push(EmojiSpan::Text(rest));
+    }
+    spans
+}
+
+/// The custom emoji of a person, defined by the tags of their metadata event
+pub fn person_emojis(pubkey: PublicKey) -> Result<Vec<CustomEmoji>, Error> {
+    match GLOBALS
+        .db()
+        .get_replaceable_event(EventKind::Metadata, pubkey, "")?
+    {
+        Some(event) => Ok(emojis_of(&event)),
+        None => Ok(vec![]),
+    }
+}
+
+/// Our own custom emoji, from our emoji list and the emoji sets it references, in
+/// list order
+pub fn my_emojis() -> Vec<CustomEmoji> {
+    let list = match Nip51List::load_mine(EventKind::UserEmojiList, "") {
+        Ok(list) => list,
+        Err(_) => return vec![],
+    };
+
+    let tags: Vec<Tag> = list.entries.iter().map(|(tag, _)| tag.clone()).collect();
+    let mut emojis = emojis_in(&tags);
+
+    for naddr in list.referenced_addrs() {
+        if naddr.kind != EventKind::EmojiSets {
+            continue;
+        }
+        if let Ok(Some(set)) = Nip51List::load(naddr.kind, naddr.author, &naddr.d) {
+            let tags: Vec<Tag> = set.entries.iter().map(|(tag, _)| tag.clone()).collect();
+            for emoji in emojis_in(&tags) {
+                if !emojis.iter().any(|e| e.shortcode == emoji.shortcode) {
+                    emojis.push(emoji);
+                }
+            }
+        }
+    }
+
+    emojis
+}
+
+/// Add 'emoji' tags for our custom emoji used in content
+pub(crate) fn add_emoji_tags(content: &str, tags: &mut Vec<Tag>) {
+    if !content.contains(':') {
+        return;
+    }
+    let defined = emojis_in(tags);
+    for emoji in my_emojis() {
+        if content.contains(&emoji.as_content())
+            && !defined.iter().any(|e| e.shortcode == emoji.shortcode)
+        {
+            tags.push(emoji.to_tag());
+        }
+    }
+}
@@ -34,6 +34,7 @@ pub enum ErrorKind {
     Nip46NeedApproval,
     Nip46ParsingError(String, String),
     Nip46RelayNeeded,
+    Nip96Error(String),
     Nostr(nostr_types::Error),
     NoPublicKey,
     NoPrivateKey,
@@ -141,6 +142,7 @@ impl std::fmt::Display for Error {
             Nip46NeedApproval => write!(f, "NIP-46 approval needed"),
             Nip46ParsingError(_id, e) => write!(f, "NIP-46 parse error: {e}"),
             Nip46RelayNeeded => write!(f, "NIP-46 relay needed to respond."),
+            Nip96Error(s) => write!(f, "NIP-96 error: {s}"),
             Nostr(e) => write!(f, "Nostr: {e}"),
             NoPublicKey => write!(f, "No public key identity available."),
             NoPrivateKey => write!(f, "No private key available."),
@@ -2,8 +2,9 @@
 
 use crate::dm_channel::DmChannel;
 use crate::globals::GLOBALS;
+use crate::lists::Nip51List;
 use crate::people::PersonList;
-use nostr_types::{Id, PublicKey, RelayUrl};
+use nostr_types::{EventKind, Id, PublicKey, RelayUrl};
 
 /// Kinds of feeds, with configuration parameteers
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -20,6 +21,14 @@ pub enum FeedKind {
     DmChat(DmChannel),
     Global,
     Relay(RelayUrl),
+    RelaySet(String),                   // 'd' tag of one of our relay sets
+    BookmarkSet(String),                // 'd' tag of one of our bookmark sets
+    Hashtag(String),                    // lowercase, without the '#'
+    Custom(String),                     // name of one of our custom feeds
+    Dvm(PublicKey),                     // a content discovery DVM
+    Channel(Id),                        // a public chat channel, by its creation event
+    Group(RelayUrl, String),            // a relay-based group, by its relay and id
+    Community(PublicKey, String, bool), // a moderated community, by owner and 'd' tag, with pending posts
 }
 
 impl std::fmt::Display for FeedKind {
@@ -40,6 +49,23 @@ impl std::fmt::Display for FeedKind {
             FeedKind::DmChat(channel) => write!(f, "{}", channel.name()),
             FeedKind::Global => write!(f, "Global"),
             FeedKind::Relay(relayurl) => write!(f, "{}", relayurl),
+            FeedKind::RelaySet(d) => write!(f, "{}", set_name(EventKind::RelaySets, d)),
+            FeedKind::BookmarkSet(d) => write!(f, "{}", set_name(EventKind::BookmarkSets, d)),
+            FeedKind::Hashtag(hashtag) => write!(f, "#{}", hashtag),
+            FeedKind::Custom(name) => write!(f, "{}", name),
+            FeedKind::Channel(id) => match crate::ChatChannel::load(*id) {
+                Ok(Some(channel)) => write!(f, "{}", channel.display_name()),
+                _ => write!(f, "{}", crate::names::hex_id_short(&(*id).into())),
+            },
+            FeedKind::Dvm(pk) => write!(f, "{}", crate::names::best_name_from_pubkey_lookup(pk)),
+            FeedKind::Group(relay, id) => match crate::GroupMetadata::load(relay, id) {
+                Ok(Some(group)) => write!(f, "{}", group.display_name()),
+                _ => write!(f, "{}", id),
+            },
+            FeedKind::Community(author, d, _) => match crate::Community::load(*author, d) {
+                Ok(Some(community)) => write!(f, "{}", community.display_name()),
+                _ => write!(f, "{}", d),
+            },
         }
     }
 }
@@ -56,6 +82,14 @@ impl FeedKind {
             Self::DmChat(_) => "dmchat".to_owned(),
             Self::Global => "global".to_owned(),
             Self::Relay(relayurl) => format!("relay {}", relayurl),
+            Self::RelaySet(d) => format!("relayset {}", d),
+            Self::BookmarkSet(d) => format!("bookmarkset {}", d),
+            Self::Hashtag(hashtag) => format!("hashtag {}", hashtag),
+            Self::Custom(name) => format!("custom {}", name),
+            Self::Dvm(pubkey) => format!("dvm{}", pubkey.as_hex_string()),
+            Self::Channel(id) => format!("channel{}", id.as_hex_string()),
+            Self::Group(relay, id) => format!("group {}'{}", relay, id),
+            Self::Community(author, d, _) => format!("community {}:{}", author.as_hex_string(), d),
         }
     }
 
@@ -69,6 +103,14 @@ impl FeedKind {
             Self::DmChat(_) => false, // always full
             Self::Global => true,
             Self::Relay(_) => true,
+            Self::RelaySet(_) => true,
+            Self::BookmarkSet(_) => false, // always full
+            Self::Hashtag(_) => true,
+            Self::Custom(_) => true,
+            Self::Dvm(_) => true,
+            Self::Channel(_) => true,
+            Self::Group(_, _) => true,
+            Self::Community(_, _, _) => true,
         }
     }
 
@@ -76,7 +118,15 @@ impl FeedKind {
         match self {
             Self::Global => true,
             Self::Relay(_) => true,
+            Self::RelaySet(_) => true,
             _ => false,
         }
     }
 }
+
+fn set_name(kind: EventKind, d: &str) -> String {
+    match Nip51List::load_mine(kind, d) {
+        Ok(list) => list.name(),
+        Err(_) => d.to_owned(),
+    }
+}
@@ -1,11 +1,16 @@
 mod feed_kind;
 pub use feed_kind::FeedKind;
 
+/// A user-defined feed
+pub type CustomFeed = crate::storage::types::CustomFeed1;
+
 use crate::comms::{ToMinionMessage, ToMinionPayload, ToMinionPayloadDetail, ToOverlordMessage};
 use crate::error::{Error, ErrorKind};
 use crate::filter_set::FilterSet;
 use crate::globals::GLOBALS;
+use crate::lists::Nip51List;
 use crate::people::PersonList;
+use crate::storage::{CustomFeedsTable, Table};
 use dashmap::DashMap;
 use nostr_types::{Event, EventKind, EventReference, Filter, Id, NAddr, PublicKey, Unixtime};
 use parking_lot::RwLock;
@@ -40,6 +45,9 @@ pub struct Feed {
     thread_parent: Arc<RwLock<Option<Id>>>,
 
     last_volatile_feed: Arc<RwLock<Option<FeedKind>>>,
+
+    // The latest job request we sent to each DVM, and how many results it asked for
+    dvm_jobs: DashMap<PublicKey, (Id, usize)>,
 }
 
 impl Default for Feed {
@@ -62,6 +70,7 @@ impl Feed {
             last_computed: Arc::new(RwLock::new(None)),
             thread_parent: Arc::new(RwLock::new(None)),
             last_volatile_feed: Arc::new(RwLock::new(None)),
+            dvm_jobs: DashMap::new(),
         }
     }
 
@@ -149,6 +158,90 @@ impl Feed {
             });
         }
 
+        // If not in a Channel feed
+        if !matches!(feed_kind, FeedKind::Channel(_)) {
+            // Stop listening to the chat channel
+            let _ = GLOBALS.to_minions.send(ToMinionMessage {
+                target: "all".to_string(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::ChatChannel(Id([0; 32]))), // id does not matter
+                },
+            });
+        }
+
+        // If not in a Dvm feed
+        if !matches!(feed_kind, FeedKind::Dvm(_)) {
+            // Stop listening to DVM results
+            let _ = GLOBALS.to_minions.send(ToMinionMessage {
+                target: "all".to_string(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::DvmResults {
+                        dvm: *DUMMY_PUBKEY, // does not matter
+                        job: Id([0; 32]),   // does not matter
+                    }),
+                },
+            });
+        }
+
+        // If not in a Community feed
+        if !matches!(feed_kind, FeedKind::Community(_, _, _)) {
+            // Stop listening to the community
+            let _ = GLOBALS.to_minions.send(ToMinionMessage {
+                target: "all".to_string(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::Community {
+                        author: *DUMMY_PUBKEY, // does not matter
+                        d: "".to_owned(),      // does not matter
+                    }),
+                },
+            });
+        }
+
+        // If not in a Custom feed
+        if !matches!(feed_kind, FeedKind::Custom(_)) {
+            // Stop listening to Custom feed events
+            let _ = GLOBALS.to_minions.send(ToMinionMessage {
+                target: "all".to_string(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::CustomFeedFuture {
+                        feed: Default::default(), // does not matter
+                        anchor: Unixtime::now(),  // does not matter
+                    }),
+                },
+            });
+        }
+
+        // If not in a Group feed
+        if !matches!(feed_kind, FeedKind::Group(_, _)) {
+            // Stop listening to the group
+            let _ = GLOBALS.to_minions.send(ToMinionMessage {
+                target: "all".to_string(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::Group("".to_owned())), // group does not matter
+                },
+            });
+        }
+
+        // If not in a Hashtag feed
+        if !matches!(feed_kind, FeedKind::Hashtag(_)) {
+            // Stop listening to Hashtag events
+            let _ = GLOBALS.to_minions.send(ToMinionMessage {
+                target: "all".to_string(),
+                payload: ToMinionPayload {
+                    job_id: 0,
+                    detail: ToMinionPayloadDetail::Unsubscribe(FilterSet::HashtagFeedFuture {
+                        hashtag: "".to_owned(),  // does not matter
+                        anchor: Unixtime::now(), // does not matter
+                    }),
+                },
+            });
+        }
+
         // If not in a Relay feed
         if let FeedKind::Relay(relay_url) = feed_kind {
             // Stop listening to Global events
@@ -247,6 +340,24 @@ impl Feed {
                     .to_overlord
                     .send(ToOverlordMessage::SetDmChannel(dm_channel.clone()));
             }
+            FeedKind::Channel(id) => {
+                // Listen for the channel's messages
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SetChatChannel(*id));
+            }
+            FeedKind::Community(author, d, _) => {
+                // Listen for the community's posts and approvals
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SetCommunityFeed(*author, d.clone()));
+            }
+            FeedKind::Group(relay, id) => {
+                // Listen for the group's content on its relay
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SetGroupFeed(relay.clone(), id.clone()));
+            }
             FeedKind::Global => {
                 let _ = GLOBALS
                     .to_overlord
@@ -257,6 +368,26 @@ impl Feed {
                     .to_overlord
                     .send(ToOverlordMessage::SetRelayFeed(relay_url.clone(), anchor));
             }
+            FeedKind::RelaySet(d) => {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SetRelaySetFeed(d.clone(), anchor));
+            }
+            FeedKind::Dvm(pubkey) => {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SetDvmFeed(*pubkey));
+            }
+            FeedKind::Custom(name) => {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SetCustomFeed(name.clone(), anchor));
+            }
+            FeedKind::Hashtag(hashtag) => {
+                let _ = GLOBALS
+                    .to_overlord
+                    .send(ToOverlordMessage::SetHashtagFeed(hashtag.clone(), anchor));
+            }
             _ => (),
         }
     }
@@ -330,6 +461,27 @@ impl Feed {
         *self.thread_parent.write_arc() = Some(id);
     }
 
+    /// The latest job request we sent to this DVM, and how many results it asked for
+    pub fn get_dvm_job(&self, dvm: PublicKey) -> Option<(Id, usize)> {
+        if let Some(r) = self.dvm_jobs.get(&dvm) {
+            return Some(*r.value());
+        }
+
+        // After a restart, pick up the job request we stored
+        let job = crate::dvm::latest_job(dvm).ok().flatten()?;
+        self.dvm_jobs.insert(dvm, job);
+        Some(job)
+    }
+
+    pub(crate) fn set_dvm_job(&self, dvm: PublicKey, job: Id, max_results: usize) {
+        self.dvm_jobs.insert(dvm, (job, max_results));
+    }
+
+    /// Is this one of our (latest) DVM job requests?
+    pub(crate) fn is_dvm_job(&self, job: Id) -> bool {
+        self.dvm_jobs.iter().any(|r| r.value().0 == job)
+    }
+
     /// Are we switching feeds?
     #[inline]
     pub fn is_switching(&self) -> bool {
@@ -421,6 +573,14 @@ impl Feed {
             FeedKind::Bookmarks => {
                 *self.current_feed_events.write_arc() = GLOBALS.current_bookmarks.read().clone();
             }
+            FeedKind::BookmarkSet(d) => {
+                // Without an identity we have no bookmark sets
+                let ids = match Nip51List::load_mine(EventKind::BookmarkSets, &d) {
+                    Ok(list) => list.event_ids()?,
+                    Err(_) => vec![],
+                };
+                *self.current_feed_events.write_arc() = ids;
+            }
             FeedKind::Inbox(indirect) => {
                 // See below, we always recompute inbox
                 self.inbox_is_indirect.store(indirect, Ordering::Relaxed);
@@ -452,7 +612,63 @@ impl Feed {
                 let ids = GLOBALS.db().dm_events(&channel)?;
                 *self.current_feed_events.write_arc() = ids;
             }
-            FeedKind::Global | FeedKind::Relay(_) => {
+            FeedKind::Channel(id) => {
+                let ids = match crate::ChatChannel::load(id)? {
+                    Some(channel) => channel.message_ids()?,
+                    None => vec![],
+                };
+                *self.current_feed_events.write_arc() = ids;
+            }
+            FeedKind::Community(author, d, pending) => {
+                let ids = match crate::Community::load(author, &d)? {
+                    Some(community) if pending => community.pending_post_ids()?,
+                    Some(community) => community.approved_post_ids()?,
+                    None => vec![],
+                };
+                *self.current_feed_events.write_arc() = ids;
+            }
+            FeedKind::Group(relay, id) => {
+                let ids = crate::group::group_event_ids(&relay, &id)?;
+                *self.current_feed_events.write_arc() = ids;
+            }
+            FeedKind::Dvm(pubkey) => {
+                let dismissed = GLOBALS.dismissed.read().await.clone();
+                let mut ids: Vec<Id> = Vec::new();
+                if let Some((job, _)) = self.get_dvm_job(pubkey) {
+                    if let Some(result) = crate::dvm::latest_result(job, pubkey)? {
+                        for id in crate::dvm::result_event_ids(&result)? {
+                            if let Some(event) = GLOBALS.db().read_event(id)? {
+                                if basic_screen(&event, true, &dismissed) {
+                                    ids.push(id);
+                                }
+                            }
+                        }
+                    }
+                }
+                *self.current_feed_events.write_arc() = ids;
+            }
+            FeedKind::Custom(name) => {
+                let events = match CustomFeedsTable::read_record(name, None)? {
+                    Some(feed) if !(feed.is_relay_feed() && feed.relays.is_empty()) => {
+                        match feed.filter() {
+                            Ok(filter) => {
+                                Self::load_event_range(anchor, filter, feed.include_replies, |e| {
+                                    feed.screen(e)
+                                })
+                                .await?
+                            }
+                            Err(_) => vec![],
+                        }
+                    }
+                    _ => vec![],
+                };
+                *self.current_feed_events.write_arc() = events;
+            }
+            FeedKind::Hashtag(hashtag) => {
+                let events = Self::load_hashtag_range(anchor, &hashtag).await?;
+                *self.current_feed_events.write_arc() = events;
+            }
+            FeedKind::Global | FeedKind::Relay(_) | FeedKind::RelaySet(_) => {
                 let dismissed = GLOBALS.dismissed.read().await.clone();
 
                 let screen_spam = {
@@ -541,6 +757,34 @@ impl Feed {
         Ok(())
     }
 
+    /// Like load_event_range, but from the hashtag index rather than by filter
+    async fn load_hashtag_range(since: Unixtime, hashtag: &String) -> Result<Vec<Id>, Error> {
+        let now = Unixtime::now();
+        let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+        let dismissed = GLOBALS.dismissed.read().await.clone();
+        let kinds = feed_displayable_event_kinds(false);
+
+        let mut events: Vec<Event> = Vec::new();
+        for id in GLOBALS.db().get_event_ids_with_hashtag(hashtag)? {
+            if let Some(event) = GLOBALS.db().read_event(id)? {
+                if kinds.contains(&event.kind)
+                    && event.created_at <= now
+                    && basic_screen(&event, true, &dismissed)
+                {
+                    events.push(event);
+                }
+            }
+        }
+
+        // Latest first
+        events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        // Everything after the anchor, plus a chunk of events preceding it
+        let after = events.iter().filter(|e| e.created_at >= since).count();
+
+        Ok(events.iter().take(after + limit).map(|e| e.id).collect())
+    }
+
     async fn load_event_range<F>(
         since: Unixtime,
         filter: Filter,
@@ -590,6 +834,7 @@ fn basic_screen(e: &Event, include_replies: bool, dismissed: &[Id]) -> bool {
         && e.kind != EventKind::EncryptedDirectMessage
         && e.kind != EventKind::DmChat
         && e.kind != EventKind::GiftWrap
+        && e.kind != EventKind::ChannelMessage // shown in their channel feed
         && !dismissed.contains(&e.id)
         && !e.is_annotation()
 }
@@ -600,6 +845,7 @@ pub fn enabled_event_kinds() -> Vec<EventKind> {
     let show_long_form = GLOBALS.db().read_setting_show_long_form();
     let direct_messages = GLOBALS.db().read_setting_direct_messages();
     let enable_zap_receipts = GLOBALS.db().read_setting_enable_zap_receipts();
+    let timestamps = crate::ots::can_verify();
 
     EventKind::iter()
         .filter(|k| {
@@ -616,12 +862,12 @@ pub fn enabled_event_kinds() -> Vec<EventKind> {
             //|| *k == EventKind::Seal // -- never subscribed to
                 || ((*k == EventKind::DmChat) && direct_messages)
                 || ((*k == EventKind::GenericRepost) && reposts)
-            //|| *k == EventKind::ChannelCreation
-            //|| *k == EventKind::ChannelMetadata
-            //|| *k == EventKind::ChannelMessage
-            //|| *k == EventKind::ChannelHideMessage
-            //|| *k == EventKind::ChannelMuteUser
-            // || *k == EventKind::Timestamp
+                || *k == EventKind::ChannelCreation
+                || *k == EventKind::ChannelMetadata
+                || *k == EventKind::ChannelMessage
+                || *k == EventKind::ChannelHideMessage
+                || *k == EventKind::ChannelMuteUser
+                || ((*k == EventKind::Timestamp) && timestamps)
                 || ((*k == EventKind::GiftWrap) && direct_messages)
             // || *k == EventKind::FileMetadata
             // || *k == EventKind::LiveChatMessage
@@ -635,24 +881,24 @@ pub fn enabled_event_kinds() -> Vec<EventKind> {
             // || *k == EventKind::ProblemTracker
             // || *k == EventKind::Reporting
             // || *k == EventKind::Label
-            // || *k == EventKind::CommunityPost
-            // || *k == EventKind::CommunityPostApproval
+            // || *k == EventKind::CommunityPost -- posts are kind 1 with an 'a' tag now
+                || *k == EventKind::CommunityPostApproval
             // || *k == EventKind::JobFeedback
             // || *k == EventKind::ZapGoal
                 || *k == EventKind::ZapRequest
                 || ((*k == EventKind::Zap) && enable_zap_receipts)
             // || *k == EventKind::Highlights
                 || *k == EventKind::MuteList
-            // || *k == EventKind::PinList
+                || *k == EventKind::PinList
                 || *k == EventKind::RelayList
             // || *k == EventKind::BookmarkList
-            // || *k == EventKind::CommunityList
+                || *k == EventKind::CommunityList
             // || *k == EventKind::PublicChatsList
             // || *k == EventKind::BlockedRelaysList
             // || *k == EventKind::SearchRelaysList
             // || *k == EventKind::UserGroups
-            // || *k == EventKind::InterestsList
-            // || *k == EventKind::UserEmojiList
+                || *k == EventKind::InterestsList
+                || *k == EventKind::UserEmojiList
                 || (*k == EventKind::DmRelayList && direct_messages)
             // || *k == EventKind::FileStorageServerList
             // || *k == EventKind::WalletInfo
@@ -664,19 +910,19 @@ pub fn enabled_event_kinds() -> Vec<EventKind> {
             // || *k == EventKind::HttpAuth
                 || *k == EventKind::FollowSets
             // || *k == EventKind::GenericSets
-            // || *k == EventKind::RelaySets
-            // || *k == EventKind::BookmarkSets
+                || *k == EventKind::RelaySets
+                || *k == EventKind::BookmarkSets
             // || *k == EventKind::CurationSets
             // || *k == EventKind::ProfileBadges
             // || *k == EventKind::BadgeDefinition
-            // || *k == EventKind::InterestSets
+                || *k == EventKind::InterestSets
             // || *k == EventKind::CreateUpdateStall
             // || *k == EventKind::CreateUpdateProduct
             // || *k == EventKind::MarketplaceUi
             // || *k == EventKind::ProductSoldAuction
                 || ((*k == EventKind::LongFormContent) && show_long_form)
             // || *k == EventKind::DraftLongFormContent
-            // || *k == EventKind::EmojiSets
+                || *k == EventKind::EmojiSets
             // || *k == EventKind::ReleaseArtifactSets
             // || *k == EventKind::AppSpecificData
             // || *k == EventKind::LiveEvent
@@ -691,7 +937,7 @@ pub fn enabled_event_kinds() -> Vec<EventKind> {
             // || *k == EventKind::CalendarEventRsvp
             // || *k == EventKind::HandlerRecommendation
             // || *k == EventKind::HandlerInformation
-            // || *k == EventKind::CommunityDefinition
+                || *k == EventKind::CommunityDefinition
         })
         .collect()
 }
@@ -702,7 +948,7 @@ pub fn feed_related_event_kinds(mut dms: bool) -> Vec<EventKind> {
         dms = false;
     }
 
-    enabled_event_kinds()
+    let mut kinds: Vec<EventKind> = enabled_event_kinds()
         .drain(..)
         .filter(|k| {
             k.is_feed_related()
@@ -711,7 +957,9 @@ pub fn feed_related_event_kinds(mut dms: bool) -> Vec<EventKind> {
                         && *k != EventKind::DmChat
                         && *k != EventKind::GiftWrap))
         })
-        .collect()
+        .collect();
+    add_video_kinds(&mut kinds);
+    kinds
 }
 
 pub fn feed_displayable_event_kinds(mut dms: bool) -> Vec<EventKind> {
@@ -719,7 +967,7 @@ pub fn feed_displayable_event_kinds(mut dms: bool) -> Vec<EventKind> {
     if !GLOBALS.identity.is_unlocked() {
         dms = false;
     }
-    enabled_event_kinds()
+    let mut kinds: Vec<EventKind> = enabled_event_kinds()
         .drain(..)
         .filter(|k| {
             k.is_feed_displayable()
@@ -728,7 +976,18 @@ pub fn feed_displayable_event_kinds(mut dms: bool) -> Vec<EventKind> {
                         && *k != EventKind::DmChat
                         && *k != EventKind::GiftWrap))
         })
-        .collect()
+        .collect();
+    add_video_kinds(&mut kinds);
+    kinds
+}
+
+// NIP-71 video events are shown in feeds, but nostr-types does not know them as such
+fn add_video_kinds(kinds: &mut Vec<EventKind>) {
+    for kind in crate::nip71::video_kinds() {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
 }
 
 pub fn feed_augment_event_kinds() -> Vec<EventKind> {
@@ -4,11 +4,12 @@ use crate::profile::Profile;
 use crate::USER_AGENT;
 use futures::stream::{FuturesUnordered, StreamExt};
 use nostr_types::{Unixtime, Url};
-use reqwest::header::ETAG;
+use parking_lot::RwLock as PRwLock;
+use reqwest::header::{AUTHORIZATION, ETAG};
 use reqwest::Client;
 use reqwest::StatusCode;
 use sha2::Digest;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
@@ -40,6 +41,9 @@ pub struct Fetcher {
 
     // Here is where we put hosts into a penalty box to time them out
     penalty_box: RwLock<HashMap<String, Unixtime>>,
+
+    // URLs of avatars, which are cached under their own budget
+    avatar_urls: PRwLock<HashSet<Url>>,
 }
 
 impl Fetcher {
@@ -206,12 +210,14 @@ impl Fetcher {
 
         // Look in both permanent and temporary cache paths
         let mut cache_file = self.cache_file(url, false);
+        let mut in_tmp_cache = false;
         let mut md: Result<fs::Metadata, std::io::Error> = fs::metadata(cache_file.as_path());
         if let Err(e) = md {
             if e.kind() != std::io::ErrorKind::NotFound {
                 tracing::info!("FETCH {url}: Failed: {e}");
                 return Err(e.into());
             }
+            in_tmp_cache = true;
             cache_file = self.cache_file(url, true);
             md = fs::metadata(cache_file.as_path());
             if let Err(ref e) = md {
@@ -239,6 +245,9 @@ impl Fetcher {
                                         "FETCH {url}: Cache Hit age={}s",
                                         dur.as_secs()
                                     );
+                                    if !in_tmp_cache {
+                                        crate::media_cache::touch(cache_file.as_path());
+                                    }
                                     return Ok(Some(contents));
                                 }
                                 Err(e) => return Err(e.into()),
@@ -250,6 +259,16 @@ impl Fetcher {
                 }
             }
             // fall through
+        } else if !use_temp_cache {
+            // We may have the same blob from another server
+            let avatar = self.avatar_urls.read().contains(url);
+            if let Some(contents) = crate::media_cache::link_existing(
+                self.cache_file(url, false).as_path(),
+                url.as_str(),
+                avatar,
+            ) {
+                return Ok(Some(contents));
+            }
         }
 
         // We can't fetch as we are not async and we don't want to block the caller.
@@ -266,6 +285,19 @@ impl Fetcher {
         Ok(None)
     }
 
+    /// Like `try_get()` with the permanent cache, for an avatar. Avatars are kept within
+    /// their own cache budget, separate from other media.
+    pub(crate) fn try_get_avatar(
+        &self,
+        url: &Url,
+        max_age: Duration,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        if !self.avatar_urls.read().contains(url) {
+            self.avatar_urls.write().insert(url.to_owned());
+        }
+        self.try_get(url, max_age, false)
+    }
+
     /// This causes the fetcher to fetch the resource. After it completes, you can pick it up
     /// the result using try_get()
     pub async fn fetch(&self, url: Url, use_temp_cache: bool) {
@@ -323,6 +355,9 @@ impl Fetcher {
         if GLOBALS.db().read_setting_set_user_agent() {
             req = req.header("User-Agent", USER_AGENT);
         };
+        if let Some(authorization) = http_authorization(url.as_str(), "GET") {
+            req = req.header(AUTHORIZATION, authorization);
+        }
 
         enum FailOutcome {
             Fail,
@@ -504,7 +539,7 @@ impl Fetcher {
         GLOBALS.bytes_read.fetch_add(bytes.len(), Ordering::Relaxed);
 
         // Write to the file
-        if let Err(e) = tokio::fs::write(cache_file.as_path(), bytes).await {
+        if let Err(e) = tokio::fs::write(cache_file.as_path(), &bytes).await {
             finish(
                 FailOutcome::Fail,
                 "writing to cache file",
@@ -516,6 +551,16 @@ impl Fetcher {
 
         tracing::debug!("FETCH {url}: Cached");
 
+        // Keep track of it in the media cache
+        if !use_temp_cache {
+            let avatar = self.avatar_urls.read().contains(&url);
+            if let Err(e) =
+                crate::media_cache::record(cache_file.as_path(), url.as_str(), &bytes, avatar)
+            {
+                tracing::warn!("FETCH {url}: Could not index cache file: {e}");
+            }
+        }
+
         // Remove from host load
         self.decrement_host_load(&host);
 
@@ -639,3 +684,25 @@ impl Fetcher {
         Ok(count)
     }
 }
+
+/// A NIP-98 `Authorization` header value for a request, if the user has allowed us to
+/// identify ourselves to its host (or a parent domain of it) and we can sign
+pub(crate) fn http_authorization(url: &str, method: &str) -> Option<String> {
+    let host = url::Url::parse(url).ok()?.host_str()?.to_lowercase();
+    let hosts = GLOBALS.db().read_setting_fetcher_auth_hosts();
+    let allowed = hosts.split_whitespace().any(|allowed| {
+        let allowed = allowed.to_lowercase();
+        host == allowed || host.ends_with(&format!(".{}", allowed))
+    });
+    if !allowed || !GLOBALS.identity.is_unlocked() {
+        return None;
+    }
+
+    match GLOBALS.identity.http_authorization(url, method, None) {
+        Ok(authorization) => Some(authorization),
+        Err(e) => {
+            tracing::warn!("Could not sign NIP-98 authorization for {}: {}", host, e);
+            None
+        }
+    }
+}
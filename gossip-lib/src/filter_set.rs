@@ -1,6 +1,7 @@
 #![allow(clippy::match_like_matches_macro)]
 
 use crate::dm_channel::DmChannel;
+use crate::feed::CustomFeed;
 use crate::globals::GLOBALS;
 use nostr_types::{EventKind, Filter, Id, NAddr, PublicKey, Tag, Unixtime};
 use std::time::Duration;
@@ -26,9 +27,38 @@ impl FeedRange {
 #[derive(Debug, Clone, PartialEq)]
 pub enum FilterSet {
     Augments(Vec<Id>),
+    ChatChannel(Id),
+    ChatChannelChunk {
+        channel: Id,
+        anchor: Unixtime,
+    },
+    ChatChannelDiscovery(Vec<Id>),
+    Community {
+        author: PublicKey,
+        d: String,
+    },
+    CommunityChunk {
+        author: PublicKey,
+        d: String,
+        anchor: Unixtime,
+    },
+    CommunityDiscovery(Vec<PublicKey>),
     Config,
+    CustomFeedFuture {
+        feed: CustomFeed,
+        anchor: Unixtime,
+    },
+    CustomFeedChunk {
+        feed: CustomFeed,
+        anchor: Unixtime,
+    },
     Discover(Vec<PublicKey>),
     DmChannel(DmChannel),
+    DvmDiscovery,
+    DvmResults {
+        dvm: PublicKey,
+        job: Id,
+    },
     FollowersOf(PublicKey),
     GeneralFeedFuture {
         pubkeys: Vec<PublicKey>,
@@ -41,6 +71,20 @@ pub enum FilterSet {
     Giftwraps(FeedRange),
     GlobalFeedFuture(Unixtime),
     GlobalFeedChunk(Unixtime),
+    Group(String),
+    GroupChunk {
+        group: String,
+        anchor: Unixtime,
+    },
+    GroupDiscovery,
+    HashtagFeedFuture {
+        hashtag: String,
+        anchor: Unixtime,
+    },
+    HashtagFeedChunk {
+        hashtag: String,
+        anchor: Unixtime,
+    },
     InboxFeedFuture(Unixtime),
     InboxFeedChunk(Unixtime),
     Metadata(Vec<PublicKey>),
@@ -62,15 +106,30 @@ impl FilterSet {
     pub fn temporary(&self) -> bool {
         match self {
             FilterSet::Augments(_) => true,
+            FilterSet::ChatChannel(_) => false,
+            FilterSet::ChatChannelChunk { .. } => true,
+            FilterSet::ChatChannelDiscovery(_) => true,
+            FilterSet::Community { .. } => false,
+            FilterSet::CommunityChunk { .. } => true,
+            FilterSet::CommunityDiscovery(_) => true,
             FilterSet::Config => false,
+            FilterSet::CustomFeedFuture { .. } => false,
+            FilterSet::CustomFeedChunk { .. } => true,
             FilterSet::Discover(_) => true,
             FilterSet::DmChannel(_) => false,
+            FilterSet::DvmDiscovery => true,
+            FilterSet::DvmResults { .. } => false,
             FilterSet::FollowersOf(_) => true,
             FilterSet::GeneralFeedFuture { .. } => false,
             FilterSet::GeneralFeedChunk { .. } => true,
             FilterSet::Giftwraps(_) => false,
             FilterSet::GlobalFeedFuture(_) => false,
             FilterSet::GlobalFeedChunk(_) => true,
+            FilterSet::Group(_) => false,
+            FilterSet::GroupChunk { .. } => true,
+            FilterSet::GroupDiscovery => true,
+            FilterSet::HashtagFeedFuture { .. } => false,
+            FilterSet::HashtagFeedChunk { .. } => true,
             FilterSet::InboxFeedFuture(_) => false,
             FilterSet::InboxFeedChunk(_) => true,
             FilterSet::Metadata(_) => true,
@@ -85,8 +144,13 @@ impl FilterSet {
 
     pub fn can_have_duplicates(&self) -> bool {
         match self {
+            FilterSet::ChatChannelChunk { .. } => true,
+            FilterSet::CommunityChunk { .. } => true,
+            FilterSet::CustomFeedChunk { .. } => true,
             FilterSet::GeneralFeedChunk { .. } => true,
             FilterSet::GlobalFeedChunk(_) => true,
+            FilterSet::GroupChunk { .. } => true,
+            FilterSet::HashtagFeedChunk { .. } => true,
             FilterSet::InboxFeedChunk(_) => true,
             FilterSet::PersonFeedChunk { .. } => true,
             _ => false,
@@ -95,8 +159,13 @@ impl FilterSet {
 
     pub fn is_loading_more(&self) -> bool {
         match self {
+            FilterSet::ChatChannelChunk { .. } => true,
+            FilterSet::CommunityChunk { .. } => true,
+            FilterSet::CustomFeedChunk { .. } => true,
             FilterSet::GeneralFeedChunk { .. } => true,
             FilterSet::GlobalFeedChunk(_) => true,
+            FilterSet::GroupChunk { .. } => true,
+            FilterSet::HashtagFeedChunk { .. } => true,
             FilterSet::InboxFeedChunk(_) => true,
             FilterSet::PersonFeedChunk { .. } => true,
             _ => false,
@@ -106,15 +175,30 @@ impl FilterSet {
     pub fn inner_handle(&self) -> &'static str {
         match self {
             FilterSet::Augments(_) => "augments",
+            FilterSet::ChatChannel(_) => "chat_channel",
+            FilterSet::ChatChannelChunk { .. } => "chat_channel_chunk",
+            FilterSet::ChatChannelDiscovery(_) => "chat_channel_discovery",
+            FilterSet::Community { .. } => "community",
+            FilterSet::CommunityChunk { .. } => "community_chunk",
+            FilterSet::CommunityDiscovery(_) => "community_discovery",
             FilterSet::Config => "config_feed",
+            FilterSet::CustomFeedFuture { .. } => "custom_feed",
+            FilterSet::CustomFeedChunk { .. } => "custom_feed_chunk",
             FilterSet::Discover(_) => "discover_feed",
             FilterSet::DmChannel(_) => "dm_channel",
+            FilterSet::DvmDiscovery => "dvm_discovery",
+            FilterSet::DvmResults { .. } => "dvm_results",
             FilterSet::FollowersOf(_) => "followers_of",
             FilterSet::GeneralFeedFuture { .. } => "general_feed",
             FilterSet::GeneralFeedChunk { .. } => "general_feed_chunk",
             FilterSet::Giftwraps(_) => "giftwraps",
             FilterSet::GlobalFeedFuture(_) => "global_feed",
             FilterSet::GlobalFeedChunk(_) => "global_feed_chunk",
+            FilterSet::Group(_) => "group",
+            FilterSet::GroupChunk { .. } => "group_chunk",
+            FilterSet::GroupDiscovery => "group_discovery",
+            FilterSet::HashtagFeedFuture { .. } => "hashtag_feed",
+            FilterSet::HashtagFeedChunk { .. } => "hashtag_feed_chunk",
             FilterSet::InboxFeedFuture(_) => "inbox_feed",
             FilterSet::InboxFeedChunk(_) => "inbox_feed_chunk",
             FilterSet::Metadata(_) => "subscribe_metadata",
@@ -154,6 +238,129 @@ impl FilterSet {
                 };
                 filters.push(filter);
             }
+            FilterSet::ChatChannel(channel) => {
+                // The channel and its metadata
+                filters.push(Filter {
+                    ids: vec![*channel],
+                    ..Default::default()
+                });
+                let mut filter = Filter {
+                    kinds: vec![EventKind::ChannelMetadata],
+                    ..Default::default()
+                };
+                filter.set_tag_values('e', vec![channel.as_hex_string()]);
+                filters.push(filter);
+
+                // The latest messages, and any new ones
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let mut filter = Filter {
+                    kinds: vec![EventKind::ChannelMessage],
+                    limit: Some(limit),
+                    ..Default::default()
+                };
+                filter.set_tag_values('e', vec![channel.as_hex_string()]);
+                filters.push(filter);
+            }
+            FilterSet::ChatChannelChunk { channel, anchor } => {
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let range = FeedRange::ChunkBefore {
+                    until: *anchor,
+                    limit,
+                };
+                let (since, until, limit) = range.since_until_limit();
+                let mut filter = Filter {
+                    kinds: vec![EventKind::ChannelMessage],
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                };
+                filter.set_tag_values('e', vec![channel.as_hex_string()]);
+                filters.push(filter);
+            }
+            FilterSet::ChatChannelDiscovery(joined) => {
+                // The channels we joined, which may be old
+                if !joined.is_empty() {
+                    filters.push(Filter {
+                        ids: joined.to_vec(),
+                        ..Default::default()
+                    });
+                }
+
+                // Recently created channels
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                filters.push(Filter {
+                    kinds: vec![EventKind::ChannelCreation],
+                    limit: Some(limit),
+                    ..Default::default()
+                });
+            }
+            FilterSet::Community { author, d } => {
+                let coordinate = crate::community::coordinate(author, d);
+
+                // The community definition
+                let mut filter = Filter {
+                    authors: vec![*author],
+                    kinds: vec![EventKind::CommunityDefinition],
+                    ..Default::default()
+                };
+                filter.set_tag_values('d', vec![d.to_owned()]);
+                filters.push(filter);
+
+                // The latest approvals and posts, and any new ones
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let mut filter = Filter {
+                    kinds: vec![
+                        EventKind::CommunityPostApproval,
+                        EventKind::TextNote,
+                        crate::community::COMMENT.into(),
+                    ],
+                    limit: Some(limit),
+                    ..Default::default()
+                };
+                filter.set_tag_values('a', vec![coordinate]);
+                filters.push(filter);
+            }
+            FilterSet::CommunityChunk { author, d, anchor } => {
+                let coordinate = crate::community::coordinate(author, d);
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let range = FeedRange::ChunkBefore {
+                    until: *anchor,
+                    limit,
+                };
+                let (since, until, limit) = range.since_until_limit();
+                let mut filter = Filter {
+                    kinds: vec![
+                        EventKind::CommunityPostApproval,
+                        EventKind::TextNote,
+                        crate::community::COMMENT.into(),
+                    ],
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                };
+                filter.set_tag_values('a', vec![coordinate]);
+                filters.push(filter);
+            }
+            FilterSet::CommunityDiscovery(owners) => {
+                // The communities we follow, which may be old
+                if !owners.is_empty() {
+                    filters.push(Filter {
+                        authors: owners.to_vec(),
+                        kinds: vec![EventKind::CommunityDefinition],
+                        ..Default::default()
+                    });
+                }
+
+                // Recently defined communities
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                filters.push(Filter {
+                    kinds: vec![EventKind::CommunityDefinition],
+                    limit: Some(limit),
+                    ..Default::default()
+                });
+            }
             FilterSet::Config => {
                 let since = Unixtime::now() - Duration::from_secs(60 * 60 * 24 * 15);
                 if let Some(pubkey) = GLOBALS.identity.public_key() {
@@ -185,11 +392,30 @@ impl FilterSet {
                             EventKind::DmRelayList,
                             EventKind::BookmarkList,
                             EventKind::UserServerList,
+                            EventKind::PinList,
+                            EventKind::PublicChatsList,
+                            EventKind::CommunityList,
+                            EventKind::BookmarkSets,
+                            EventKind::InterestsList,
+                            EventKind::InterestSets,
+                            EventKind::RelaySets,
+                            EventKind::UserEmojiList,
+                            EventKind::EmojiSets,
+                            EventKind::ProfileBadges,
+                            EventKind::BadgeDefinition,
                         ],
                         // these are all replaceable, no since required
                         ..Default::default()
                     });
 
+                    // Badges awarded to me
+                    let mut awards_filter = Filter {
+                        kinds: vec![EventKind::BadgeAward],
+                        ..Default::default()
+                    };
+                    awards_filter.set_tag_values('p', vec![pubkey.as_hex_string()]);
+                    filters.push(awards_filter);
+
                     // Events I posted recently, including feed_displayable and
                     //  augments (deletions, reactions, timestamp, label,reporting, and zap)
                     filters.push(Filter {
@@ -198,6 +424,31 @@ impl FilterSet {
                         since: Some(since),
                         ..Default::default()
                     });
+
+                    // Our channel moderation (hidden messages and muted users), all of it
+                    filters.push(Filter {
+                        authors: vec![pubkey],
+                        kinds: vec![EventKind::ChannelHideMessage, EventKind::ChannelMuteUser],
+                        ..Default::default()
+                    });
+                }
+            }
+            FilterSet::CustomFeedFuture { feed, anchor } => {
+                if let Ok(mut filter) = feed.filter() {
+                    let range = FeedRange::After { since: *anchor };
+                    (filter.since, filter.until, filter.limit) = range.since_until_limit();
+                    filters.push(filter);
+                }
+            }
+            FilterSet::CustomFeedChunk { feed, anchor } => {
+                if let Ok(mut filter) = feed.filter() {
+                    let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                    let range = FeedRange::ChunkBefore {
+                        until: *anchor,
+                        limit,
+                    };
+                    (filter.since, filter.until, filter.limit) = range.since_until_limit();
+                    filters.push(filter);
                 }
             }
             FilterSet::Discover(pubkeys) => {
@@ -229,6 +480,25 @@ impl FilterSet {
                 filter.set_tag_values('p', authors.iter().map(|x| x.as_hex_string()).collect());
                 filters.push(filter);
             }
+            FilterSet::DvmDiscovery => {
+                let mut filter = Filter {
+                    kinds: vec![EventKind::HandlerInformation],
+                    ..Default::default()
+                };
+                let values = vec![crate::dvm::CONTENT_DISCOVERY_REQUEST.to_string()];
+                filter.set_tag_values('k', values);
+                filters.push(filter);
+            }
+            FilterSet::DvmResults { dvm, job } => {
+                let mut filter = Filter {
+                    authors: vec![*dvm],
+                    kinds: vec![crate::dvm::CONTENT_DISCOVERY_RESULT.into()],
+                    ..Default::default()
+                };
+                let values = vec![job.as_hex_string()];
+                filter.set_tag_values('e', values);
+                filters.push(filter);
+            }
             FilterSet::FollowersOf(pubkey) => {
                 let mut filter = Filter {
                     kinds: vec![EventKind::ContactList],
@@ -338,6 +608,87 @@ impl FilterSet {
                     ..Default::default()
                 });
             }
+            FilterSet::Group(group) => {
+                // The group metadata, admins and members
+                let mut filter = Filter {
+                    kinds: crate::group::group_metadata_kinds(),
+                    ..Default::default()
+                };
+                filter.set_tag_values('d', vec![group.to_owned()]);
+                filters.push(filter);
+
+                // The latest content, and any new content
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let mut filter = Filter {
+                    kinds: crate::group::group_content_kinds(),
+                    limit: Some(limit),
+                    ..Default::default()
+                };
+                filter.set_tag_values('h', vec![group.to_owned()]);
+                filters.push(filter);
+            }
+            FilterSet::GroupChunk { group, anchor } => {
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let range = FeedRange::ChunkBefore {
+                    until: *anchor,
+                    limit,
+                };
+                let (since, until, limit) = range.since_until_limit();
+                let mut filter = Filter {
+                    kinds: crate::group::group_content_kinds(),
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                };
+                filter.set_tag_values('h', vec![group.to_owned()]);
+                filters.push(filter);
+            }
+            FilterSet::GroupDiscovery => {
+                // The metadata of every group on the relay
+                filters.push(Filter {
+                    kinds: vec![crate::group::GROUP_METADATA.into()],
+                    ..Default::default()
+                });
+            }
+            FilterSet::HashtagFeedFuture { hashtag, anchor } => {
+                // Allow all feed related event kinds (excluding DMs)
+                // Do not load feed related or the limit will be wrong
+                let event_kinds = crate::feed::feed_displayable_event_kinds(false);
+
+                let range = FeedRange::After { since: *anchor };
+                let (since, until, limit) = range.since_until_limit();
+                let mut filter = Filter {
+                    kinds: event_kinds,
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                };
+                filter.set_tag_values('t', vec![hashtag.to_owned()]);
+                filters.push(filter);
+            }
+            FilterSet::HashtagFeedChunk { hashtag, anchor } => {
+                // Allow all feed related event kinds (excluding DMs)
+                // Do not load feed related or the limit will be wrong
+                let event_kinds = crate::feed::feed_displayable_event_kinds(false);
+
+                let limit = GLOBALS.db().read_setting_load_more_count() as usize;
+                let range = FeedRange::ChunkBefore {
+                    until: *anchor,
+                    limit,
+                };
+                let (since, until, limit) = range.since_until_limit();
+                let mut filter = Filter {
+                    kinds: event_kinds,
+                    since,
+                    until,
+                    limit,
+                    ..Default::default()
+                };
+                filter.set_tag_values('t', vec![hashtag.to_owned()]);
+                filters.push(filter);
+            }
             FilterSet::InboxFeedFuture(anchor) => {
                 if let Some(pubkey) = GLOBALS.identity.public_key() {
                     let mut filter = Self::inbox_base_filter(pubkey, spamsafe);
@@ -375,6 +726,10 @@ impl FilterSet {
                         EventKind::RelayList,
                         EventKind::DmRelayList,
                         EventKind::HandlerRecommendation,
+                        EventKind::PinList,
+                        EventKind::UserStatus,
+                        crate::nip39::EXTERNAL_IDENTITIES.into(),
+                        EventKind::ProfileBadges,
                     ],
                     // FIXME: we could probably get a since-last-fetched-their-metadata here.
                     //        but relays should just return the latest of these.
@@ -1,7 +1,9 @@
 use crate::blossom::{BlobDescriptor, Blossom};
 use crate::bookmarks::BookmarkList;
 use crate::comms::{RelayJob, ToMinionMessage, ToOverlordMessage};
+use crate::count::Counts;
 use crate::delegation::Delegation;
+use crate::dm_search::DmSearchResult;
 use crate::error::Error;
 use crate::feed::Feed;
 use crate::fetcher::Fetcher;
@@ -9,6 +11,7 @@ use crate::gossip_identity::GossipIdentity;
 use crate::media::Media;
 use crate::minion::MinionExitReason;
 use crate::misc::ZapState;
+use crate::nip96::Nip96;
 use crate::pending::Pending;
 use crate::people::{FollowList, People, Person};
 use crate::relay::Relay;
@@ -19,7 +22,7 @@ use crate::status::StatusQueue;
 use crate::storage::{HandlersTable, Storage, Table};
 use crate::RunState;
 use dashmap::DashMap;
-use nostr_types::{Event, EventKind, Id, Profile, PublicKey, RelayUrl, UncheckedUrl};
+use nostr_types::{Event, EventKind, FileMetadata, Id, Profile, PublicKey, RelayUrl, UncheckedUrl};
 use parking_lot::RwLock as PRwLock;
 use regex::Regex;
 use rhai::{Engine, AST};
@@ -127,6 +130,7 @@ pub struct Globals {
     //pub naddrs_being_searched_for: PRwLock<Vec<NAddr>>, // being searched for
     pub people_search_results: PRwLock<Vec<Person>>,
     pub note_search_results: PRwLock<Vec<Event>>,
+    pub dm_search_results: PRwLock<Vec<DmSearchResult>>,
 
     /// UI note cache invalidation per note
     // when we update an augment (deletion/reaction/zap) the UI must recompute
@@ -196,14 +200,29 @@ pub struct Globals {
     /// Blossom (the uploader)
     pub blossom: OnceLock<Blossom>,
 
-    /// Blossom Uploads (Path to Url)
+    /// NIP-96 (the other uploader)
+    pub nip96: OnceLock<Nip96>,
+
+    /// Uploads, to Blossom or NIP-96 (Path to Url)
     pub blossom_uploads: DashMap<PathBuf, Result<BlobDescriptor, Error>>,
 
+    /// File metadata of our uploads (by sha256), for their imeta tags
+    pub upload_file_metadata: DashMap<String, FileMetadata>,
+
+    /// Our blobs on each of our blossom servers (by base URL)
+    pub blossom_blobs: DashMap<String, Result<Vec<BlobDescriptor>, Error>>,
+
+    /// Content in the media cache (sha256 to the name of a cache file holding it)
+    pub media_blobs: DashMap<String, String>,
+
     /// Followers (we keep it in memory only, for just one person)
     pub followers: PRwLock<FollowList>,
 
     /// Follows (we keep it in memory only, for just one person)
     pub follows: PRwLock<FollowList>,
+
+    /// NIP-45 counts (followers, replies, reactions, reposts)
+    pub counts: Counts,
 }
 
 lazy_static! {
@@ -256,6 +275,7 @@ lazy_static! {
             //naddrs_being_searched_for: PRwLock::new(Vec::new()),
             people_search_results: PRwLock::new(Vec::new()),
             note_search_results: PRwLock::new(Vec::new()),
+            dm_search_results: PRwLock::new(Vec::new()),
             ui_notes_to_invalidate: PRwLock::new(Vec::new()),
             ui_people_to_invalidate: PRwLock::new(Vec::new()),
             ui_invalidate_all: AtomicBool::new(false),
@@ -280,9 +300,14 @@ lazy_static! {
             relay_tests: DashMap::new(),
             handlers: DashMap::new(),
             blossom: OnceLock::new(),
+            nip96: OnceLock::new(),
             blossom_uploads: DashMap::new(),
+            blossom_blobs: DashMap::new(),
+            media_blobs: DashMap::new(),
+            upload_file_metadata: DashMap::new(),
             followers: PRwLock::new(FollowList::default()),
             follows: PRwLock::new(FollowList::default()),
+            counts: Counts::new(),
         }
     };
 }
@@ -1,12 +1,14 @@
 use crate::bookmarks::BookmarkList;
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
+use base64::Engine;
 use nostr_types::{
     ContentEncryptionAlgorithm, DelegationConditions, EncryptedPrivateKey, Event, EventKind,
     EventV1, EventV2, Filter, Id, Identity, KeySecurity, Metadata, PreEvent, PrivateKey, PublicKey,
-    Rumor, RumorV1, RumorV2, Signature,
+    Rumor, RumorV1, RumorV2, Signature, Tag, Unixtime,
 };
 use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use tokio::task;
@@ -206,6 +208,44 @@ impl GossipIdentity {
             .sign_event_with_pow(input, zero_bits, work_sender)?)
     }
 
+    /// A NIP-98 `Authorization` header value for an HTTP request: a signed kind 27235
+    /// event naming the URL and method (and the hash of the body, if given), base64
+    /// encoded after "Nostr "
+    pub fn http_authorization(
+        &self,
+        url: &str,
+        method: &str,
+        payload: Option<&[u8]>,
+    ) -> Result<String, Error> {
+        let public_key = match self.public_key() {
+            Some(pk) => pk,
+            None => return Err(ErrorKind::NoPublicKey.into()),
+        };
+
+        let mut tags: Vec<Tag> = vec![Tag::new(&["u", url]), Tag::new(&["method", method])];
+        if let Some(body) = payload {
+            let hash = hex::encode(Sha256::digest(body));
+            tags.push(Tag::new(&["payload", &hash]));
+        }
+
+        let pre_event = PreEvent {
+            pubkey: public_key,
+            created_at: Unixtime::now(),
+            kind: EventKind::HttpAuth,
+            tags,
+            content: "".to_owned(),
+        };
+        Ok(format!("Nostr {}", self.sign_authorization(pre_event)?))
+    }
+
+    /// Sign an event for an HTTP `Authorization` header (NIP-98 and Blossom), returning it
+    /// base64 encoded
+    pub fn sign_authorization(&self, input: PreEvent) -> Result<String, Error> {
+        let event = self.sign_event(input)?;
+        let event_json = serde_json::to_string(&event)?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(event_json))
+    }
+
     pub fn export_private_key_bech32(&self, pass: &str) -> Result<(String, bool), Error> {
         let log_n = GLOBALS.db().read_setting_log_n();
         Ok(self
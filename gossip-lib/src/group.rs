@@ -0,0 +1,238 @@
+//! NIP-29 relay-based groups
+//!
+//! A group lives on a single relay which enforces its membership. The relay publishes
+//! the group metadata (kind 39000), admins (kind 39001) and members (kind 39002) with
+//! the group id as their 'd' tag. Events within a group carry the group id in an 'h'
+//! tag: kind 9 chat messages, kind 11 threads and kind 1111 comments on threads. We ask
+//! to join or leave a group with kind 9021 and kind 9022 events sent to its relay.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use nostr_types::{Event, EventKind, Filter, Id, PreEvent, PublicKey, RelayUrl, Tag, Unixtime};
+
+/// A chat message in a group
+pub const GROUP_CHAT_MESSAGE: u32 = 9;
+
+/// A thread in a group
+pub const GROUP_THREAD: u32 = 11;
+
+/// A comment, used for replies to threads
+pub const COMMENT: u32 = 1111;
+
+/// A request to join a group
+pub const GROUP_JOIN_REQUEST: u32 = 9021;
+
+/// A request to leave a group
+pub const GROUP_LEAVE_REQUEST: u32 = 9022;
+
+/// Group metadata, published by the relay
+pub const GROUP_METADATA: u32 = 39000;
+
+/// Group admins, published by the relay
+pub const GROUP_ADMINS: u32 = 39001;
+
+/// Group members, published by the relay
+pub const GROUP_MEMBERS: u32 = 39002;
+
+/// The event kinds that make up the content of a group
+pub fn group_content_kinds() -> Vec<EventKind> {
+    vec![
+        GROUP_CHAT_MESSAGE.into(),
+        GROUP_THREAD.into(),
+        COMMENT.into(),
+    ]
+}
+
+/// The event kinds a relay publishes about its groups
+pub fn group_metadata_kinds() -> Vec<EventKind> {
+    vec![
+        GROUP_METADATA.into(),
+        GROUP_ADMINS.into(),
+        GROUP_MEMBERS.into(),
+    ]
+}
+
+/// The metadata of a NIP-29 group
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupMetadata {
+    /// The relay hosting the group
+    pub relay: RelayUrl,
+
+    /// The group id
+    pub id: String,
+
+    pub name: String,
+    pub about: String,
+    pub picture: String,
+
+    /// Whether anyone can read the group
+    pub public: bool,
+
+    /// Whether join requests are accepted automatically
+    pub open: bool,
+}
+
+impl GroupMetadata {
+    /// Interpret a group metadata event
+    pub fn from_event(event: &Event, relay: &RelayUrl) -> Result<GroupMetadata, Error> {
+        if u32::from(event.kind) != GROUP_METADATA {
+            return Err(ErrorKind::WrongEventKind.into());
+        }
+
+        let mut metadata = GroupMetadata {
+            relay: relay.to_owned(),
+            id: event.parameter().unwrap_or("".to_owned()),
+            name: "".to_owned(),
+            about: "".to_owned(),
+            picture: "".to_owned(),
+            public: true,
+            open: true,
+        };
+
+        for tag in event.tags.iter() {
+            match tag.tagname() {
+                "name" => metadata.name = tag.value().to_owned(),
+                "about" => metadata.about = tag.value().to_owned(),
+                "picture" => metadata.picture = tag.value().to_owned(),
+                "private" => metadata.public = false,
+                "closed" => metadata.open = false,
+                _ => {}
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// The name of the group, falling back to its id
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() {
+            self.id.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// All the groups we know of (locally) on a relay, sorted by name
+    pub fn load_on_relay(relay: &RelayUrl) -> Result<Vec<GroupMetadata>, Error> {
+        let mut filter = Filter::new();
+        filter.add_event_kind(GROUP_METADATA.into());
+
+        let mut groups: Vec<GroupMetadata> = Vec::new();
+        for event in GLOBALS
+            .db()
+            .find_events_by_filter(&filter, |event| seen_on(event.id, relay))?
+        {
+            let group = Self::from_event(&event, relay)?;
+            // Events are newest first, so keep the first of each group
+            if !group.id.is_empty() && !groups.iter().any(|g| g.id == group.id) {
+                groups.push(group);
+            }
+        }
+        groups.sort_by_key(|g| g.display_name().to_lowercase());
+        Ok(groups)
+    }
+
+    /// Load the metadata of a group
+    pub fn load(relay: &RelayUrl, id: &str) -> Result<Option<GroupMetadata>, Error> {
+        match latest_relay_event(relay, id, GROUP_METADATA)? {
+            Some(event) => Ok(Some(Self::from_event(&event, relay)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Whether an event was seen on a relay
+fn seen_on(id: Id, relay: &RelayUrl) -> bool {
+    GLOBALS
+        .db()
+        .get_event_seen_on_relay(id)
+        .unwrap_or_default()
+        .iter()
+        .any(|(url, _)| url == relay)
+}
+
+/// The latest event of a kind that the relay published about a group
+fn latest_relay_event(relay: &RelayUrl, id: &str, kind: u32) -> Result<Option<Event>, Error> {
+    let mut filter = Filter::new();
+    filter.add_event_kind(kind.into());
+    filter.set_tag_values('d', vec![id.to_owned()]);
+    Ok(GLOBALS
+        .db()
+        .find_events_by_filter(&filter, |event| seen_on(event.id, relay))?
+        .drain(..)
+        .next())
+}
+
+/// The admins of a group, with their roles
+pub fn group_admins(relay: &RelayUrl, id: &str) -> Result<Vec<(PublicKey, Vec<String>)>, Error> {
+    let mut admins: Vec<(PublicKey, Vec<String>)> = Vec::new();
+    if let Some(event) = latest_relay_event(relay, id, GROUP_ADMINS)? {
+        for tag in event.tags.iter() {
+            if tag.tagname() != "p" {
+                continue;
+            }
+            if let Ok(pubkey) = PublicKey::try_from_hex_string(tag.value(), true) {
+                let roles: Vec<String> = tag.clone().into_inner().drain(..).skip(2).collect();
+                admins.push((pubkey, roles));
+            }
+        }
+    }
+    Ok(admins)
+}
+
+/// The members of a group
+pub fn group_members(relay: &RelayUrl, id: &str) -> Result<Vec<PublicKey>, Error> {
+    let mut members: Vec<PublicKey> = Vec::new();
+    if let Some(event) = latest_relay_event(relay, id, GROUP_MEMBERS)? {
+        for tag in event.tags.iter() {
+            if tag.tagname() != "p" {
+                continue;
+            }
+            if let Ok(pubkey) = PublicKey::try_from_hex_string(tag.value(), true) {
+                members.push(pubkey);
+            }
+        }
+    }
+    Ok(members)
+}
+
+/// The locally available content of a group, newest first. Only events seen on the
+/// group's relay belong to the group, as the same id may be used on other relays.
+pub fn group_event_ids(relay: &RelayUrl, id: &str) -> Result<Vec<Id>, Error> {
+    let mut filter = Filter::new();
+    filter.kinds = group_content_kinds();
+    filter.set_tag_values('h', vec![id.to_owned()]);
+
+    let mut events = GLOBALS
+        .db()
+        .find_events_by_filter(&filter, |event| seen_on(event.id, relay))?;
+    events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(events.iter().map(|e| e.id).collect())
+}
+
+/// The group id of an event, if it is in a group
+pub fn event_group_id(event: &Event) -> Option<String> {
+    event
+        .tags
+        .iter()
+        .find(|tag| tag.tagname() == "h")
+        .map(|tag| tag.value().to_owned())
+}
+
+/// Create a join request (kind 9021) or a leave request (kind 9022)
+pub(crate) fn membership_request(kind: u32, id: &str) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: kind.into(),
+        tags: vec![Tag::new(&["h", id])],
+        content: "".to_owned(),
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
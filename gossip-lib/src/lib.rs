@@ -64,31 +64,53 @@
 //! with the storage engine. In some cases, the `Overlord` has more complex code for doing this,
 //! but in many cases, you can interact with `GLOBALS.db()` directly.
 
+pub mod badge;
+pub use badge::BadgeDefinition;
+
 pub mod blossom;
 pub use blossom::Blossom;
 
 pub mod bookmarks;
 pub use bookmarks::BookmarkList;
 
+pub mod chat_channel;
+pub use chat_channel::ChatChannel;
+
+pub mod community;
+pub use community::Community;
+
 /// Defines messages sent to the overlord
 pub mod comms;
 
+mod count;
+pub use count::{Count, CountKind, CountResult, Counts, Hll};
+
 mod delegation;
 pub use delegation::Delegation;
 
 mod dm_channel;
-pub use dm_channel::{DmChannel, DmChannelData};
+pub use dm_channel::{DmChannel, DmChannelData, DmConversation};
+
+/// Searching direct messages
+pub mod dm_search;
+pub use dm_search::{DmSearch, DmSearchResult};
+
+mod dvm;
+pub use dvm::Dvm;
 
 // direct quick-temporary communication with relays, without overlord/minion involvement
 pub mod direct;
 
+pub mod emoji;
+pub use emoji::CustomEmoji;
+
 mod error;
 pub use error::{Error, ErrorKind};
 
 mod feed;
 pub use feed::{
     enabled_event_kinds, feed_augment_event_kinds, feed_displayable_event_kinds,
-    feed_related_event_kinds, Feed, FeedKind,
+    feed_related_event_kinds, CustomFeed, Feed, FeedKind,
 };
 
 mod fetcher;
@@ -99,14 +121,24 @@ mod filter_set;
 mod globals;
 pub use globals::{Globals, GLOBALS};
 
+pub mod group;
+pub use group::GroupMetadata;
+
 mod gossip_identity;
 pub use gossip_identity::GossipIdentity;
 
+mod lists;
+pub use lists::{nip51_kind_name, Nip51List, NIP51_LIST_KINDS};
+
 pub mod manager;
 
 mod media;
 pub use media::{media_url_mimetype, Media, MediaLoadingResult};
 
+/// Media cache size budgets and eviction
+pub mod media_cache;
+pub use media_cache::CacheUsage;
+
 mod minion;
 
 mod misc;
@@ -118,10 +150,30 @@ pub mod names;
 /// nip05 handling
 pub mod nip05;
 
+/// NIP-17 file messages
+pub mod nip17;
+pub use nip17::FileMessage;
+
+/// nip39 handling
+pub mod nip39;
+pub use nip39::IdentityClaim;
+
+/// NIP-71 video events
+pub mod nip71;
+pub use nip71::PostedMedia;
+
+/// NIP-96 HTTP file storage
+pub mod nip96;
+pub use nip96::Nip96;
+
 #[allow(dead_code)]
 pub mod nostr_connect_server;
 pub use nostr_connect_server::{Nip46Server, Nip46UnconnectedServer};
 
+/// NIP-03 OpenTimestamps attestations
+pub mod ots;
+pub use ots::OtsProof;
+
 mod overlord;
 pub use overlord::Overlord;
 
@@ -166,10 +218,21 @@ pub use status::StatusQueue;
 
 mod storage;
 pub use storage::types::*;
-pub use storage::{FollowingsTable, HandlersTable, PersonTable, Storage, Table};
+pub use storage::{
+    CacheEntriesTable, CustomFeedsTable, DmConversationsTable, DmSearchTable, FollowingsTable,
+    GroupsTable, HandlersTable, IdentityClaimsTable, OtsProofsTable, PersonTable, Storage, Table,
+};
 
 mod tasks;
 
+/// Preparing media for upload
+pub mod upload;
+
+pub mod user_status;
+pub use user_status::UserStatus;
+
+pub mod website_reaction;
+
 #[macro_use]
 extern crate lazy_static;
 
@@ -0,0 +1,308 @@
+//! NIP-51 lists and sets
+//!
+//! Follow sets, the mute list, bookmarks and relay lists have their own dedicated
+//! handling elsewhere. The remaining lists and sets that we support are handled here
+//! generically, as a vector of tags each of which may be public or private. Private
+//! entries are stored encrypted to ourselves in the content, the same way bookmarks are.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use nostr_types::{
+    ContentEncryptionAlgorithm, Event, EventKind, Filter, Id, NAddr, PreEvent, PublicKey, RelayUrl,
+    Tag, Unixtime,
+};
+use std::collections::BTreeMap;
+
+/// The list and set kinds handled by [Nip51List]
+pub const NIP51_LIST_KINDS: [EventKind; 9] = [
+    EventKind::PinList,
+    EventKind::PublicChatsList,
+    EventKind::CommunityList,
+    EventKind::BookmarkSets,
+    EventKind::InterestsList,
+    EventKind::InterestSets,
+    EventKind::RelaySets,
+    EventKind::UserEmojiList,
+    EventKind::EmojiSets,
+];
+
+/// A human readable name for a NIP-51 list kind
+pub fn nip51_kind_name(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::PinList => "Pinned Notes",
+        EventKind::PublicChatsList => "Public Chats",
+        EventKind::CommunityList => "Communities",
+        EventKind::BookmarkSets => "Bookmark Sets",
+        EventKind::InterestsList => "Interests",
+        EventKind::InterestSets => "Interest Sets",
+        EventKind::RelaySets => "Relay Sets",
+        EventKind::UserEmojiList => "Emojis",
+        EventKind::EmojiSets => "Emoji Sets",
+        _ => "Unknown List",
+    }
+}
+
+/// Encrypt tags to ourselves, for the content of a list with private entries
+pub(crate) fn encrypt_private_tags(public_key: &PublicKey, tags: &[Tag]) -> Result<String, Error> {
+    let private_json = serde_json::to_string(tags)?;
+    GLOBALS.identity.encrypt(
+        public_key,
+        &private_json,
+        ContentEncryptionAlgorithm::Nip44v2,
+    )
+}
+
+/// Decrypt the private tags of one of our lists. If this fails (e.g. the list is not
+/// ours, or we are not unlocked) no tags are returned.
+pub(crate) fn decrypt_private_tags(public_key: &PublicKey, event: &Event) -> Vec<Tag> {
+    if event.content.is_empty() {
+        return vec![];
+    }
+
+    match GLOBALS.identity.decrypt(public_key, &event.content) {
+        Ok(json_string) => serde_json::from_str::<Vec<Tag>>(&json_string).unwrap_or_default(),
+        Err(_) => vec![],
+    }
+}
+
+/// A NIP-51 list (kind 10000-19999) or set (kind 30000-39999)
+#[derive(Debug, Clone)]
+pub struct Nip51List {
+    pub kind: EventKind,
+
+    /// The 'd' tag. This is empty for lists (which are not sets).
+    pub d: String,
+
+    pub title: String,
+    pub description: String,
+
+    /// The list entries, each flagged if private
+    pub entries: Vec<(Tag, bool)>,
+}
+
+impl Nip51List {
+    pub fn new(kind: EventKind, d: String) -> Self {
+        Self {
+            kind,
+            d,
+            title: "".to_owned(),
+            description: "".to_owned(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Is this a set (parameterized, one of many) rather than a single list?
+    pub fn is_set(&self) -> bool {
+        self.kind.is_parameterized_replaceable()
+    }
+
+    /// The title, falling back to the 'd' tag, falling back to the kind name
+    pub fn name(&self) -> String {
+        if !self.title.is_empty() {
+            self.title.clone()
+        } else if !self.d.is_empty() {
+            self.d.clone()
+        } else {
+            nip51_kind_name(self.kind).to_owned()
+        }
+    }
+
+    fn add_tags(&mut self, tags: &[Tag], private: bool) {
+        for tag in tags.iter() {
+            match tag.tagname() {
+                "d" => continue,
+                "title" => self.title = tag.value().to_owned(),
+                "description" => self.description = tag.value().to_owned(),
+                _ => self.entries.push((tag.clone(), private)),
+            }
+        }
+    }
+
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if !NIP51_LIST_KINDS.contains(&event.kind) {
+            return Err(ErrorKind::WrongEventKind.into());
+        }
+
+        let mut list = Self::new(event.kind, event.parameter().unwrap_or("".to_owned()));
+        list.add_tags(event.tags.as_ref(), false);
+
+        // Only our own lists have private entries that we can read
+        if let Some(public_key) = GLOBALS.identity.public_key() {
+            if event.pubkey == public_key {
+                list.add_tags(&decrypt_private_tags(&public_key, event), true);
+            }
+        }
+
+        Ok(list)
+    }
+
+    pub fn into_event(&self) -> Result<Event, Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            None => return Err(ErrorKind::NoPublicKey.into()),
+            Some(pk) => pk,
+        };
+
+        let mut tags: Vec<Tag> = Vec::new();
+        if self.is_set() {
+            tags.push(Tag::new(&["d", self.d.as_str()]));
+        }
+        if !self.title.is_empty() {
+            tags.push(Tag::new(&["title", self.title.as_str()]));
+        }
+        if !self.description.is_empty() {
+            tags.push(Tag::new(&["description", self.description.as_str()]));
+        }
+
+        let mut private: Vec<Tag> = Vec::new();
+        for (tag, is_private) in self.entries.iter() {
+            if *is_private {
+                private.push(tag.clone());
+            } else {
+                tags.push(tag.clone());
+            }
+        }
+
+        let content = if private.is_empty() {
+            "".to_owned()
+        } else {
+            encrypt_private_tags(&public_key, &private)?
+        };
+
+        let pre_event = PreEvent {
+            pubkey: public_key,
+            created_at: Unixtime::now(),
+            kind: self.kind,
+            tags,
+            content,
+        };
+
+        GLOBALS.identity.sign_event(pre_event)
+    }
+
+    /// Add an entry. Returns false if an entry with the same tagname and value was
+    /// already present.
+    pub fn add(&mut self, tag: Tag, private: bool) -> bool {
+        if self.contains(tag.tagname(), tag.value()) {
+            return false;
+        }
+        self.entries.push((tag, private));
+        true
+    }
+
+    /// Remove the entry with this tagname and value. Returns false if it was not present.
+    pub fn remove(&mut self, tagname: &str, value: &str) -> bool {
+        let len = self.entries.len();
+        self.entries
+            .retain(|(tag, _)| !(tag.tagname() == tagname && tag.value() == value));
+        self.entries.len() != len
+    }
+
+    pub fn contains(&self, tagname: &str, value: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|(tag, _)| tag.tagname() == tagname && tag.value() == value)
+    }
+
+    /// The values of all entries with this tagname
+    pub fn values(&self, tagname: &str) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(tag, _)| tag.tagname() == tagname)
+            .map(|(tag, _)| tag.value().to_owned())
+            .collect()
+    }
+
+    /// The relays of a relay set
+    pub fn relay_urls(&self) -> Vec<RelayUrl> {
+        self.values("relay")
+            .iter()
+            .filter_map(|r| RelayUrl::try_from_str(r).ok())
+            .collect()
+    }
+
+    /// The ids referenced by 'e' entries (e.g. pins), in list order
+    pub fn referenced_ids(&self) -> Vec<Id> {
+        self.entries
+            .iter()
+            .filter(|(tag, _)| tag.tagname() == "e")
+            .filter_map(|(tag, _)| tag.parse_event().ok().map(|(id, _, _, _)| id))
+            .collect()
+    }
+
+    /// The addresses referenced by 'a' entries (e.g. communities), in list order
+    pub fn referenced_addrs(&self) -> Vec<NAddr> {
+        self.entries
+            .iter()
+            .filter(|(tag, _)| tag.tagname() == "a")
+            .filter_map(|(tag, _)| tag.parse_address().ok().map(|(ea, _)| ea))
+            .collect()
+    }
+
+    /// The locally available events referenced by 'e' and 'a' entries (e.g. pins and
+    /// bookmark sets), latest first
+    pub fn event_ids(&self) -> Result<Vec<Id>, Error> {
+        let mut map: BTreeMap<Unixtime, Id> = BTreeMap::new();
+
+        for (tag, _) in self.entries.iter() {
+            match tag.tagname() {
+                "e" => {
+                    if let Ok((id, _, _, _)) = tag.parse_event() {
+                        if let Some(event) = GLOBALS.db().read_event(id)? {
+                            map.insert(event.created_at, id);
+                        }
+                    }
+                }
+                "a" => {
+                    if let Ok((ea, _)) = tag.parse_address() {
+                        if let Some(event) = GLOBALS
+                            .db()
+                            .get_replaceable_event(ea.kind, ea.author, &ea.d)?
+                        {
+                            map.insert(event.created_at, event.id);
+                        }
+                    }
+                }
+                _ => continue,
+            }
+        }
+
+        Ok(map.iter().rev().map(|(_, v)| *v).collect())
+    }
+
+    /// Load a list (or one set) from local storage
+    pub fn load(kind: EventKind, pubkey: PublicKey, d: &str) -> Result<Option<Self>, Error> {
+        match GLOBALS.db().get_replaceable_event(kind, pubkey, d)? {
+            Some(event) => Ok(Some(Self::from_event(&event)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load all the sets of a kind by this author from local storage, sorted by name
+    pub fn load_all(kind: EventKind, pubkey: PublicKey) -> Result<Vec<Self>, Error> {
+        let mut filter = Filter::new();
+        filter.add_event_kind(kind);
+        filter.add_author(pubkey);
+
+        let mut lists: Vec<Self> = GLOBALS
+            .db()
+            .find_events_by_filter(&filter, |_| true)?
+            .iter()
+            .filter_map(|event| Self::from_event(event).ok())
+            .collect();
+        lists.sort_by_key(|list| list.name().to_lowercase());
+        Ok(lists)
+    }
+
+    /// Load one of our own lists, or a new empty one if we don't have it yet
+    pub fn load_mine(kind: EventKind, d: &str) -> Result<Self, Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            None => return Err(ErrorKind::NoPublicKey.into()),
+            Some(pk) => pk,
+        };
+
+        match Self::load(kind, public_key, d)? {
+            Some(list) => Ok(list),
+            None => Ok(Self::new(kind, d.to_owned())),
+        }
+    }
+}
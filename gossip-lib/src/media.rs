@@ -1,10 +1,11 @@
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
+use crate::nip17::FileMessage;
 use dashmap::{DashMap, DashSet};
 use image::imageops;
 use image::imageops::FilterType;
 use image::{DynamicImage, Rgba, RgbaImage};
-use nostr_types::{FileMetadata, UncheckedUrl, Url};
+use nostr_types::{FileMetadata, PublicKey, UncheckedUrl, Url};
 use std::fmt;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
@@ -36,6 +37,16 @@ pub struct Media {
     image_temp: DashMap<Url, RgbaImage>,
     media_pending_processing: DashSet<Url>,
     failed_media: DashMap<UncheckedUrl, String>,
+
+    // Who posted a Url, so we can look for its blob on their blossom servers
+    authors: DashMap<Url, PublicKey>,
+
+    // For a broken blossom Url, the Url of the same blob we are trying instead, and
+    // those left to try
+    blossom_fallbacks: DashMap<Url, (Url, Vec<Url>)>,
+
+    // Files sent to us encrypted (NIP-17 file messages), which we decrypt as they load
+    encrypted: DashMap<Url, FileMessage>,
 }
 
 impl Default for Media {
@@ -50,6 +61,9 @@ impl Media {
             image_temp: DashMap::new(),
             media_pending_processing: DashSet::new(),
             failed_media: DashMap::new(),
+            authors: DashMap::new(),
+            blossom_fallbacks: DashMap::new(),
+            encrypted: DashMap::new(),
         }
     }
 
@@ -81,6 +95,67 @@ impl Media {
     /// Retry a failed Url
     pub fn retry_failed(&self, unchecked_url: &UncheckedUrl) {
         self.failed_media.remove(unchecked_url);
+        if let Ok(url) = Url::try_from_unchecked_url(unchecked_url) {
+            self.blossom_fallbacks.remove(&url);
+        }
+    }
+
+    /// Set who posted a Url, so if it breaks we can look for it on their blossom servers
+    pub fn set_author(&self, url: &Url, pubkey: PublicKey) {
+        if !self.authors.contains_key(url) {
+            self.authors.insert(url.to_owned(), pubkey);
+        }
+    }
+
+    /// Set that a Url is an encrypted file, to be decrypted when it is loaded
+    pub fn set_encrypted(&self, url: &Url, file_message: &FileMessage) {
+        if !self.encrypted.contains_key(url) {
+            self.encrypted
+                .insert(url.to_owned(), file_message.to_owned());
+        }
+    }
+
+    // If the Url is a blossom blob, the Urls of the same blob on the blossom servers of
+    // its author and on ours
+    fn blossom_candidates(&self, url: &Url) -> Vec<Url> {
+        let (hash, ext) = match crate::blossom::blob_hash_of(url.as_str()) {
+            Some(he) => he,
+            None => return vec![],
+        };
+
+        let mut servers: Vec<String> = Vec::new();
+        if let Some(pubkey) = self.authors.get(url) {
+            servers.extend(crate::blossom::user_server_base_urls(*pubkey));
+        }
+        servers.extend(crate::blossom::server_base_urls());
+
+        let mut candidates: Vec<Url> = Vec::new();
+        for server in servers {
+            let candidate = format!("{}{}{}", server, hash, ext);
+            if let Ok(candidate) = Url::try_from_str(&candidate) {
+                if candidate != *url && !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+
+    // When a Url fails, try the next blossom server that might have its blob, or
+    // give up on it
+    fn fail_or_fallback(&self, url: &Url, error: String) -> MediaLoadingResult<Vec<u8>> {
+        let mut left = match self.blossom_fallbacks.get(url) {
+            Some(fallback) => fallback.1.clone(),
+            None => self.blossom_candidates(url),
+        };
+        if left.is_empty() {
+            self.set_has_failed(&url.to_unchecked_url(), error.clone());
+            return MediaLoadingResult::Failed(error);
+        }
+        let next = left.remove(0);
+        tracing::info!("{} failed ({}), trying {}", url, error, next);
+        self.blossom_fallbacks.insert(url.to_owned(), (next, left));
+        MediaLoadingResult::Loading
     }
 
     /// Get an image by Url
@@ -167,40 +242,60 @@ impl Media {
             return MediaLoadingResult::Disabled;
         }
 
+        // If the Url broke and it is a blossom blob, we may be fetching it from elsewhere
+        let fetch_url = match self.blossom_fallbacks.get(url) {
+            Some(fallback) => fallback.0.clone(),
+            None => url.to_owned(),
+        };
+
         match GLOBALS.fetcher.try_get(
-            url,
+            &fetch_url,
             Duration::from_secs(60 * 60 * GLOBALS.db().read_setting_media_becomes_stale_hours()),
             use_temp_cache,
         ) {
             Ok(None) => MediaLoadingResult::Loading,
             Ok(Some(bytes)) => {
-                // Verify metadata hash
-                if let Some(file_metadata) = &file_metadata {
-                    if let Some(x) = &file_metadata.x {
-                        use sha2::{Digest, Sha256};
-                        let mut hasher = Sha256::new();
-                        hasher.update(&bytes);
-                        let sha256hash = hasher.finalize();
-                        let hash_str = hex::encode(sha256hash);
-                        if hash_str != *x {
-                            if url.as_str() == "https://mikedilger.com/bs.png" {
-                                tracing::error!("Hash Mismatch Computed");
-                            }
-                            let error = "Hash Mismatch".to_string();
-                            self.set_has_failed(&url.to_unchecked_url(), error.clone());
-                            return MediaLoadingResult::Failed(error);
+                // Verify metadata hash, or the blossom hash if we fetched it from elsewhere
+                let expected_hash = match file_metadata.and_then(|fm| fm.x.clone()) {
+                    Some(x) => Some(x),
+                    None if fetch_url != *url => {
+                        crate::blossom::blob_hash_of(fetch_url.as_str()).map(|(hash, _)| hash)
+                    }
+                    None => None,
+                };
+                if let Some(x) = expected_hash {
+                    use sha2::{Digest, Sha256};
+                    let mut hasher = Sha256::new();
+                    hasher.update(&bytes);
+                    let sha256hash = hasher.finalize();
+                    let hash_str = hex::encode(sha256hash);
+                    if hash_str != x {
+                        if url.as_str() == "https://mikedilger.com/bs.png" {
+                            tracing::error!("Hash Mismatch Computed");
                         }
+                        return self.fail_or_fallback(url, "Hash Mismatch".to_string());
                     }
                 }
 
+                // Decrypt files sent to us encrypted
+                if let Some(file_message) = self.encrypted.get(url) {
+                    return match file_message.decrypt(&bytes) {
+                        Ok(plaintext) => MediaLoadingResult::Ready(plaintext),
+                        Err(e) => {
+                            let error = format!("{e}");
+                            self.set_has_failed(&url.to_unchecked_url(), error.clone());
+                            MediaLoadingResult::Failed(error)
+                        }
+                    };
+                }
+
                 MediaLoadingResult::Ready(bytes)
             }
             Err(e) => {
                 let error = format!("{e}");
                 tracing::error!("{}", error);
-                // this cannot recover without new metadata
-                self.set_has_failed(&url.to_unchecked_url(), error.clone());
-                MediaLoadingResult::Failed(error)
+                // this cannot recover without new metadata, unless the blob is elsewhere
+                self.fail_or_fallback(url, error)
             }
         }
     }
@@ -263,7 +358,7 @@ pub(crate) fn load_image_bytes(
     }
 }
 
-fn adjust_orientation(image_bytes: &[u8], image: DynamicImage) -> DynamicImage {
+pub(crate) fn adjust_orientation(image_bytes: &[u8], image: DynamicImage) -> DynamicImage {
     match get_orientation(image_bytes) {
         1 => image,
         2 => DynamicImage::ImageRgba8(imageops::flip_horizontal(&image)),
@@ -380,6 +475,18 @@ pub fn media_url_mimetype(s: &str) -> Option<&'static str> {
         Some("video/x-ms-wmv")
     } else if lower.ends_with(".3gp") {
         Some("video/3gpp")
+    } else if lower.ends_with(".mp3") {
+        Some("audio/mpeg")
+    } else if lower.ends_with(".m4a") {
+        Some("audio/mp4")
+    } else if lower.ends_with(".ogg") || lower.ends_with(".oga") {
+        Some("audio/ogg")
+    } else if lower.ends_with(".opus") {
+        Some("audio/opus")
+    } else if lower.ends_with(".flac") {
+        Some("audio/flac")
+    } else if lower.ends_with(".wav") {
+        Some("audio/wav")
     } else {
         None
     }
@@ -0,0 +1,329 @@
+//! The media cache, kept within size budgets
+//!
+//! Files the fetcher downloads into the (permanent) cache are recorded in the
+//! `cache_entries` table with their size, the hash of their content, and when they were
+//! last used. Avatars and post media each have a budget, and when either goes over its
+//! budget we evict its least recently used files. Files with the same content (such as a
+//! Blossom blob found at several servers) are hard links to a single copy, and count
+//! once.
+
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use crate::profile::Profile;
+use crate::storage::types::CacheEntry1;
+use crate::storage::{CacheEntriesTable, Table};
+use nostr_types::Unixtime;
+use parking_lot::Mutex;
+use sha2::Digest;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+pub type CacheEntry = CacheEntry1;
+
+// Don't rewrite an entry every time it is used, only when its last access time is
+// this far (in seconds) out of date
+const TOUCH_GRANULARITY: i64 = 600;
+
+// Keeps two evictions from running at once
+static ENFORCE_LOCK: Mutex<()> = Mutex::new(());
+
+/// What the media cache is using
+#[derive(Debug, Clone, Default)]
+pub struct CacheUsage {
+    /// Bytes used by avatars
+    pub avatar_bytes: u64,
+
+    /// Bytes used by post media (and anything else)
+    pub media_bytes: u64,
+
+    /// Number of cached files
+    pub files: usize,
+
+    /// Files and bytes by host, largest first. Bytes are before deduplication.
+    pub hosts: Vec<(String, usize, u64)>,
+}
+
+// The name of a cache file, which is the key of its entry
+fn file_key(cache_file: &Path) -> Option<String> {
+    cache_file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_owned())
+}
+
+// What identifies the content of an entry. Files cached before we kept track have no
+// hash, and count as unique.
+fn content_id(entry: &CacheEntry) -> &str {
+    if entry.sha256.is_empty() {
+        &entry.key
+    } else {
+        &entry.sha256
+    }
+}
+
+fn host_of(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_owned()))
+        .unwrap_or_default()
+}
+
+/// Note that a cache file was used
+pub(crate) fn touch(cache_file: &Path) {
+    let key = match file_key(cache_file) {
+        Some(k) => k,
+        None => return,
+    };
+    let now = Unixtime::now().0;
+    if let Ok(Some(mut entry)) = CacheEntriesTable::read_record(key, None) {
+        if entry.last_access + TOUCH_GRANULARITY < now {
+            entry.last_access = now;
+            if let Err(e) = CacheEntriesTable::write_record(&mut entry, None) {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+}
+
+/// Record a file that was just written into the cache. If we already have its content
+/// under another URL, the file is replaced with a link to that copy.
+pub(crate) fn record(
+    cache_file: &Path,
+    url: &str,
+    bytes: &[u8],
+    avatar: bool,
+) -> Result<(), Error> {
+    let key = match file_key(cache_file) {
+        Some(k) => k,
+        None => return Ok(()),
+    };
+    let sha256 = hex::encode(sha2::Sha256::digest(bytes));
+
+    let existing = GLOBALS.media_blobs.get(&sha256).map(|k| k.value().clone());
+    match existing {
+        Some(other) if other != key => {
+            let other_file = cache_file.with_file_name(&other);
+            let link = cache_file.with_extension("link");
+            if fs::hard_link(&other_file, &link).is_ok() && fs::rename(&link, cache_file).is_err() {
+                let _ = fs::remove_file(&link);
+            }
+        }
+        Some(_) => {}
+        None => {
+            GLOBALS.media_blobs.insert(sha256.clone(), key.clone());
+        }
+    }
+
+    // Once an avatar, always an avatar
+    let avatar = avatar
+        || matches!(
+            CacheEntriesTable::read_record(key.clone(), None),
+            Ok(Some(ref entry)) if entry.avatar
+        );
+
+    let mut entry = CacheEntry {
+        key,
+        url: url.to_owned(),
+        host: host_of(url),
+        size: bytes.len() as u64,
+        sha256,
+        avatar,
+        last_access: Unixtime::now().0,
+    };
+    CacheEntriesTable::write_record(&mut entry, None)?;
+
+    Ok(())
+}
+
+/// If a URL names a Blossom blob whose content we already have from elsewhere, link it
+/// into place as the cache file for that URL and return its contents
+pub(crate) fn link_existing(cache_file: &Path, url: &str, avatar: bool) -> Option<Vec<u8>> {
+    let (hash, _) = crate::blossom::blob_hash_of(url)?;
+    let other = GLOBALS.media_blobs.get(&hash).map(|k| k.value().clone())?;
+    let other_file = cache_file.with_file_name(other);
+    fs::hard_link(&other_file, cache_file).ok()?;
+    let contents = fs::read(cache_file).ok()?;
+    if let Err(e) = record(cache_file, url, &contents, avatar) {
+        tracing::warn!("{}", e);
+    }
+    tracing::debug!("FETCH {url}: Found the same blob in the cache");
+    Some(contents)
+}
+
+/// Bring the cache index up to date with the cache directory, then evict the least
+/// recently used files of each class that is over its budget. Returns how many files
+/// were evicted. This walks the cache directory, so call it with spawn_blocking.
+pub fn enforce_limits() -> Result<usize, Error> {
+    let _lock = ENFORCE_LOCK.lock();
+
+    let cache_dir = Profile::cache_dir(false)?;
+
+    let mut entries: HashMap<String, CacheEntry> = CacheEntriesTable::filter_records(|_| true)?
+        .into_iter()
+        .map(|entry| (entry.key.clone(), entry))
+        .collect();
+
+    // Index files cached before we kept track
+    let mut present: HashSet<String> = HashSet::new();
+    for dirent in fs::read_dir(cache_dir.as_path())? {
+        let dirent = dirent?;
+        let name = match dirent.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        // Skip etags, and links being made
+        if name.contains('.') {
+            continue;
+        }
+        let metadata = match dirent.metadata() {
+            Ok(md) if md.is_file() => md,
+            _ => continue,
+        };
+        present.insert(name.clone());
+        if !entries.contains_key(&name) {
+            let last_access = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            let mut entry = CacheEntry {
+                key: name.clone(),
+                url: "".to_owned(),
+                host: "".to_owned(),
+                size: metadata.len(),
+                sha256: "".to_owned(),
+                avatar: false,
+                last_access,
+            };
+            CacheEntriesTable::write_record(&mut entry, None)?;
+            entries.insert(name, entry);
+        }
+    }
+
+    // Forget files that are gone
+    let gone: Vec<String> = entries
+        .keys()
+        .filter(|key| !present.contains(*key))
+        .cloned()
+        .collect();
+    for key in gone {
+        CacheEntriesTable::delete_record(key.clone(), None)?;
+        entries.remove(&key);
+    }
+
+    let mut count: usize = 0;
+    for avatar in [true, false] {
+        let budget_mb = if avatar {
+            GLOBALS.db().read_setting_cache_avatar_budget_mb()
+        } else {
+            GLOBALS.db().read_setting_cache_media_budget_mb()
+        };
+        let budget = budget_mb * 1024 * 1024;
+
+        let mut class: Vec<CacheEntry> = entries
+            .values()
+            .filter(|entry| entry.avatar == avatar)
+            .cloned()
+            .collect();
+
+        // How many files share each content, and the bytes of distinct content
+        let mut copies: HashMap<String, usize> = HashMap::new();
+        let mut usage: u64 = 0;
+        for entry in class.iter() {
+            let n = copies.entry(content_id(entry).to_owned()).or_insert(0);
+            if *n == 0 {
+                usage += entry.size;
+            }
+            *n += 1;
+        }
+        if usage <= budget {
+            continue;
+        }
+
+        // Least recently used first
+        class.sort_by_key(|entry| entry.last_access);
+        for entry in class.iter() {
+            if usage <= budget {
+                break;
+            }
+            let path = cache_dir.join(&entry.key);
+            if let Err(e) = fs::remove_file(path.as_path()) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!("Could not evict {}: {}", entry.key, e);
+                    continue;
+                }
+            }
+            let _ = fs::remove_file(path.with_extension("etag"));
+            CacheEntriesTable::delete_record(entry.key.clone(), None)?;
+            entries.remove(&entry.key);
+            count += 1;
+
+            // The space is only freed when the last copy goes
+            if let Some(n) = copies.get_mut(content_id(entry)) {
+                *n -= 1;
+                if *n == 0 {
+                    usage = usage.saturating_sub(entry.size);
+                }
+            }
+        }
+    }
+
+    // Rebuild the index of content we have
+    GLOBALS.media_blobs.clear();
+    for entry in entries.values() {
+        if !entry.sha256.is_empty() {
+            GLOBALS
+                .media_blobs
+                .entry(entry.sha256.clone())
+                .or_insert(entry.key.clone());
+        }
+    }
+
+    if count > 0 {
+        tracing::info!("Evicted {} files from the media cache", count);
+    }
+
+    Ok(count)
+}
+
+/// What the media cache is using, by class and by host
+pub fn usage() -> Result<CacheUsage, Error> {
+    let entries = CacheEntriesTable::filter_records(|_| true)?;
+
+    let mut usage = CacheUsage {
+        files: entries.len(),
+        ..Default::default()
+    };
+
+    let mut seen: HashSet<(bool, String)> = HashSet::new();
+    let mut hosts: HashMap<String, (usize, u64)> = HashMap::new();
+    for entry in entries.iter() {
+        if seen.insert((entry.avatar, content_id(entry).to_owned())) {
+            if entry.avatar {
+                usage.avatar_bytes += entry.size;
+            } else {
+                usage.media_bytes += entry.size;
+            }
+        }
+
+        let host = if entry.host.is_empty() {
+            "(unknown)".to_owned()
+        } else {
+            entry.host.clone()
+        };
+        let h = hosts.entry(host).or_insert((0, 0));
+        h.0 += 1;
+        h.1 += entry.size;
+    }
+
+    usage.hosts = hosts
+        .into_iter()
+        .map(|(host, (files, bytes))| (host, files, bytes))
+        .collect();
+    usage.hosts.sort_by(|a, b| b.2.cmp(&a.2));
+
+    Ok(usage)
+}
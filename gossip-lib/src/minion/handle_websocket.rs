@@ -1,5 +1,6 @@
 use super::{AuthState, Minion};
 use crate::comms::ToOverlordMessage;
+use crate::count::{CountResult, Hll};
 use crate::error::Error;
 use crate::globals::GLOBALS;
 use nostr_types::{RelayMessage, Unixtime};
@@ -168,10 +169,12 @@ impl Minion {
                             self.auth_state = AuthState::Failed;
                             // Auth failed.
                             tracing::warn!("AUTH failed to {}: {}", &self.url, ok_message);
+                            self.fail_posts_waiting_for_auth()?;
                         } else {
                             tracing::info!("Authenticated to {}", &self.url);
                             self.auth_state = AuthState::Authenticated;
                             self.try_subscribe_waiting().await?;
+                            self.post_waiting_for_auth().await?;
                         }
                         return Ok(());
                     }
@@ -188,12 +191,47 @@ impl Minion {
                             tracing::info!("Fake-authenticated to {}", &self.url);
                             self.auth_state = AuthState::FakeAuthenticated;
                             self.try_subscribe_waiting().await?;
+                            // Relays won't take our posts from a fake identity
+                            self.fail_posts_waiting_for_auth()?;
                         }
                         return Ok(());
                     }
                 }
 
-                if let Some(job_id) = self.posting_ids.get(&id).copied() {
+                if self.posting_ids.contains_key(&id) {
+                    // The relay wants to know who we are first (e.g. for protected events)
+                    if !ok
+                        && ok_message.starts_with("auth-required")
+                        && self.dbrelay.allow_auth != Some(false)
+                        && GLOBALS.identity.is_unlocked()
+                    {
+                        match self.auth_state {
+                            AuthState::None => {
+                                // post again once auth completes
+                                self.posts_waiting_for_auth.push(id);
+                                if self.auth_challenge.is_empty() {
+                                    // Wait for the relay's AUTH challenge
+                                } else if GLOBALS.db().read_setting_relay_auth_requires_approval()
+                                    && self.dbrelay.allow_auth.is_none()
+                                {
+                                    self.request_auth_approval();
+                                } else {
+                                    self.authenticate().await?;
+                                }
+                                return Ok(());
+                            }
+                            AuthState::Waiting(_) => {
+                                // post again once auth completes
+                                self.posts_waiting_for_auth.push(id);
+                                return Ok(());
+                            }
+                            _ => {
+                                // We are authenticated (perhaps not as the author), or
+                                // cannot be. Fail this post.
+                            }
+                        }
+                    }
+
                     if ok {
                         // Save seen_on data
                         // (it was already processed by the overlord before the minion got it,
@@ -209,27 +247,7 @@ impl Minion {
                         self.bump_failure_count().await;
                     }
 
-                    let mut job_is_done: bool = false;
-                    {
-                        // Take it out of the posting_jobs
-                        if let Some(job_ids) = self.posting_jobs.get_mut(&job_id) {
-                            job_ids.retain(|id_in_vec| *id_in_vec != id);
-                            job_is_done = job_ids.is_empty();
-                        } // else is not expected, but if it happens we ignore it.
-                    }
-
-                    if job_is_done {
-                        self.posting_jobs.remove(&job_id);
-
-                        // Tell overlord
-                        self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
-                            self.url.clone(),
-                            job_id,
-                        ))?;
-                    }
-
-                    // Take it out of the posting_ids whether or not job is done
-                    self.posting_ids.remove(&id);
+                    self.finish_post(id)?;
                 }
             }
             RelayMessage::Auth(challenge) => {
@@ -243,22 +261,19 @@ impl Minion {
                     match self.dbrelay.allow_auth {
                         Some(true) => self.authenticate().await?,
                         Some(false) => self.fake_authenticate().await?,
-                        None => {
-                            if let Some(pubkey) = GLOBALS.identity.public_key() {
-                                GLOBALS.pending.insert(
-                                    crate::pending::PendingItem::RelayAuthenticationRequest {
-                                        account: pubkey,
-                                        relay: self.url.clone(),
-                                    },
-                                );
-                            }
-                        }
+                        None => self.request_auth_approval(),
                     }
                 } else {
                     self.authenticate().await?
                 }
             }
             RelayMessage::Closed(subid, message) => {
+                // Relays that don't do NIP-45 may close our COUNT
+                if let Some(kind) = self.finish_count(&subid.0)? {
+                    tracing::debug!("{}: COUNT {:?} closed: {}", &self.url, kind, message);
+                    return Ok(());
+                }
+
                 let handle = self
                     .subscription_map
                     .get_handle_by_id(&subid.0)
@@ -367,11 +382,32 @@ impl Minion {
                 tracing::debug!("{}: removed subscription {}", &self.url, handle);
                 let _ = self.subscription_map.remove(&handle);
             }
-            RelayMessage::Count(_subid, _count_result) => {
-                tracing::info!(
-                    "{} Relay returned count results that we do not yet support",
-                    &self.url
+            RelayMessage::Count(subid, _count_result) => {
+                let kind = match self.finish_count(&subid.0)? {
+                    Some(kind) => kind,
+                    None => {
+                        tracing::debug!("{}: COUNT for unknown id {:?}", &self.url, subid);
+                        return Ok(());
+                    }
+                };
+
+                // nostr_types doesn't give us the `approximate` or `hll` fields
+                // so we take them from the raw message.
+                let value: serde_json::Value = serde_json::from_str(&ws_message)?;
+                let count_result = CountResult {
+                    count: value[2]["count"].as_u64().unwrap_or(0),
+                    approximate: value[2]["approximate"].as_bool().unwrap_or(false),
+                    hll: value[2]["hll"].as_str().and_then(Hll::from_hex),
+                };
+                tracing::debug!(
+                    "{}: COUNT {:?} = {} (approximate={}, hll={})",
+                    &self.url,
+                    kind,
+                    count_result.count,
+                    count_result.approximate,
+                    count_result.hll.is_some()
                 );
+                GLOBALS.counts.record(kind, self.url.clone(), count_result);
             }
         }
 
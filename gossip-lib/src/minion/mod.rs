@@ -3,6 +3,7 @@ mod subscription;
 mod subscription_map;
 
 use crate::comms::{ToMinionMessage, ToMinionPayload, ToMinionPayloadDetail, ToOverlordMessage};
+use crate::count::CountKind;
 use crate::error::{Error, ErrorKind};
 use crate::filter_set::FilterSet;
 use crate::globals::GLOBALS;
@@ -60,6 +61,12 @@ pub struct EventSeekState {
     pub asked: bool,
 }
 
+pub struct CountState {
+    pub job_id: u64,
+    pub kind: CountKind,
+    pub asked_at: Unixtime,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MinionExitReason {
     GotDisconnected,
@@ -94,9 +101,12 @@ pub struct Minion {
     posting_ids: HashMap<Id, u64>,
     sought_events: HashMap<Id, EventSeekState>,
     sought_naddrs: HashMap<NAddr, EventSeekState>,
+    counts: HashMap<String, CountState>,
+    next_count_id: u32,
     last_message_sent: String,
     auth_challenge: String,
     subscriptions_waiting_for_auth: HashMap<String, Unixtime>,
+    posts_waiting_for_auth: Vec<Id>,
     subscriptions_waiting_for_metadata: Vec<(u64, Vec<PublicKey>)>,
     subscriptions_rate_limited: Vec<String>,
     read_runstate: WatchReceiver<RunState>,
@@ -141,9 +151,12 @@ impl Minion {
             posting_ids: HashMap::new(),
             sought_events: HashMap::new(),
             sought_naddrs: HashMap::new(),
+            counts: HashMap::new(),
+            next_count_id: 0,
             last_message_sent: String::new(),
             auth_challenge: "".to_string(),
             subscriptions_waiting_for_auth: HashMap::new(),
+            posts_waiting_for_auth: Vec::new(),
             subscriptions_waiting_for_metadata: Vec::new(),
             subscriptions_rate_limited: Vec::new(),
             read_runstate,
@@ -451,6 +464,9 @@ impl Minion {
                 self.get_events().await?;
                 self.get_naddrs().await?;
 
+                // Give up on counts the relay never answered
+                self.expire_counts()?;
+
                 // Try to subscribe to subscriptions waiting for something
                 self.try_subscribe_waiting().await?;
             },
@@ -507,6 +523,7 @@ impl Minion {
             && self.subscriptions_waiting_for_auth.is_empty()
             && self.subscriptions_waiting_for_metadata.is_empty()
             && self.posting_jobs.is_empty()
+            && self.counts.is_empty()
         {
             let now = Unixtime::now();
             if let Some(when) = self.subscriptions_empty_asof {
@@ -577,6 +594,11 @@ impl Minion {
                     );
                 }
             }
+            ToMinionPayloadDetail::Count(kinds) => {
+                for kind in kinds {
+                    self.count(kind, message.job_id).await?;
+                }
+            }
             ToMinionPayloadDetail::FetchEvent(id) => {
                 // We don't ask the relay immediately. See task_timer.
                 self.sought_events
@@ -736,6 +758,68 @@ impl Minion {
         Ok(())
     }
 
+    async fn count(&mut self, kind: CountKind, job_id: u64) -> Result<(), Error> {
+        // COUNT messages use subscription ids too, but they close themselves
+        // after the relay answers so we don't put them in the subscription map.
+        let id = format!("count_{}", self.next_count_id);
+        self.next_count_id += 1;
+
+        let wire = serde_json::to_string(&serde_json::json!(["COUNT", &id, kind.filter()]))?;
+        let websocket_stream = self.stream.as_mut().unwrap();
+        tracing::trace!("{}: Sending {}", &self.url, &wire);
+        self.last_message_sent = wire.clone();
+        websocket_stream.send(WsMessage::Text(wire)).await?;
+
+        self.counts.insert(
+            id,
+            CountState {
+                job_id,
+                kind,
+                asked_at: Unixtime::now(),
+            },
+        );
+
+        Ok(())
+    }
+
+    // Remove a count we are no longer waiting for, telling the overlord if that
+    // was the last count of the job
+    fn finish_count(&mut self, id: &str) -> Result<Option<CountKind>, Error> {
+        let count_state = match self.counts.remove(id) {
+            Some(cs) => cs,
+            None => return Ok(None),
+        };
+
+        if !self
+            .counts
+            .values()
+            .any(|cs| cs.job_id == count_state.job_id)
+        {
+            self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                self.url.clone(),
+                count_state.job_id,
+            ))?;
+        }
+
+        Ok(Some(count_state.kind))
+    }
+
+    fn expire_counts(&mut self) -> Result<(), Error> {
+        let timeout = Duration::from_secs(GLOBALS.db().read_setting_fetcher_timeout_sec());
+        let now = Unixtime::now();
+        let expired: Vec<String> = self
+            .counts
+            .iter()
+            .filter(|(_, cs)| now - cs.asked_at > timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in expired {
+            tracing::debug!("{}: COUNT {} was never answered", &self.url, id);
+            self.finish_count(&id)?;
+        }
+        Ok(())
+    }
+
     // This is run every tick
     async fn try_subscribe_waiting(&mut self) -> Result<(), Error> {
         // Subscribe to metadata that is waiting (unless we already have a
@@ -964,6 +1048,75 @@ impl Minion {
         Ok(())
     }
 
+    // Ask the user whether we may authenticate to this relay
+    fn request_auth_approval(&self) {
+        if let Some(pubkey) = GLOBALS.identity.public_key() {
+            GLOBALS
+                .pending
+                .insert(crate::pending::PendingItem::RelayAuthenticationRequest {
+                    account: pubkey,
+                    relay: self.url.clone(),
+                });
+        }
+    }
+
+    // Post again the events the relay refused until we authenticated
+    async fn post_waiting_for_auth(&mut self) -> Result<(), Error> {
+        let ids = std::mem::take(&mut self.posts_waiting_for_auth);
+        for id in ids {
+            match GLOBALS.db().read_event(id)? {
+                Some(event) => {
+                    let msg = ClientMessage::Event(Box::new(event));
+                    let wire = serde_json::to_string(&msg)?;
+                    let ws_stream = self.stream.as_mut().unwrap();
+                    self.last_message_sent = wire.clone();
+                    ws_stream.send(WsMessage::Text(wire)).await?;
+                    tracing::info!("Posted event to {} again after AUTH", &self.url);
+                }
+                None => self.finish_post(id)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Give up on the events the relay refused until we authenticated
+    fn fail_posts_waiting_for_auth(&mut self) -> Result<(), Error> {
+        let ids = std::mem::take(&mut self.posts_waiting_for_auth);
+        for id in ids {
+            self.finish_post(id)?;
+        }
+        Ok(())
+    }
+
+    // We have the relay's final answer about an event we posted
+    fn finish_post(&mut self, id: Id) -> Result<(), Error> {
+        let job_id = match self.posting_ids.remove(&id) {
+            Some(job_id) => job_id,
+            None => return Ok(()),
+        };
+
+        let mut job_is_done: bool = false;
+        {
+            // Take it out of the posting_jobs
+            if let Some(job_ids) = self.posting_jobs.get_mut(&job_id) {
+                job_ids.retain(|id_in_vec| *id_in_vec != id);
+                job_is_done = job_ids.is_empty();
+            } // else is not expected, but if it happens we ignore it.
+        }
+
+        if job_is_done {
+            self.posting_jobs.remove(&job_id);
+
+            // Tell overlord
+            self.to_overlord.send(ToOverlordMessage::MinionJobComplete(
+                self.url.clone(),
+                job_id,
+            ))?;
+        }
+
+        Ok(())
+    }
+
     async fn fake_authenticate(&mut self) -> Result<(), Error> {
         if self.auth_state.is_authenticated()
             || self.auth_state.is_waiting()
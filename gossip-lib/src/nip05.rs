@@ -185,18 +185,20 @@ pub fn parse_nip05(nip05: &str) -> Result<(String, String), Error> {
 async fn fetch_nip05(user: &str, domain: &str) -> Result<Nip05, Error> {
     // FIXME add user-agent if configured
 
-    let nip05_future = reqwest::Client::builder()
+    let url = format!("https://{}/.well-known/nostr.json?name={}", domain, user);
+    let mut req = reqwest::Client::builder()
         .timeout(std::time::Duration::new(60, 0))
         .redirect(reqwest::redirect::Policy::none()) // see NIP-05
         .gzip(true)
         .brotli(true)
         .deflate(true)
         .build()?
-        .get(format!(
-            "https://{}/.well-known/nostr.json?name={}",
-            domain, user
-        ))
-        .send();
+        .get(&url);
+    // Private NIP-05 endpoints may want to know who is asking
+    if let Some(authorization) = crate::fetcher::http_authorization(&url, "GET") {
+        req = req.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    let nip05_future = req.send();
     let response = nip05_future.await?;
     let bytes = response.bytes().await?;
     GLOBALS.bytes_read.fetch_add(bytes.len(), Ordering::Relaxed);
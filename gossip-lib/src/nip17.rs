@@ -0,0 +1,358 @@
+//! NIP-17 file messages
+//!
+//! A file sent in a NIP-17 DM is encrypted locally with AES-GCM under a fresh key, and
+//! the ciphertext is uploaded to a Blossom server. A kind 15 rumor, giftwrapped to each
+//! member of the conversation like a kind 14 chat message, carries the URL in its content
+//! and the key, nonce and hashes in its tags. Only the members can decrypt the file, and
+//! the server only ever sees ciphertext.
+
+use crate::error::{Error, ErrorKind};
+use crate::upload::PreparedUpload;
+use aes_gcm::aead::consts::{U12, U16};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::aes::Aes256;
+use aes_gcm::{AesGcm, Key, Nonce};
+use nostr_types::{Event, EventKind, FileMetadata, Tag, UncheckedUrl};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// A file message (the kind of the rumor inside the giftwrap)
+pub const FILE_MESSAGE: u32 = 15;
+
+/// The encryption algorithm we use and understand
+pub const ALGORITHM: &str = "aes-gcm";
+
+// Other clients use 16 byte nonces, though 12 is usual for AES-GCM. We send 16 and
+// accept either.
+type Aes256Gcm16 = AesGcm<Aes256, U16>;
+type Aes256Gcm12 = AesGcm<Aes256, U12>;
+
+/// Whether an event kind is a file message
+pub fn is_file_message(kind: EventKind) -> bool {
+    u32::from(kind) == FILE_MESSAGE
+}
+
+/// A file, encrypted for sending
+pub struct EncryptedFile {
+    /// The key (hex)
+    pub key: String,
+
+    /// The nonce (hex)
+    pub nonce: String,
+
+    /// The encrypted file
+    pub ciphertext: Vec<u8>,
+
+    /// The SHA-256 of the unencrypted file (hex)
+    pub original_hash: String,
+}
+
+/// Encrypt a file under a fresh key
+pub fn encrypt(data: &[u8]) -> Result<EncryptedFile, Error> {
+    let key: [u8; 32] = rand::random();
+    let nonce: [u8; 16] = rand::random();
+
+    let cipher = Aes256Gcm16::new(Key::<Aes256Gcm16>::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::<U16>::from_slice(&nonce), data)
+        .map_err(|_| ErrorKind::General("File encryption failed".to_owned()))?;
+
+    Ok(EncryptedFile {
+        key: hex::encode(key),
+        nonce: hex::encode(nonce),
+        ciphertext,
+        original_hash: hex::encode(Sha256::digest(data)),
+    })
+}
+
+/// The tags of a file message for an encrypted file uploaded to a URL
+pub(crate) fn file_message_tags(prepared: &PreparedUpload, encrypted: &EncryptedFile) -> Vec<Tag> {
+    let mut tags = vec![
+        Tag::new(&["file-type", &format!("{}", prepared.mime)]),
+        Tag::new(&["encryption-algorithm", ALGORITHM]),
+        Tag::new(&["decryption-key", &encrypted.key]),
+        Tag::new(&["decryption-nonce", &encrypted.nonce]),
+        Tag::new(&["x", &hex::encode(Sha256::digest(&encrypted.ciphertext))]),
+        Tag::new(&["ox", &encrypted.original_hash]),
+        Tag::new(&["size", &format!("{}", encrypted.ciphertext.len())]),
+    ];
+    if let Some((w, h)) = prepared.dim {
+        tags.push(Tag::new(&["dim", &format!("{}x{}", w, h)]));
+    }
+    if let Some(ref blurhash) = prepared.blurhash {
+        tags.push(Tag::new(&["blurhash", blurhash]));
+    }
+    tags
+}
+
+/// A file received in a file message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMessage {
+    /// Where the encrypted file is
+    pub url: String,
+
+    /// The mime type of the decrypted file
+    pub mime: Option<String>,
+
+    /// The key (hex)
+    pub key: String,
+
+    /// The nonce (hex)
+    pub nonce: String,
+
+    /// The SHA-256 of the encrypted file (hex)
+    pub x: Option<String>,
+
+    /// The SHA-256 of the decrypted file (hex)
+    pub ox: Option<String>,
+
+    /// The size of the encrypted file
+    pub size: Option<u64>,
+
+    /// Image dimensions
+    pub dim: Option<(usize, usize)>,
+
+    /// Image blurhash
+    pub blurhash: Option<String>,
+}
+
+impl FileMessage {
+    /// File metadata for fetching the encrypted file (so its hash is checked as it is
+    /// fetched) and for showing it (with the type of the decrypted file)
+    pub fn file_metadata(&self) -> FileMetadata {
+        let mut fm = FileMetadata::new(UncheckedUrl(self.url.clone()));
+        fm.m = self.mime.clone();
+        fm.x = self.x.clone();
+        fm.size = self.size;
+        fm.dim = self.dim;
+        fm.blurhash = self.blurhash.clone();
+        fm
+    }
+
+    /// Decrypt the fetched file, and check it is the file that was sent
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let bad = |what: &str| -> Error { ErrorKind::General(format!("File {}", what)).into() };
+
+        let key = hex::decode(&self.key).map_err(|_| bad("key is not hex"))?;
+        let nonce = hex::decode(&self.nonce).map_err(|_| bad("nonce is not hex"))?;
+        if key.len() != 32 {
+            return Err(bad("key is the wrong size"));
+        }
+
+        let plaintext = match nonce.len() {
+            16 => Aes256Gcm16::new(Key::<Aes256Gcm16>::from_slice(&key))
+                .decrypt(Nonce::<U16>::from_slice(&nonce), ciphertext),
+            12 => Aes256Gcm12::new(Key::<Aes256Gcm12>::from_slice(&key))
+                .decrypt(Nonce::<U12>::from_slice(&nonce), ciphertext),
+            _ => return Err(bad("nonce is the wrong size")),
+        }
+        .map_err(|_| bad("decryption failed"))?;
+
+        if let Some(ref ox) = self.ox {
+            if hex::encode(Sha256::digest(&plaintext)) != ox.to_lowercase() {
+                return Err(bad("does not match its hash"));
+            }
+        }
+
+        Ok(plaintext)
+    }
+
+    /// A name to save the file as
+    pub fn filename(&self) -> String {
+        let stem = url::Url::parse(&self.url)
+            .ok()
+            .and_then(|u| {
+                u.path_segments()
+                    .and_then(|mut s| s.next_back().map(|s| s.to_owned()))
+            })
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "file".to_owned());
+        let stem = match stem.split_once('.') {
+            Some((stem, _)) => stem.to_owned(),
+            None => stem,
+        };
+        let ext = self
+            .mime
+            .as_deref()
+            .and_then(mime_guess::get_mime_extensions_str)
+            .and_then(|exts| exts.first())
+            .copied()
+            .unwrap_or("bin");
+        format!("{}.{}", stem, ext)
+    }
+}
+
+/// The file in a file message (an unwrapped rumor), if it is one we can decrypt
+pub fn file_message(event: &Event) -> Option<FileMessage> {
+    if !is_file_message(event.kind) {
+        return None;
+    }
+
+    let value = |name: &str| -> Option<String> {
+        event
+            .tags
+            .iter()
+            .find(|tag| tag.tagname() == name)
+            .map(|tag| tag.value().to_owned())
+            .filter(|value| !value.is_empty())
+    };
+
+    if value("encryption-algorithm").as_deref() != Some(ALGORITHM) {
+        return None;
+    }
+
+    Some(FileMessage {
+        url: event.content.trim().to_owned(),
+        mime: value("file-type"),
+        key: value("decryption-key")?,
+        nonce: value("decryption-nonce")?,
+        x: value("x"),
+        ox: value("ox"),
+        size: value("size").and_then(|s| s.parse::<u64>().ok()),
+        dim: value("dim").and_then(|d| {
+            let (w, h) = d.split_once('x')?;
+            Some((w.parse::<usize>().ok()?, h.parse::<usize>().ok()?))
+        }),
+        blurhash: value("blurhash"),
+    })
+}
+
+/// Save a decrypted file into the downloads directory, returning where it went
+pub fn save(file_message: &FileMessage, data: &[u8]) -> Result<PathBuf, Error> {
+    let dir = match dirs::download_dir().or_else(dirs::home_dir) {
+        Some(dir) => dir,
+        None => {
+            return Err(ErrorKind::General("No downloads directory".to_owned()).into());
+        }
+    };
+
+    // Don't overwrite anything
+    let filename = file_message.filename();
+    let mut path = dir.join(&filename);
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}", n, filename));
+        n += 1;
+    }
+
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn file_message_for(encrypted: &EncryptedFile) -> FileMessage {
+        FileMessage {
+            url: "https://blossom.example.com/0123abcd.bin".to_owned(),
+            mime: Some("image/png".to_owned()),
+            key: encrypted.key.clone(),
+            nonce: encrypted.nonce.clone(),
+            x: Some(hex::encode(Sha256::digest(&encrypted.ciphertext))),
+            ox: Some(encrypted.original_hash.clone()),
+            size: Some(encrypted.ciphertext.len() as u64),
+            dim: None,
+            blurhash: None,
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let data = b"a picture of a cat";
+        let encrypted = encrypt(data).unwrap();
+        assert_eq!(hex::decode(&encrypted.key).unwrap().len(), 32);
+        assert_eq!(hex::decode(&encrypted.nonce).unwrap().len(), 16);
+        assert_ne!(encrypted.ciphertext.as_slice(), data);
+
+        let fm = file_message_for(&encrypted);
+        assert_eq!(fm.decrypt(&encrypted.ciphertext).unwrap(), data);
+
+        // Tampered with
+        let mut ciphertext = encrypted.ciphertext.clone();
+        ciphertext[0] ^= 1;
+        assert!(fm.decrypt(&ciphertext).is_err());
+
+        // The wrong key
+        let other = encrypt(data).unwrap();
+        let fm = FileMessage {
+            key: other.key,
+            ..file_message_for(&encrypted)
+        };
+        assert!(fm.decrypt(&encrypted.ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_nonce_sizes() {
+        let data = b"some file";
+        let key: [u8; 32] = [3; 32];
+        let nonce: [u8; 12] = [5; 12];
+        let ciphertext = Aes256Gcm12::new(Key::<Aes256Gcm12>::from_slice(&key))
+            .encrypt(Nonce::<U12>::from_slice(&nonce), &data[..])
+            .unwrap();
+        let encrypted = EncryptedFile {
+            key: hex::encode(key),
+            nonce: hex::encode(nonce),
+            ciphertext,
+            original_hash: hex::encode(Sha256::digest(data)),
+        };
+        let fm = file_message_for(&encrypted);
+        assert_eq!(fm.decrypt(&encrypted.ciphertext).unwrap(), data);
+
+        for bad_nonce in [
+            hex::encode([5u8; 8]),
+            hex::encode([5u8; 24]),
+            "xyz".to_owned(),
+        ] {
+            let fm = FileMessage {
+                nonce: bad_nonce,
+                ..file_message_for(&encrypted)
+            };
+            assert!(fm.decrypt(&encrypted.ciphertext).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ox_mismatch() {
+        let encrypted = encrypt(b"the file that was sent").unwrap();
+
+        let fm = FileMessage {
+            ox: Some(hex::encode(Sha256::digest(b"some other file"))),
+            ..file_message_for(&encrypted)
+        };
+        assert!(fm.decrypt(&encrypted.ciphertext).is_err());
+
+        // Hashes in upper case are fine, as is having none
+        let fm = FileMessage {
+            ox: Some(encrypted.original_hash.to_uppercase()),
+            ..file_message_for(&encrypted)
+        };
+        assert!(fm.decrypt(&encrypted.ciphertext).is_ok());
+        let fm = FileMessage {
+            ox: None,
+            ..file_message_for(&encrypted)
+        };
+        assert!(fm.decrypt(&encrypted.ciphertext).is_ok());
+    }
+
+    #[test]
+    fn test_filename() {
+        let encrypted = encrypt(b"x").unwrap();
+        let fm = file_message_for(&encrypted);
+        assert_eq!(fm.filename(), "0123abcd.png");
+
+        let fm = FileMessage {
+            url: "https://example.com/".to_owned(),
+            mime: None,
+            ..file_message_for(&encrypted)
+        };
+        assert_eq!(fm.filename(), "file.bin");
+
+        let fm = FileMessage {
+            url: "not a url".to_owned(),
+            mime: Some("application/pdf".to_owned()),
+            ..file_message_for(&encrypted)
+        };
+        assert_eq!(fm.filename(), "file.pdf");
+    }
+}
@@ -0,0 +1,389 @@
+//! NIP-39 external identities
+//!
+//! People claim identities on other platforms with 'i' tags of the form
+//! `["i", "platform:identity", "proof"]`, either in a kind 10011 event or (as originally
+//! specified) in their metadata event. The proof is something on that platform, such as a
+//! gist or a post, that mentions their npub. We check the proofs we can through the
+//! Fetcher and remember the results, rechecking on the same schedule as NIP-05.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use crate::storage::types::Record;
+use crate::storage::{IdentityClaimsTable, Table};
+use nostr_types::{Event, EventKind, PublicKey, Tag, Unixtime, Url};
+use std::time::Duration;
+use tokio::task;
+
+/// IdentityClaim type, aliased to the latest version
+pub type IdentityClaim = crate::storage::types::IdentityClaim1;
+
+/// A list of external identities
+pub const EXTERNAL_IDENTITIES: u32 = 10011;
+
+/// The claimed identities of a person, sorted by platform
+pub fn claims_of(pubkey: PublicKey) -> Result<Vec<IdentityClaim>, Error> {
+    let mut claims = IdentityClaimsTable::filter_records(|c| c.pubkey == pubkey)?;
+    claims.sort_by(|a, b| {
+        a.platform
+            .cmp(&b.platform)
+            .then(a.identity.cmp(&b.identity))
+    });
+    Ok(claims)
+}
+
+/// Where a person can look at the proof of a claim
+pub fn proof_url(claim: &IdentityClaim) -> Option<String> {
+    match claim.platform.as_str() {
+        "github" => Some(format!(
+            "https://gist.github.com/{}/{}",
+            claim.identity, claim.proof
+        )),
+        "twitter" => Some(format!(
+            "https://twitter.com/{}/status/{}",
+            claim.identity, claim.proof
+        )),
+        "mastodon" => Some(format!("https://{}/{}", claim.identity, claim.proof)),
+        "telegram" => Some(format!("https://t.me/{}", claim.proof)),
+        _ => None,
+    }
+}
+
+/// Whether we know how to check the proof of a claim
+pub fn can_verify(claim: &IdentityClaim) -> bool {
+    check_url(claim).is_some()
+}
+
+// Where we fetch the proof of a claim from, if we know how to check it
+fn check_url(claim: &IdentityClaim) -> Option<Url> {
+    let url = match claim.platform.as_str() {
+        "github" => format!("https://api.github.com/gists/{}", claim.proof),
+        "mastodon" => {
+            let (instance, _) = claim.identity.split_once('/')?;
+            format!("https://{}/api/v1/statuses/{}", instance, claim.proof)
+        }
+        "telegram" => format!("https://t.me/{}?embed=1", claim.proof),
+        _ => return None,
+    };
+    Url::try_from_str(&url).ok()
+}
+
+/// Update the claims of a person from an event carrying 'i' tags, then check any
+/// proofs that are due for checking
+pub(crate) fn process_identity_claims(event: &Event) -> Result<(), Error> {
+    let mut claims = parse_claims(event.pubkey, event.created_at, &event.tags);
+
+    // Metadata without claims may just be from a client that doesn't know about them
+    if claims.is_empty() && event.kind == EventKind::Metadata {
+        return Ok(());
+    }
+
+    let existing = IdentityClaimsTable::filter_records(|c| c.pubkey == event.pubkey)?;
+
+    // Ignore claims older than the ones we have
+    if existing.iter().any(|c| c.created_at > event.created_at.0) {
+        return Ok(());
+    }
+
+    // Drop claims that are no longer made
+    for old in existing.iter() {
+        if !claims
+            .iter()
+            .any(|c| c.platform == old.platform && c.identity == old.identity)
+        {
+            IdentityClaimsTable::delete_record(old.key(), None)?;
+        }
+    }
+
+    for mut claim in claims.drain(..) {
+        // Keep the verification result if the proof has not changed
+        if let Some(old) = existing.iter().find(|c| {
+            c.platform == claim.platform && c.identity == claim.identity && c.proof == claim.proof
+        }) {
+            claim.valid = old.valid;
+            claim.last_checked = old.last_checked;
+        }
+        IdentityClaimsTable::write_record(&mut claim, None)?;
+
+        if needs_check(&claim) {
+            claim.last_checked = Some(Unixtime::now().0 as u64);
+            IdentityClaimsTable::write_record(&mut claim, None)?;
+            task::spawn(async move {
+                if let Err(e) = verify_claim(claim).await {
+                    tracing::warn!("{}", e);
+                }
+            });
+        }
+    }
+
+    GLOBALS.ui_people_to_invalidate.write().push(event.pubkey);
+
+    Ok(())
+}
+
+// The claims made by 'i' tags, not yet checked
+fn parse_claims(pubkey: PublicKey, created_at: Unixtime, tags: &[Tag]) -> Vec<IdentityClaim> {
+    let mut claims: Vec<IdentityClaim> = Vec::new();
+    for tag in tags.iter() {
+        if tag.tagname() != "i" {
+            continue;
+        }
+        let (platform, identity) = match tag.value().split_once(':') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        claims.push(IdentityClaim {
+            pubkey,
+            platform: platform.to_lowercase(),
+            identity: identity.to_owned(),
+            proof: tag.get_index(2).to_owned(),
+            created_at: created_at.0,
+            valid: false,
+            last_checked: None,
+        });
+    }
+    claims
+}
+
+// Recheck like NIP-05: rarely if valid, more often if invalid
+fn needs_check(claim: &IdentityClaim) -> bool {
+    if !GLOBALS.db().read_setting_check_nip39() || !can_verify(claim) {
+        return false;
+    }
+    match claim.last_checked {
+        Some(last) => {
+            let recheck_duration = if claim.valid {
+                Duration::from_secs(
+                    60 * 60
+                        * GLOBALS
+                            .db()
+                            .read_setting_nip05_becomes_stale_if_valid_hours(),
+                )
+            } else {
+                Duration::from_secs(
+                    60 * GLOBALS
+                        .db()
+                        .read_setting_nip05_becomes_stale_if_invalid_minutes(),
+                )
+            };
+            Unixtime::now() - Unixtime(last as i64) > recheck_duration
+        }
+        None => true,
+    }
+}
+
+// This updates the database with the result
+async fn verify_claim(mut claim: IdentityClaim) -> Result<(), Error> {
+    let url = match check_url(&claim) {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+
+    // Fetch the proof and wait for it
+    GLOBALS.fetcher.fetch(url.clone(), true).await;
+    let bytes = match GLOBALS
+        .fetcher
+        .try_get(&url, Duration::from_secs(60 * 60), true)?
+    {
+        Some(bytes) => bytes,
+        None => {
+            return Err(ErrorKind::General(format!(
+                "Could not fetch the {} proof of {}",
+                claim.platform, claim.identity
+            ))
+            .into())
+        }
+    };
+
+    claim.valid = proof_is_valid(&claim, &bytes);
+    claim.last_checked = Some(Unixtime::now().0 as u64);
+    IdentityClaimsTable::write_record(&mut claim, None)?;
+
+    // UI cache invalidation (so the person gets rerendered)
+    GLOBALS.ui_people_to_invalidate.write().push(claim.pubkey);
+
+    Ok(())
+}
+
+// Whether the fetched proof is by the claimed identity and mentions the npub
+fn proof_is_valid(claim: &IdentityClaim, bytes: &[u8]) -> bool {
+    let npub = claim.pubkey.as_bech32_string();
+    match claim.platform.as_str() {
+        "github" => {
+            let gist: serde_json::Value = match serde_json::from_slice(bytes) {
+                Ok(v) => v,
+                Err(_) => return false,
+            };
+            let owner_matches = gist["owner"]["login"]
+                .as_str()
+                .map(|login| login.eq_ignore_ascii_case(&claim.identity))
+                .unwrap_or(false);
+            let mentions_npub = match gist["files"].as_object() {
+                Some(files) => files.values().any(|file| {
+                    file["content"]
+                        .as_str()
+                        .map(|content| content.contains(&npub))
+                        .unwrap_or(false)
+                }),
+                None => false,
+            };
+            owner_matches && mentions_npub
+        }
+        "mastodon" => {
+            let status: serde_json::Value = match serde_json::from_slice(bytes) {
+                Ok(v) => v,
+                Err(_) => return false,
+            };
+            let username = claim
+                .identity
+                .split_once('/')
+                .map(|(_, user)| user.trim_start_matches('@'))
+                .unwrap_or("");
+            let author_matches = status["account"]["username"]
+                .as_str()
+                .map(|u| u.eq_ignore_ascii_case(username))
+                .unwrap_or(false);
+            let mentions_npub = status["content"]
+                .as_str()
+                .map(|content| content.contains(&npub))
+                .unwrap_or(false);
+            author_matches && mentions_npub
+        }
+        "telegram" => String::from_utf8_lossy(bytes).contains(&npub),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pubkey() -> PublicKey {
+        PublicKey::try_from_hex_string(
+            "ce4e68468c717280aa2fdd9db282897c969c172ba06fd7096b785c3c3ce79903",
+            false,
+        )
+        .unwrap()
+    }
+
+    fn claim(platform: &str, identity: &str, proof: &str) -> IdentityClaim {
+        IdentityClaim {
+            pubkey: pubkey(),
+            platform: platform.to_owned(),
+            identity: identity.to_owned(),
+            proof: proof.to_owned(),
+            created_at: 0,
+            valid: false,
+            last_checked: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_claims() {
+        let tags = vec![
+            Tag::new(&["i", "GitHub:semisol", "9721ce4ee4fceb91c9711ca2a6c9a5ab"]),
+            Tag::new(&[
+                "i",
+                "mastodon:bitcoinhackers.org/@semisol",
+                "109775066355589974",
+            ]),
+            Tag::new(&["i", "no-colon", "proof"]),
+            Tag::new(&["p", "github:semisol", "proof"]),
+            Tag::new(&["i", "telegram:semisol"]),
+        ];
+        let claims = parse_claims(pubkey(), Unixtime(1700000000), &tags);
+        assert_eq!(claims.len(), 3);
+
+        assert_eq!(claims[0].platform, "github");
+        assert_eq!(claims[0].identity, "semisol");
+        assert_eq!(claims[0].proof, "9721ce4ee4fceb91c9711ca2a6c9a5ab");
+        assert_eq!(claims[0].created_at, 1700000000);
+        assert!(!claims[0].valid);
+
+        assert_eq!(claims[1].identity, "bitcoinhackers.org/@semisol");
+        assert_eq!(claims[2].platform, "telegram");
+        assert_eq!(claims[2].proof, "");
+    }
+
+    #[test]
+    fn test_claim_urls() {
+        let github = claim("github", "semisol", "9721ce4ee4fceb91c9711ca2a6c9a5ab");
+        assert_eq!(
+            proof_url(&github).as_deref(),
+            Some("https://gist.github.com/semisol/9721ce4ee4fceb91c9711ca2a6c9a5ab")
+        );
+        assert!(can_verify(&github));
+
+        let mastodon = claim(
+            "mastodon",
+            "bitcoinhackers.org/@semisol",
+            "109775066355589974",
+        );
+        assert_eq!(
+            check_url(&mastodon)
+                .map(|u| u.as_str().to_owned())
+                .as_deref(),
+            Some("https://bitcoinhackers.org/api/v1/statuses/109775066355589974")
+        );
+
+        // Mastodon identities need an instance
+        assert!(!can_verify(&claim("mastodon", "semisol", "1")));
+
+        // We can link to tweets but can't check them
+        let twitter = claim("twitter", "semisol_public", "1619358434134196225");
+        assert!(proof_url(&twitter).is_some());
+        assert!(!can_verify(&twitter));
+
+        assert!(proof_url(&claim("myspace", "tom", "1")).is_none());
+    }
+
+    #[test]
+    fn test_proof_is_valid() {
+        let npub = pubkey().as_bech32_string();
+
+        let github = claim("github", "semisol", "abc");
+        let gist = |owner: &str, content: &str| {
+            serde_json::json!({
+                "owner": { "login": owner },
+                "files": { "nostr.md": { "content": content } }
+            })
+            .to_string()
+        };
+        let proof = format!("Verifying that I control the following Nostr public key: {npub}");
+        assert!(proof_is_valid(&github, gist("Semisol", &proof).as_bytes()));
+        assert!(!proof_is_valid(&github, gist("someone", &proof).as_bytes()));
+        assert!(!proof_is_valid(
+            &github,
+            gist("semisol", "hello").as_bytes()
+        ));
+        assert!(!proof_is_valid(&github, b"not json"));
+
+        let mastodon = claim("mastodon", "bitcoinhackers.org/@semisol", "1");
+        let status = |username: &str| {
+            serde_json::json!({
+                "account": { "username": username },
+                "content": format!("<p>{npub}</p>")
+            })
+            .to_string()
+        };
+        assert!(proof_is_valid(&mastodon, status("semisol").as_bytes()));
+        assert!(!proof_is_valid(&mastodon, status("other").as_bytes()));
+
+        let telegram = claim("telegram", "1087295469", "nostrdirectory/770");
+        assert!(proof_is_valid(&telegram, proof.as_bytes()));
+        assert!(!proof_is_valid(&telegram, b"<html></html>"));
+
+        assert!(!proof_is_valid(
+            &claim("twitter", "x", "1"),
+            proof.as_bytes()
+        ));
+    }
+
+    #[test]
+    fn test_claim_key() {
+        let claim = claim("github", "semisol", "abc");
+        assert_eq!(
+            claim.key(),
+            format!("{}'github:semisol", pubkey().as_hex_string())
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! NIP-71 video events, and the media people post
+//!
+//! Video events are kind 21 (normal) and kind 22 (short, vertical) events. Their videos
+//! are given in NIP-92 `imeta` tags, often several of them for different resolutions,
+//! with a `title` tag and a summary in the content. Any `imeta` tag may name a poster
+//! frame for its video in an `image` entry.
+
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use nostr_types::{ContentSegment, Event, EventKind, Filter, Id, PublicKey, ShatteredContent, Tag};
+use std::collections::HashSet;
+
+/// A (normal, landscape) video event
+pub const VIDEO: u32 = 21;
+
+/// A short (vertical) video event
+pub const SHORT_VIDEO: u32 = 22;
+
+/// The event kinds of video events
+pub fn video_kinds() -> Vec<EventKind> {
+    vec![VIDEO.into(), SHORT_VIDEO.into()]
+}
+
+/// Whether an event kind is a video event
+pub fn is_video(kind: EventKind) -> bool {
+    let kind = u32::from(kind);
+    kind == VIDEO || kind == SHORT_VIDEO
+}
+
+// The entries ("name value") of each imeta tag
+fn imeta_entries(tags: &[Tag]) -> Vec<Vec<String>> {
+    let mut output: Vec<Vec<String>> = Vec::new();
+    for tag in tags.iter() {
+        if tag.tagname() != "imeta" {
+            continue;
+        }
+        let mut entries: Vec<String> = Vec::new();
+        let mut i = 1;
+        loop {
+            let entry = tag.get_index(i);
+            if entry.is_empty() {
+                break;
+            }
+            entries.push(entry.to_owned());
+            i += 1;
+        }
+        output.push(entries);
+    }
+    output
+}
+
+// The value of the first entry of a name
+fn entry_value<'a>(entries: &'a [String], name: &str) -> Option<&'a str> {
+    entries
+        .iter()
+        .find_map(|entry| match entry.split_once(' ') {
+            Some((n, value)) if n == name => Some(value),
+            _ => None,
+        })
+}
+
+/// An entry of the imeta tag for a URL, e.g. "image" for the poster frame of a video
+pub fn imeta_entry(event: &Event, url: &str, name: &str) -> Option<String> {
+    imeta_entry_of(&event.tags, url, name)
+}
+
+fn imeta_entry_of(tags: &[Tag], url: &str, name: &str) -> Option<String> {
+    imeta_entries(tags)
+        .iter()
+        .find(|entries| entry_value(entries, "url") == Some(url))
+        .and_then(|entries| entry_value(entries, name))
+        .map(|value| value.to_owned())
+}
+
+/// The content we show for a video event: its summary, then its video. Where there are
+/// several variants of the video, we show the first.
+pub fn display_content(event: &Event) -> String {
+    video_content(&event.content, &event.tags)
+}
+
+fn video_content(summary: &str, tags: &[Tag]) -> String {
+    let mut content = summary.trim().to_owned();
+    if let Some(url) = imeta_entries(tags)
+        .iter()
+        .find_map(|entries| entry_value(entries, "url"))
+    {
+        if !content.is_empty() {
+            content.push_str("\n\n");
+        }
+        content.push_str(url);
+    }
+    content
+}
+
+/// A piece of media someone posted
+#[derive(Debug, Clone)]
+pub struct PostedMedia {
+    /// The event it was posted in
+    pub id: Id,
+
+    /// Where it is
+    pub url: String,
+
+    /// Its mime type
+    pub mimetype: String,
+
+    /// The poster frame, for a video
+    pub poster: Option<String>,
+}
+
+impl PostedMedia {
+    pub fn is_image(&self) -> bool {
+        self.mimetype.starts_with("image/")
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.mimetype.starts_with("video/")
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.mimetype.starts_with("audio/")
+    }
+}
+
+/// The images, videos and audio a person has posted, newest first, from the events we
+/// have of theirs
+pub fn posted_media(pubkey: PublicKey) -> Result<Vec<PostedMedia>, Error> {
+    let mut filter = Filter::new();
+    filter.add_author(pubkey);
+    filter.add_event_kind(EventKind::TextNote);
+    filter.add_event_kind(EventKind::Comment);
+    for kind in video_kinds() {
+        filter.add_event_kind(kind);
+    }
+
+    let mut media: Vec<PostedMedia> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    for event in GLOBALS.db().find_events_by_filter(&filter, |_| true)? {
+        for item in media_in(event.id, &event.content, &event.tags) {
+            if seen.insert(item.url.clone()) {
+                media.push(item);
+            }
+        }
+    }
+
+    Ok(media)
+}
+
+// The images, videos and audio in an event: those in imeta tags (which know their type)
+// then those linked in the content
+fn media_in(id: Id, content: &str, tags: &[Tag]) -> Vec<PostedMedia> {
+    let mut found: Vec<(String, Option<String>)> = imeta_entries(tags)
+        .iter()
+        .filter_map(|entries| {
+            entry_value(entries, "url").map(|url| {
+                (
+                    url.to_owned(),
+                    entry_value(entries, "m").map(|m| m.to_owned()),
+                )
+            })
+        })
+        .collect();
+
+    let shattered_content = ShatteredContent::new(content.to_owned());
+    for segment in shattered_content.segments.iter() {
+        if let ContentSegment::Hyperlink(span) = segment {
+            if let Some(link) = shattered_content.slice(span) {
+                found.push((link.to_owned(), None));
+            }
+        }
+    }
+
+    let mut media: Vec<PostedMedia> = Vec::new();
+    for (url, mimetype) in found.drain(..) {
+        if media.iter().any(|m| m.url == url) {
+            continue;
+        }
+        let mimetype = match mimetype {
+            Some(m) => m,
+            None => match url::Url::parse(&url)
+                .ok()
+                .and_then(|u| crate::media_url_mimetype(u.path()))
+            {
+                Some(m) => m.to_owned(),
+                None => continue,
+            },
+        };
+        let item = PostedMedia {
+            id,
+            poster: imeta_entry_of(tags, &url, "image"),
+            url,
+            mimetype,
+        };
+        if item.is_image() || item.is_video() || item.is_audio() {
+            media.push(item);
+        }
+    }
+    media
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn video_tags() -> Vec<Tag> {
+        vec![
+            Tag::new(&["title", "A walk"]),
+            Tag::new(&[
+                "imeta",
+                "dim 1920x1080",
+                "url https://example.com/1080.mp4",
+                "m video/mp4",
+                "image https://example.com/poster.jpg",
+            ]),
+            Tag::new(&[
+                "imeta",
+                "dim 1280x720",
+                "url https://example.com/720.mp4",
+                "m video/mp4",
+            ]),
+        ]
+    }
+
+    #[test]
+    fn test_video_kinds() {
+        assert!(is_video(EventKind::from(VIDEO)));
+        assert!(is_video(EventKind::from(SHORT_VIDEO)));
+        assert!(!is_video(EventKind::TextNote));
+        assert_eq!(video_kinds().len(), 2);
+    }
+
+    #[test]
+    fn test_imeta_entries() {
+        let tags = video_tags();
+        let entries = imeta_entries(&tags);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].len(), 4);
+        assert_eq!(entry_value(&entries[1], "dim"), Some("1280x720"));
+        assert_eq!(entry_value(&entries[1], "image"), None);
+
+        assert_eq!(
+            imeta_entry_of(&tags, "https://example.com/1080.mp4", "image").as_deref(),
+            Some("https://example.com/poster.jpg")
+        );
+        assert_eq!(
+            imeta_entry_of(&tags, "https://example.com/720.mp4", "image"),
+            None
+        );
+        assert_eq!(
+            imeta_entry_of(&tags, "https://example.com/other.mp4", "m"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_video_content() {
+        let tags = video_tags();
+        assert_eq!(
+            video_content(" Down the beach \n", &tags),
+            "Down the beach\n\nhttps://example.com/1080.mp4"
+        );
+        assert_eq!(video_content("", &tags), "https://example.com/1080.mp4");
+        assert_eq!(video_content("No video", &[]), "No video");
+    }
+
+    #[test]
+    fn test_media_in() {
+        let id = Id([1; 32]);
+        let mut tags = video_tags();
+        tags.push(Tag::new(&[
+            "imeta",
+            "url https://example.com/a.png",
+            "m image/png",
+        ]));
+        let content = "Listen https://example.com/song.mp3 and read https://example.com/page \
+                       and see https://example.com/a.png";
+
+        let media = media_in(id, content, &tags);
+        let urls: Vec<&str> = media.iter().map(|m| m.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/1080.mp4",
+                "https://example.com/720.mp4",
+                "https://example.com/a.png",
+                "https://example.com/song.mp3",
+            ]
+        );
+
+        assert!(media[0].is_video());
+        assert_eq!(
+            media[0].poster.as_deref(),
+            Some("https://example.com/poster.jpg")
+        );
+        assert!(media[2].is_image());
+        assert!(media[3].is_audio());
+        assert_eq!(media[3].mimetype, "audio/mpeg");
+        assert!(media.iter().all(|m| m.id == id));
+    }
+}
@@ -0,0 +1,465 @@
+//! NIP-96 HTTP file storage
+//!
+//! An alternative to Blossom that many hosts speak. A server describes itself at
+//! `/.well-known/nostr/nip96.json` (possibly delegating to another server), and files are
+//! uploaded to its `api_url` as multipart form data with a NIP-98 `Authorization` header.
+//! The server answers with the NIP-94 tags of the file as stored, which may have been
+//! transformed (e.g. re-encoded), so we take the URL and hash from there.
+
+use crate::blossom::BlobDescriptor;
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use crate::upload::PreparedUpload;
+use nostr_types::{FileMetadata, UncheckedUrl, Unixtime};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, Response};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// How long to wait for a server that is still processing an upload
+const PROCESSING_POLLS: usize = 60;
+const PROCESSING_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A server's `/.well-known/nostr/nip96.json`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerInfo {
+    /// Where to upload to
+    #[serde(default)]
+    pub api_url: String,
+
+    /// Where files are downloaded from, if not the api_url
+    #[serde(default)]
+    pub download_url: Option<String>,
+
+    /// Another server that does the work for this one
+    #[serde(default)]
+    pub delegated_to_url: Option<String>,
+
+    /// Content types accepted
+    #[serde(default)]
+    pub content_types: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct UploadResponse {
+    #[serde(default)]
+    status: String,
+
+    #[serde(default)]
+    message: Option<String>,
+
+    #[serde(default)]
+    processing_url: Option<String>,
+
+    #[serde(default)]
+    nip94_event: Option<Nip94Event>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Nip94Event {
+    #[serde(default)]
+    tags: Vec<Vec<String>>,
+
+    #[serde(default)]
+    content: String,
+}
+
+pub struct Nip96 {
+    client: Client,
+}
+
+impl Nip96 {
+    pub fn new() -> Result<Nip96, Error> {
+        let connect_timeout =
+            Duration::new(GLOBALS.db().read_setting_fetcher_connect_timeout_sec(), 0);
+        let timeout = Duration::new(GLOBALS.db().read_setting_fetcher_timeout_sec(), 0);
+
+        let client = Client::builder()
+            .gzip(false)
+            .brotli(false)
+            .deflate(false)
+            .connect_timeout(connect_timeout)
+            .timeout(timeout)
+            .build()?;
+
+        Ok(Nip96 { client })
+    }
+
+    /// GET /.well-known/nostr/nip96.json, following a delegation
+    pub async fn discover(&self, base_url: String) -> Result<ServerInfo, Error> {
+        let mut base_url = base_url;
+        // Only follow one delegation, so two servers cannot send us around in circles
+        for _ in 0..2 {
+            let url = format!("{}.well-known/nostr/nip96.json", base_url);
+            let response = self.client.get(url).send().await?;
+            if response.status().as_u16() >= 300 {
+                return Err(get_error(&response));
+            }
+            let info: ServerInfo = response.json().await?;
+            if !info.api_url.is_empty() {
+                return Ok(info);
+            }
+            match info.delegated_to_url {
+                Some(ref delegated) => base_url = crate::blossom::base_url(delegated)?,
+                None => break,
+            }
+        }
+        Err(ErrorKind::Nip96Error(format!("{} has no api_url", base_url)).into())
+    }
+
+    /// POST <api_url> with the file as multipart form data
+    pub async fn upload(
+        &self,
+        base_url: String,
+        data: Vec<u8>,
+        filename: &str,
+        prepared: &PreparedUpload,
+    ) -> Result<FileMetadata, Error> {
+        let info = self.discover(base_url).await?;
+
+        // The hash makes a boundary that will not be found in the data
+        let boundary = format!("gossip-{}", prepared.hash);
+        let body = multipart_body(&boundary, &data, filename, prepared);
+
+        let authorization =
+            GLOBALS
+                .identity
+                .http_authorization(&info.api_url, "POST", Some(&body))?;
+
+        let response = self
+            .client
+            .post(&info.api_url)
+            .header(AUTHORIZATION, authorization)
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(body)
+            .send()
+            .await?;
+
+        if response.status().as_u16() >= 300 {
+            return Err(get_error(&response));
+        }
+        let mut upload_response: UploadResponse = response.json().await?;
+
+        // Some servers process the file before they can say where it is
+        if let Some(processing_url) = upload_response.processing_url.clone() {
+            let mut polls = 0;
+            while upload_response.nip94_event.is_none()
+                && upload_response.status != "error"
+                && polls < PROCESSING_POLLS
+            {
+                tokio::time::sleep(PROCESSING_POLL_INTERVAL).await;
+                let response = self.client.get(&processing_url).send().await?;
+                if response.status().as_u16() >= 300 {
+                    return Err(get_error(&response));
+                }
+                upload_response = response.json().await?;
+                polls += 1;
+            }
+        }
+
+        match upload_response.nip94_event {
+            Some(ref nip94_event) => file_metadata(nip94_event, prepared),
+            None => Err(ErrorKind::Nip96Error(
+                upload_response.message.unwrap_or(upload_response.status),
+            )
+            .into()),
+        }
+    }
+}
+
+/// The shared NIP-96 client
+pub(crate) fn nip96() -> Result<&'static Nip96, Error> {
+    match GLOBALS.nip96.get() {
+        Some(n) => Ok(n),
+        None => {
+            let nip96 = Nip96::new()?;
+            let _ = GLOBALS.nip96.set(nip96);
+            GLOBALS.nip96.get().ok_or_else(|| {
+                ErrorKind::Nip96Error("Could not set up the NIP-96 client".to_owned()).into()
+            })
+        }
+    }
+}
+
+/// The base URLs of our NIP-96 servers, in the order configured
+pub fn server_base_urls() -> Vec<String> {
+    GLOBALS
+        .db()
+        .read_setting_nip96_servers()
+        .split_whitespace()
+        .filter_map(|server| match crate::blossom::base_url(server) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                tracing::warn!("Bad NIP-96 server {}: {}", server, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// A blob descriptor for an upload, so NIP-96 uploads look like Blossom ones to the UI
+pub(crate) fn blob_descriptor(file_metadata: &FileMetadata) -> BlobDescriptor {
+    BlobDescriptor {
+        url: file_metadata.url.0.clone(),
+        sha256: file_metadata.x.clone().unwrap_or_default(),
+        size: file_metadata.size.unwrap_or(0),
+        mime_type: file_metadata.m.clone(),
+        uploaded: Some(Unixtime::now().0 as u64),
+        created: None,
+    }
+}
+
+// Our own description of the file, overridden by what the server says about it
+fn file_metadata(
+    nip94_event: &Nip94Event,
+    prepared: &PreparedUpload,
+) -> Result<FileMetadata, Error> {
+    let url = nip94_event
+        .tags
+        .iter()
+        .find(|tag| tag.len() > 1 && tag[0] == "url")
+        .map(|tag| tag[1].clone())
+        .ok_or_else(|| -> Error {
+            ErrorKind::Nip96Error("The server did not say where it put the file".to_owned()).into()
+        })?;
+
+    let mut fm = FileMetadata::new(UncheckedUrl(url));
+    fm.m = Some(format!("{}", prepared.mime));
+    fm.x = Some(format!("{}", prepared.hash));
+    fm.size = Some(prepared.size);
+    fm.dim = prepared.dim;
+    fm.blurhash = prepared.blurhash.clone();
+    if !nip94_event.content.is_empty() {
+        fm.alt = Some(nip94_event.content.clone());
+    }
+
+    // If the server changed the file, our size and dimensions are of the wrong file
+    let transformed = nip94_event
+        .tags
+        .iter()
+        .any(|tag| tag.len() > 1 && tag[0] == "x" && fm.x.as_ref() != Some(&tag[1]));
+    if transformed {
+        fm.size = None;
+        fm.dim = None;
+    }
+
+    for tag in nip94_event.tags.iter() {
+        if tag.len() < 2 {
+            continue;
+        }
+        let value = &tag[1];
+        match tag[0].as_str() {
+            "m" => fm.m = Some(value.clone()),
+            "x" => fm.x = Some(value.clone()),
+            "size" => {
+                if let Ok(size) = value.parse::<u64>() {
+                    fm.size = Some(size);
+                }
+            }
+            "dim" => {
+                if let Some((w, h)) = value.split_once('x') {
+                    if let (Ok(w), Ok(h)) = (w.parse::<usize>(), h.parse::<usize>()) {
+                        fm.dim = Some((w, h));
+                    }
+                }
+            }
+            "blurhash" => fm.blurhash = Some(value.clone()),
+            "alt" => fm.alt = Some(value.clone()),
+            "summary" => fm.summary = Some(value.clone()),
+            _ => {}
+        }
+    }
+
+    Ok(fm)
+}
+
+fn get_error(response: &Response) -> Error {
+    ErrorKind::Nip96Error(format!("{}", response.status())).into()
+}
+
+// The multipart form data of an upload
+fn multipart_body(
+    boundary: &str,
+    data: &[u8],
+    filename: &str,
+    prepared: &PreparedUpload,
+) -> Vec<u8> {
+    let mut body: Vec<u8> = Vec::with_capacity(data.len() + 512);
+    for (name, value) in [
+        ("size", format!("{}", prepared.size)),
+        ("content_type", format!("{}", prepared.mime)),
+    ] {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary,
+            filename.replace('"', ""),
+            prepared.mime
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blossom::HashOutput;
+
+    fn prepared(data: &[u8]) -> PreparedUpload {
+        PreparedUpload {
+            data: None,
+            hash: HashOutput::from_bytes(data),
+            mime: mime::IMAGE_PNG,
+            size: data.len() as u64,
+            dim: Some((640, 480)),
+            blurhash: Some("LEHV6nWB2yk8pyo0adR*.7kCMdnj".to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_parse_server_info() {
+        let info: ServerInfo = serde_json::from_str(
+            r#"{
+                "api_url": "https://nostr.build/api/v2/nip96/upload",
+                "download_url": "https://image.nostr.build",
+                "supported_nips": [94, 96, 98],
+                "content_types": ["image/jpeg", "video/webm"],
+                "plans": { "free": { "name": "Free" } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(info.api_url, "https://nostr.build/api/v2/nip96/upload");
+        assert_eq!(
+            info.download_url.as_deref(),
+            Some("https://image.nostr.build")
+        );
+        assert_eq!(info.content_types.unwrap().len(), 2);
+        assert!(info.delegated_to_url.is_none());
+
+        let delegating: ServerInfo =
+            serde_json::from_str(r#"{ "delegated_to_url": "https://other.example.com" }"#).unwrap();
+        assert!(delegating.api_url.is_empty());
+        assert_eq!(
+            delegating.delegated_to_url.as_deref(),
+            Some("https://other.example.com")
+        );
+    }
+
+    #[test]
+    fn test_parse_upload_response() {
+        let response: UploadResponse = serde_json::from_str(
+            r#"{
+                "status": "processing",
+                "message": "Processing. Please check again later for updated status.",
+                "percentage": 15,
+                "processing_url": "https://example.com/processing/1"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(response.status, "processing");
+        assert!(response.nip94_event.is_none());
+        assert_eq!(
+            response.processing_url.as_deref(),
+            Some("https://example.com/processing/1")
+        );
+
+        let response: UploadResponse = serde_json::from_str(
+            r#"{
+                "status": "success",
+                "nip94_event": {
+                    "tags": [["url", "https://example.com/a.png"], ["ox", "abc"]],
+                    "content": ""
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(response.nip94_event.unwrap().tags.len(), 2);
+    }
+
+    #[test]
+    fn test_file_metadata() {
+        let data = b"not really a png";
+        let prepared = prepared(data);
+        let hash = format!("{}", prepared.hash);
+
+        // The server stored it as it was
+        let event = Nip94Event {
+            tags: vec![
+                vec!["url".to_owned(), "https://example.com/a.png".to_owned()],
+                vec!["ox".to_owned(), hash.clone()],
+                vec!["x".to_owned(), hash.clone()],
+            ],
+            content: "a picture".to_owned(),
+        };
+        let fm = file_metadata(&event, &prepared).unwrap();
+        assert_eq!(fm.url.0, "https://example.com/a.png");
+        assert_eq!(fm.x.as_deref(), Some(hash.as_str()));
+        assert_eq!(fm.size, Some(data.len() as u64));
+        assert_eq!(fm.dim, Some((640, 480)));
+        assert_eq!(fm.alt.as_deref(), Some("a picture"));
+        assert_eq!(fm.m.as_deref(), Some("image/png"));
+
+        // The server re-encoded it
+        let event = Nip94Event {
+            tags: vec![
+                vec!["url".to_owned(), "https://example.com/a.webp".to_owned()],
+                vec!["x".to_owned(), "ff".repeat(32)],
+                vec!["m".to_owned(), "image/webp".to_owned()],
+                vec!["dim".to_owned(), "320x240".to_owned()],
+                vec!["size".to_owned(), "bad".to_owned()],
+            ],
+            content: "".to_owned(),
+        };
+        let fm = file_metadata(&event, &prepared).unwrap();
+        assert_eq!(fm.x, Some("ff".repeat(32)));
+        assert_eq!(fm.m.as_deref(), Some("image/webp"));
+        assert_eq!(fm.dim, Some((320, 240)));
+        assert_eq!(fm.size, None);
+        assert_eq!(fm.blurhash, prepared.blurhash);
+        assert!(fm.alt.is_none());
+    }
+
+    #[test]
+    fn test_file_metadata_without_url() {
+        let data = b"not really a png";
+        let prepared = prepared(data);
+        let event = Nip94Event {
+            tags: vec![
+                vec!["ox".to_owned(), format!("{}", prepared.hash)],
+                vec!["url".to_owned()],
+            ],
+            content: "".to_owned(),
+        };
+        let err = file_metadata(&event, &prepared).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::Nip96Error(_)));
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let data = b"\x89PNG data";
+        let body = multipart_body("gossip-b", data, "my \"cat\".png", &prepared(data));
+        let expected = [
+            &b"--gossip-b\r\nContent-Disposition: form-data; name=\"size\"\r\n\r\n9\r\n"[..],
+            b"--gossip-b\r\nContent-Disposition: form-data; name=\"content_type\"\r\n\r\nimage/png\r\n",
+            b"--gossip-b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"my cat.png\"\r\nContent-Type: image/png\r\n\r\n",
+            data,
+            b"\r\n--gossip-b--\r\n",
+        ]
+        .concat();
+        assert_eq!(body, expected);
+    }
+}
@@ -0,0 +1,791 @@
+//! NIP-03 OpenTimestamps attestations
+//!
+//! A kind 1040 event carries a (base64 encoded) OpenTimestamps proof that the event in its
+//! 'e' tag existed by a certain bitcoin block. The proof is a tree of operations that turn
+//! the event id into the merkle root of that block. We verify proofs offline, against
+//! block headers from a local file (80 bytes per block from the genesis block on, as
+//! Electrum keeps them) or from a bitcoind we trust.
+//!
+//! We can also have our own posts timestamped by a calendar server. Its proof is pending
+//! until the calendar gets it into a block a few hours later, when we upgrade the proof
+//! and publish the attestation.
+
+use crate::comms::ToOverlordMessage;
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use crate::storage::{OtsProofsTable, Table};
+use base64::Engine;
+use nostr_types::{Event, EventKind, Id, PreEvent, Tag, Unixtime};
+use sha2::Digest;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::task;
+
+/// OtsProof type, aliased to the latest version
+pub type OtsProof = crate::storage::types::OtsProof1;
+
+const MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const BITCOIN_ATTESTATION: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+const PENDING_ATTESTATION: [u8; 8] = [0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e];
+
+// Limits from the OpenTimestamps reference implementation
+const MAX_MSG_LENGTH: usize = 4096;
+const MAX_DEPTH: usize = 256;
+
+// How long to wait between attempts to upgrade a pending proof
+const UPGRADE_INTERVAL_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Append(Vec<u8>),
+    Prepend(Vec<u8>),
+    Reverse,
+    Hexlify,
+    Sha1,
+    Ripemd160,
+    Sha256,
+    Keccak256,
+}
+
+impl Op {
+    fn read(reader: &mut Reader, tag: u8) -> Result<Op, Error> {
+        Ok(match tag {
+            0xf0 => Op::Append(reader.varbytes(MAX_MSG_LENGTH)?),
+            0xf1 => Op::Prepend(reader.varbytes(MAX_MSG_LENGTH)?),
+            0xf2 => Op::Reverse,
+            0xf3 => Op::Hexlify,
+            0x02 => Op::Sha1,
+            0x03 => Op::Ripemd160,
+            0x08 => Op::Sha256,
+            0x67 => Op::Keccak256,
+            _ => return Err(ots_error(format!("unknown operation {:#04x}", tag))),
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Op::Append(arg) => {
+                out.push(0xf0);
+                write_varbytes(out, arg);
+            }
+            Op::Prepend(arg) => {
+                out.push(0xf1);
+                write_varbytes(out, arg);
+            }
+            Op::Reverse => out.push(0xf2),
+            Op::Hexlify => out.push(0xf3),
+            Op::Sha1 => out.push(0x02),
+            Op::Ripemd160 => out.push(0x03),
+            Op::Sha256 => out.push(0x08),
+            Op::Keccak256 => out.push(0x67),
+        }
+    }
+
+    /// Apply the operation to a message. Returns None for the (legacy) hash operations
+    /// we don't implement, which leaves that branch of a proof unverifiable.
+    fn apply(&self, msg: &[u8]) -> Option<Vec<u8>> {
+        let result = match self {
+            Op::Append(arg) => [msg, arg].concat(),
+            Op::Prepend(arg) => [arg, msg].concat(),
+            Op::Reverse => msg.iter().rev().copied().collect(),
+            Op::Hexlify => hex::encode(msg).into_bytes(),
+            Op::Sha256 => sha2::Sha256::digest(msg).to_vec(),
+            Op::Sha1 | Op::Ripemd160 | Op::Keccak256 => return None,
+        };
+        if result.len() > MAX_MSG_LENGTH {
+            None
+        } else {
+            Some(result)
+        }
+    }
+}
+
+/// An attestation in an OpenTimestamps proof
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attestation {
+    /// The message is the merkle root of the bitcoin block at this height
+    Bitcoin(u64),
+
+    /// A calendar server has the message and will get it into a block
+    Pending(String),
+
+    /// An attestation we don't know about
+    Unknown([u8; 8], Vec<u8>),
+}
+
+impl Attestation {
+    fn read(reader: &mut Reader) -> Result<Attestation, Error> {
+        let mut tag = [0u8; 8];
+        tag.copy_from_slice(reader.bytes(8)?);
+        let payload = reader.varbytes(8192)?;
+        let mut payload_reader = Reader::new(&payload);
+        Ok(if tag == BITCOIN_ATTESTATION {
+            Attestation::Bitcoin(payload_reader.varuint()?)
+        } else if tag == PENDING_ATTESTATION {
+            let uri = payload_reader.varbytes(1000)?;
+            Attestation::Pending(String::from_utf8_lossy(&uri).into_owned())
+        } else {
+            Attestation::Unknown(tag, payload)
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let mut payload: Vec<u8> = Vec::new();
+        match self {
+            Attestation::Bitcoin(height) => {
+                out.extend_from_slice(&BITCOIN_ATTESTATION);
+                write_varuint(&mut payload, *height);
+            }
+            Attestation::Pending(uri) => {
+                out.extend_from_slice(&PENDING_ATTESTATION);
+                write_varbytes(&mut payload, uri.as_bytes());
+            }
+            Attestation::Unknown(tag, unknown) => {
+                out.extend_from_slice(tag);
+                payload.extend_from_slice(unknown);
+            }
+        }
+        write_varbytes(out, &payload);
+    }
+}
+
+/// A tree of operations on a message, with attestations of what the message was at each
+/// step
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Timestamp {
+    attestations: Vec<Attestation>,
+    ops: Vec<(Op, Timestamp)>,
+}
+
+impl Timestamp {
+    fn read(reader: &mut Reader, depth: usize) -> Result<Timestamp, Error> {
+        if depth > MAX_DEPTH {
+            return Err(ots_error("proof is too deep"));
+        }
+
+        let mut timestamp = Timestamp::default();
+
+        // All but the last item are prefixed with 0xff
+        let mut tag = reader.byte()?;
+        while tag == 0xff {
+            let item_tag = reader.byte()?;
+            timestamp.read_item(reader, item_tag, depth)?;
+            tag = reader.byte()?;
+        }
+        timestamp.read_item(reader, tag, depth)?;
+
+        Ok(timestamp)
+    }
+
+    fn read_item(&mut self, reader: &mut Reader, tag: u8, depth: usize) -> Result<(), Error> {
+        if tag == 0x00 {
+            self.attestations.push(Attestation::read(reader)?);
+        } else {
+            let op = Op::read(reader, tag)?;
+            let child = Timestamp::read(reader, depth + 1)?;
+            self.ops.push((op, child));
+        }
+        Ok(())
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let count = self.attestations.len() + self.ops.len();
+        let mut written = 0;
+        for attestation in self.attestations.iter() {
+            written += 1;
+            if written < count {
+                out.push(0xff);
+            }
+            out.push(0x00);
+            attestation.write(out);
+        }
+        for (op, child) in self.ops.iter() {
+            written += 1;
+            if written < count {
+                out.push(0xff);
+            }
+            op.write(out);
+            child.write(out);
+        }
+    }
+
+    /// All the attestations with the message they attest to (None if we could not
+    /// compute it), and the path of ops leading to them
+    fn attestations(
+        &self,
+        msg: Option<&[u8]>,
+        path: &mut Vec<usize>,
+        out: &mut Vec<(Attestation, Option<Vec<u8>>, Vec<usize>)>,
+    ) {
+        for attestation in self.attestations.iter() {
+            out.push((attestation.clone(), msg.map(|m| m.to_vec()), path.clone()));
+        }
+        for (i, (op, child)) in self.ops.iter().enumerate() {
+            let next = msg.and_then(|m| op.apply(m));
+            path.push(i);
+            child.attestations(next.as_deref(), path, out);
+            path.pop();
+        }
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> &mut Timestamp {
+        let mut node = self;
+        for i in path.iter() {
+            node = &mut node.ops[*i].1;
+        }
+        node
+    }
+
+    /// Drop everything but bitcoin attestations. Returns false if nothing is left.
+    fn prune(&mut self) -> bool {
+        self.attestations
+            .retain(|a| matches!(a, Attestation::Bitcoin(_)));
+        self.ops.retain_mut(|(_, child)| child.prune());
+        !self.attestations.is_empty() || !self.ops.is_empty()
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.pos + len > self.bytes.len() {
+            return Err(ots_error("proof is truncated"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varuint(&mut self) -> Result<u64, Error> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            value |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+            if shift > 63 {
+                return Err(ots_error("varuint is too large"));
+            }
+        }
+    }
+
+    fn varbytes(&mut self, max: usize) -> Result<Vec<u8>, Error> {
+        let len = self.varuint()? as usize;
+        if len > max {
+            return Err(ots_error("varbytes is too long"));
+        }
+        Ok(self.bytes(len)?.to_vec())
+    }
+}
+
+fn write_varuint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let b = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(b);
+            return;
+        }
+        out.push(b | 0x80);
+    }
+}
+
+fn write_varbytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varuint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn ots_error<S: Into<String>>(msg: S) -> Error {
+    ErrorKind::General(format!("OpenTimestamps: {}", msg.into())).into()
+}
+
+// Read a (detached) OTS file, returning the digest it timestamps
+fn read_file(bytes: &[u8]) -> Result<(Vec<u8>, Timestamp), Error> {
+    let mut reader = Reader::new(bytes);
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(ots_error("not an OpenTimestamps proof"));
+    }
+    if reader.varuint()? != 1 {
+        return Err(ots_error("unsupported version"));
+    }
+    // NIP-03 proofs are of event ids, which are sha256 digests
+    if reader.byte()? != 0x08 {
+        return Err(ots_error("the proof is not of a sha256 digest"));
+    }
+    let digest = reader.bytes(32)?.to_vec();
+    let timestamp = Timestamp::read(&mut reader, 0)?;
+    Ok((digest, timestamp))
+}
+
+fn write_file(digest: &[u8], timestamp: &Timestamp) -> Vec<u8> {
+    let mut out: Vec<u8> = MAGIC.to_vec();
+    write_varuint(&mut out, 1);
+    out.push(0x08);
+    out.extend_from_slice(digest);
+    timestamp.write(&mut out);
+    out
+}
+
+/// Whether we have somewhere to get block headers from, and so can verify proofs
+pub fn can_verify() -> bool {
+    !GLOBALS.db().read_setting_ots_headers_file().is_empty()
+        || !GLOBALS.db().read_setting_ots_bitcoind_url().is_empty()
+}
+
+/// The verified timestamp of an event: the bitcoin block height and the time of
+/// that block
+pub fn verified_timestamp(id: Id) -> Option<(u64, i64)> {
+    match OtsProofsTable::read_record(id.as_hex_string(), None) {
+        Ok(Some(proof)) => match (proof.height, proof.block_time) {
+            (Some(height), Some(time)) => Some((height, time)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+struct BlockHeader {
+    // In the byte order of the raw header (which is how the proof computes it)
+    merkle_root: Vec<u8>,
+    time: i64,
+}
+
+// The header of a block from a headers file, or None if the file doesn't have it yet
+fn read_header(path: &str, height: u64) -> Result<Option<BlockHeader>, Error> {
+    let offset = match height.checked_mul(80) {
+        Some(offset) => offset,
+        None => return Ok(None),
+    };
+    let mut file = std::fs::File::open(path)?;
+    if offset.saturating_add(80) > file.metadata()?.len() {
+        return Ok(None);
+    }
+    let mut raw = [0u8; 80];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut raw)?;
+    Ok(Some(BlockHeader {
+        merkle_root: raw[36..68].to_vec(),
+        time: u32::from_le_bytes(raw[68..72].try_into()?) as i64,
+    }))
+}
+
+async fn block_header(height: u64) -> Result<BlockHeader, Error> {
+    let path = GLOBALS.db().read_setting_ots_headers_file();
+    if !path.is_empty() {
+        if let Some(header) = task::spawn_blocking(move || read_header(&path, height)).await?? {
+            return Ok(header);
+        }
+        // The file doesn't have that block yet, try bitcoind
+    }
+
+    let url = GLOBALS.db().read_setting_ots_bitcoind_url();
+    if url.is_empty() {
+        return Err(ots_error(format!("no block header for block {}", height)));
+    }
+    let hash = bitcoind_rpc(&url, "getblockhash", serde_json::json!([height])).await?;
+    let header = bitcoind_rpc(&url, "getblockheader", serde_json::json!([hash, true])).await?;
+    let mut merkle_root = match header["merkleroot"].as_str() {
+        Some(hex_root) => {
+            hex::decode(hex_root).map_err(|_| ots_error("bitcoind sent a bad merkle root"))?
+        }
+        None => return Err(ots_error("bitcoind sent no merkle root")),
+    };
+    // bitcoind shows hashes byte-reversed
+    merkle_root.reverse();
+    Ok(BlockHeader {
+        merkle_root,
+        time: header["time"].as_i64().unwrap_or(0),
+    })
+}
+
+async fn bitcoind_rpc(
+    url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, Error> {
+    // reqwest takes basic auth credentials from the url
+    let response = reqwest::Client::builder()
+        .timeout(Duration::new(30, 0))
+        .build()?
+        .post(url)
+        .json(&serde_json::json!({
+            "jsonrpc": "1.0",
+            "id": "gossip",
+            "method": method,
+            "params": params,
+        }))
+        .send()
+        .await?;
+    let value: serde_json::Value = response.json().await?;
+    if !value["error"].is_null() {
+        return Err(ots_error(format!("bitcoind: {}", value["error"])));
+    }
+    Ok(value["result"].clone())
+}
+
+// The bitcoin attestations of a proof, with the merkle root each claims, lowest first
+fn bitcoin_attestations(digest: &[u8], timestamp: &Timestamp) -> Vec<(u64, Vec<u8>)> {
+    let mut found = Vec::new();
+    timestamp.attestations(Some(digest), &mut Vec::new(), &mut found);
+
+    let mut attestations: Vec<(u64, Vec<u8>)> = found
+        .drain(..)
+        .filter_map(|(attestation, msg, _)| match (attestation, msg) {
+            (Attestation::Bitcoin(height), Some(msg)) => Some((height, msg)),
+            _ => None,
+        })
+        .collect();
+    attestations.sort();
+    attestations
+}
+
+// The lowest block the proof verifies against. Attestations of blocks we can't get the
+// header of are skipped.
+async fn verify(digest: &[u8], timestamp: &Timestamp) -> Option<(u64, i64)> {
+    for (height, msg) in bitcoin_attestations(digest, timestamp) {
+        let header = match block_header(height).await {
+            Ok(header) => header,
+            Err(e) => {
+                tracing::info!("{}", e);
+                continue;
+            }
+        };
+        if header.merkle_root == msg {
+            return Some((height, header.time));
+        }
+    }
+    None
+}
+
+/// Verify the proof in an attestation event (in the background), if we can
+pub(crate) fn process_attestation(event: &Event) {
+    if !can_verify() {
+        return;
+    }
+    let event = event.clone();
+    task::spawn(async move {
+        if let Err(e) = verify_attestation(event).await {
+            tracing::warn!("{}", e);
+        }
+    });
+}
+
+async fn verify_attestation(event: Event) -> Result<(), Error> {
+    let id = match event.tags.iter().find_map(|tag| tag.parse_event().ok()) {
+        Some((id, _, _, _)) => id,
+        None => return Err(ots_error("attestation doesn't reference an event")),
+    };
+
+    // Keep the best verified proof we have
+    let existing = OtsProofsTable::read_record(id.as_hex_string(), None)?;
+    if let Some(existing) = &existing {
+        if existing.attestation == Some(event.id.as_hex_string()) && existing.last_checked.is_some()
+        {
+            return Ok(());
+        }
+    }
+
+    let proof = base64::engine::general_purpose::STANDARD
+        .decode(event.content.trim())
+        .map_err(|_| ots_error("attestation content is not base64"))?;
+    let (digest, timestamp) = read_file(&proof)?;
+    if digest != id.0 {
+        return Err(ots_error("the proof is of a different event"));
+    }
+
+    let verified = verify(&digest, &timestamp).await;
+    if let Some(existing) = &existing {
+        let better = match (verified, existing.height) {
+            (Some((height, _)), Some(existing_height)) => height < existing_height,
+            (_, Some(_)) => false,
+            (_, None) => !existing.pending,
+        };
+        if !better {
+            return Ok(());
+        }
+    }
+
+    let mut record = OtsProof {
+        event: id.as_hex_string(),
+        attestation: Some(event.id.as_hex_string()),
+        proof,
+        height: verified.map(|(height, _)| height),
+        block_time: verified.map(|(_, time)| time),
+        pending: false,
+        last_checked: Some(Unixtime::now().0),
+    };
+    OtsProofsTable::write_record(&mut record, None)?;
+
+    // So the note gets rerendered
+    GLOBALS.ui_notes_to_invalidate.write().push(id);
+
+    Ok(())
+}
+
+fn client() -> Result<reqwest::Client, Error> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::new(30, 0))
+        .build()?)
+}
+
+/// Ask our calendar server to timestamp one of our events (in the background). The proof
+/// is pending until it gets into a block.
+pub(crate) fn request_timestamp(id: Id) {
+    task::spawn(async move {
+        if let Err(e) = inner_request_timestamp(id).await {
+            tracing::warn!("{}", e);
+        }
+    });
+}
+
+async fn inner_request_timestamp(id: Id) -> Result<(), Error> {
+    let calendar = GLOBALS.db().read_setting_ots_calendar_url();
+    let response = client()?
+        .post(format!("{}/digest", calendar.trim_end_matches('/')))
+        .header("Accept", "application/vnd.opentimestamps.v1")
+        .body(id.0.to_vec())
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(ots_error(format!(
+            "calendar {} responded {}",
+            calendar,
+            response.status()
+        )));
+    }
+    let bytes = response.bytes().await?;
+    let timestamp = Timestamp::read(&mut Reader::new(&bytes), 0)?;
+
+    let mut record = OtsProof {
+        event: id.as_hex_string(),
+        attestation: None,
+        proof: write_file(&id.0, &timestamp),
+        height: None,
+        block_time: None,
+        pending: true,
+        last_checked: Some(Unixtime::now().0),
+    };
+    OtsProofsTable::write_record(&mut record, None)?;
+
+    Ok(())
+}
+
+// Whether upgrade_pending() is running, so runs don't overlap
+static UPGRADING: AtomicBool = AtomicBool::new(false);
+
+/// Try to upgrade our pending proofs, publishing the attestations of those that made it
+/// into a block. Does nothing if this is already running.
+pub(crate) async fn upgrade_pending() {
+    if UPGRADING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    inner_upgrade_pending().await;
+    UPGRADING.store(false, Ordering::SeqCst);
+}
+
+async fn inner_upgrade_pending() {
+    let now = Unixtime::now().0;
+    let pending = match OtsProofsTable::filter_records(|p| {
+        p.pending
+            && p.last_checked
+                .map(|t| now - t > UPGRADE_INTERVAL_SECS)
+                .unwrap_or(true)
+    }) {
+        Ok(pending) => pending,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    };
+
+    for mut record in pending {
+        if let Err(e) = upgrade(&mut record).await {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+async fn upgrade(record: &mut OtsProof) -> Result<(), Error> {
+    let (digest, mut timestamp) = read_file(&record.proof)?;
+
+    let mut found = Vec::new();
+    timestamp.attestations(Some(&digest), &mut Vec::new(), &mut found);
+
+    let client = client()?;
+    let mut upgraded = false;
+    for (attestation, msg, path) in found.iter() {
+        let (uri, msg) = match (attestation, msg) {
+            (Attestation::Pending(uri), Some(msg)) => (uri, msg),
+            _ => continue,
+        };
+        let response = client
+            .get(format!(
+                "{}/timestamp/{}",
+                uri.trim_end_matches('/'),
+                hex::encode(msg)
+            ))
+            .header("Accept", "application/vnd.opentimestamps.v1")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            // Not in a block yet
+            continue;
+        }
+        let bytes = response.bytes().await?;
+        let new = Timestamp::read(&mut Reader::new(&bytes), 0)?;
+
+        let node = timestamp.node_mut(path);
+        node.attestations.retain(|a| a != attestation);
+        node.attestations.extend(new.attestations);
+        node.ops.extend(new.ops);
+        upgraded = true;
+    }
+
+    record.last_checked = Some(Unixtime::now().0);
+    if upgraded {
+        // Publish just the bitcoin attestations
+        let mut complete = timestamp.clone();
+        if complete.prune() {
+            let proof = write_file(&digest, &complete);
+            let event = attestation_event(record, &proof)?;
+            record.proof = proof;
+            record.attestation = Some(event.id.as_hex_string());
+            record.pending = false;
+            OtsProofsTable::write_record(record, None)?;
+
+            crate::process::process_new_event(&event, None, None, false, false)?;
+            let _ = GLOBALS
+                .to_overlord
+                .send(ToOverlordMessage::PostAgain(event));
+            return Ok(());
+        }
+        record.proof = write_file(&digest, &timestamp);
+    }
+    OtsProofsTable::write_record(record, None)?;
+
+    Ok(())
+}
+
+// Create an attestation (kind 1040) of one of our events
+fn attestation_event(record: &OtsProof, proof: &[u8]) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let id = Id::try_from_hex_string(&record.event)?;
+    let mut tags: Vec<Tag> = vec![Tag::new_event(id, None, None, None)];
+    if let Some(event) = GLOBALS.db().read_event(id)? {
+        tags.push(Tag::new(&["k", &u32::from(event.kind).to_string()]));
+    }
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: EventKind::Timestamp,
+        tags,
+        content: base64::engine::general_purpose::STANDARD.encode(proof),
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // The genesis block: its header, and its only transaction (so the merkle root is
+    // that transaction's id)
+    const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+    const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    // A proof that 32 bytes of the genesis coinbase existed by block 0, encoded by hand
+    fn genesis_proof() -> (Vec<u8>, Vec<u8>) {
+        let tx = hex::decode(GENESIS_COINBASE).unwrap();
+        let digest = tx[135..167].to_vec();
+
+        let mut proof = MAGIC.to_vec();
+        proof.push(0x01); // version
+        proof.push(0x08); // sha256
+        proof.extend_from_slice(&digest);
+        proof.push(0xf1); // prepend
+        proof.extend_from_slice(&[0x87, 0x01]); // 135
+        proof.extend_from_slice(&tx[..135]);
+        proof.push(0xf0); // append
+        proof.push(37);
+        proof.extend_from_slice(&tx[167..]);
+        proof.push(0x08); // sha256
+        proof.push(0x08); // sha256
+        proof.push(0x00); // attestation
+        proof.extend_from_slice(&BITCOIN_ATTESTATION);
+        proof.extend_from_slice(&[0x01, 0x00]); // height 0
+
+        (digest, proof)
+    }
+
+    fn headers_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("gossip-ots-test-{}", name));
+        std::fs::write(&path, hex::decode(GENESIS_HEADER).unwrap()).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_parse_and_verify_proof() {
+        let (expected_digest, proof) = genesis_proof();
+        let (digest, timestamp) = read_file(&proof).unwrap();
+        assert_eq!(digest, expected_digest);
+
+        // It writes back out the same
+        assert_eq!(write_file(&digest, &timestamp), proof);
+
+        let attestations = bitcoin_attestations(&digest, &timestamp);
+        assert_eq!(attestations.len(), 1);
+        let (height, merkle_root) = &attestations[0];
+        assert_eq!(*height, 0);
+
+        let path = headers_file("verify");
+        let header = read_header(&path, *height).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(header.merkle_root, *merkle_root);
+        assert_eq!(header.time, 1231006505);
+    }
+
+    #[test]
+    fn test_reject_truncated_proof() {
+        let (_, proof) = genesis_proof();
+        for len in [0, 10, MAGIC.len() + 2, proof.len() - 1] {
+            assert!(read_file(&proof[..len]).is_err());
+        }
+
+        // A length prefix past the end of the proof
+        let mut proof = proof[..MAGIC.len() + 34].to_vec();
+        proof.extend_from_slice(&[0xf0, 0x7f, 0x00]);
+        assert!(read_file(&proof).is_err());
+    }
+
+    #[test]
+    fn test_reject_heights_past_the_headers() {
+        let path = headers_file("heights");
+        assert!(read_header(&path, 0).unwrap().is_some());
+        assert!(read_header(&path, 1).unwrap().is_none());
+        assert!(read_header(&path, u64::MAX / 80).unwrap().is_none());
+        assert!(read_header(&path, u64::MAX).unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,13 +1,20 @@
-use crate::blossom::{Blossom, HashOutput};
+use crate::badge::BadgeDefinition;
+use crate::chat_channel::ChatChannel;
 use crate::comms::{
-    RelayConnectionReason, RelayJob, ToMinionMessage, ToMinionPayload, ToMinionPayloadDetail,
-    ToOverlordMessage,
+    PostDestination, RelayConnectionReason, RelayJob, ToMinionMessage, ToMinionPayload,
+    ToMinionPayloadDetail, ToOverlordMessage,
 };
+use crate::community::Community;
+use crate::count::CountKind;
 use crate::dm_channel::DmChannel;
+use crate::dm_search::DmSearch;
+use crate::dvm::{self, Dvm};
 use crate::error::{Error, ErrorKind};
-use crate::feed::FeedKind;
+use crate::feed::{CustomFeed, FeedKind};
 use crate::filter_set::{FeedRange, FilterSet};
 use crate::globals::GLOBALS;
+use crate::group::{self, GroupMetadata};
+use crate::lists::Nip51List;
 use crate::manager;
 use crate::minion::MinionExitReason;
 use crate::misc::{Private, ZapState};
@@ -18,8 +25,8 @@ use crate::relay;
 use crate::relay::Relay;
 use crate::relay_picker::RelayAssignment;
 use crate::relay_test_results::{RelayTestResult, RelayTestResults};
-use crate::storage::types::{HandlerKey, ScoreFactors};
-use crate::storage::{PersonTable, Table};
+use crate::storage::types::{Group1, HandlerKey, ScoreFactors};
+use crate::storage::{CustomFeedsTable, GroupsTable, PersonTable, Table};
 use crate::RunState;
 use heed::RwTxn;
 use http::StatusCode;
@@ -588,6 +595,9 @@ impl Overlord {
 
     async fn handle_message(&mut self, message: ToOverlordMessage) -> Result<(), Error> {
         match message {
+            ToOverlordMessage::AcceptBadge(id, accept) => {
+                self.accept_badge(id, accept)?;
+            }
             ToOverlordMessage::AddRelay(relay_url) => {
                 self.add_relay(relay_url).await?;
             }
@@ -597,14 +607,26 @@ impl Overlord {
             ToOverlordMessage::AdvertiseRelayListOne(relay_url, event, dmevent) => {
                 self.advertise_relay_list_one(relay_url, event, dmevent)?;
             }
+            ToOverlordMessage::ApproveCommunityPost(author, d, id) => {
+                self.approve_community_post(author, d, id)?;
+            }
             ToOverlordMessage::AuthApproved(relay_url, permanent) => {
                 self.auth_approved(relay_url, permanent)?;
             }
             ToOverlordMessage::AuthDeclined(relay_url, permanent) => {
                 self.auth_declined(relay_url, permanent)?;
             }
-            ToOverlordMessage::BlossomUpload(pathbuf) => {
-                self.blossom_upload(pathbuf).await?;
+            ToOverlordMessage::AwardBadge(d, awardees) => {
+                self.award_badge(d, awardees)?;
+            }
+            ToOverlordMessage::BlossomDelete(sha256) => {
+                self.blossom_delete(sha256);
+            }
+            ToOverlordMessage::BlossomList => {
+                self.blossom_list();
+            }
+            ToOverlordMessage::BlossomUpload(pathbuf, dm) => {
+                self.blossom_upload(pathbuf, dm).await?;
             }
             ToOverlordMessage::BookmarkAdd(er, private) => {
                 self.bookmark_add(er, private)?;
@@ -624,6 +646,12 @@ impl Overlord {
             ToOverlordMessage::ConnectDeclined(relay_url, permanent) => {
                 self.connect_declined(relay_url, permanent)?;
             }
+            ToOverlordMessage::Count(kinds) => {
+                self.count(kinds)?;
+            }
+            ToOverlordMessage::CreateBadge(definition) => {
+                self.create_badge(definition)?;
+            }
             ToOverlordMessage::DelegationReset => {
                 Self::delegation_reset().await?;
             }
@@ -639,6 +667,18 @@ impl Overlord {
             ToOverlordMessage::DeletePub => {
                 Self::delete_pub().await?;
             }
+            ToOverlordMessage::DiscoverChatChannels => {
+                self.discover_chat_channels()?;
+            }
+            ToOverlordMessage::DiscoverCommunities => {
+                self.discover_communities()?;
+            }
+            ToOverlordMessage::DiscoverDvms => {
+                self.discover_dvms()?;
+            }
+            ToOverlordMessage::DiscoverGroups(relay_url) => {
+                self.discover_groups(relay_url)?;
+            }
             ToOverlordMessage::DropRelay(relay_url) => {
                 self.drop_relay(relay_url)?;
             }
@@ -666,6 +706,9 @@ impl Overlord {
                     GLOBALS.status_queue.write().write(format!("{}", e));
                 }
             }
+            ToOverlordMessage::HideChannelMessage(id) => {
+                self.hide_channel_message(id)?;
+            }
             ToOverlordMessage::HideOrShowRelay(relay_url, hidden) => {
                 Self::hide_or_show_relay(relay_url, hidden)?;
             }
@@ -675,6 +718,12 @@ impl Overlord {
             ToOverlordMessage::ImportPub(pubstr) => {
                 Self::import_pub(pubstr)?;
             }
+            ToOverlordMessage::JoinGroup(relay_url, id) => {
+                self.join_group(relay_url, id)?;
+            }
+            ToOverlordMessage::LeaveGroup(relay_url, id) => {
+                self.leave_group(relay_url, id)?;
+            }
             ToOverlordMessage::LoadMoreCurrentFeed => {
                 self.load_more()?;
             }
@@ -698,9 +747,24 @@ impl Overlord {
                     }
                 }
             }
+            ToOverlordMessage::MuteChannelUser(pubkey) => {
+                self.mute_channel_user(pubkey)?;
+            }
             ToOverlordMessage::Nip46ServerOpApprovalResponse(pubkey, parsed_command, approval) => {
                 self.nip46_server_op_approval_response(pubkey, parsed_command, approval)?;
             }
+            ToOverlordMessage::Nip51ListAdd(kind, d, tag, private) => {
+                self.nip51_list_add(kind, d, tag, private)?;
+            }
+            ToOverlordMessage::Nip51ListDelete(kind, d) => {
+                self.nip51_list_delete(kind, d)?;
+            }
+            ToOverlordMessage::Nip51ListRm(kind, d, tagname, value) => {
+                self.nip51_list_rm(kind, d, tagname, value)?;
+            }
+            ToOverlordMessage::Nip51ListSave(list) => {
+                self.nip51_list_save(list)?;
+            }
             ToOverlordMessage::RefreshScoresAndPickRelays => {
                 self.refresh_scores_and_pick_relays().await?;
             }
@@ -709,9 +773,9 @@ impl Overlord {
                 tags,
                 in_reply_to,
                 annotation,
-                dm_channel,
+                destination,
             } => {
-                self.post(content, tags, in_reply_to, annotation, dm_channel)
+                self.post(content, tags, in_reply_to, annotation, destination)
                     .await?;
             }
             ToOverlordMessage::PostAgain(event) => {
@@ -738,12 +802,18 @@ impl Overlord {
             ToOverlordMessage::PushMetadata(metadata) => {
                 self.push_metadata(metadata)?;
             }
+            ToOverlordMessage::PushStatus(d, content, expiration) => {
+                self.push_status(d, content, expiration)?;
+            }
             ToOverlordMessage::RankRelay(relay_url, rank) => {
                 Self::rank_relay(relay_url, rank)?;
             }
             ToOverlordMessage::React(id, pubkey, emoji) => {
                 self.react(id, pubkey, emoji)?;
             }
+            ToOverlordMessage::ReactToWebsite(url, reaction) => {
+                self.react_to_website(url, reaction)?;
+            }
             ToOverlordMessage::ReengageMinion(url, jobs) => {
                 manager::engage_minion(url, jobs);
             }
@@ -753,27 +823,54 @@ impl Overlord {
             ToOverlordMessage::Repost(id) => {
                 self.repost(id)?;
             }
+            ToOverlordMessage::SearchDms(query) => {
+                Self::search_dms(query)?;
+            }
             ToOverlordMessage::SearchLocally(text) => {
                 Self::search_locally(text)?;
             }
             ToOverlordMessage::SearchRelays(text) => {
                 Self::search_relays(text)?;
             }
+            ToOverlordMessage::SendFileMessage(pathbuf, dm_channel) => {
+                self.send_file_message(pathbuf, dm_channel).await?;
+            }
             ToOverlordMessage::SetActivePerson(pubkey) => {
                 Self::set_active_person(pubkey).await?;
             }
+            ToOverlordMessage::SetChatChannel(id) => {
+                self.set_chat_channel(id)?;
+            }
+            ToOverlordMessage::SetCommunityFeed(author, d) => {
+                self.set_community_feed(author, d)?;
+            }
+            ToOverlordMessage::SetCustomFeed(name, anchor) => {
+                self.set_custom_feed(name, anchor)?;
+            }
             ToOverlordMessage::SetDmChannel(dmchannel) => {
                 self.set_dm_channel(dmchannel)?;
             }
+            ToOverlordMessage::SetDvmFeed(dvm) => {
+                self.set_dvm_feed(dvm)?;
+            }
             ToOverlordMessage::SetGlobalFeed(anchor) => {
                 self.set_global_feed(anchor)?;
             }
+            ToOverlordMessage::SetGroupFeed(relay_url, id) => {
+                self.set_group_feed(relay_url, id)?;
+            }
+            ToOverlordMessage::SetHashtagFeed(hashtag, anchor) => {
+                self.set_hashtag_feed(hashtag, anchor)?;
+            }
             ToOverlordMessage::SetPersonFeed(pubkey, anchor) => {
                 self.set_person_feed(pubkey, anchor)?;
             }
             ToOverlordMessage::SetRelayFeed(relay_url, anchor) => {
                 self.set_relay_feed(relay_url, anchor)?;
             }
+            ToOverlordMessage::SetRelaySetFeed(d, anchor) => {
+                self.set_relay_set_feed(d, anchor)?;
+            }
             ToOverlordMessage::SetThreadFeed {
                 id,
                 referenced_by,
@@ -837,6 +934,12 @@ impl Overlord {
         Ok(())
     }
 
+    /// Add a badge awarded to us to our profile badges, or remove it, and publish them
+    pub fn accept_badge(&mut self, id: Id, accept: bool) -> Result<(), Error> {
+        let event = crate::badge::profile_badges_event(id, accept)?;
+        self.post_list_event(event)
+    }
+
     /// Add a new relay to gossip
     pub async fn add_relay(&mut self, relay_url: RelayUrl) -> Result<(), Error> {
         // Create relay if missing
@@ -963,6 +1066,58 @@ impl Overlord {
         Ok(())
     }
 
+    /// Approve a post to a community that we moderate
+    pub fn approve_community_post(
+        &mut self,
+        author: PublicKey,
+        d: String,
+        id: Id,
+    ) -> Result<(), Error> {
+        let community = match Community::load(author, &d)? {
+            Some(c) => c,
+            None => return Err(ErrorKind::General("Community not found".to_owned()).into()),
+        };
+        match GLOBALS.identity.public_key() {
+            Some(pk) if community.is_moderator(&pk) => {}
+            _ => {
+                GLOBALS
+                    .status_queue
+                    .write()
+                    .write("You are not a moderator of this community.".to_owned());
+                return Ok(());
+            }
+        }
+        let post = match GLOBALS.db().read_event(id)? {
+            Some(event) => event,
+            None => return Err(ErrorKind::EventNotFound.into()),
+        };
+
+        let event = community.approval_event(&post)?;
+
+        // Process it locally, then send it to our outboxes and the community relays
+        crate::process::process_new_event(&event, None, None, false, false)?;
+        let mut relay_urls = relay::relays_to_post_to(&event)?;
+        for url in community.relays.iter() {
+            if !relay_urls.contains(url) {
+                relay_urls.push(url.to_owned());
+            }
+        }
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::PostEvent,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event]),
+                },
+            }],
+        );
+
+        GLOBALS.feed.sync_recompute();
+
+        Ok(())
+    }
+
     /// User has approved authentication on this relay. Save this result for later
     /// and inform the minion.
     pub fn auth_approved(&mut self, relay_url: RelayUrl, permanent: bool) -> Result<(), Error> {
@@ -1033,73 +1188,130 @@ impl Overlord {
         Ok(())
     }
 
-    pub async fn blossom_upload(&mut self, pathbuf: PathBuf) -> Result<(), Error> {
+    /// Award one of our badges to people
+    pub fn award_badge(&mut self, d: String, awardees: Vec<PublicKey>) -> Result<(), Error> {
+        if awardees.is_empty() {
+            return Ok(());
+        }
+        let event = crate::badge::award_event(&d, &awardees)?;
+
+        // Process it locally, then send it to our outboxes and the awardees' inboxes
+        crate::process::process_new_event(&event, None, None, false, false)?;
+        self.post_again(event)
+    }
+
+    /// Delete our blob from all of our blossom servers
+    pub fn blossom_delete(&mut self, sha256: String) {
         std::mem::drop(tokio::spawn(async move {
-            if let Err(e) = Overlord::inner_blossom_upload(pathbuf.clone()).await {
-                GLOBALS.blossom_uploads.insert(pathbuf, Err(e));
+            if let Err(e) = crate::blossom::delete_everywhere(sha256).await {
+                tracing::error!("{}", e);
             }
         }));
-
-        Ok(())
     }
 
-    async fn inner_blossom_upload(pathbuf: PathBuf) -> Result<(), Error> {
-        let blossom = match GLOBALS.blossom.get() {
-            Some(b) => b,
-            None => {
-                let blossom = Blossom::new()?;
-                let _ = GLOBALS.blossom.set(blossom);
-                GLOBALS.blossom.get().unwrap()
+    /// List our blobs on all of our blossom servers, into GLOBALS.blossom_blobs
+    pub fn blossom_list(&mut self) {
+        std::mem::drop(tokio::spawn(async move {
+            if let Err(e) = crate::blossom::list_all().await {
+                tracing::error!("{}", e);
             }
-        };
+        }));
+    }
 
-        let base_url = {
-            let blossom_servers = GLOBALS.db().read_setting_blossom_servers();
-            let first = blossom_servers.split_whitespace().next();
-            match first {
-                Some(bs) => {
-                    use http::uri::{Parts, PathAndQuery, Scheme};
-                    use http::Uri;
-
-                    let uri = bs.parse::<Uri>()?;
-                    let mut parts: Parts = uri.into_parts();
-                    parts.path_and_query = Some(PathAndQuery::from_static("/")); // Force no path
-                    if parts.scheme.is_none() {
-                        // Default to https
-                        parts.scheme = Some(Scheme::HTTPS);
-                    }
-                    let uri = Uri::from_parts(parts)?;
-                    format!("{}", uri)
-                }
-                None => return Err(ErrorKind::General("Blossom not configured".to_owned()).into()),
+    pub async fn blossom_upload(&mut self, pathbuf: PathBuf, dm: bool) -> Result<(), Error> {
+        std::mem::drop(tokio::spawn(async move {
+            if let Err(e) = Overlord::inner_blossom_upload(pathbuf.clone(), dm).await {
+                GLOBALS.blossom_uploads.insert(pathbuf, Err(e));
             }
-        };
+        }));
+
+        Ok(())
+    }
 
-        // metadata
-        let metadata = tokio::fs::metadata(&pathbuf).await?;
+    async fn inner_blossom_upload(pathbuf: PathBuf, dm: bool) -> Result<(), Error> {
+        let (base_url, nip96) = match crate::upload::upload_server() {
+            Some(server) => server,
+            None => return Err(ErrorKind::General("Uploads not configured".to_owned()).into()),
+        };
 
-        // hash
-        let hash = HashOutput::from_file(&pathbuf)?;
+        // strip metadata, resize, hash and describe
+        let mut prepared = crate::upload::prepare(pathbuf.clone()).await?;
 
-        // mime type
-        let mime = crate::blossom::get_content_type(&pathbuf)?;
+        // NIP-96 (multipart form data)
+        if nip96 {
+            let data = match prepared.data.take() {
+                Some(data) => data,
+                None => tokio::fs::read(&pathbuf).await?,
+            };
+            let filename = pathbuf
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "upload".to_owned());
+            let result = crate::nip96::nip96()?
+                .upload(base_url, data, &filename, &prepared)
+                .await;
+            let result = result.map(|fm| {
+                tracing::info!("Uploaded {} to {}", pathbuf.display(), &fm.url.0);
+                let bd = crate::nip96::blob_descriptor(&fm);
+                if let Err(e) = crate::upload::uploaded(fm, dm) {
+                    tracing::warn!("{}", e);
+                }
+                bd
+            });
+            GLOBALS.blossom_uploads.insert(pathbuf, result);
+            return Ok(());
+        }
 
-        // open
-        let file = tokio::fs::File::open(&pathbuf).await?;
+        let blossom = crate::blossom::blossom()?;
 
         // upload
-        let result = blossom
-            .upload(file, base_url, hash, mime, metadata.len())
-            .await;
+        let result = match prepared.data.take() {
+            Some(data) => {
+                blossom
+                    .upload(
+                        data,
+                        base_url.clone(),
+                        prepared.hash,
+                        prepared.mime.clone(),
+                        prepared.size,
+                    )
+                    .await
+            }
+            None => {
+                let file = tokio::fs::File::open(&pathbuf).await?;
+                blossom
+                    .upload(
+                        file,
+                        base_url.clone(),
+                        prepared.hash,
+                        prepared.mime.clone(),
+                        prepared.size,
+                    )
+                    .await
+            }
+        };
         if let Ok(ref bd) = result {
             println!("UPLOADED:  {} -> {}", pathbuf.display(), &bd.url);
+            if let Err(e) = crate::upload::uploaded(prepared.file_metadata(bd), dm) {
+                tracing::warn!("{}", e);
+            }
         }
+
+        // Mirror it to our other servers (BUD-04)
+        let mirror = result.as_ref().ok().cloned();
         GLOBALS.blossom_uploads.insert(pathbuf, result);
+        if let Some(bd) = mirror {
+            if let Err(e) = crate::blossom::mirror_everywhere(&bd, &base_url).await {
+                tracing::warn!("{}", e);
+            }
+        }
 
         Ok(())
     }
 
-    fn post_bookmarks(&mut self, event: Event) -> Result<(), Error> {
+    /// Post one of our lists (bookmarks, NIP-51 lists and sets), or a similar event such as
+    /// channel moderation, to our write relays
+    fn post_list_event(&mut self, event: Event) -> Result<(), Error> {
         // Process this event locally (ignore any error)
         let _ = crate::process::process_new_event(&event, None, None, false, false);
 
@@ -1126,7 +1338,7 @@ impl Overlord {
         if added {
             GLOBALS.recompute_current_bookmarks.notify_one();
             let event = GLOBALS.bookmarks.read_arc().into_event()?;
-            self.post_bookmarks(event)?;
+            self.post_list_event(event)?;
         }
 
         Ok(())
@@ -1139,7 +1351,7 @@ impl Overlord {
         if removed {
             GLOBALS.recompute_current_bookmarks.notify_one();
             let event = GLOBALS.bookmarks.read_arc().into_event()?;
-            self.post_bookmarks(event)?;
+            self.post_list_event(event)?;
         }
 
         Ok(())
@@ -1202,6 +1414,75 @@ impl Overlord {
         Ok(())
     }
 
+    /// Ask relays to COUNT events (NIP-45). Results appear in `GLOBALS.counts`.
+    ///
+    /// Followers are counted on the best relays we know of (up to the
+    /// `num_relays_for_counting` setting). Note interactions are counted on the
+    /// relays where we would look for replies to the note.
+    pub fn count(&mut self, mut kinds: Vec<CountKind>) -> Result<(), Error> {
+        let mut counts_by_relay: HashMap<RelayUrl, Vec<CountKind>> = HashMap::new();
+        let mut counting_relays: Option<Vec<RelayUrl>> = None;
+
+        for kind in kinds.drain(..) {
+            let relays = match kind.note_id() {
+                Some(id) => match GLOBALS.db().read_event(id)? {
+                    Some(event) => relay::relays_for_seeking_replies(&event)?,
+                    None => continue,
+                },
+                None => {
+                    if counting_relays.is_none() {
+                        counting_relays = Some(Self::counting_relays()?);
+                    }
+                    counting_relays.clone().unwrap_or_default()
+                }
+            };
+
+            for relay_url in relays {
+                counts_by_relay
+                    .entry(relay_url)
+                    .and_modify(|vec| {
+                        if !vec.contains(&kind) {
+                            vec.push(kind)
+                        }
+                    })
+                    .or_insert(vec![kind]);
+            }
+        }
+
+        for (relay_url, kinds) in counts_by_relay.drain() {
+            manager::engage_minion(
+                relay_url,
+                vec![RelayJob {
+                    reason: RelayConnectionReason::Counting,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Count(kinds),
+                    },
+                }],
+            );
+        }
+
+        Ok(())
+    }
+
+    // The best relays for counting things that are not specific to anybody's outbox
+    fn counting_relays() -> Result<Vec<RelayUrl>, Error> {
+        let mut relays = Relay::choose_relays(0, |r| r.is_good_for_advertise())?;
+        relays.sort_by(|a, b| {
+            b.adjusted_score(ScoreFactors::FULLY_ADJUSTED)
+                .partial_cmp(&a.adjusted_score(ScoreFactors::FULLY_ADJUSTED))
+                .unwrap()
+        });
+        relays.truncate(GLOBALS.db().read_setting_num_relays_for_counting() as usize);
+        Ok(relays.iter().map(|r| r.url.clone()).collect())
+    }
+
+    /// Publish a badge definition
+    pub fn create_badge(&mut self, definition: BadgeDefinition) -> Result<(), Error> {
+        let event = crate::badge::definition_event(&definition)?;
+        self.post_list_event(event)
+    }
+
     /// Remove any key delegation setup
     pub async fn delegation_reset() -> Result<(), Error> {
         if GLOBALS.delegation.reset() {
@@ -1439,6 +1720,85 @@ impl Overlord {
         Ok(())
     }
 
+    /// Look for public chat channels on our read relays, including any we joined that we
+    /// don't have yet
+    pub fn discover_chat_channels(&mut self) -> Result<(), Error> {
+        let joined = match Nip51List::load_mine(EventKind::PublicChatsList, "") {
+            Ok(list) => list.referenced_ids(),
+            Err(_) => vec![],
+        };
+        let relay_urls = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::DiscoverChatChannels,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::ChatChannelDiscovery(
+                        joined,
+                    )),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Look for moderated communities on our read relays, including the definitions of
+    /// those we follow
+    pub fn discover_communities(&mut self) -> Result<(), Error> {
+        let owners: Vec<PublicKey> = crate::community::followed_communities()
+            .iter()
+            .map(|naddr| naddr.author)
+            .collect();
+        let relay_urls = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::DiscoverCommunities,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::CommunityDiscovery(owners)),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Look for content discovery DVMs (NIP-89 handler information) on our read relays
+    pub fn discover_dvms(&mut self) -> Result<(), Error> {
+        let relay_urls = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::DiscoverDvms,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::DvmDiscovery),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Look for NIP-29 groups on a relay
+    pub fn discover_groups(&mut self, relay_url: RelayUrl) -> Result<(), Error> {
+        manager::run_jobs_on_all_relays(
+            vec![relay_url],
+            vec![RelayJob {
+                reason: RelayConnectionReason::DiscoverGroups,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::GroupDiscovery),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
     /// Disconnect from the specified relay. This may not happen immediately if the minion
     /// handling that relay is stuck waiting for a timeout.
     pub fn drop_relay(&mut self, relay_url: RelayUrl) -> Result<(), Error> {
@@ -1571,6 +1931,17 @@ impl Overlord {
         Ok(())
     }
 
+    /// Hide a public chat channel message from ourselves (NIP-28 kind 43)
+    pub fn hide_channel_message(&mut self, id: Id) -> Result<(), Error> {
+        let event = crate::chat_channel::moderation_event(
+            EventKind::ChannelHideMessage,
+            Tag::new_event(id, None, None, None),
+        )?;
+        self.post_list_event(event)?;
+        GLOBALS.feed.sync_recompute();
+        Ok(())
+    }
+
     /// Hide or Show a relay. This adjusts the `hidden` a flag on the `Relay` record
     /// (You could easily do this yourself by talking to GLOBALS.db() directly too)
     pub fn hide_or_show_relay(relay_url: RelayUrl, hidden: bool) -> Result<(), Error> {
@@ -1636,6 +2007,51 @@ impl Overlord {
         Ok(())
     }
 
+    /// Ask the relay to let us join a NIP-29 group, and remember that we joined it
+    pub fn join_group(&mut self, relay_url: RelayUrl, id: String) -> Result<(), Error> {
+        let event = group::membership_request(group::GROUP_JOIN_REQUEST, &id)?;
+        Self::post_to_group_relay(relay_url.clone(), event);
+
+        let name = match GroupMetadata::load(&relay_url, &id)? {
+            Some(metadata) => metadata.display_name(),
+            None => id.clone(),
+        };
+        GroupsTable::write_record(
+            &mut Group1 {
+                relay: relay_url,
+                id,
+                name,
+            },
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    /// Ask the relay to remove us from a NIP-29 group, and forget that we joined it
+    pub fn leave_group(&mut self, relay_url: RelayUrl, id: String) -> Result<(), Error> {
+        let event = group::membership_request(group::GROUP_LEAVE_REQUEST, &id)?;
+        Self::post_to_group_relay(relay_url.clone(), event);
+
+        GroupsTable::delete_record(Group1::key_for(&relay_url, &id), None)?;
+
+        Ok(())
+    }
+
+    /// Membership requests only go to the group's relay
+    fn post_to_group_relay(relay_url: RelayUrl, event: Event) {
+        manager::run_jobs_on_all_relays(
+            vec![relay_url],
+            vec![RelayJob {
+                reason: RelayConnectionReason::GroupMembership,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event]),
+                },
+            }],
+        );
+    }
+
     pub fn load_more(&mut self) -> Result<(), Error> {
         // Change the feed range:
         let anchor = GLOBALS.feed.load_more()?;
@@ -1708,12 +2124,134 @@ impl Overlord {
                     }],
                 );
             }
+            FeedKind::Custom(name) => {
+                if let Some(feed) = CustomFeedsTable::read_record(name, None)? {
+                    manager::run_jobs_on_all_relays(
+                        Self::custom_feed_relay_urls(&feed)?,
+                        vec![RelayJob {
+                            reason: RelayConnectionReason::SubscribeCustomFeed,
+                            payload: ToMinionPayload {
+                                job_id: rand::random::<u64>(),
+                                detail: ToMinionPayloadDetail::Subscribe(
+                                    FilterSet::CustomFeedChunk { feed, anchor },
+                                ),
+                            },
+                        }],
+                    );
+                }
+            }
+            FeedKind::Hashtag(hashtag) => {
+                manager::run_jobs_on_all_relays(
+                    Self::hashtag_relay_urls()?,
+                    vec![RelayJob {
+                        reason: RelayConnectionReason::SubscribeHashtag,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(FilterSet::HashtagFeedChunk {
+                                hashtag,
+                                anchor,
+                            }),
+                        },
+                    }],
+                );
+            }
+            FeedKind::RelaySet(d) => {
+                let relay_urls = Nip51List::load_mine(EventKind::RelaySets, &d)?.relay_urls();
+                manager::run_jobs_on_all_relays(
+                    relay_urls,
+                    vec![RelayJob {
+                        reason: RelayConnectionReason::SubscribeGlobal,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(FilterSet::GlobalFeedChunk(
+                                anchor,
+                            )),
+                        },
+                    }],
+                );
+            }
+            FeedKind::Dvm(pubkey) => {
+                // DVMs are not paged, so ask again for more results
+                let count = GLOBALS
+                    .feed
+                    .get_dvm_job(pubkey)
+                    .map(|(_, c)| c)
+                    .unwrap_or(0)
+                    + GLOBALS.db().read_setting_load_more_count() as usize;
+                self.dvm_job_request(pubkey, count)?;
+            }
+            FeedKind::Channel(id) => {
+                if let Some(channel) = ChatChannel::load(id)? {
+                    manager::run_jobs_on_all_relays(
+                        Self::chat_channel_relay_urls(&channel)?,
+                        vec![RelayJob {
+                            reason: RelayConnectionReason::SubscribeChatChannel,
+                            payload: ToMinionPayload {
+                                job_id: rand::random::<u64>(),
+                                detail: ToMinionPayloadDetail::Subscribe(
+                                    FilterSet::ChatChannelChunk {
+                                        channel: id,
+                                        anchor,
+                                    },
+                                ),
+                            },
+                        }],
+                    );
+                }
+            }
+            FeedKind::Community(author, d, _) => {
+                let relay_urls = match Community::load(author, &d)? {
+                    Some(community) => Self::community_relay_urls(&community)?,
+                    None => Relay::choose_relay_urls(Relay::READ, |_| true)?,
+                };
+                manager::run_jobs_on_all_relays(
+                    relay_urls,
+                    vec![RelayJob {
+                        reason: RelayConnectionReason::SubscribeCommunity,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(FilterSet::CommunityChunk {
+                                author,
+                                d,
+                                anchor,
+                            }),
+                        },
+                    }],
+                );
+            }
+            FeedKind::Group(relay_url, group) => {
+                // Groups are only on their own relay
+                manager::run_jobs_on_all_relays(
+                    vec![relay_url],
+                    vec![RelayJob {
+                        reason: RelayConnectionReason::SubscribeGroup,
+                        payload: ToMinionPayload {
+                            job_id: rand::random::<u64>(),
+                            detail: ToMinionPayloadDetail::Subscribe(FilterSet::GroupChunk {
+                                group,
+                                anchor,
+                            }),
+                        },
+                    }],
+                );
+            }
             _ => (), // other feeds can't load more
         }
 
         Ok(())
     }
 
+    /// Mute a person in all public chat channels (NIP-28 kind 44)
+    pub fn mute_channel_user(&mut self, pubkey: PublicKey) -> Result<(), Error> {
+        let event = crate::chat_channel::moderation_event(
+            EventKind::ChannelMuteUser,
+            Tag::new_pubkey(pubkey, None, None),
+        )?;
+        self.post_list_event(event)?;
+        GLOBALS.feed.sync_recompute();
+        Ok(())
+    }
+
     /// Process approved nip46 server operation
     pub fn nip46_server_op_approval_response(
         &mut self,
@@ -1747,6 +2285,87 @@ impl Overlord {
         Ok(())
     }
 
+    /// Adds an entry to one of our NIP-51 lists or sets, and publishes it
+    pub fn nip51_list_add(
+        &mut self,
+        kind: EventKind,
+        d: String,
+        tag: Tag,
+        private: bool,
+    ) -> Result<(), Error> {
+        let mut list = Nip51List::load_mine(kind, &d)?;
+        if list.add(tag, private) {
+            self.nip51_list_save(list)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes one of our NIP-51 sets, locally and on relays
+    pub fn nip51_list_delete(&mut self, kind: EventKind, d: String) -> Result<(), Error> {
+        let public_key = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => return Err(ErrorKind::NoPublicKey.into()),
+        };
+
+        let existing = GLOBALS.db().get_replaceable_event(kind, public_key, &d)?;
+
+        // Generate a deletion event
+        let event = {
+            // Include an "a" tag for the entire group
+            let ea = NAddr {
+                d,
+                relays: vec![],
+                kind,
+                author: public_key,
+            };
+            let mut tags: Vec<Tag> = vec![Tag::new_address(&ea, None)];
+
+            // Include an "e" tag for the event we have
+            if let Some(ref existing) = existing {
+                tags.push(Tag::new_event(existing.id, None, None, Some(public_key)));
+            }
+
+            let pre_event = PreEvent {
+                pubkey: public_key,
+                created_at: Unixtime::now(),
+                kind: EventKind::EventDeletion,
+                tags,
+                content: "Deleting list".to_owned(),
+            };
+
+            GLOBALS.identity.sign_event(pre_event)?
+        };
+
+        // Delete the set locally
+        if let Some(existing) = existing {
+            GLOBALS.db().delete_event(existing.id, None)?;
+        }
+
+        self.post_list_event(event)
+    }
+
+    /// Removes the entry with this tagname and value from one of our NIP-51 lists or sets,
+    /// and publishes it
+    pub fn nip51_list_rm(
+        &mut self,
+        kind: EventKind,
+        d: String,
+        tagname: String,
+        value: String,
+    ) -> Result<(), Error> {
+        let mut list = Nip51List::load_mine(kind, &d)?;
+        if list.remove(&tagname, &value) {
+            self.nip51_list_save(list)?;
+        }
+        Ok(())
+    }
+
+    /// Publishes one of our NIP-51 lists or sets
+    pub fn nip51_list_save(&mut self, list: Nip51List) -> Result<(), Error> {
+        let event = list.into_event()?;
+        self.post_list_event(event)
+    }
+
     /// Trigger the relay picker to find relays for people not fully covered
     pub async fn refresh_scores_and_pick_relays(&mut self) -> Result<(), Error> {
         // When manually doing this, we refresh person_relay scores first which
@@ -1788,7 +2407,7 @@ impl Overlord {
 
     /// React to a post. The backend doesn't read the event, so you have to supply the
     /// pubkey author too.
-    pub fn react(&mut self, id: Id, pubkey: PublicKey, reaction: char) -> Result<(), Error> {
+    pub fn react(&mut self, id: Id, pubkey: PublicKey, reaction: String) -> Result<(), Error> {
         let event = {
             let public_key = match GLOBALS.identity.public_key() {
                 Some(pk) => pk,
@@ -1808,6 +2427,14 @@ impl Overlord {
                 Tag::new_pubkey(pubkey, None, None),
             ];
 
+            // A custom emoji reaction must define the emoji
+            if let Some(emoji) = crate::emoji::my_emojis()
+                .into_iter()
+                .find(|e| e.as_content() == reaction)
+            {
+                tags.push(emoji.to_tag());
+            }
+
             if GLOBALS.db().read_setting_set_client_tag() {
                 tags.push(Tag::new(&["client", "gossip"]));
             }
@@ -1817,7 +2444,7 @@ impl Overlord {
                 created_at: Unixtime::now(),
                 kind: EventKind::Reaction,
                 tags,
-                content: reaction.to_string(),
+                content: reaction,
             };
 
             let powint = GLOBALS.db().read_setting_pow();
@@ -1856,14 +2483,23 @@ impl Overlord {
         Ok(())
     }
 
-    /// Post a TextNote (kind 1) event
+    /// React to a website (kind 17)
+    pub fn react_to_website(&mut self, url: String, reaction: String) -> Result<(), Error> {
+        let event = crate::website_reaction::reaction_event(&url, reaction)?;
+
+        // Process it locally so we count it, then post it
+        crate::process::process_new_event(&event, None, None, false, false)?;
+        self.post_again(event)
+    }
+
+    /// Post a TextNote (kind 1) event, or whatever kind of post its destination takes
     pub async fn post(
         &mut self,
         content: String,
         tags: Vec<Tag>,
         in_reply_to: Option<Id>,
         annotation: bool,
-        dm_channel: Option<DmChannel>,
+        destination: PostDestination,
     ) -> Result<(), Error> {
         let author = match GLOBALS.identity.public_key() {
             Some(pk) => pk,
@@ -1873,9 +2509,13 @@ impl Overlord {
             }
         };
 
+        // Timestamp our posts, but not our DMs (the calendar would see when we DM)
+        let timestamp = GLOBALS.db().read_setting_timestamp_own_posts()
+            && !matches!(destination, PostDestination::Dm(_));
+
         // Prepare events for posting
-        let mut prepared_events = match dm_channel {
-            Some(channel) => {
+        let mut prepared_events = match destination {
+            PostDestination::Dm(channel) => {
                 if channel.can_use_nip17() {
                     crate::post::prepare_post_nip17(author, content, tags, channel, annotation)
                         .await?
@@ -1883,7 +2523,42 @@ impl Overlord {
                     crate::post::prepare_post_nip04(author, content, channel, annotation)?
                 }
             }
-            None => {
+            PostDestination::Channel(channel) => {
+                crate::post::prepare_post_channel(
+                    author,
+                    content,
+                    tags,
+                    channel,
+                    in_reply_to,
+                    annotation,
+                )
+                .await?
+            }
+            PostDestination::Group(relay, group) => {
+                crate::post::prepare_post_group(
+                    author,
+                    content,
+                    tags,
+                    relay,
+                    group,
+                    in_reply_to,
+                    annotation,
+                )
+                .await?
+            }
+            PostDestination::Community(community_author, d) => {
+                crate::post::prepare_post_community(
+                    author,
+                    content,
+                    tags,
+                    community_author,
+                    d,
+                    in_reply_to,
+                    annotation,
+                )
+                .await?
+            }
+            PostDestination::Note => {
                 crate::post::prepare_post_normal(author, content, tags, in_reply_to, annotation)
                     .await?
             }
@@ -1894,6 +2569,10 @@ impl Overlord {
             // Process this event locally (ignore any error)
             let _ = crate::process::process_new_event(&event, None, None, false, false);
 
+            if timestamp {
+                crate::ots::request_timestamp(event.id);
+            }
+
             for url in &relay_urls {
                 tracing::debug!("Asking {} to post", url);
             }
@@ -1914,6 +2593,15 @@ impl Overlord {
     }
 
     pub fn post_again(&mut self, event: Event) -> Result<(), Error> {
+        // Relays would refuse a protected event from anyone but its author
+        if relay::is_protected(&event) && Some(event.pubkey) != GLOBALS.identity.public_key() {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("Cannot post a protected event by someone else.".to_owned());
+            return Ok(());
+        }
+
         let relay_urls = relay::relays_to_post_to(&event)?;
 
         for url in &relay_urls {
@@ -1965,7 +2653,10 @@ impl Overlord {
             0,
         );
 
-        let count = GLOBALS.fetcher.prune(age).await?;
+        let mut count = GLOBALS.fetcher.prune(age).await?;
+
+        // Then bring the cache within its budgets
+        count += tokio::task::spawn_blocking(crate::media_cache::enforce_limits).await??;
 
         GLOBALS
             .status_queue
@@ -2124,21 +2815,48 @@ impl Overlord {
             content: serde_json::to_string(&metadata)?,
         };
 
-        let event = GLOBALS.identity.sign_event(pre_event)?;
+        let event = GLOBALS.identity.sign_event(pre_event)?;
+
+        // Push to all of the relays we post to
+        // Send it the event to pull our followers
+        let relay_urls: Vec<RelayUrl> = Relay::choose_relay_urls(Relay::WRITE, |_| true)?;
+        for url in &relay_urls {
+            tracing::debug!("Pushing Metadata to {}", url);
+        }
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::PostMetadata,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Publish one of the user's statuses (e.g. "general" or "music"). An empty content
+    /// clears the status.
+    pub fn push_status(
+        &mut self,
+        d: String,
+        content: String,
+        expiration: Option<Unixtime>,
+    ) -> Result<(), Error> {
+        let event = crate::user_status::status_event(&d, &content, expiration)?;
 
-        // Push to all of the relays we post to
-        // Send it the event to pull our followers
-        let relay_urls: Vec<RelayUrl> = Relay::choose_relay_urls(Relay::WRITE, |_| true)?;
-        for url in &relay_urls {
-            tracing::debug!("Pushing Metadata to {}", url);
-        }
+        // Process it locally so it shows right away, then send it to our outboxes
+        crate::process::process_new_event(&event, None, None, false, false)?;
+        let relay_urls = relay::relays_to_post_to(&event)?;
         manager::run_jobs_on_all_relays(
             relay_urls,
             vec![RelayJob {
-                reason: RelayConnectionReason::PostMetadata,
+                reason: RelayConnectionReason::PostEvent,
                 payload: ToMinionPayload {
                     job_id: rand::random::<u64>(),
-                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event]),
                 },
             }],
         );
@@ -2264,7 +2982,12 @@ impl Overlord {
                 created_at: Unixtime::now(),
                 kind,
                 tags,
-                content: serde_json::to_string(&reposted_event)?,
+                // The content of a protected event should not leak to other relays
+                content: if relay::is_protected(&reposted_event) {
+                    "".to_owned()
+                } else {
+                    serde_json::to_string(&reposted_event)?
+                },
             };
 
             let powint = GLOBALS.db().read_setting_pow();
@@ -2312,6 +3035,21 @@ impl Overlord {
         Ok(())
     }
 
+    /// Search DMs in the local search index.
+    /// Search results eventually arrive in `GLOBALS.dm_search_results`
+    pub fn search_dms(query: DmSearch) -> Result<(), Error> {
+        if GLOBALS.db().get_flag_rebuild_dm_search_needed() {
+            GLOBALS
+                .status_queue
+                .write()
+                .write("DMs are still being indexed for search.".to_string());
+        }
+
+        *GLOBALS.dm_search_results.write() = crate::dm_search::search(&query)?;
+
+        Ok(())
+    }
+
     /// Search people and notes in the local database.
     /// Search results eventually arrive in `GLOBALS.people_search_results` and `GLOBALS.note_search_results`
     pub fn search_locally(mut text: String) -> Result<(), Error> {
@@ -2455,6 +3193,86 @@ impl Overlord {
         Ok(())
     }
 
+    /// Encrypt a local file, upload it to our blossom server, and send it to a DM
+    /// channel in a NIP-17 file message
+    pub async fn send_file_message(
+        &mut self,
+        pathbuf: PathBuf,
+        dm_channel: DmChannel,
+    ) -> Result<(), Error> {
+        std::mem::drop(tokio::spawn(async move {
+            if let Err(e) = Overlord::inner_send_file_message(pathbuf.clone(), dm_channel).await {
+                GLOBALS.blossom_uploads.insert(pathbuf, Err(e));
+            }
+        }));
+
+        Ok(())
+    }
+
+    async fn inner_send_file_message(pathbuf: PathBuf, dm_channel: DmChannel) -> Result<(), Error> {
+        let author = match GLOBALS.identity.public_key() {
+            Some(pk) => pk,
+            None => return Err(ErrorKind::NoPublicKey.into()),
+        };
+
+        // Only blossom, as NIP-96 servers may transform what we upload, and they
+        // cannot do that to ciphertext
+        let base_url = match crate::blossom::server_base_urls().into_iter().next() {
+            Some(url) => url,
+            None => {
+                return Err(
+                    ErrorKind::General("Encrypted files need a blossom server".to_owned()).into(),
+                )
+            }
+        };
+
+        // strip metadata, resize, hash and describe, then encrypt
+        let mut prepared = crate::upload::prepare(pathbuf.clone()).await?;
+        let data = match prepared.data.take() {
+            Some(data) => data,
+            None => tokio::fs::read(&pathbuf).await?,
+        };
+        let encrypted = crate::nip17::encrypt(&data)?;
+
+        let bd = crate::blossom::blossom()?
+            .upload(
+                encrypted.ciphertext.clone(),
+                base_url,
+                crate::blossom::HashOutput::from_bytes(&encrypted.ciphertext),
+                mime::APPLICATION_OCTET_STREAM,
+                encrypted.ciphertext.len() as u64,
+            )
+            .await?;
+        tracing::info!("Uploaded {} to {} (encrypted)", pathbuf.display(), &bd.url);
+
+        let mut prepared_events = crate::post::prepare_file_message_nip17(
+            author,
+            bd.url.clone(),
+            &prepared,
+            &encrypted,
+            dm_channel,
+        )?;
+        GLOBALS.blossom_uploads.insert(pathbuf, Ok(bd));
+
+        for (event, relay_urls) in prepared_events.drain(..) {
+            // Process this event locally (ignore any error)
+            let _ = crate::process::process_new_event(&event, None, None, false, false);
+
+            manager::run_jobs_on_all_relays(
+                relay_urls,
+                vec![RelayJob {
+                    reason: RelayConnectionReason::PostEvent,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
+                    },
+                }],
+            );
+        }
+
+        Ok(())
+    }
+
     /// Set a particular person as active in the `People` structure. This affects the results of
     /// some functions of that structure
     pub async fn set_active_person(pubkey: PublicKey) -> Result<(), Error> {
@@ -2462,6 +3280,115 @@ impl Overlord {
         Ok(())
     }
 
+    fn set_chat_channel(&mut self, id: Id) -> Result<(), Error> {
+        // If we don't have the channel yet, our read relays may
+        let relay_urls = match ChatChannel::load(id)? {
+            Some(channel) => Self::chat_channel_relay_urls(&channel)?,
+            None => Relay::choose_relay_urls(Relay::READ, |_| true)?,
+        };
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::SubscribeChatChannel,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::ChatChannel(id)),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Channels are read from their own relays and our read relays
+    fn chat_channel_relay_urls(channel: &ChatChannel) -> Result<Vec<RelayUrl>, Error> {
+        let mut relay_urls = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+        for url in channel.relays.iter() {
+            if !relay_urls.contains(url) {
+                relay_urls.push(url.to_owned());
+            }
+        }
+        Ok(relay_urls)
+    }
+
+    fn set_community_feed(&mut self, author: PublicKey, d: String) -> Result<(), Error> {
+        // If we don't have the community yet, our read relays may
+        let relay_urls = match Community::load(author, &d)? {
+            Some(community) => Self::community_relay_urls(&community)?,
+            None => Relay::choose_relay_urls(Relay::READ, |_| true)?,
+        };
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::SubscribeCommunity,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::Community { author, d }),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    /// Communities are read from their own relays and our read relays
+    fn community_relay_urls(community: &Community) -> Result<Vec<RelayUrl>, Error> {
+        let mut relay_urls = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+        for url in community.relays.iter() {
+            if !relay_urls.contains(url) {
+                relay_urls.push(url.to_owned());
+            }
+        }
+        Ok(relay_urls)
+    }
+
+    fn set_custom_feed(&mut self, name: String, anchor: Unixtime) -> Result<(), Error> {
+        let feed = match CustomFeedsTable::read_record(name, None)? {
+            Some(feed) => feed,
+            None => return Ok(()),
+        };
+
+        manager::run_jobs_on_all_relays(
+            Self::custom_feed_relay_urls(&feed)?,
+            vec![
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeCustomFeed,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::CustomFeedFuture {
+                            feed: feed.clone(),
+                            anchor,
+                        }),
+                    },
+                },
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeCustomFeed,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::CustomFeedChunk {
+                            feed,
+                            anchor,
+                        }),
+                    },
+                },
+            ],
+        );
+
+        Ok(())
+    }
+
+    /// Custom feeds are fetched from their own relays if they have any, else from our
+    /// read relays. Relay feeds without relays are not fetched at all.
+    fn custom_feed_relay_urls(feed: &CustomFeed) -> Result<Vec<RelayUrl>, Error> {
+        if !feed.relays.is_empty() {
+            Ok(feed.relays.clone())
+        } else if feed.is_relay_feed() {
+            Ok(vec![])
+        } else {
+            Relay::choose_relay_urls(Relay::READ, |_| true)
+        }
+    }
+
     fn set_dm_channel(&mut self, dmchannel: DmChannel) -> Result<(), Error> {
         // subscribe to channel on outbox and inbox relays
         //   outbox: you may have written them there. Other clients may have too.
@@ -2486,6 +3413,84 @@ impl Overlord {
         Ok(())
     }
 
+    fn set_dvm_feed(&mut self, pubkey: PublicKey) -> Result<(), Error> {
+        match GLOBALS.feed.get_dvm_job(pubkey) {
+            Some((job, _)) => {
+                // Keep listening for results to the job we already sent
+                if let Some(dvm) = Dvm::load(pubkey)? {
+                    Self::subscribe_dvm_results(&dvm, job)?;
+                }
+            }
+            None => {
+                let count = GLOBALS.db().read_setting_load_more_count() as usize;
+                self.dvm_job_request(pubkey, count)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send a content discovery job request to a DVM, and listen for its results
+    fn dvm_job_request(&mut self, pubkey: PublicKey, max_results: usize) -> Result<(), Error> {
+        let dvm = match Dvm::load(pubkey)? {
+            Some(dvm) => dvm,
+            None => {
+                return Err(
+                    ErrorKind::General("No handler information for that DVM".to_owned()).into(),
+                )
+            }
+        };
+
+        let event = dvm.job_request(max_results, &dvm::result_relays()?)?;
+        GLOBALS.db().write_event(&event, None)?;
+        GLOBALS.feed.set_dvm_job(pubkey, event.id, max_results);
+
+        // Post the request where the DVM will see it
+        let mut relay_urls = Relay::choose_relay_urls(Relay::WRITE, |_| true)?;
+        for url in dvm.relays.iter() {
+            if !relay_urls.contains(url) {
+                relay_urls.push(url.to_owned());
+            }
+        }
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::DvmJob,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::PostEvents(vec![event.clone()]),
+                },
+            }],
+        );
+
+        Self::subscribe_dvm_results(&dvm, event.id)
+    }
+
+    /// Listen for DVM results on the relays we asked for them on, and the DVM's own relays
+    fn subscribe_dvm_results(dvm: &Dvm, job: Id) -> Result<(), Error> {
+        let mut relay_urls = dvm::result_relays()?;
+        for url in dvm.relays.iter() {
+            if !relay_urls.contains(url) {
+                relay_urls.push(url.to_owned());
+            }
+        }
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![RelayJob {
+                reason: RelayConnectionReason::DvmJob,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::DvmResults {
+                        dvm: dvm.pubkey,
+                        job,
+                    }),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
     fn set_global_feed(&mut self, anchor: Unixtime) -> Result<(), Error> {
         let relay_urls = Relay::choose_relay_urls(Relay::GLOBAL, |_| true)?;
         manager::run_jobs_on_all_relays(
@@ -2515,6 +3520,63 @@ impl Overlord {
         Ok(())
     }
 
+    /// Groups are read from their own relay only, not via the relay picker
+    fn set_group_feed(&mut self, relay_url: RelayUrl, id: String) -> Result<(), Error> {
+        manager::run_jobs_on_all_relays(
+            vec![relay_url],
+            vec![RelayJob {
+                reason: RelayConnectionReason::SubscribeGroup,
+                payload: ToMinionPayload {
+                    job_id: rand::random::<u64>(),
+                    detail: ToMinionPayloadDetail::Subscribe(FilterSet::Group(id)),
+                },
+            }],
+        );
+
+        Ok(())
+    }
+
+    fn set_hashtag_feed(&mut self, hashtag: String, anchor: Unixtime) -> Result<(), Error> {
+        manager::run_jobs_on_all_relays(
+            Self::hashtag_relay_urls()?,
+            vec![
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeHashtag,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::HashtagFeedFuture {
+                            hashtag: hashtag.clone(),
+                            anchor,
+                        }),
+                    },
+                },
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeHashtag,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::HashtagFeedChunk {
+                            hashtag,
+                            anchor,
+                        }),
+                    },
+                },
+            ],
+        );
+
+        Ok(())
+    }
+
+    /// Hashtag feeds are fetched from our read relays and our search relays
+    fn hashtag_relay_urls() -> Result<Vec<RelayUrl>, Error> {
+        let mut relay_urls = Relay::choose_relay_urls(Relay::READ, |_| true)?;
+        for url in Relay::choose_relay_urls(Relay::SEARCH, |_| true)? {
+            if !relay_urls.contains(&url) {
+                relay_urls.push(url);
+            }
+        }
+        Ok(relay_urls)
+    }
+
     fn set_person_feed(&mut self, pubkey: PublicKey, anchor: Unixtime) -> Result<(), Error> {
         let relays: Vec<RelayUrl> = relay::get_some_pubkey_outboxes(pubkey)?;
         manager::run_jobs_on_all_relays(
@@ -2574,6 +3636,35 @@ impl Overlord {
         Ok(())
     }
 
+    fn set_relay_set_feed(&mut self, d: String, anchor: Unixtime) -> Result<(), Error> {
+        let relay_urls = Nip51List::load_mine(EventKind::RelaySets, &d)?.relay_urls();
+        manager::run_jobs_on_all_relays(
+            relay_urls,
+            vec![
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeGlobal,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::GlobalFeedFuture(
+                            anchor,
+                        )),
+                    },
+                },
+                RelayJob {
+                    reason: RelayConnectionReason::SubscribeGlobal,
+                    payload: ToMinionPayload {
+                        job_id: rand::random::<u64>(),
+                        detail: ToMinionPayloadDetail::Subscribe(FilterSet::GlobalFeedChunk(
+                            anchor,
+                        )),
+                    },
+                },
+            ],
+        );
+
+        Ok(())
+    }
+
     /// This function:
     ///   1. Sets GLOBALS.feed thread_parent to the highest locally connected event
     ///   2. Engages the Seeker to climb ancestors from that event
@@ -3099,14 +4190,7 @@ impl Overlord {
         }
 
         // Query relays for contact lists to get the count updated
-        let mut relays = Relay::choose_relays(0, |r| r.is_good_for_advertise())?;
-        relays.sort_by(|a, b| {
-            b.adjusted_score(ScoreFactors::FULLY_ADJUSTED)
-                .partial_cmp(&a.adjusted_score(ScoreFactors::FULLY_ADJUSTED))
-                .unwrap()
-        });
-        relays.truncate(GLOBALS.db().read_setting_num_relays_for_counting() as usize);
-        let relays: Vec<RelayUrl> = relays.iter().map(|r| r.url.clone()).collect();
+        let relays = Self::counting_relays()?;
         manager::run_jobs_on_all_relays(
             relays,
             vec![RelayJob {
@@ -3676,8 +4760,10 @@ impl Overlord {
     pub fn visible_notes_changed(&mut self, mut visible: Vec<Id>) -> Result<(), Error> {
         // Work out which relays to use to find augments for which ids
         let mut augment_subs: HashMap<RelayUrl, Vec<Id>> = HashMap::new();
+        let mut counted: Vec<Id> = Vec::new();
         for id in visible.drain(..) {
             if let Some(event) = GLOBALS.db().read_event(id)? {
+                counted.push(id);
                 let relays = relay::relays_for_seeking_replies(&event)?;
                 for relay_url in relays {
                     augment_subs
@@ -3692,6 +4778,18 @@ impl Overlord {
             }
         }
 
+        // Count interactions on the visible notes, if relays can do it for us
+        if GLOBALS.db().read_setting_count_note_interactions() {
+            let kinds: Vec<CountKind> = counted
+                .drain(..)
+                .flat_map(CountKind::for_note)
+                .filter(|kind| GLOBALS.counts.should_request(*kind))
+                .collect();
+            if !kinds.is_empty() {
+                self.count(kinds)?;
+            }
+        }
+
         // Create jobs for minions
         for (relay_url, ids) in augment_subs.drain() {
             manager::engage_minion(
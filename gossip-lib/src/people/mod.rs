@@ -2,11 +2,13 @@ mod follow_list;
 pub use follow_list::FollowList;
 
 use crate::comms::ToOverlordMessage;
+use crate::emoji::CustomEmoji;
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use crate::misc::{Freshness, Private};
 use crate::relay;
 use crate::storage::{PersonTable, Table};
+use crate::user_status::UserStatus;
 use dashmap::{DashMap, DashSet};
 use image::RgbaImage;
 use nostr_types::{
@@ -58,6 +60,14 @@ pub struct People {
     // This only relates to the Metadata event, not subsequent avatar or nip05
     // loads.
     fetching_metadata: DashMap<PublicKey, Unixtime>,
+
+    // NIP-38 statuses, loaded from the database as the UI asks for them and
+    // forgotten when a new status event comes in
+    statuses: DashMap<PublicKey, Vec<UserStatus>>,
+
+    // NIP-30 custom emoji used in names, loaded like statuses and forgotten when
+    // new metadata comes in
+    name_emojis: DashMap<PublicKey, Vec<CustomEmoji>>,
 }
 
 impl Default for People {
@@ -77,9 +87,63 @@ impl People {
             recheck_nip05: DashSet::new(),
             people_of_interest: DashSet::new(),
             fetching_metadata: DashMap::new(),
+            statuses: DashMap::new(),
+            name_emojis: DashMap::new(),
+        }
+    }
+
+    /// Get the current (set and unexpired) statuses of a person, the general status first
+    pub fn get_statuses(&self, pubkey: PublicKey) -> Vec<UserStatus> {
+        if !self.statuses.contains_key(&pubkey) {
+            match UserStatus::load(pubkey) {
+                Ok(statuses) => {
+                    self.statuses.insert(pubkey, statuses);
+                }
+                Err(e) => {
+                    tracing::error!("{}", e);
+                    return vec![];
+                }
+            }
+        }
+
+        match self.statuses.get(&pubkey) {
+            Some(statuses) => statuses
+                .iter()
+                .filter(|s| s.is_current())
+                .cloned()
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Forget the cached statuses of a person, so they are reloaded when next needed
+    pub(crate) fn forget_statuses(&self, pubkey: PublicKey) {
+        self.statuses.remove(&pubkey);
+    }
+
+    /// Get the custom emoji a person defines for use in their name and about
+    pub fn get_name_emojis(&self, pubkey: PublicKey) -> Vec<CustomEmoji> {
+        if let Some(emojis) = self.name_emojis.get(&pubkey) {
+            return emojis.clone();
+        }
+
+        match crate::emoji::person_emojis(pubkey) {
+            Ok(emojis) => {
+                self.name_emojis.insert(pubkey, emojis.clone());
+                emojis
+            }
+            Err(e) => {
+                tracing::error!("{}", e);
+                vec![]
+            }
         }
     }
 
+    /// Forget the cached custom emoji of a person, so they are reloaded when next needed
+    pub(crate) fn forget_name_emojis(&self, pubkey: PublicKey) {
+        self.name_emojis.remove(&pubkey);
+    }
+
     /// Get all the pubkeys that the user subscribes to in any list
     /// (We also force the current user into this list)
     pub fn get_subscribed_pubkeys(&self) -> Vec<PublicKey> {
@@ -413,10 +477,9 @@ impl People {
             }
         };
 
-        match GLOBALS.fetcher.try_get(
+        match GLOBALS.fetcher.try_get_avatar(
             &url,
             Duration::from_secs(60 * 60 * GLOBALS.db().read_setting_avatar_becomes_stale_hours()),
-            false,
         ) {
             // cache expires in 3 days
             Ok(None) => None,
@@ -1,8 +1,10 @@
 use crate::dm_channel::DmChannel;
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
+use crate::nip17::EncryptedFile;
 use crate::relay;
 use crate::relay::Relay;
+use crate::upload::PreparedUpload;
 use nostr_types::{
     ContentEncryptionAlgorithm, ContentSegment, Event, EventKind, EventReference, FileMetadata, Id,
     NAddr, NostrBech32, PreEvent, PublicKey, RelayUrl, ShatteredContent, Tag, UncheckedUrl,
@@ -58,6 +60,271 @@ pub async fn prepare_post_normal(
     Ok(vec![(event, relays)])
 }
 
+/// Prepare a NIP-28 public chat message (kind 42) in the channel whose creation event is
+/// `channel`, possibly replying to another message in it
+pub async fn prepare_post_channel(
+    author: PublicKey,
+    content: String,
+    mut tags: Vec<Tag>,
+    channel: Id,
+    in_reply_to: Option<Id>,
+    annotation: bool,
+) -> Result<Vec<(Event, Vec<RelayUrl>)>, Error> {
+    let chat_channel = match crate::ChatChannel::load(channel)? {
+        Some(c) => c,
+        None => return Err("Cannot find the channel we are posting to.".into()),
+    };
+
+    add_gossip_tag(&mut tags);
+
+    add_tags_mirroring_content(&content, &mut tags, false).await;
+
+    // The channel is always the root
+    add_event_to_tags(
+        &mut tags,
+        channel,
+        chat_channel.relays.first().map(|u| u.to_unchecked_url()),
+        None,
+        "root",
+    );
+
+    // Reply to the message, and tag its author
+    if let Some(parent_id) = in_reply_to {
+        let parent = match GLOBALS.db().read_event(parent_id)? {
+            Some(e) => e,
+            None => return Err("Cannot find event we are replying to.".into()),
+        };
+        add_event_to_tags(&mut tags, parent_id, None, Some(parent.pubkey), "reply");
+        if parent.pubkey != author {
+            nostr_types::add_pubkey_to_tags(&mut tags, parent.pubkey, None);
+        }
+    }
+
+    if annotation {
+        tags.push(Tag::new(&["annotation"]))
+    }
+
+    let pre_event = PreEvent {
+        pubkey: author,
+        created_at: Unixtime::now(),
+        kind: EventKind::ChannelMessage,
+        tags,
+        content,
+    };
+
+    let event = GLOBALS.identity.sign_event(pre_event)?;
+
+    // Our outboxes and those of the people tagged, plus the channel's relays
+    let mut relays = relay::relays_to_post_to(&event)?;
+    for url in chat_channel.relays {
+        if !relays.contains(&url) {
+            relays.push(url);
+        }
+    }
+
+    Ok(vec![(event, relays)])
+}
+
+/// Prepare a post in a NIP-29 group. Top level posts are chat messages (kind 9), or
+/// threads (kind 11) if they have a subject, which becomes the thread title. Replies
+/// to chat messages quote them, and replies to threads are comments (kind 1111).
+/// Group posts only go to the group's relay.
+pub async fn prepare_post_group(
+    author: PublicKey,
+    content: String,
+    mut tags: Vec<Tag>,
+    relay: RelayUrl,
+    group: String,
+    in_reply_to: Option<Id>,
+    annotation: bool,
+) -> Result<Vec<(Event, Vec<RelayUrl>)>, Error> {
+    use crate::group::{COMMENT, GROUP_CHAT_MESSAGE, GROUP_THREAD};
+
+    add_gossip_tag(&mut tags);
+
+    add_tags_mirroring_content(&content, &mut tags, false).await;
+
+    let relay_str = relay.as_str().to_owned();
+
+    let kind: u32 = match in_reply_to {
+        Some(parent_id) => {
+            let parent = match GLOBALS.db().read_event(parent_id)? {
+                Some(e) => e,
+                None => return Err("Cannot find event we are replying to.".into()),
+            };
+            let parent_kind = u32::from(parent.kind);
+            if parent_kind == GROUP_CHAT_MESSAGE {
+                tags.push(Tag::new(&[
+                    "q",
+                    &parent_id.as_hex_string(),
+                    &relay_str,
+                    &parent.pubkey.as_hex_string(),
+                ]));
+                GROUP_CHAT_MESSAGE
+            } else {
+                // The root is the thread, either the parent or the parent's root
+                let (root_id, root_kind, root_pubkey) = if parent_kind == COMMENT {
+                    let root_id = parent
+                        .tags
+                        .iter()
+                        .find(|t| t.tagname() == "E")
+                        .map(|t| t.value().to_owned())
+                        .unwrap_or(parent_id.as_hex_string());
+                    let root_kind = parent
+                        .tags
+                        .iter()
+                        .find(|t| t.tagname() == "K")
+                        .map(|t| t.value().to_owned())
+                        .unwrap_or(GROUP_THREAD.to_string());
+                    let root_pubkey = parent
+                        .tags
+                        .iter()
+                        .find(|t| t.tagname() == "P")
+                        .map(|t| t.value().to_owned())
+                        .unwrap_or(parent.pubkey.as_hex_string());
+                    (root_id, root_kind, root_pubkey)
+                } else {
+                    (
+                        parent_id.as_hex_string(),
+                        parent_kind.to_string(),
+                        parent.pubkey.as_hex_string(),
+                    )
+                };
+                tags.push(Tag::new(&["E", &root_id, &relay_str, &root_pubkey]));
+                tags.push(Tag::new(&["K", &root_kind]));
+                tags.push(Tag::new(&["P", &root_pubkey]));
+                tags.push(Tag::new(&[
+                    "e",
+                    &parent_id.as_hex_string(),
+                    &relay_str,
+                    &parent.pubkey.as_hex_string(),
+                ]));
+                tags.push(Tag::new(&["k", &parent_kind.to_string()]));
+                if parent.pubkey != author {
+                    nostr_types::add_pubkey_to_tags(&mut tags, parent.pubkey, None);
+                }
+                COMMENT
+            }
+        }
+        None => {
+            // A subject makes it a thread, with the subject as its title
+            let title = tags
+                .iter()
+                .find_map(|t| t.parse_subject().ok().map(|s| s.to_owned()));
+            match title {
+                Some(title) => {
+                    tags.retain(|t| t.parse_subject().is_err());
+                    tags.push(Tag::new(&["title", &title]));
+                    GROUP_THREAD
+                }
+                None => GROUP_CHAT_MESSAGE,
+            }
+        }
+    };
+
+    tags.push(Tag::new(&["h", &group]));
+
+    if annotation {
+        tags.push(Tag::new(&["annotation"]))
+    }
+
+    let pre_event = PreEvent {
+        pubkey: author,
+        created_at: Unixtime::now(),
+        kind: kind.into(),
+        tags,
+        content,
+    };
+
+    let event = GLOBALS.identity.sign_event(pre_event)?;
+
+    Ok(vec![(event, vec![relay])])
+}
+
+/// Prepare a post to a NIP-72 community. Posts are comments (kind 1111) whose root is
+/// the community definition; replies also reference the post they reply to. Posts go to
+/// our usual relays plus the community's relays.
+pub async fn prepare_post_community(
+    author: PublicKey,
+    content: String,
+    mut tags: Vec<Tag>,
+    community_author: PublicKey,
+    d: String,
+    in_reply_to: Option<Id>,
+    annotation: bool,
+) -> Result<Vec<(Event, Vec<RelayUrl>)>, Error> {
+    let community = match crate::Community::load(community_author, &d)? {
+        Some(c) => c,
+        None => return Err("Cannot find the community we are posting to.".into()),
+    };
+
+    add_gossip_tag(&mut tags);
+
+    add_tags_mirroring_content(&content, &mut tags, false).await;
+
+    let coordinate = community.coordinate();
+    let relay_str = community
+        .relays
+        .first()
+        .map(|r| r.as_str().to_owned())
+        .unwrap_or_default();
+    let community_kind = u32::from(EventKind::CommunityDefinition).to_string();
+    let owner = community.author.as_hex_string();
+
+    // The community is always the root
+    tags.push(Tag::new(&["A", &coordinate, &relay_str]));
+    tags.push(Tag::new(&["K", &community_kind]));
+    tags.push(Tag::new(&["P", &owner]));
+
+    match in_reply_to {
+        Some(parent_id) => {
+            let parent = match GLOBALS.db().read_event(parent_id)? {
+                Some(e) => e,
+                None => return Err("Cannot find event we are replying to.".into()),
+            };
+            tags.push(Tag::new(&[
+                "e",
+                &parent_id.as_hex_string(),
+                &relay_str,
+                &parent.pubkey.as_hex_string(),
+            ]));
+            tags.push(Tag::new(&["k", &u32::from(parent.kind).to_string()]));
+            if parent.pubkey != author {
+                nostr_types::add_pubkey_to_tags(&mut tags, parent.pubkey, None);
+            }
+        }
+        None => {
+            // A top level post has the community as its parent too
+            tags.push(Tag::new(&["a", &coordinate, &relay_str]));
+            tags.push(Tag::new(&["k", &community_kind]));
+            tags.push(Tag::new(&["p", &owner]));
+        }
+    }
+
+    if annotation {
+        tags.push(Tag::new(&["annotation"]))
+    }
+
+    let pre_event = PreEvent {
+        pubkey: author,
+        created_at: Unixtime::now(),
+        kind: crate::community::COMMENT.into(),
+        tags,
+        content,
+    };
+
+    let event = GLOBALS.identity.sign_event(pre_event)?;
+
+    let mut relays = relay::relays_to_post_to(&event)?;
+    for url in community.relays {
+        if !relays.contains(&url) {
+            relays.push(url);
+        }
+    }
+
+    Ok(vec![(event, relays)])
+}
+
 pub fn prepare_post_nip04(
     author: PublicKey,
     content: String,
@@ -143,6 +410,50 @@ pub async fn prepare_post_nip17(
         content,
     };
 
+    giftwrap_to_channel(pre_event, &dm_channel, our_pk)
+}
+
+/// Prepare a NIP-17 file message (kind 15) for a file we encrypted and uploaded
+pub fn prepare_file_message_nip17(
+    author: PublicKey,
+    url: String,
+    prepared: &PreparedUpload,
+    encrypted: &EncryptedFile,
+    dm_channel: DmChannel,
+) -> Result<Vec<(Event, Vec<RelayUrl>)>, Error> {
+    if !dm_channel.can_use_nip17() {
+        return Err(ErrorKind::UsersCantUseNip17.into());
+    }
+
+    let our_pk = match GLOBALS.db().read_setting_public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+
+    let mut tags: Vec<Tag> = Vec::new();
+    for pk in dm_channel.keys() {
+        nostr_types::add_pubkey_to_tags(&mut tags, *pk, None);
+    }
+    tags.extend(crate::nip17::file_message_tags(prepared, encrypted));
+    add_gossip_tag(&mut tags);
+
+    let pre_event = PreEvent {
+        pubkey: author,
+        created_at: Unixtime::now(),
+        kind: crate::nip17::FILE_MESSAGE.into(),
+        tags,
+        content: url,
+    };
+
+    giftwrap_to_channel(pre_event, &dm_channel, our_pk)
+}
+
+// Giftwrap a rumor to each member of a DM channel, and a copy to us
+fn giftwrap_to_channel(
+    pre_event: PreEvent,
+    dm_channel: &DmChannel,
+    our_pk: PublicKey,
+) -> Result<Vec<(Event, Vec<RelayUrl>)>, Error> {
     let mut output: Vec<(Event, Vec<RelayUrl>)> = Vec::new();
 
     // To all recipients
@@ -236,6 +547,9 @@ async fn add_tags_mirroring_content(content: &str, tags: &mut Vec<Tag>, direct_m
     for capture in GLOBALS.hashtag_regex.captures_iter(content) {
         tags.push(Tag::new_hashtag(capture[1][1..].to_string()));
     }
+
+    // Define the custom emoji we used
+    crate::emoji::add_emoji_tags(content, tags);
 }
 
 async fn add_imeta_tag(urlstr: &str, mimetype: &str, tags: &mut Vec<Tag>) {
@@ -245,6 +559,16 @@ async fn add_imeta_tag(urlstr: &str, mimetype: &str, tags: &mut Vec<Tag>) {
         _ => return,
     };
 
+    // We described our own uploads before uploading them
+    if let Some((hash, _)) = crate::blossom::blob_hash_of(urlstr) {
+        if let Some(file_metadata) = GLOBALS.upload_file_metadata.get(&hash) {
+            let mut imeta = file_metadata.clone();
+            imeta.url = url.to_unchecked_url();
+            tags.push(imeta.to_imeta_tag());
+            return;
+        }
+    }
+
     // Fetch the link and wait for it
     GLOBALS.fetcher.fetch(url.clone(), false).await;
 
@@ -280,18 +604,10 @@ async fn add_imeta_tag(urlstr: &str, mimetype: &str, tags: &mut Vec<Tag>) {
         imeta.x = Some(hash);
 
         if mimetype.starts_with("image") {
-            use image::{DynamicImage, GenericImageView};
+            use image::GenericImageView;
             if let Ok(dynamic_image) = image::load_from_memory(&bytes) {
                 let (w, h) = dynamic_image.dimensions();
-                // Convert to RGBA8
-                let dynamic_image = DynamicImage::ImageRgba8(dynamic_image.to_rgba8());
-                if let Ok(blurhash) = blurhash::encode(
-                    (4 * w / h).min(9),
-                    (4 * h / w).min(9),
-                    w,
-                    h,
-                    dynamic_image.as_bytes(),
-                ) {
+                if let Some(blurhash) = crate::upload::blurhash_of(&dynamic_image) {
                     imeta.blurhash = Some(blurhash);
                     imeta.dim = Some((w as usize, h as usize));
                 }
@@ -3,7 +3,7 @@ use crate::error::Error;
 use crate::globals::GLOBALS;
 use crate::people::{PersonList, PersonListMetadata};
 use crate::storage::table::Table;
-use nostr_types::{Event, RelayUrl};
+use nostr_types::{Event, NAddr, RelayUrl};
 
 // EventKind::Metadata
 pub fn process_metadata(event: &Event) -> Result<(), Error> {
@@ -13,14 +13,21 @@ pub fn process_metadata(event: &Event) -> Result<(), Error> {
     GLOBALS
         .people
         .update_metadata(&event.pubkey, metadata, event.created_at)?;
+    crate::nip39::process_identity_claims(event)?;
+    GLOBALS.people.forget_name_emojis(event.pubkey);
     Ok(())
 }
 
+// NIP-39 external identities (kind 10011)
+pub fn process_external_identities(event: &Event) -> Result<(), Error> {
+    crate::nip39::process_identity_claims(event)
+}
+
 // EventKind::HandlerRecommendation
 // Collect handler recommendations, then fetch the handler information
 pub fn process_handler_recommendation(event: &Event) -> Result<(), Error> {
     use crate::storage::types::HandlerKey;
-    use nostr_types::{EventKind, NAddr};
+    use nostr_types::EventKind;
 
     // NOTE: We don't care what 'd' kind is given, we collect these for all kinds.
 
@@ -251,23 +258,151 @@ pub fn process_nostr_connect(event: &Event, seen_on: Option<RelayUrl>) -> Result
 pub fn process_user_server_list(event: &Event, ours: bool) -> Result<(), Error> {
     if ours {
         // Update blossom servers
-        let mut servers: String = "".to_owned();
-        let mut virgin: bool = true;
-        for tag in &event.tags {
-            if tag.tagname() == "server" {
-                if !virgin {
-                    servers.push('\n');
+        let servers = crate::blossom::servers_of(event).join("\n");
+        GLOBALS.db().write_setting_blossom_servers(&servers, None)?;
+    }
+
+    Ok(())
+}
+
+// Content discovery DVM job result (kind 6300)
+pub fn process_dvm_result(event: &Event, seen_on: Option<&RelayUrl>) -> Result<(), Error> {
+    use nostr_types::EventReference;
+
+    // Only fetch the events of results to our own (latest) job requests
+    let ours = event.tags.iter().any(|tag| match tag.parse_event() {
+        Ok((id, _, _, _)) => GLOBALS.feed.is_dvm_job(id),
+        Err(_) => false,
+    });
+    if !ours {
+        return Ok(());
+    }
+
+    for eref in crate::dvm::result_references(event) {
+        match eref {
+            EventReference::Id { id, mut relays, .. } => {
+                if GLOBALS.db().read_event(id)?.is_none() {
+                    if relays.is_empty() {
+                        relays.extend(seen_on.cloned());
+                    }
+                    let _ = GLOBALS
+                        .to_overlord
+                        .send(ToOverlordMessage::FetchEvent(id, relays));
+                }
+            }
+            EventReference::Addr(mut ea) => {
+                if let Ok(None) = GLOBALS
+                    .db()
+                    .get_replaceable_event(ea.kind, ea.author, &ea.d)
+                {
+                    if let Some(seen_on_url) = seen_on {
+                        let seen_on_unchecked_url = seen_on_url.to_unchecked_url();
+                        if !ea.relays.contains(&seen_on_unchecked_url) {
+                            ea.relays.push(seen_on_unchecked_url);
+                        }
+                    }
+                    let _ = GLOBALS.to_overlord.send(ToOverlordMessage::FetchNAddr(ea));
                 }
-                servers.push_str(tag.value());
-                virgin = false;
             }
         }
-        GLOBALS.db().write_setting_blossom_servers(&servers, None)?;
     }
 
     Ok(())
 }
 
+// EventKind::CommunityPostApproval
+pub fn process_community_post_approval(
+    event: &Event,
+    seen_on: Option<&RelayUrl>,
+    verify: bool,
+) -> Result<(), Error> {
+    // Approvals carry the post they approve, so we usually don't need to fetch it
+    if let Some(post) = crate::community::approved_post(event) {
+        if GLOBALS.db().read_event(post.id)?.is_none() {
+            crate::process::process_new_event(&post, None, None, verify, false)?;
+        }
+        return Ok(());
+    }
+
+    for tag in &event.tags {
+        if let Ok((id, _, _, _)) = tag.parse_event() {
+            if GLOBALS.db().read_event(id)?.is_none() {
+                let _ = GLOBALS.to_overlord.send(ToOverlordMessage::FetchEvent(
+                    id,
+                    seen_on.cloned().into_iter().collect(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// EventKind::BadgeAward
+// Fetch the definition of the badge if we don't have it
+pub fn process_badge_award(event: &Event, seen_on: Option<&RelayUrl>) -> Result<(), Error> {
+    if let Some(naddr) = crate::badge::awarded_badge(event) {
+        fetch_naddr_if_missing(naddr, seen_on)?;
+    }
+    Ok(())
+}
+
+// EventKind::ProfileBadges
+// Fetch the awards and badge definitions that we don't have
+pub fn process_profile_badges(event: &Event, seen_on: Option<&RelayUrl>) -> Result<(), Error> {
+    for (naddr, id) in crate::badge::pairs_of(event) {
+        if GLOBALS.db().read_event(id)?.is_none() {
+            let _ = GLOBALS.to_overlord.send(ToOverlordMessage::FetchEvent(
+                id,
+                seen_on.cloned().into_iter().collect(),
+            ));
+        }
+        fetch_naddr_if_missing(naddr, seen_on)?;
+    }
+    Ok(())
+}
+
+// EventKind::UserEmojiList
+// Fetch the emoji sets our list references that we don't have
+pub fn process_emoji_list(
+    event: &Event,
+    ours: bool,
+    seen_on: Option<&RelayUrl>,
+) -> Result<(), Error> {
+    use nostr_types::EventKind;
+
+    if !ours {
+        return Ok(());
+    }
+    for tag in event.tags.iter() {
+        if let Ok((naddr, _)) = tag.parse_address() {
+            if naddr.kind == EventKind::EmojiSets {
+                fetch_naddr_if_missing(naddr, seen_on)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn fetch_naddr_if_missing(mut naddr: NAddr, seen_on: Option<&RelayUrl>) -> Result<(), Error> {
+    if GLOBALS
+        .db()
+        .get_replaceable_event(naddr.kind, naddr.author, &naddr.d)?
+        .is_none()
+    {
+        if let Some(url) = seen_on {
+            let unchecked_url = url.to_unchecked_url();
+            if !naddr.relays.contains(&unchecked_url) {
+                naddr.relays.push(unchecked_url);
+            }
+        }
+        let _ = GLOBALS
+            .to_overlord
+            .send(ToOverlordMessage::FetchNAddr(naddr));
+    }
+    Ok(())
+}
+
 pub fn process_somebody_elses_contact_list(event: &Event, force: bool) -> Result<(), Error> {
     use crate::people::PersonList;
     use crate::storage::Storage;
@@ -219,6 +219,11 @@ pub fn process_new_event(
         }
     }
 
+    // Index DMs for search
+    if let Err(e) = crate::dm_search::index(event) {
+        tracing::warn!("{}", e);
+    }
+
     // Create referenced relays and people, and update person_relay associations
     if seen_on.is_some() {
         for tag in event.tags.iter() {
@@ -257,7 +262,7 @@ pub fn process_new_event(
     let invalid_ids = process_relationships_of_event(event, None)?;
     GLOBALS.ui_notes_to_invalidate.write().extend(&invalid_ids);
 
-    if event.kind.is_feed_displayable() {
+    if event.kind.is_feed_displayable() || crate::nip71::is_video(event.kind) {
         process_feed_displayable_content(event, seen_on.as_ref(), now)?;
     }
 
@@ -280,6 +285,20 @@ pub fn process_new_event(
         EventKind::Repost => by_kind::process_repost(event, verify)?,
         EventKind::NostrConnect => by_kind::process_nostr_connect(event, seen_on.clone())?,
         EventKind::UserServerList => by_kind::process_user_server_list(event, ours)?,
+        EventKind::UserStatus => GLOBALS.people.forget_statuses(event.pubkey),
+        EventKind::BadgeAward => by_kind::process_badge_award(event, seen_on.as_ref())?,
+        EventKind::ProfileBadges => by_kind::process_profile_badges(event, seen_on.as_ref())?,
+        EventKind::UserEmojiList => by_kind::process_emoji_list(event, ours, seen_on.as_ref())?,
+        EventKind::Timestamp => crate::ots::process_attestation(event),
+        EventKind::CommunityPostApproval => {
+            by_kind::process_community_post_approval(event, seen_on.as_ref(), verify)?
+        }
+        _ if u32::from(event.kind) == crate::nip39::EXTERNAL_IDENTITIES => {
+            by_kind::process_external_identities(event)?
+        }
+        _ if u32::from(event.kind) == crate::dvm::CONTENT_DISCOVERY_RESULT => {
+            by_kind::process_dvm_result(event, seen_on.as_ref())?
+        }
         _ => {}
     }
 
@@ -671,6 +690,29 @@ pub(crate) fn process_relationships_of_event(
         }
     }
 
+    // Community post approvals
+    if event.kind == EventKind::CommunityPostApproval {
+        let in_community = event
+            .tags
+            .iter()
+            .find(|tag| tag.tagname() == "a")
+            .map(|tag| tag.value().to_owned());
+        if let Some(in_community) = in_community {
+            for tag in &event.tags {
+                if let Ok((id, _, _, _)) = tag.parse_event() {
+                    GLOBALS.db().write_relationship_by_id(
+                        id,
+                        event.id,
+                        RelationshipById::Approves {
+                            in_community: in_community.clone(),
+                        },
+                        Some(txn),
+                    )?;
+                }
+            }
+        }
+    }
+
     // JobResult
     if event.kind.is_job_result() {
         for tag in &event.tags {
@@ -211,9 +211,44 @@ pub fn relays_to_post_to(event: &Event) -> Result<Vec<RelayUrl>, Error> {
     relays.sort();
     relays.dedup();
 
+    // Relays only accept protected events from their authenticated author
+    if is_protected(event) {
+        let mut auth_relays: Vec<RelayUrl> = Vec::new();
+        for url in relays.drain(..) {
+            let relay = GLOBALS.db().read_or_create_relay(&url, None)?;
+            if supports_auth(&relay) {
+                auth_relays.push(url);
+            } else {
+                GLOBALS.status_queue.write().write(format!(
+                    "Not posting protected event to {}, it does not support AUTH (NIP-42)",
+                    url
+                ));
+            }
+        }
+        relays = auth_relays;
+    }
+
     Ok(relays)
 }
 
+/// Is this event protected (NIP-70), so only its author may publish it to relays?
+pub fn is_protected(event: &Event) -> bool {
+    event.tags.iter().any(|t| t.tagname() == "-")
+}
+
+/// Can we AUTH (NIP-42) to this relay? Either it says so, or we already have.
+pub fn supports_auth(relay: &Relay) -> bool {
+    if relay.allow_auth == Some(false) {
+        return false;
+    }
+    relay.allow_auth == Some(true)
+        || relay
+            .nip11
+            .as_ref()
+            .map(|doc| doc.supported_nips.contains(&42))
+            .unwrap_or(false)
+}
+
 /// Only RelayUsage::Outbox and RelayUsage::Inbox are supported.
 ///
 /// Output scores range from 0.0 to 1.0
@@ -19,6 +19,7 @@ pub enum EventFilterCaller {
     Thread,
     Inbox,
     Global,
+    Custom,
 }
 
 #[derive(Debug, Clone)]
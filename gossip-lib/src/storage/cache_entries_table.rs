@@ -0,0 +1,50 @@
+use super::types::CacheEntry1;
+use super::Table;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use heed::types::Bytes;
+use heed::Database;
+use std::sync::Mutex;
+
+static CACHE_ENTRIES_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut CACHE_ENTRIES_DB: Option<Database<Bytes, Bytes>> = None;
+
+pub struct CacheEntriesTable {}
+
+impl Table for CacheEntriesTable {
+    type Item = CacheEntry1;
+
+    fn lmdb_name() -> &'static str {
+        "cache_entries"
+    }
+
+    fn db() -> Result<Database<Bytes, Bytes>, Error> {
+        unsafe {
+            if let Some(db) = CACHE_ENTRIES_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = CACHE_ENTRIES_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = CACHE_ENTRIES_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = GLOBALS.db().env.write_txn()?;
+                let db = GLOBALS
+                    .db()
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    .name(Self::lmdb_name())
+                    .create(&mut txn)?;
+                txn.commit()?;
+                CACHE_ENTRIES_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+}
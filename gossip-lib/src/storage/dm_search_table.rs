@@ -0,0 +1,50 @@
+use super::types::DmSearchEntry1;
+use super::Table;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use heed::types::Bytes;
+use heed::Database;
+use std::sync::Mutex;
+
+static DM_SEARCH_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut DM_SEARCH_DB: Option<Database<Bytes, Bytes>> = None;
+
+pub struct DmSearchTable {}
+
+impl Table for DmSearchTable {
+    type Item = DmSearchEntry1;
+
+    fn lmdb_name() -> &'static str {
+        "dm_search"
+    }
+
+    fn db() -> Result<Database<Bytes, Bytes>, Error> {
+        unsafe {
+            if let Some(db) = DM_SEARCH_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = DM_SEARCH_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = DM_SEARCH_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = GLOBALS.db().env.write_txn()?;
+                let db = GLOBALS
+                    .db()
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    .name(Self::lmdb_name())
+                    .create(&mut txn)?;
+                txn.commit()?;
+                DM_SEARCH_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+}
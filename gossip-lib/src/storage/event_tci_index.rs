@@ -8,7 +8,7 @@ use std::sync::Mutex;
 // This replaces event_tci_index which didn't have the
 // reverse created_at suffix.
 
-pub(super) const INDEXED_TAGS: [&str; 4] = ["a", "d", "p", "delegation"];
+pub(super) const INDEXED_TAGS: [&str; 5] = ["a", "d", "h", "p", "delegation"];
 
 // This indexes these tags, except for "p" tags we only index it if
 //   1) the "p" tag is our user, or
@@ -65,7 +65,6 @@ impl Storage {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub(crate) fn get_event_ids_with_hashtag1(&self, hashtag: &String) -> Result<Vec<Id>, Error> {
         let key = key!(hashtag.as_bytes());
         if key.is_empty() {
@@ -0,0 +1,50 @@
+use super::types::IdentityClaim1;
+use super::Table;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use heed::types::Bytes;
+use heed::Database;
+use std::sync::Mutex;
+
+static IDENTITY_CLAIMS_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut IDENTITY_CLAIMS_DB: Option<Database<Bytes, Bytes>> = None;
+
+pub struct IdentityClaimsTable {}
+
+impl Table for IdentityClaimsTable {
+    type Item = IdentityClaim1;
+
+    fn lmdb_name() -> &'static str {
+        "identity_claims"
+    }
+
+    fn db() -> Result<Database<Bytes, Bytes>, Error> {
+        unsafe {
+            if let Some(db) = IDENTITY_CLAIMS_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = IDENTITY_CLAIMS_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = IDENTITY_CLAIMS_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = GLOBALS.db().env.write_txn()?;
+                let db = GLOBALS
+                    .db()
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    .name(Self::lmdb_name())
+                    .create(&mut txn)?;
+                txn.commit()?;
+                IDENTITY_CLAIMS_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use crate::error::Error;
+use crate::storage::Storage;
+use heed::RwTxn;
+use nostr_types::Event;
+use speedy::Readable;
+
+impl Storage {
+    pub(super) fn m49_trigger(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    pub(super) fn m49_migrate<'a>(
+        &'a self,
+        prefix: &str,
+        txn: &mut RwTxn<'a>,
+    ) -> Result<(), Error> {
+        // Info message
+        tracing::info!("{prefix}: Rebuilding hashtag index (hashtags are now lowercase)...");
+
+        // Actually do the migration
+        self.m49_rebuild_hashtags(txn)?;
+
+        Ok(())
+    }
+
+    fn m49_rebuild_hashtags<'a>(&'a self, txn: &mut RwTxn<'a>) -> Result<(), Error> {
+        self.db_hashtags()?.clear(txn)?;
+
+        let loop_txn = self.env.read_txn()?;
+        for result in self.db_events()?.iter(&loop_txn)? {
+            let (_key, val) = result?;
+            let event = Event::read_from_buffer(val)?;
+            for hashtag in event.hashtags() {
+                if hashtag.is_empty() {
+                    continue;
+                } // upstream bug
+                self.add_hashtag(&hashtag, event.id, Some(txn))?;
+            }
+        }
+
+        Ok(())
+    }
+}
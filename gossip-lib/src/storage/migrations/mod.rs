@@ -31,6 +31,8 @@ mod m44;
 mod m45;
 mod m46;
 mod m47;
+mod m48;
+mod m49;
 
 use super::Storage;
 use crate::error::{Error, ErrorKind};
@@ -38,7 +40,7 @@ use heed::RwTxn;
 
 impl Storage {
     const MIN_MIGRATION_LEVEL: u32 = 23;
-    const MAX_MIGRATION_LEVEL: u32 = 47;
+    const MAX_MIGRATION_LEVEL: u32 = 49;
 
     /// Initialize the database from empty
     pub(super) fn init_from_empty(&self) -> Result<(), Error> {
@@ -138,6 +140,8 @@ impl Storage {
             45 => self.m45_trigger()?,
             46 => self.m46_trigger()?,
             47 => self.m47_trigger()?,
+            48 => self.m48_trigger()?,
+            49 => self.m49_trigger()?,
             _ => panic!("Unreachable migration level"),
         }
 
@@ -176,6 +180,8 @@ impl Storage {
             45 => self.m45_migrate(&prefix, txn)?,
             46 => self.m46_migrate(&prefix, txn)?,
             47 => self.m47_migrate(&prefix, txn)?,
+            48 => self.m48_migrate(&prefix, txn)?,
+            49 => self.m49_migrate(&prefix, txn)?,
             _ => panic!("Unreachable migration level"),
         };
 
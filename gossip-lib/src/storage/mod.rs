@@ -22,6 +22,20 @@ pub mod followings_table;
 pub use followings_table::FollowingsTable;
 pub mod handlers_table;
 pub use handlers_table::HandlersTable;
+pub mod custom_feeds_table;
+pub use custom_feeds_table::CustomFeedsTable;
+pub mod groups_table;
+pub use groups_table::GroupsTable;
+pub mod identity_claims_table;
+pub use identity_claims_table::IdentityClaimsTable;
+pub mod ots_proofs_table;
+pub use ots_proofs_table::OtsProofsTable;
+pub mod cache_entries_table;
+pub use cache_entries_table::CacheEntriesTable;
+pub mod dm_conversations_table;
+pub use dm_conversations_table::DmConversationsTable;
+pub mod dm_search_table;
+pub use dm_search_table::DmSearchTable;
 
 // database implementations
 mod configured_handlers;
@@ -60,7 +74,7 @@ mod relays3;
 mod unindexed_giftwraps1;
 mod versioned;
 
-use crate::dm_channel::{DmChannel, DmChannelData};
+use crate::dm_channel::{DmChannel, DmChannelData, DmConversation};
 use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use crate::misc::Private;
@@ -123,7 +137,7 @@ impl Storage {
         }
 
         // builder.max_readers(126); // this is the default
-        builder.max_dbs(32);
+        builder.max_dbs(48);
 
         // This has to be big enough for all the data.
         // Note that it is the size of the map in VIRTUAL address space,
@@ -267,6 +281,13 @@ impl Storage {
         let _ = PersonTable::db()?;
         let _ = FollowingsTable::db()?;
         let _ = HandlersTable::db()?;
+        let _ = CustomFeedsTable::db()?;
+        let _ = GroupsTable::db()?;
+        let _ = IdentityClaimsTable::db()?;
+        let _ = OtsProofsTable::db()?;
+        let _ = CacheEntriesTable::db()?;
+        let _ = DmConversationsTable::db()?;
+        let _ = DmSearchTable::db()?;
 
         // Do migrations
         match self.read_migration_level()? {
@@ -592,6 +613,7 @@ impl Storage {
         true
     );
     def_flag!(rebuild_fof_needed, b"rebuild_fof_needed", true);
+    def_flag!(rebuild_dm_search_needed, b"rebuild_dm_search_needed", true);
 
     // Settings ----------------------------------------------------------
 
@@ -604,6 +626,7 @@ impl Storage {
     def_setting!(load_avatars, b"load_avatars", bool, true);
     def_setting!(load_media, b"load_media", bool, true);
     def_setting!(check_nip05, b"check_nip05", bool, true);
+    def_setting!(check_nip39, b"check_nip39", bool, true);
     def_setting!(wgpu_renderer, b"wgpu_renderer", bool, false);
     def_setting!(
         automatically_fetch_metadata,
@@ -638,8 +661,15 @@ impl Storage {
         60 * 15
     );
     def_setting!(hide_mutes_entirely, b"hide_mutes_entirely", bool, true);
+    def_setting!(hide_downvoted, b"hide_downvoted", bool, false);
     def_setting!(reactions, b"reactions", bool, true);
     def_setting!(enable_zap_receipts, b"enable_zap_receipts", bool, true);
+    def_setting!(
+        count_note_interactions,
+        b"count_note_interactions",
+        bool,
+        false
+    );
     def_setting!(show_media, b"show_media", bool, true);
     def_setting!(
         approve_content_warning,
@@ -794,8 +824,16 @@ impl Storage {
         u64,
         600
     );
+    def_setting!(
+        fetcher_auth_hosts,
+        b"fetcher_auth_hosts",
+        String,
+        "".to_string()
+    );
     def_setting!(prune_period_days, b"prune_period_days", u64, 90);
     def_setting!(cache_prune_period_days, b"cache_prune_period_days", u64, 90);
+    def_setting!(cache_avatar_budget_mb, b"cache_avatar_budget_mb", u64, 200);
+    def_setting!(cache_media_budget_mb, b"cache_media_budget_mb", u64, 2000);
     def_setting!(
         avoid_spam_on_unsafe_relays,
         b"avoid_spam_on_unsafe_relays",
@@ -827,6 +865,35 @@ impl Storage {
         false
     );
     def_setting!(blossom_servers, b"blossom_servers", String, "".to_string());
+    def_setting!(nip96_servers, b"nip96_servers", String, "".to_string());
+    def_setting!(upload_server, b"upload_server", String, "".to_string());
+    def_setting!(upload_strip_metadata, b"upload_strip_metadata", bool, true);
+    def_setting!(upload_max_dimension, b"upload_max_dimension", u32, 0);
+    def_setting!(
+        upload_publish_file_metadata,
+        b"upload_publish_file_metadata",
+        bool,
+        false
+    );
+    def_setting!(
+        ots_headers_file,
+        b"ots_headers_file",
+        String,
+        "".to_string()
+    );
+    def_setting!(
+        ots_bitcoind_url,
+        b"ots_bitcoind_url",
+        String,
+        "".to_string()
+    );
+    def_setting!(
+        ots_calendar_url,
+        b"ots_calendar_url",
+        String,
+        "https://alice.btc.calendar.opentimestamps.org".to_string()
+    );
+    def_setting!(timestamp_own_posts, b"timestamp_own_posts", bool, false);
 
     // -------------------------------------------------------------------
 
@@ -958,7 +1025,7 @@ impl Storage {
         self.is_event_viewed1(id)
     }
 
-    /// Associate a hashtag to an event
+    /// Associate a hashtag to an event. Hashtags are indexed in lowercase.
     #[inline]
     pub fn add_hashtag<'a>(
         &'a self,
@@ -966,14 +1033,13 @@ impl Storage {
         id: Id,
         rw_txn: Option<&mut RwTxn<'a>>,
     ) -> Result<(), Error> {
-        self.add_hashtag1(hashtag, id, rw_txn)
+        self.add_hashtag1(&hashtag.to_lowercase(), id, rw_txn)
     }
 
-    /// Get events with a given hashtag
+    /// Get events with a given hashtag (case insensitive)
     #[inline]
-    #[allow(dead_code)]
     pub fn get_event_ids_with_hashtag(&self, hashtag: &String) -> Result<Vec<Id>, Error> {
-        self.get_event_ids_with_hashtag1(hashtag)
+        self.get_event_ids_with_hashtag1(&hashtag.to_lowercase())
     }
 
     /// Write a relay record.
@@ -2002,18 +2068,15 @@ impl Storage {
             .collect())
     }
 
-    /// Returns the list of reactions and whether or not this account has already reacted to this event
-    #[allow(clippy::type_complexity)]
-    pub fn get_reactions(&self, id: Id) -> Result<(Vec<(char, usize)>, Option<char>), Error> {
-        // Whether or not the Gossip user already reacted to this event
-        let mut our_reaction: Option<char> = None;
-
+    /// Returns who reacted to this event with what (at most one reaction per person), with
+    /// the id of each reaction. Empty reactions are returned as '+'.
+    pub fn get_reactors(&self, id: Id) -> Result<Vec<(PublicKey, String, Id)>, Error> {
         // Get the event (once self-reactions get deleted we can remove this)
         let maybe_target_event = self.read_event(id)?;
 
         // Collect up to one reaction per pubkey
-        let mut phase1: HashMap<PublicKey, char> = HashMap::new();
-        for (_, rel) in self.find_relationships_by_id(id)? {
+        let mut reactors: HashMap<PublicKey, (String, Id)> = HashMap::new();
+        for (reaction_id, rel) in self.find_relationships_by_id(id)? {
             if let RelationshipById::ReactsTo { by, reaction } = rel {
                 if let Some(target_event) = &maybe_target_event {
                     if target_event.pubkey == by {
@@ -2021,30 +2084,39 @@ impl Storage {
                         continue;
                     }
                 }
-                let symbol: char = if reaction.is_empty() {
-                    '+'
-                } else if reaction.starts_with(":") && reaction.ends_with(":") {
-                    '□' // placeholder for custom reaction that we don't support
+                let reaction = if reaction.is_empty() {
+                    "+".to_owned()
                 } else {
-                    reaction.chars().next().unwrap()
+                    reaction
                 };
-                phase1.insert(by, symbol);
-                if Some(by) == GLOBALS.identity.public_key() {
-                    our_reaction = Some(symbol);
-                }
+                reactors.insert(by, (reaction, reaction_id));
             }
         }
 
+        Ok(reactors
+            .drain()
+            .map(|(by, (reaction, reaction_id))| (by, reaction, reaction_id))
+            .collect())
+    }
+
+    /// Returns the list of reactions and whether or not this account has already reacted to this event
+    pub fn get_reactions(&self, id: Id) -> Result<(Vec<(String, usize)>, Option<String>), Error> {
+        // Whether or not the Gossip user already reacted to this event
+        let mut our_reaction: Option<String> = None;
+
         // Collate by reaction
-        let mut output: HashMap<char, usize> = HashMap::new();
-        for (_, symbol) in phase1 {
+        let mut output: HashMap<String, usize> = HashMap::new();
+        for (by, reaction, _) in self.get_reactors(id)? {
+            if Some(by) == GLOBALS.identity.public_key() {
+                our_reaction = Some(reaction.clone());
+            }
             output
-                .entry(symbol)
+                .entry(reaction)
                 .and_modify(|count| *count += 1)
                 .or_insert_with(|| 1);
         }
 
-        let mut v: Vec<(char, usize)> = output.drain().collect();
+        let mut v: Vec<(String, usize)> = output.drain().collect();
         v.sort();
         Ok((v, our_reaction))
     }
@@ -2284,82 +2356,126 @@ impl Storage {
         // Map from channel to latest-message-time and unread-count
         let mut map: HashMap<DmChannel, DmChannelData> = HashMap::new();
 
+        // What we keep about each conversation, and the latest subject set in each
+        let mut conversations: HashMap<DmChannel, DmConversation> = HashMap::new();
+        let mut subjects: HashMap<DmChannel, (Unixtime, String)> = HashMap::new();
+
         for event in &events {
-            let unread: usize = if event.pubkey == my_pubkey {
-                // Do not count self-authored events as unread, irrespective of whether they are viewed
-                0
-            } else {
-                1 - self.is_event_viewed(event.id)? as usize
-            };
-            if event.kind == EventKind::EncryptedDirectMessage {
-                let time = event.created_at;
+            // The channel, the time, and the content of the message
+            let (dmchannel, time, content) = if event.kind == EventKind::EncryptedDirectMessage {
                 let dmchannel = match DmChannel::from_event(event, Some(my_pubkey)) {
                     Some(dmc) => dmc,
                     None => continue,
                 };
-                if let Some(dmcdata) = map.get_mut(&dmchannel) {
-                    if time > dmcdata.latest_message_created_at {
-                        dmcdata.latest_message_created_at = time;
-                        dmcdata.latest_message_content =
-                            GLOBALS.identity.decrypt_event_contents(event).ok();
-                    }
-                    dmcdata.message_count += 1;
-                    dmcdata.unread_message_count += unread;
-                } else {
-                    map.insert(
-                        dmchannel.clone(),
-                        DmChannelData {
-                            dm_channel: dmchannel,
-                            latest_message_created_at: time,
-                            latest_message_content: GLOBALS
-                                .identity
-                                .decrypt_event_contents(event)
-                                .ok(),
-                            message_count: 1,
-                            unread_message_count: unread,
-                        },
-                    );
-                }
+                let content = GLOBALS.identity.decrypt_event_contents(event).ok();
+                (dmchannel, event.created_at, content)
             } else if event.kind == EventKind::GiftWrap {
-                if let Ok(rumor) = GLOBALS.identity.unwrap_giftwrap(event) {
-                    let rumor_event = rumor.into_event_with_bad_signature();
-                    let time = rumor_event.created_at;
-                    let dmchannel = match DmChannel::from_event(&rumor_event, Some(my_pubkey)) {
-                        Some(dmc) => dmc,
-                        None => continue,
-                    };
-                    if let Some(dmcdata) = map.get_mut(&dmchannel) {
-                        if time > dmcdata.latest_message_created_at {
-                            dmcdata.latest_message_created_at = time;
-                            dmcdata.latest_message_content = Some(rumor_event.content.clone());
-                        }
-                        dmcdata.message_count += 1;
-                        dmcdata.unread_message_count += unread;
-                    } else {
-                        map.insert(
-                            dmchannel.clone(),
-                            DmChannelData {
-                                dm_channel: dmchannel,
-                                latest_message_created_at: time,
-                                latest_message_content: Some(rumor_event.content.clone()),
-                                message_count: 1,
-                                unread_message_count: unread,
-                            },
-                        );
+                let rumor = match GLOBALS.identity.unwrap_giftwrap(event) {
+                    Ok(rumor) => rumor,
+                    Err(_) => continue,
+                };
+                let rumor_event = rumor.into_event_with_bad_signature();
+                let dmchannel = match DmChannel::from_event(&rumor_event, Some(my_pubkey)) {
+                    Some(dmc) => dmc,
+                    None => continue,
+                };
+                let time = rumor_event.created_at;
+
+                // NIP-17: the latest subject names the conversation
+                if let Some(tag) = rumor_event.tags.iter().find(|t| t.tagname() == "subject") {
+                    let subject = tag.value().trim();
+                    if !subject.is_empty()
+                        && subjects.get(&dmchannel).is_none_or(|(at, _)| time > *at)
+                    {
+                        subjects.insert(dmchannel.clone(), (time, subject.to_owned()));
                     }
                 }
+
+                // Show files as such, not as the URL of their ciphertext
+                let content = if crate::nip17::is_file_message(rumor_event.kind) {
+                    "[File]".to_owned()
+                } else {
+                    rumor_event.content.clone()
+                };
+                (dmchannel, time, Some(content))
+            } else {
+                continue;
+            };
+
+            let conversation = conversations
+                .entry(dmchannel.clone())
+                .or_insert_with(|| dmchannel.conversation());
+
+            let unread: usize = if event.pubkey == my_pubkey {
+                // Do not count self-authored events as unread, irrespective of whether they are viewed
+                0
+            } else if time.0 <= conversation.read_until {
+                // The conversation was read past this
+                0
+            } else {
+                1 - self.is_event_viewed(event.id)? as usize
+            };
+
+            if let Some(dmcdata) = map.get_mut(&dmchannel) {
+                if time > dmcdata.latest_message_created_at {
+                    dmcdata.latest_message_created_at = time;
+                    dmcdata.latest_message_content = content;
+                }
+                dmcdata.message_count += 1;
+                dmcdata.unread_message_count += unread;
+            } else {
+                map.insert(
+                    dmchannel.clone(),
+                    DmChannelData {
+                        dm_channel: dmchannel,
+                        latest_message_created_at: time,
+                        latest_message_content: content,
+                        message_count: 1,
+                        unread_message_count: unread,
+                        subject: conversation.subject.clone(),
+                        muted: conversation.muted,
+                        archived: conversation.archived,
+                        pinned: conversation.pinned,
+                        continued: conversation.successor.is_some(),
+                    },
+                );
+            }
+        }
+
+        // Keep the subjects we found
+        for (dmchannel, (time, subject)) in subjects.drain() {
+            dmchannel.saw_subject(&subject, time)?;
+            if let Some(dmcdata) = map.get_mut(&dmchannel) {
+                dmcdata.subject = dmchannel.conversation().subject;
             }
         }
 
         let mut output: Vec<DmChannelData> = map.drain().map(|e| e.1).collect();
         output.sort_by(|a, b| {
-            b.latest_message_created_at
-                .cmp(&a.latest_message_created_at)
+            b.pinned
+                .cmp(&a.pinned)
+                .then(
+                    b.latest_message_created_at
+                        .cmp(&a.latest_message_created_at),
+                )
                 .then(b.unread_message_count.cmp(&a.unread_message_count))
         });
         Ok(output)
     }
 
+    /// Mark all the DMs in a channel as read
+    pub fn mark_dm_channel_read(&self, channel: &DmChannel) -> Result<(), Error> {
+        let ids = self.dm_events(channel)?;
+
+        let mut txn = self.get_write_txn()?;
+        for id in ids {
+            self.mark_event_viewed(id, Some(&mut txn))?;
+        }
+        txn.commit()?;
+
+        Ok(())
+    }
+
     /// Get DM events (by id) in a channel
     pub fn dm_events(&self, channel: &DmChannel) -> Result<Vec<Id>, Error> {
         let my_pubkey = match GLOBALS.identity.public_key() {
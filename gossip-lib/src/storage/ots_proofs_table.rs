@@ -0,0 +1,50 @@
+use super::types::OtsProof1;
+use super::Table;
+use crate::error::Error;
+use crate::globals::GLOBALS;
+use heed::types::Bytes;
+use heed::Database;
+use std::sync::Mutex;
+
+static OTS_PROOFS_DB_CREATE_LOCK: Mutex<()> = Mutex::new(());
+static mut OTS_PROOFS_DB: Option<Database<Bytes, Bytes>> = None;
+
+pub struct OtsProofsTable {}
+
+impl Table for OtsProofsTable {
+    type Item = OtsProof1;
+
+    fn lmdb_name() -> &'static str {
+        "ots_proofs"
+    }
+
+    fn db() -> Result<Database<Bytes, Bytes>, Error> {
+        unsafe {
+            if let Some(db) = OTS_PROOFS_DB {
+                Ok(db)
+            } else {
+                // Lock.  This drops when anything returns.
+                let _lock = OTS_PROOFS_DB_CREATE_LOCK.lock();
+
+                // In case of a race, check again
+                if let Some(db) = OTS_PROOFS_DB {
+                    return Ok(db);
+                }
+
+                // Create it. We know that nobody else is doing this and that
+                // it cannot happen twice.
+                let mut txn = GLOBALS.db().env.write_txn()?;
+                let db = GLOBALS
+                    .db()
+                    .env
+                    .database_options()
+                    .types::<Bytes, Bytes>()
+                    .name(Self::lmdb_name())
+                    .create(&mut txn)?;
+                txn.commit()?;
+                OTS_PROOFS_DB = Some(db);
+                Ok(db)
+            }
+        }
+    }
+}
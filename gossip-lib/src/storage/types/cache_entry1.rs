@@ -0,0 +1,54 @@
+use super::{ByteRep, Record};
+use crate::error::Error;
+use speedy::{Readable, Writable};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// A file in the cache of downloaded files
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct CacheEntry1 {
+    /// The name of the cache file (the SHA-256 of the URL, hex)
+    pub key: String,
+
+    /// The URL it was downloaded from (empty if it was cached before we kept track)
+    pub url: String,
+
+    /// The host it was downloaded from
+    pub host: String,
+
+    /// The size of the file
+    pub size: u64,
+
+    /// The SHA-256 of the content (hex)
+    pub sha256: String,
+
+    /// Whether it is an avatar (which has its own budget)
+    pub avatar: bool,
+
+    /// When it was last used
+    pub last_access: i64,
+}
+
+impl ByteRep for CacheEntry1 {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.write_to_vec()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::read_from_buffer(bytes)?)
+    }
+}
+
+impl Record for CacheEntry1 {
+    type Key = String;
+
+    /// Entries are only created for files we have
+    fn new(_k: Self::Key) -> Option<Self> {
+        None
+    }
+
+    /// Get the key of a record
+    fn key(&self) -> Self::Key {
+        self.key.clone()
+    }
+}
@@ -0,0 +1,167 @@
+use super::{ByteRep, PersonList1, Record};
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use nostr_types::{Event, EventKind, Filter, PublicKey, RelayUrl};
+use speedy::{Readable, Writable};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// A user-defined feed, which is a saved filter
+#[derive(Debug, Clone, Default, PartialEq, Readable, Writable)]
+pub struct CustomFeed1 {
+    /// The name of the feed (this is the key)
+    pub name: String,
+
+    /// Authors to include
+    pub authors: Vec<PublicKey>,
+
+    /// Person lists whose members are included as authors
+    pub lists: Vec<PersonList1>,
+
+    /// Event kinds to include. If empty, all feed displayable kinds are included.
+    pub kinds: Vec<EventKind>,
+
+    /// Hashtags (lowercase, without the '#'), any of which must be present
+    pub hashtags: Vec<String>,
+
+    /// Relays to subscribe to. If empty, our read relays are used.
+    pub relays: Vec<RelayUrl>,
+
+    /// Keywords, any of which must be present in the content (case insensitive)
+    pub include: Vec<String>,
+
+    /// Keywords, none of which may be present in the content (case insensitive)
+    pub exclude: Vec<String>,
+
+    /// Whether to include replies
+    pub include_replies: bool,
+
+    /// Whether to run the spam filter (with caller "Custom")
+    pub apply_spam_filter: bool,
+}
+
+impl CustomFeed1 {
+    /// All the authors, including the members of the lists
+    pub fn all_authors(&self) -> Result<Vec<PublicKey>, Error> {
+        let mut authors = self.authors.clone();
+        for list in self.lists.iter() {
+            for (pubkey, _) in GLOBALS.db().get_people_in_list(*list)? {
+                if !authors.contains(&pubkey) {
+                    authors.push(pubkey);
+                }
+            }
+        }
+        Ok(authors)
+    }
+
+    /// The event kinds, defaulting to all feed displayable kinds
+    pub fn event_kinds(&self) -> Vec<EventKind> {
+        if self.kinds.is_empty() {
+            crate::feed_displayable_event_kinds(false)
+        } else {
+            self.kinds.clone()
+        }
+    }
+
+    /// The filter for this feed, without any time range. Keywords and the spam filter
+    /// cannot be expressed in a filter, see [screen](Self::screen).
+    ///
+    /// This errors if the feed has lists but nobody is in them, rather than returning
+    /// a filter that matches every author.
+    pub fn filter(&self) -> Result<Filter, Error> {
+        let mut filter = Filter::new();
+        filter.authors = self.all_authors()?;
+        if filter.authors.is_empty() && !self.lists.is_empty() {
+            return Err(ErrorKind::Empty("authors".to_owned()).into());
+        }
+        filter.kinds = self.event_kinds();
+        if !self.hashtags.is_empty() {
+            filter.set_tag_values('t', self.hashtags.clone());
+        }
+        Ok(filter)
+    }
+
+    /// A feed with no authors, lists or hashtags is the global feed of its relays. Such
+    /// a feed only shows events seen on those relays, and is empty if it has none.
+    pub fn is_relay_feed(&self) -> bool {
+        self.authors.is_empty() && self.lists.is_empty() && self.hashtags.is_empty()
+    }
+
+    /// Screen an event for the parts of this feed that a filter cannot express
+    pub fn screen(&self, event: &Event) -> bool {
+        if self.is_relay_feed() {
+            let seen_on = GLOBALS
+                .db()
+                .get_event_seen_on_relay(event.id)
+                .unwrap_or_default();
+            if !seen_on.iter().any(|(url, _)| self.relays.contains(url)) {
+                return false;
+            }
+        }
+
+        if !self.include.is_empty() || !self.exclude.is_empty() {
+            let content = event.content.to_lowercase();
+            if !self.include.is_empty()
+                && !self
+                    .include
+                    .iter()
+                    .any(|k| content.contains(&k.to_lowercase()))
+            {
+                return false;
+            }
+            if self
+                .exclude
+                .iter()
+                .any(|k| content.contains(&k.to_lowercase()))
+            {
+                return false;
+            }
+        }
+
+        if self.apply_spam_filter {
+            use crate::spam_filter::{filter_event, EventFilterAction, EventFilterCaller};
+            if filter_event(event.clone(), EventFilterCaller::Custom, false)
+                != EventFilterAction::Allow
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl ByteRep for CustomFeed1 {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.write_to_vec()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::read_from_buffer(bytes)?)
+    }
+}
+
+impl Record for CustomFeed1 {
+    type Key = String;
+
+    /// Create a new empty feed
+    fn new(k: Self::Key) -> Option<Self> {
+        Some(CustomFeed1 {
+            name: k,
+            ..Default::default()
+        })
+    }
+
+    /// Get the key of a record
+    fn key(&self) -> Self::Key {
+        self.name.clone()
+    }
+
+    /// Keep hashtags in the form they are indexed in
+    fn stabilize(&mut self) {
+        for hashtag in self.hashtags.iter_mut() {
+            *hashtag = hashtag.trim_start_matches('#').to_lowercase();
+        }
+        self.hashtags.retain(|h| !h.is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use super::{ByteRep, Record};
+use crate::error::Error;
+use nostr_types::PublicKey;
+use speedy::{Readable, Writable};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// What we know and keep about a DM conversation, beyond its messages
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct DmConversation1 {
+    /// The unique id of the DM channel
+    pub id: String,
+
+    /// The members (other than us)
+    pub members: Vec<PublicKey>,
+
+    /// The subject (NIP-17 `subject` tag) of the conversation, if it has one
+    pub subject: Option<String>,
+
+    /// When the subject was set
+    pub subject_at: i64,
+
+    /// The conversation this one continues, with different members
+    pub predecessor: Option<String>,
+
+    /// The conversation that continues this one, with different members
+    pub successor: Option<String>,
+
+    /// Muted (its unread messages do not count toward the unread DMs indicator)
+    pub muted: bool,
+
+    /// Archived (not listed unless asked for)
+    pub archived: bool,
+
+    /// Pinned (listed first)
+    pub pinned: bool,
+
+    /// Messages up to this time have been read
+    pub read_until: i64,
+}
+
+impl ByteRep for DmConversation1 {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.write_to_vec()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::read_from_buffer(bytes)?)
+    }
+}
+
+impl Record for DmConversation1 {
+    type Key = String;
+
+    /// Conversations are created knowing their members
+    fn new(_k: Self::Key) -> Option<Self> {
+        None
+    }
+
+    /// Get the key of a record
+    fn key(&self) -> Self::Key {
+        self.id.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dm_conversation_byte_rep() {
+        let member = PublicKey::try_from_hex_string(
+            "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245",
+            false,
+        )
+        .unwrap();
+        let conversation = DmConversation1 {
+            id: "ab".repeat(32),
+            members: vec![member],
+            subject: Some("Weekend plans".to_owned()),
+            subject_at: 1700000000,
+            predecessor: Some("cd".repeat(32)),
+            successor: None,
+            muted: true,
+            archived: false,
+            pinned: true,
+            read_until: 1700000100,
+        };
+
+        let bytes = conversation.to_bytes().unwrap();
+        let back = DmConversation1::from_bytes(&bytes).unwrap();
+        assert_eq!(back, conversation);
+        assert_eq!(back.key(), "ab".repeat(32));
+
+        assert!(DmConversation1::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(DmConversation1::new("ab".repeat(32)).is_none());
+    }
+}
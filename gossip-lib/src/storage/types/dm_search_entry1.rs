@@ -0,0 +1,46 @@
+use super::{ByteRep, Record};
+use crate::error::Error;
+use speedy::{Readable, Writable};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// One DM in the search index, encrypted. Its channel and id are only given as HMACs
+/// under the index key, so the table reveals nothing the giftwraps do not.
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct DmSearchEntry1 {
+    /// HMAC of the unique id of the DM channel (hex)
+    pub channel: String,
+
+    /// HMAC of the id of the message (hex)
+    pub message: String,
+
+    /// The nonce the message was encrypted with
+    pub nonce: Vec<u8>,
+
+    /// The encrypted message
+    pub ciphertext: Vec<u8>,
+}
+
+impl ByteRep for DmSearchEntry1 {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.write_to_vec()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::read_from_buffer(bytes)?)
+    }
+}
+
+impl Record for DmSearchEntry1 {
+    type Key = String;
+
+    /// Entries are created by encrypting a message
+    fn new(_k: Self::Key) -> Option<Self> {
+        None
+    }
+
+    /// Get the key of a record (so a channel's messages are together)
+    fn key(&self) -> Self::Key {
+        format!("{}{}", self.channel, self.message)
+    }
+}
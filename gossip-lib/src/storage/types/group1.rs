@@ -0,0 +1,50 @@
+use super::{ByteRep, Record};
+use crate::error::Error;
+use nostr_types::RelayUrl;
+use speedy::{Readable, Writable};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// A NIP-29 relay-based group that we have joined
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct Group1 {
+    /// The relay hosting the group
+    pub relay: RelayUrl,
+
+    /// The group id (the 'h' tag of its events)
+    pub id: String,
+
+    /// The name of the group when we joined it
+    pub name: String,
+}
+
+impl Group1 {
+    /// The key of a group (relay and group id)
+    pub fn key_for(relay: &RelayUrl, id: &str) -> String {
+        format!("{}'{}", relay, id)
+    }
+}
+
+impl ByteRep for Group1 {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.write_to_vec()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::read_from_buffer(bytes)?)
+    }
+}
+
+impl Record for Group1 {
+    type Key = String;
+
+    /// Groups are only created when joined
+    fn new(_k: Self::Key) -> Option<Self> {
+        None
+    }
+
+    /// Get the key of a record
+    fn key(&self) -> Self::Key {
+        Self::key_for(&self.relay, &self.id)
+    }
+}
@@ -0,0 +1,62 @@
+use super::{ByteRep, Record};
+use crate::error::Error;
+use nostr_types::PublicKey;
+use speedy::{Readable, Writable};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// A NIP-39 claim that a person controls an identity on another platform
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct IdentityClaim1 {
+    /// The person making the claim
+    pub pubkey: PublicKey,
+
+    /// The platform, e.g. "github" or "mastodon"
+    pub platform: String,
+
+    /// The identity on that platform
+    pub identity: String,
+
+    /// What proves the claim (platform specific, e.g. a gist id)
+    pub proof: String,
+
+    /// When the event making the claim was created
+    pub created_at: i64,
+
+    /// If the proof checked out to be valid
+    pub valid: bool,
+
+    /// When the proof was last checked (to determine if we need to check again)
+    pub last_checked: Option<u64>,
+}
+
+impl IdentityClaim1 {
+    /// The key of a claim (person, platform and identity)
+    pub fn key_for(pubkey: &PublicKey, platform: &str, identity: &str) -> String {
+        format!("{}'{}:{}", pubkey.as_hex_string(), platform, identity)
+    }
+}
+
+impl ByteRep for IdentityClaim1 {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.write_to_vec()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::read_from_buffer(bytes)?)
+    }
+}
+
+impl Record for IdentityClaim1 {
+    type Key = String;
+
+    /// Claims are only created from events
+    fn new(_k: Self::Key) -> Option<Self> {
+        None
+    }
+
+    /// Get the key of a record
+    fn key(&self) -> Self::Key {
+        Self::key_for(&self.pubkey, &self.platform, &self.identity)
+    }
+}
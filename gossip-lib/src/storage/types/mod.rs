@@ -1,6 +1,27 @@
+mod cache_entry1;
+pub use cache_entry1::CacheEntry1;
+
+mod custom_feed1;
+pub use custom_feed1::CustomFeed1;
+
+mod dm_conversation1;
+pub use dm_conversation1::DmConversation1;
+
+mod dm_search_entry1;
+pub use dm_search_entry1::DmSearchEntry1;
+
+mod group1;
+pub use group1::Group1;
+
 mod handler;
 pub use handler::{Handler, HandlerKey};
 
+mod identity_claim1;
+pub use identity_claim1::IdentityClaim1;
+
+mod ots_proof1;
+pub use ots_proof1::OtsProof1;
+
 mod person2;
 pub use person2::Person2;
 
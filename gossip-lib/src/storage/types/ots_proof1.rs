@@ -0,0 +1,54 @@
+use super::{ByteRep, Record};
+use crate::error::Error;
+use speedy::{Readable, Writable};
+
+// THIS IS HISTORICAL FOR MIGRATIONS AND THE STRUCTURES SHOULD NOT BE EDITED
+
+/// A NIP-03 OpenTimestamps proof of an event, and what we know about it
+#[derive(Debug, Clone, PartialEq, Readable, Writable)]
+pub struct OtsProof1 {
+    /// The id of the event that is timestamped (hex)
+    pub event: String,
+
+    /// The id of the kind 1040 attestation event carrying the proof (hex), if there is one
+    pub attestation: Option<String>,
+
+    /// The OTS proof file
+    pub proof: Vec<u8>,
+
+    /// The bitcoin block the proof was verified against, if it was
+    pub height: Option<u64>,
+
+    /// The time of that block
+    pub block_time: Option<i64>,
+
+    /// If we requested this timestamp and are waiting for it to get into a block
+    pub pending: bool,
+
+    /// When the proof was last verified or upgraded
+    pub last_checked: Option<i64>,
+}
+
+impl ByteRep for OtsProof1 {
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.write_to_vec()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::read_from_buffer(bytes)?)
+    }
+}
+
+impl Record for OtsProof1 {
+    type Key = String;
+
+    /// Proofs are only created from events or calendar responses
+    fn new(_k: Self::Key) -> Option<Self> {
+        None
+    }
+
+    /// Get the key of a record
+    fn key(&self) -> Self::Key {
+        self.event.clone()
+    }
+}
@@ -49,11 +49,13 @@ pub enum RelationshipById2 {
     // NIP-57 Lightning Zaps
     Zaps { by: PublicKey, amount: MilliSatoshi },
 
-    // NIP-72 Moderated Communities (Reddit-style)
-    // Approves { in_community: NAddr },
-
     // NIP-90 Data Vending Machines
     SuppliesJobResult,
+
+    // NIP-72 Moderated Communities (Reddit-style)
+    // This is last so that stored relationships keep their encoding.
+    // The community is its 'a' tag value.
+    Approves { in_community: String },
 }
 
 impl From<RelationshipById1> for RelationshipById2 {
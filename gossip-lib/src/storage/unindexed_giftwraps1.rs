@@ -2,7 +2,7 @@ use crate::error::{Error, ErrorKind};
 use crate::globals::GLOBALS;
 use crate::storage::{RawDatabase, Storage};
 use heed::types::Bytes;
-use nostr_types::Id;
+use nostr_types::{Event, Id};
 use std::sync::Mutex;
 
 // Id -> ()
@@ -58,6 +58,7 @@ impl Storage {
             ids.push(id);
         }
 
+        let mut events: Vec<Event> = Vec::new();
         let mut txn = self.env.write_txn()?;
         for id in ids {
             if let Some(event) = self.read_event(id)? {
@@ -70,6 +71,7 @@ impl Storage {
                 )?;
                 self.write_event_kci_index(event.kind, event.created_at, event.id, Some(&mut txn))?;
                 self.write_event_tci_index(&event, Some(&mut txn))?;
+                events.push(event);
             }
             self.db_unindexed_giftwraps1()?
                 .delete(&mut txn, id.as_slice())?;
@@ -77,6 +79,11 @@ impl Storage {
 
         txn.commit()?;
 
+        // Now we can read them, index them for search too
+        if let Err(e) = crate::dm_search::index_giftwraps(&events) {
+            tracing::warn!("{}", e);
+        }
+
         Ok(())
     }
 }
@@ -72,6 +72,20 @@ async fn do_online_tasks(tick: usize) {
     if tick % 2 == 0 {
         GLOBALS.people.maybe_fetch_metadata().await;
     }
+
+    // Re-mirror our blobs missing from any of our blossom servers (every hour)
+    if tick % 3600 == 0 {
+        tokio::task::spawn(async {
+            if let Err(e) = crate::blossom::remirror_missing().await {
+                tracing::warn!("{}", e);
+            }
+        });
+    }
+
+    // Upgrade pending timestamps of our posts (every 10 minutes)
+    if tick % 600 == 0 {
+        tokio::task::spawn(crate::ots::upgrade_pending());
+    }
 }
 
 async fn do_general_tasks(tick: usize) {
@@ -79,7 +93,11 @@ async fn do_general_tasks(tick: usize) {
     if tick % 3 == 0 {
         // Update unread dm channels, whether or not we are in that feed
         if let Ok(channels) = GLOBALS.db().dm_channels() {
-            let unread = channels.iter().map(|c| c.unread_message_count).sum();
+            let unread = channels
+                .iter()
+                .filter(|c| !c.muted)
+                .map(|c| c.unread_message_count)
+                .sum();
             GLOBALS.unread_dms.store(unread, Ordering::Relaxed);
         }
 
@@ -88,6 +106,27 @@ async fn do_general_tasks(tick: usize) {
 
     // Update handlers for quick menu rendering
     let _ = GLOBALS.update_handlers();
+
+    // Build the DM search index once we can open DMs (checked every 30 seconds)
+    if tick % 30 == 5
+        && GLOBALS.identity.is_unlocked()
+        && GLOBALS.db().get_flag_rebuild_dm_search_needed()
+    {
+        tokio::task::spawn_blocking(|| {
+            if let Err(e) = crate::dm_search::rebuild() {
+                tracing::error!("{}", e);
+            }
+        });
+    }
+
+    // Keep the media cache within its budgets (soon after startup, then every 10 minutes)
+    if tick % 600 == 10 {
+        tokio::task::spawn_blocking(|| {
+            if let Err(e) = crate::media_cache::enforce_limits() {
+                tracing::error!("{}", e);
+            }
+        });
+    }
 }
 
 async fn update_inbox_indicator() {
@@ -0,0 +1,331 @@
+//! Preparing media for upload
+//!
+//! Phones put the GPS location (and more) into the EXIF metadata of photos, so before
+//! uploading we decode and re-encode images, which drops it, optionally downscaling them
+//! on the way. We also work out the NIP-94 file metadata (hash, size, dimensions and
+//! blurhash) here, so that posts can carry a full NIP-92 `imeta` tag for our uploads
+//! without fetching them back.
+
+use crate::blossom::{BlobDescriptor, HashOutput};
+use crate::comms::ToOverlordMessage;
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use mime::Mime;
+use nostr_types::{EventKind, FileMetadata, PreEvent, Tag, UncheckedUrl, Unixtime};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// A file ready to be uploaded
+pub struct PreparedUpload {
+    /// The data to upload, or None to upload the file as it is
+    pub data: Option<Vec<u8>>,
+
+    /// The SHA-256 hash of what we upload
+    pub hash: HashOutput,
+
+    /// The mime type
+    pub mime: Mime,
+
+    /// The size of what we upload
+    pub size: u64,
+
+    /// Image dimensions
+    pub dim: Option<(usize, usize)>,
+
+    /// Image blurhash
+    pub blurhash: Option<String>,
+}
+
+impl PreparedUpload {
+    /// The file metadata of the upload, now that we know where it went
+    pub fn file_metadata(&self, bd: &BlobDescriptor) -> FileMetadata {
+        let mut fm = FileMetadata::new(UncheckedUrl(bd.url.clone()));
+        fm.m = Some(format!("{}", self.mime));
+        fm.x = Some(format!("{}", self.hash));
+        fm.size = Some(self.size);
+        fm.dim = self.dim;
+        fm.blurhash = self.blurhash.clone();
+        fm
+    }
+}
+
+/// The servers we can upload to, Blossom servers first. The bool is true for NIP-96 servers.
+pub fn upload_servers() -> Vec<(String, bool)> {
+    let mut servers: Vec<(String, bool)> = crate::blossom::server_base_urls()
+        .into_iter()
+        .map(|url| (url, false))
+        .collect();
+    servers.extend(
+        crate::nip96::server_base_urls()
+            .into_iter()
+            .map(|url| (url, true)),
+    );
+    servers
+}
+
+/// The server to upload to: the one chosen in settings, else the first we have
+pub fn upload_server() -> Option<(String, bool)> {
+    let servers = upload_servers();
+    let chosen = GLOBALS.db().read_setting_upload_server();
+    if let Ok(chosen) = crate::blossom::base_url(&chosen) {
+        if let Some(server) = servers.iter().find(|(url, _)| *url == chosen) {
+            return Some(server.clone());
+        }
+    }
+    servers.into_iter().next()
+}
+
+/// Prepare a file for upload (in the background, as image work is slow)
+pub(crate) async fn prepare(path: PathBuf) -> Result<PreparedUpload, Error> {
+    tokio::task::spawn_blocking(move || prepare_blocking(&path)).await?
+}
+
+fn prepare_blocking(path: &Path) -> Result<PreparedUpload, Error> {
+    let mime = crate::blossom::get_content_type(path)?;
+    let strip = GLOBALS.db().read_setting_upload_strip_metadata();
+    let max = GLOBALS.db().read_setting_upload_max_dimension();
+
+    let format = match reencode_format(&mime, strip)? {
+        Some(format) => format,
+        None => {
+            let size = std::fs::metadata(path)?.len();
+            return Ok(PreparedUpload {
+                data: None,
+                hash: HashOutput::from_file(path)?,
+                mime,
+                size,
+                dim: None,
+                blurhash: None,
+            });
+        }
+    };
+
+    let bytes = std::fs::read(path)?;
+    let (data, image) = reencode(bytes, format, strip, max)?;
+
+    let (w, h) = image.dimensions();
+    Ok(PreparedUpload {
+        hash: HashOutput::from_bytes(&data),
+        size: data.len() as u64,
+        data: Some(data),
+        mime,
+        dim: Some((w as usize, h as usize)),
+        blurhash: blurhash_of(&image),
+    })
+}
+
+// The format to re-encode a file in, or None to upload it as it is. We can only
+// re-encode the formats the image crate is built with, so other images (WebP, HEIC,
+// TIFF and the like, which carry EXIF too) are refused when we are to strip metadata.
+// GIFs and SVGs don't carry EXIF and go as they are.
+fn reencode_format(mime: &Mime, strip: bool) -> Result<Option<ImageFormat>, Error> {
+    if *mime == mime::IMAGE_JPEG {
+        Ok(Some(ImageFormat::Jpeg))
+    } else if *mime == mime::IMAGE_PNG {
+        Ok(Some(ImageFormat::Png))
+    } else if strip
+        && mime.type_() == mime::IMAGE
+        && *mime != mime::IMAGE_GIF
+        && *mime != mime::IMAGE_SVG
+    {
+        Err(ErrorKind::General(format!(
+            "Can't strip the metadata from {} images. Convert it to JPEG or PNG, or turn off \
+             stripping metadata in the upload settings.",
+            mime.essence_str()
+        ))
+        .into())
+    } else {
+        Ok(None)
+    }
+}
+
+// Decode an image, and re-encode it if we are stripping its metadata or it needs to be
+// downscaled. Returns what to upload and the (possibly downscaled) image.
+fn reencode(
+    bytes: Vec<u8>,
+    format: ImageFormat,
+    strip: bool,
+    max: u32,
+) -> Result<(Vec<u8>, DynamicImage), Error> {
+    let mut image = image::load_from_memory_with_format(&bytes, format)?;
+    let resize = max > 0 && (image.width() > max || image.height() > max);
+
+    if !strip && !resize {
+        return Ok((bytes, image));
+    }
+
+    // The orientation is in the metadata we are dropping
+    image = crate::media::adjust_orientation(&bytes, image);
+    if resize {
+        // This preserves aspect ratio. The sizes represent bounds.
+        image = image.resize(max, max, FilterType::CatmullRom);
+    }
+    let data = encode(&image, format)?;
+    Ok((data, image))
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, Error> {
+    let mut data: Vec<u8> = Vec::new();
+    if format == ImageFormat::Jpeg {
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut data, 85);
+        // JPEG has no alpha channel
+        DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+    } else {
+        image.write_to(&mut Cursor::new(&mut data), format)?;
+    }
+    Ok(data)
+}
+
+/// The blurhash of an image. This is computed on a small copy, as it is slow on big
+/// images and the result is blurry anyway.
+pub(crate) fn blurhash_of(image: &DynamicImage) -> Option<String> {
+    let (w, h) = image.dimensions();
+    if w == 0 || h == 0 {
+        return None;
+    }
+    let small = image.thumbnail(64, 64).to_rgba8();
+    blurhash::encode(
+        (4 * w / h).clamp(1, 9),
+        (4 * h / w).clamp(1, 9),
+        small.width(),
+        small.height(),
+        small.as_raw(),
+    )
+    .ok()
+}
+
+/// Remember the file metadata of an upload for its imeta tag, and publish it as a NIP-94
+/// event if the user wants that (but never for uploads into DMs)
+pub(crate) fn uploaded(file_metadata: FileMetadata, dm: bool) -> Result<(), Error> {
+    if let Some(x) = &file_metadata.x {
+        GLOBALS
+            .upload_file_metadata
+            .insert(x.to_owned(), file_metadata.clone());
+    }
+
+    if dm || !GLOBALS.db().read_setting_upload_publish_file_metadata() {
+        return Ok(());
+    }
+
+    let public_key = match GLOBALS.identity.public_key() {
+        Some(pk) => pk,
+        None => return Err(ErrorKind::NoPublicKey.into()),
+    };
+
+    // The imeta tag has the same fields, as "name value" entries
+    let imeta = file_metadata.to_imeta_tag();
+    let mut tags: Vec<Tag> = Vec::new();
+    let mut i = 1;
+    loop {
+        let entry = imeta.get_index(i);
+        if entry.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = entry.split_once(' ') {
+            tags.push(Tag::new(&[name, value]));
+        }
+        i += 1;
+    }
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: EventKind::FileMetadata,
+        tags,
+        content: file_metadata.alt.clone().unwrap_or_default(),
+    };
+    let event = GLOBALS.identity.sign_event(pre_event)?;
+
+    crate::process::process_new_event(&event, None, None, false, false)?;
+    GLOBALS
+        .to_overlord
+        .send(ToOverlordMessage::PostAgain(event))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A JPEG carrying an EXIF GPS latitude, as a phone would write
+    fn jpeg_with_gps() -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::new(16, 8));
+        let jpeg = encode(&image, ImageFormat::Jpeg).unwrap();
+
+        // Little endian TIFF: IFD0 points at a GPS IFD holding GPSLatitudeRef = "N"
+        let mut tiff: Vec<u8> = vec![b'I', b'I', 0x2a, 0x00, 0x08, 0x00, 0x00, 0x00];
+        tiff.extend_from_slice(&[0x01, 0x00]);
+        tiff.extend_from_slice(&[0x25, 0x88, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        tiff.extend_from_slice(&[26, 0x00, 0x00, 0x00]);
+        tiff.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        tiff.extend_from_slice(&[0x01, 0x00]);
+        tiff.extend_from_slice(&[0x01, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00]);
+        tiff.extend_from_slice(&[b'N', 0x00, 0x00, 0x00]);
+        tiff.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+
+        let mut app1: Vec<u8> = vec![0xff, 0xe1];
+        app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        app1.extend_from_slice(b"Exif\0\0");
+        app1.extend_from_slice(&tiff);
+
+        // Right after the start of image marker
+        [&jpeg[..2], &app1, &jpeg[2..]].concat()
+    }
+
+    fn gps_latitude_ref(bytes: &[u8]) -> Option<String> {
+        let exif = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(bytes))
+            .ok()?;
+        let field = exif.get_field(exif::Tag::GPSLatitudeRef, exif::In::PRIMARY)?;
+        match field.value {
+            exif::Value::Ascii(ref values) => values
+                .first()
+                .map(|v| String::from_utf8_lossy(v).into_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_strip_gps_from_jpeg() {
+        let bytes = jpeg_with_gps();
+        assert_eq!(gps_latitude_ref(&bytes).as_deref(), Some("N"));
+
+        // Kept when not stripping
+        let (data, _) = reencode(bytes.clone(), ImageFormat::Jpeg, false, 0).unwrap();
+        assert_eq!(data, bytes);
+
+        let (data, image) = reencode(bytes, ImageFormat::Jpeg, true, 0).unwrap();
+        assert_eq!(gps_latitude_ref(&data), None);
+        assert_eq!(image.dimensions(), (16, 8));
+        assert!(image::load_from_memory_with_format(&data, ImageFormat::Jpeg).is_ok());
+    }
+
+    #[test]
+    fn test_downscale() {
+        let (data, image) = reencode(jpeg_with_gps(), ImageFormat::Jpeg, false, 4).unwrap();
+        assert_eq!(image.dimensions(), (4, 2));
+        assert_eq!(gps_latitude_ref(&data), None);
+    }
+
+    #[test]
+    fn test_refuse_images_we_cannot_strip() {
+        let webp: Mime = "image/webp".parse().unwrap();
+        let heic: Mime = "image/heic".parse().unwrap();
+        let tiff: Mime = "image/tiff".parse().unwrap();
+        for mime in [&webp, &heic, &tiff] {
+            assert!(reencode_format(mime, true).is_err());
+            assert_eq!(reencode_format(mime, false).unwrap(), None);
+        }
+
+        assert_eq!(
+            reencode_format(&mime::IMAGE_JPEG, true).unwrap(),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(reencode_format(&mime::IMAGE_GIF, true).unwrap(), None);
+        assert_eq!(reencode_format(&mime::APPLICATION_PDF, true).unwrap(), None);
+    }
+}
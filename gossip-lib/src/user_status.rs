@@ -0,0 +1,125 @@
+//! NIP-38 user statuses
+//!
+//! A user status is a kind 30315 event whose 'd' tag says what kind of status it is,
+//! such as "general" or "music". An empty content clears the status, and statuses may
+//! carry an 'expiration' tag after which they should no longer be shown.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use nostr_types::{Event, EventKind, Filter, PreEvent, PublicKey, Tag, Unixtime};
+
+/// A general status, such as "working" or "on vacation"
+pub const GENERAL: &str = "general";
+
+/// What the user is listening to
+pub const MUSIC: &str = "music";
+
+/// A NIP-38 user status
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserStatus {
+    pub pubkey: PublicKey,
+
+    /// The type of status (the 'd' tag), e.g. "general" or "music"
+    pub d: String,
+
+    pub content: String,
+
+    /// A link associated with the status, from its 'r' tag
+    pub url: Option<String>,
+
+    pub created_at: Unixtime,
+
+    /// When the status stops being valid
+    pub expiration: Option<Unixtime>,
+}
+
+impl UserStatus {
+    /// Interpret a user status event
+    pub fn from_event(event: &Event) -> Result<UserStatus, Error> {
+        if event.kind != EventKind::UserStatus {
+            return Err(ErrorKind::WrongEventKind.into());
+        }
+
+        let mut status = UserStatus {
+            pubkey: event.pubkey,
+            d: event.parameter().unwrap_or("".to_owned()),
+            content: event.content.clone(),
+            url: None,
+            created_at: event.created_at,
+            expiration: None,
+        };
+
+        for tag in event.tags.iter() {
+            match tag.tagname() {
+                "r" => status.url = Some(tag.value().to_owned()),
+                "expiration" => {
+                    if let Ok(secs) = tag.value().parse::<i64>() {
+                        status.expiration = Some(Unixtime(secs));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Whether the status has expired
+    pub fn is_expired(&self) -> bool {
+        match self.expiration {
+            Some(expiration) => expiration < Unixtime::now(),
+            None => false,
+        }
+    }
+
+    /// Whether the status should be shown (it is set and has not expired)
+    pub fn is_current(&self) -> bool {
+        !self.content.is_empty() && !self.is_expired()
+    }
+
+    /// Load the latest status of each type for a person from local storage, the
+    /// general status first. This includes cleared and expired statuses.
+    pub fn load(pubkey: PublicKey) -> Result<Vec<UserStatus>, Error> {
+        let mut filter = Filter::new();
+        filter.add_author(pubkey);
+        filter.add_event_kind(EventKind::UserStatus);
+
+        let mut statuses: Vec<UserStatus> = Vec::new();
+        for event in GLOBALS.db().find_events_by_filter(&filter, |_| true)? {
+            let status = Self::from_event(&event)?;
+            // Events are newest first, so keep the first of each type
+            if !statuses.iter().any(|s| s.d == status.d) {
+                statuses.push(status);
+            }
+        }
+        statuses.sort_by_key(|s| s.d != GENERAL);
+        Ok(statuses)
+    }
+}
+
+/// Create a user status event. An empty content clears the status.
+pub(crate) fn status_event(
+    d: &str,
+    content: &str,
+    expiration: Option<Unixtime>,
+) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let mut tags: Vec<Tag> = vec![Tag::new_identifier(d.to_owned())];
+    if let Some(expiration) = expiration {
+        tags.push(Tag::new(&["expiration", &format!("{}", expiration)]));
+    }
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: EventKind::UserStatus,
+        tags,
+        content: content.to_owned(),
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}
@@ -0,0 +1,78 @@
+//! NIP-25 reactions to websites
+//!
+//! A kind 17 event reacts to the website in its 'r' tag the same way a kind 7 event
+//! reacts to another event.
+
+use crate::error::{Error, ErrorKind};
+use crate::globals::GLOBALS;
+use nostr_types::{Event, Filter, PreEvent, PublicKey, Tag, Unixtime};
+use std::collections::HashMap;
+
+/// A reaction to a website
+pub const WEBSITE_REACTION: u32 = 17;
+
+/// The reactions we have to a website with how many people reacted with each, and our
+/// own reaction. Empty reactions are counted as '+'.
+pub fn reactions_to(url: &str) -> Result<(Vec<(String, usize)>, Option<String>), Error> {
+    let mut filter = Filter::new();
+    filter.add_event_kind(WEBSITE_REACTION.into());
+    filter.set_tag_values('r', vec![url.to_owned()]);
+
+    let mut our_reaction: Option<String> = None;
+    let mut reacted: Vec<PublicKey> = Vec::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    // Events are newest first, so count the latest reaction of each person
+    for event in GLOBALS.db().find_events_by_filter(&filter, |_| true)? {
+        if reacted.contains(&event.pubkey) {
+            continue;
+        }
+        reacted.push(event.pubkey);
+
+        let reaction = if event.content.is_empty() {
+            "+".to_owned()
+        } else {
+            event.content.clone()
+        };
+        if Some(event.pubkey) == GLOBALS.identity.public_key() {
+            our_reaction = Some(reaction.clone());
+        }
+        *counts.entry(reaction).or_insert(0) += 1;
+    }
+
+    let mut v: Vec<(String, usize)> = counts.drain().collect();
+    v.sort();
+    Ok((v, our_reaction))
+}
+
+/// Create a reaction (kind 17) to a website
+pub(crate) fn reaction_event(url: &str, reaction: String) -> Result<Event, Error> {
+    let public_key = match GLOBALS.identity.public_key() {
+        None => return Err(ErrorKind::NoPublicKey.into()),
+        Some(pk) => pk,
+    };
+
+    let mut tags: Vec<Tag> = vec![Tag::new(&["r", url])];
+
+    // A custom emoji reaction must define the emoji
+    if let Some(emoji) = crate::emoji::my_emojis()
+        .into_iter()
+        .find(|e| e.as_content() == reaction)
+    {
+        tags.push(emoji.to_tag());
+    }
+
+    if GLOBALS.db().read_setting_set_client_tag() {
+        tags.push(Tag::new(&["client", "gossip"]));
+    }
+
+    let pre_event = PreEvent {
+        pubkey: public_key,
+        created_at: Unixtime::now(),
+        kind: WEBSITE_REACTION.into(),
+        tags,
+        content: reaction,
+    };
+
+    GLOBALS.identity.sign_event(pre_event)
+}